@@ -0,0 +1,127 @@
+use crate::api::common::{cursor_link_header, CursorQuery, Page, PaginationQuery};
+use crate::api::error::ApiError;
+use crate::api::extract::Query;
+use crate::api::requester::Requester;
+use axum::body::StreamBody;
+use axum::extract::{Extension, OriginalUri};
+use axum::http::header::CONTENT_TYPE;
+use axum::response::{Headers, IntoResponse, Response};
+use axum::{BoxError, Json};
+use futures::stream;
+use std::sync::Arc;
+use taskrs_core::logic::FilterableModelTrait;
+use taskrs_core::models::audit_event::AuditEvent;
+use taskrs_core::models::filter::ListQuery;
+use taskrs_core::permissions::Permission;
+use taskrs_db::sea_orm::DbConn;
+
+/// Number of events fetched at once while exporting
+const EXPORT_BATCH_SIZE: usize = 500;
+
+#[instrument(name = "list_audit_events", level = "debug", skip_all)]
+pub async fn list(
+    OriginalUri(uri): OriginalUri,
+    Query(pagination): Query<PaginationQuery>,
+    Query(cursor): Query<CursorQuery>,
+    Query(params): Query<Vec<(String, String)>>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Response, ApiError> {
+    requester
+        .require_permission(Permission::AuditRead, db.as_ref())
+        .await?;
+
+    let query = ListQuery::from_pairs(params)?;
+    let limit = pagination.limit();
+
+    if cursor.cursor.is_some() {
+        let page = AuditEvent::find_by_query_cursor(
+            query,
+            cursor.cursor(),
+            limit,
+            cursor.count,
+            db.as_ref(),
+        )
+        .await?;
+        return Ok((cursor_link_header(&page, &uri, limit), Json(page)).into_response());
+    }
+
+    let (items, total) =
+        AuditEvent::find_by_query(query, pagination.page, limit, db.as_ref()).await?;
+
+    let page = Page {
+        items,
+        total,
+        page: pagination.page,
+        limit,
+    };
+
+    Ok((page.link_header(&uri), Json(page)).into_response())
+}
+
+/// Streams all events matching the filters as newline delimited JSON.
+/// Events are fetched in batches, so the export does not need to fit into memory.
+#[instrument(name = "export_audit_events", level = "debug", skip_all)]
+pub async fn export(
+    Query(params): Query<Vec<(String, String)>>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Response, ApiError> {
+    requester
+        .require_permission(Permission::AuditRead, db.as_ref())
+        .await?;
+
+    let query = ListQuery::from_pairs(params)?;
+
+    // Fetch the first batch eagerly, so invalid filters fail the request instead of the stream
+    let first = AuditEvent::find_by_query_cursor(
+        query.clone(),
+        None,
+        EXPORT_BATCH_SIZE,
+        false,
+        db.as_ref(),
+    )
+    .await?;
+
+    let batches = stream::unfold(Some(first), move |page| {
+        let query = query.clone();
+        let db = db.clone();
+        async move {
+            let page = page?;
+            let lines = page
+                .items
+                .iter()
+                .map(|event| serde_json::to_string(event).map(|line| line + "\n"))
+                .collect::<Result<String, _>>();
+
+            let next = match &page.next {
+                Some(cursor) => AuditEvent::find_by_query_cursor(
+                    query,
+                    Some(cursor.clone()),
+                    EXPORT_BATCH_SIZE,
+                    false,
+                    db.as_ref(),
+                )
+                .await
+                .map(Some),
+                None => Ok(None),
+            };
+
+            // Once streaming started errors can only abort the response
+            match (lines, next) {
+                (Ok(lines), Ok(next)) => Some((Ok(lines), next)),
+                (Err(err), _) => Some((Err(BoxError::from(err)), None)),
+                (_, Err(err)) => {
+                    error!("Error while exporting audit events: {}", err);
+                    Some((Err(BoxError::from(err)), None))
+                }
+            }
+        }
+    });
+
+    Ok((
+        Headers([(CONTENT_TYPE, "application/x-ndjson")]),
+        StreamBody::new(batches),
+    )
+        .into_response())
+}
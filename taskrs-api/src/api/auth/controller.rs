@@ -1,11 +1,12 @@
 use crate::api::auth::error::AuthError;
 use crate::api::auth::{ACCESS_TOKEN_COOKIE, REFRESH_TOKEN_COOKIE};
 use crate::api::error::ApiError;
+use crate::api::extract::Json;
 use crate::api::requester::Requester;
 use crate::application::ApplicationState;
 use axum::extract::Extension;
-use axum::Json;
 use std::sync::Arc;
+use taskrs_core::error::{AuthError as CoreAuthError, Error};
 use taskrs_core::models::auth::{Auth, Token};
 use taskrs_db::sea_orm::DbConn;
 use time::Duration;
@@ -32,7 +33,16 @@ pub async fn login(
 
     debug!("Generate access and refresh tokens");
     let config = state.config.authentication.clone().into_settings();
-    let tokens = login_data.login(&config, db.as_ref()).await?;
+    let tokens = login_data
+        .login(&config, db.as_ref())
+        .await
+        .map_err(|err| match err {
+            // Unknown emails are not told apart from wrong passwords
+            Error::Auth(CoreAuthError::UnknownEmail | CoreAuthError::WrongPassword) => {
+                AuthError::Credentials.into()
+            }
+            err => ApiError::from(err),
+        })?;
 
     cookies.add(
         Cookie::build(ACCESS_TOKEN_COOKIE, tokens.access_token)
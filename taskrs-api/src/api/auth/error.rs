@@ -1,40 +1,72 @@
+use crate::api::error::Problem;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
-use axum::Json;
-use serde_json::json;
 
 #[derive(Debug)]
 pub enum AuthError {
     AccessToken,
     RefreshToken,
     Credentials,
+    /// The user of a token was removed or disabled
     User,
+    UserDisabled,
+    /// The current password of the user is wrong, e.g. on a password change
+    WrongPassword,
     RevokeRefreshToken,
+    EmailChangeToken,
 }
 
-impl IntoResponse for AuthError {
-    fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            AuthError::AccessToken => {
-                (StatusCode::UNAUTHORIZED, "Invalid access token".to_string())
-            }
+impl AuthError {
+    pub fn problem(&self) -> Problem {
+        let (status, code, title) = match self {
+            AuthError::AccessToken => (
+                StatusCode::UNAUTHORIZED,
+                "auth.invalid_access_token",
+                "Invalid access token",
+            ),
             AuthError::RefreshToken => (
                 StatusCode::UNAUTHORIZED,
-                "Invalid refresh token".to_string(),
+                "auth.invalid_refresh_token",
+                "Invalid refresh token",
+            ),
+            AuthError::Credentials => (
+                StatusCode::BAD_REQUEST,
+                "auth.invalid_credentials",
+                "Invalid credentials",
             ),
-            AuthError::Credentials => (StatusCode::BAD_REQUEST, "Invalid credentials".to_string()),
             AuthError::User => (
                 StatusCode::BAD_REQUEST,
-                "User removed or disabled".to_string(),
+                "auth.user_unavailable",
+                "User removed or disabled",
+            ),
+            AuthError::UserDisabled => (
+                StatusCode::FORBIDDEN,
+                "auth.user_disabled",
+                "User is disabled",
+            ),
+            AuthError::WrongPassword => (
+                StatusCode::BAD_REQUEST,
+                "auth.wrong_password",
+                "Current password is wrong",
+            ),
+            AuthError::RevokeRefreshToken => (
+                StatusCode::BAD_REQUEST,
+                "auth.unknown_refresh_token",
+                "Refresh token does not exist",
+            ),
+            AuthError::EmailChangeToken => (
+                StatusCode::BAD_REQUEST,
+                "auth.invalid_email_change_token",
+                "Invalid or outdated email change token",
             ),
-            AuthError::RevokeRefreshToken => {
-                (StatusCode::BAD_REQUEST, "Invalid refresh token".to_string())
-            }
         };
 
-        let body = Json(json!({
-            "error": error_message,
-        }));
-        (status, body).into_response()
+        Problem::new(status, code, title)
+    }
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        self.problem().into_response()
     }
 }
@@ -0,0 +1,756 @@
+use crate::api::batch::error::BatchError;
+use crate::api::common::IgnoredIds;
+use crate::api::error::ApiError;
+use crate::api::extract::Json;
+use crate::api::requester::Requester;
+use crate::api::users::controller::{create_user, update_user};
+use axum::extract::Extension;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::Arc;
+use taskrs_core::error::RbacError;
+use taskrs_core::logic::{
+    events, partition_ids, CreateModelTrait, DeleteModelTrait, ReadModelTrait, UpdateModelTrait,
+};
+use taskrs_core::models::grant::GrantValidity;
+use taskrs_core::models::group::{Group, GroupCreate, GroupUpdate};
+use taskrs_core::models::role::{Role, RoleCreate, RoleUpdate};
+use taskrs_core::models::user::{User, UserCreate, UserUpdate};
+use taskrs_core::permissions::Permission;
+use taskrs_db::models::{group, permission, role, user};
+use taskrs_db::sea_orm::sea_query::IntoCondition;
+use taskrs_db::sea_orm::{ColumnTrait, ConnectionTrait, DbConn, DbErr};
+
+const MAX_OPERATIONS: usize = 500;
+
+/// How the operations of a batch are committed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BatchMode {
+    /// All operations are committed together, the first failed operation rolls back the batch
+    #[default]
+    Atomic,
+    /// Every operation is committed on its own, failed operations do not affect the others
+    BestEffort,
+}
+
+/// Entities supported by batch operations
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BatchEntity {
+    Groups,
+    Roles,
+    Users,
+}
+
+/// Relations of entities that can be granted and revoked
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BatchRelation {
+    Members,
+    Permissions,
+    Roles,
+    Users,
+}
+
+/// A single operation of a batch.
+/// `data` is the request body of the single endpoint, e.g. `POST /users` on create.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum Operation {
+    Create {
+        entity: BatchEntity,
+        data: serde_json::Value,
+    },
+    Update {
+        entity: BatchEntity,
+        id: i32,
+        data: serde_json::Value,
+    },
+    Delete {
+        entity: BatchEntity,
+        id: i32,
+    },
+    Grant(RelationOperation),
+    Revoke(RelationOperation),
+}
+
+/// Grants or revokes related entities, e.g. the roles of a user
+#[derive(Clone, Debug, Deserialize)]
+pub struct RelationOperation {
+    pub entity: BatchEntity,
+    pub id: i32,
+    pub relation: BatchRelation,
+    pub ids: Vec<i32>,
+    /// Only supported by grants of roles and permissions to users
+    #[serde(flatten)]
+    pub validity: GrantValidity,
+}
+
+/// Request body of a batch
+#[derive(Clone, Debug, Deserialize)]
+pub struct BatchRequest {
+    #[serde(default)]
+    pub mode: BatchMode,
+    pub operations: Vec<Operation>,
+}
+
+/// Result of a single operation, with the status and body the single endpoint would respond with
+#[derive(Clone, Debug, Serialize)]
+pub struct OperationResult {
+    pub index: usize,
+    pub status: u16,
+    pub body: serde_json::Value,
+}
+
+impl OperationResult {
+    fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+impl IntoResponse for OperationResult {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        (status, Json(self)).into_response()
+    }
+}
+
+/// Response of a batch, results are ordered like the operations
+#[derive(Clone, Debug, Serialize)]
+pub struct BatchResponse {
+    pub results: Vec<OperationResult>,
+}
+
+/// Operations that run together.
+/// In atomic batches, consecutive deletes of the same entity are run with a single statement.
+/// Best effort batches delete one entity at a time, so a failed delete does not fail the others.
+/// Creates always run one at a time, results hold the created entities,
+/// while `create_many` only returns the id of the last one and creating users hashes their passwords.
+enum Step {
+    Operation(usize, Operation),
+    Deletes(BatchEntity, Vec<(usize, i32)>),
+}
+
+/// Reason a transaction of a batch was rolled back
+enum Rollback {
+    Failed(Vec<OperationResult>),
+    Error(ApiError),
+}
+
+impl From<DbErr> for Rollback {
+    fn from(err: DbErr) -> Self {
+        Self::Error(ApiError::Database(err))
+    }
+}
+
+impl Operation {
+    /// Permission required to run the operation
+    fn permission(&self) -> Result<Permission, ApiError> {
+        let permission = match self {
+            Operation::Create { entity, .. } => match entity {
+                BatchEntity::Groups => Permission::GroupsCreate,
+                BatchEntity::Roles => Permission::RolesCreate,
+                BatchEntity::Users => Permission::UsersCreate,
+            },
+            Operation::Update { entity, .. } => match entity {
+                BatchEntity::Groups => Permission::GroupsUpdate,
+                BatchEntity::Roles => Permission::RolesUpdate,
+                BatchEntity::Users => Permission::UsersUpdate,
+            },
+            Operation::Delete { entity, .. } => match entity {
+                BatchEntity::Groups => Permission::GroupsDelete,
+                BatchEntity::Roles => Permission::RolesDelete,
+                BatchEntity::Users => Permission::UsersDelete,
+            },
+            Operation::Grant(operation) | Operation::Revoke(operation) => {
+                match (operation.entity, operation.relation) {
+                    (BatchEntity::Groups, BatchRelation::Members) => {
+                        Permission::GroupsManageMembers
+                    }
+                    (BatchEntity::Groups, BatchRelation::Roles) => Permission::GroupsManageRoles,
+                    (BatchEntity::Roles, BatchRelation::Permissions) => {
+                        Permission::RolesManagePermissions
+                    }
+                    (BatchEntity::Roles, BatchRelation::Users) => Permission::RolesManageUsers,
+                    (BatchEntity::Users, BatchRelation::Permissions) => {
+                        Permission::UsersManagePermissions
+                    }
+                    (BatchEntity::Users, BatchRelation::Roles) => Permission::UsersManageRoles,
+                    _ => return Err(BatchError::UnsupportedRelation.into()),
+                }
+            }
+        };
+
+        Ok(permission)
+    }
+}
+
+#[instrument(
+    name = "batch",
+    level = "debug",
+    skip_all,
+    fields(mode = ?batch.mode, operations = batch.operations.len())
+)]
+pub async fn batch(
+    Json(batch): Json<BatchRequest>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Response, ApiError> {
+    if batch.operations.len() > MAX_OPERATIONS {
+        return Err(BatchError::TooManyOperations(MAX_OPERATIONS).into());
+    }
+
+    debug!("Check permissions of the operations");
+    let mut results = Vec::new();
+    let mut operations = Vec::new();
+    let mut granted = HashSet::new();
+    for (index, operation) in batch.operations.into_iter().enumerate() {
+        let failed =
+            match require_operation_permission(&operation, &requester, &mut granted, db.as_ref())
+                .await
+            {
+                Ok(()) => {
+                    operations.push((index, operation));
+                    continue;
+                }
+                Err(e) => operation_result(index, Err(e)),
+            };
+
+        let failed = failed.await;
+        if batch.mode == BatchMode::Atomic {
+            return Ok(failed.into_response());
+        }
+        results.push(failed);
+    }
+
+    match run_operations(operations, batch.mode, db.as_ref()).await {
+        Ok(operation_results) => results.extend(operation_results),
+        Err(Rollback::Failed(mut failed)) => {
+            if let Some(failed) = failed.pop() {
+                return Ok(failed.into_response());
+            }
+        }
+        Err(Rollback::Error(e)) => return Err(e),
+    }
+
+    results.sort_by_key(|result| result.index);
+
+    Ok(Json(BatchResponse { results }).into_response())
+}
+
+/// Fails if the requester is missing the permission of an operation.
+/// Granted permissions are remembered, so they are only checked once per batch.
+async fn require_operation_permission(
+    operation: &Operation,
+    requester: &Requester,
+    granted: &mut HashSet<Permission>,
+    db: &DbConn,
+) -> Result<(), ApiError> {
+    let permission = operation.permission()?;
+    if !granted.contains(&permission) {
+        requester.require_permission(permission, db).await?;
+        granted.insert(permission);
+    }
+
+    Ok(())
+}
+
+/// Runs the operations of a batch, committed as the mode says.
+/// Atomic batches are rolled back with the result of the failed operation.
+async fn run_operations(
+    operations: Vec<(usize, Operation)>,
+    mode: BatchMode,
+    db: &DbConn,
+) -> Result<Vec<OperationResult>, Rollback> {
+    let steps = steps(operations, mode);
+    match mode {
+        BatchMode::Atomic => {
+            debug!("Run operations in a single transaction");
+            run_transaction(steps, true, db).await
+        }
+        BatchMode::BestEffort => {
+            debug!("Run operations in their own transactions");
+            let mut results = Vec::new();
+            for step in steps {
+                match run_transaction(vec![step], false, db).await {
+                    Ok(step_results) | Err(Rollback::Failed(step_results)) => {
+                        results.extend(step_results)
+                    }
+                    Err(Rollback::Error(e)) => return Err(Rollback::Error(e)),
+                }
+            }
+            Ok(results)
+        }
+    }
+}
+
+/// Groups operations into the steps they are run in, keeping their order
+fn steps(operations: Vec<(usize, Operation)>, mode: BatchMode) -> Vec<Step> {
+    let mut steps: Vec<Step> = Vec::new();
+    for (index, operation) in operations {
+        match (operation, steps.last_mut()) {
+            (Operation::Delete { entity, id }, Some(Step::Deletes(run_entity, deletes)))
+                if *run_entity == entity =>
+            {
+                deletes.push((index, id))
+            }
+            (Operation::Delete { entity, id }, _) if mode == BatchMode::Atomic => {
+                steps.push(Step::Deletes(entity, vec![(index, id)]))
+            }
+            (operation, _) => steps.push(Step::Operation(index, operation)),
+        }
+    }
+
+    steps
+}
+
+/// Runs steps in a transaction and publishes their events once it is committed.
+/// With `atomic`, the transaction is rolled back by the first failed operation,
+/// otherwise only if no operation succeeded.
+async fn run_transaction(
+    steps: Vec<Step>,
+    atomic: bool,
+    db: &DbConn,
+) -> Result<Vec<OperationResult>, Rollback> {
+    events::after_commit(async move {
+        let txn = db.begin().await?;
+
+        let mut results = Vec::new();
+        for step in steps {
+            for result in run_step(step, &txn).await {
+                if atomic && !result.is_success() {
+                    txn.rollback().await?;
+                    return Err(Rollback::Failed(vec![result]));
+                }
+                results.push(result);
+            }
+        }
+
+        if !results.iter().any(OperationResult::is_success) {
+            txn.rollback().await?;
+            return Err(Rollback::Failed(results));
+        }
+        txn.commit().await?;
+
+        Ok(results)
+    })
+    .await
+}
+
+async fn run_step<'a, C>(step: Step, db: &'a C) -> Vec<OperationResult>
+where
+    C: ConnectionTrait<'a>,
+{
+    match step {
+        Step::Operation(index, operation) => {
+            vec![operation_result(index, run_operation(operation, db).await).await]
+        }
+        Step::Deletes(entity, deletes) => run_deletes(entity, deletes, db).await,
+    }
+}
+
+async fn run_operation<'a, C>(
+    operation: Operation,
+    db: &'a C,
+) -> Result<serde_json::Value, ApiError>
+where
+    C: ConnectionTrait<'a>,
+{
+    match operation {
+        Operation::Create { entity, data } => Ok(match entity {
+            BatchEntity::Groups => {
+                let group: GroupCreate = from_data(data)?;
+                json!(Group::create(group, db).await?)
+            }
+            BatchEntity::Roles => {
+                let role: RoleCreate = from_data(data)?;
+                json!(Role::create(role, db).await?)
+            }
+            BatchEntity::Users => {
+                let user: UserCreate = from_data(data)?;
+                json!(create_user(user, db).await?)
+            }
+        }),
+        Operation::Update { entity, id, data } => Ok(match entity {
+            BatchEntity::Groups => {
+                if Group::get(id, db).await?.is_none() {
+                    return Err(ApiError::NotFound);
+                }
+                let group = GroupUpdate {
+                    id,
+                    ..from_data(data)?
+                };
+                json!(Group::update(group, db).await?)
+            }
+            BatchEntity::Roles => {
+                if Role::get(id, db).await?.is_none() {
+                    return Err(ApiError::NotFound);
+                }
+                let role = RoleUpdate {
+                    id,
+                    ..from_data(data)?
+                };
+                json!(Role::update(role, db).await?)
+            }
+            BatchEntity::Users => {
+                let user = UserUpdate {
+                    id,
+                    ..from_data(data)?
+                };
+                json!(update_user(user, db).await?)
+            }
+        }),
+        Operation::Delete { entity, id } => {
+            require_deletable(entity, id, db).await?;
+            delete_ids(entity, vec![id], db).await?;
+            Ok(serde_json::Value::Null)
+        }
+        Operation::Grant(operation) => run_relation_operation(operation, true, db).await,
+        Operation::Revoke(operation) => run_relation_operation(operation, false, db).await,
+    }
+}
+
+/// Deletes the entities of consecutive delete operations with a single statement
+async fn run_deletes<'a, C>(
+    entity: BatchEntity,
+    deletes: Vec<(usize, i32)>,
+    db: &'a C,
+) -> Vec<OperationResult>
+where
+    C: ConnectionTrait<'a>,
+{
+    let mut results = Vec::new();
+    let mut indices = Vec::new();
+    let mut ids = Vec::new();
+    for (index, id) in deletes {
+        let failed = match require_deletable(entity, id, db).await {
+            Ok(()) => {
+                indices.push(index);
+                ids.push(id);
+                continue;
+            }
+            Err(e) => operation_result(index, Err(e)),
+        };
+        results.push(failed.await);
+    }
+
+    if ids.is_empty() {
+        return results;
+    }
+
+    let failed = match delete_ids(entity, ids, db).await {
+        Ok(()) => {
+            results.extend(indices.into_iter().map(|index| OperationResult {
+                index,
+                status: StatusCode::OK.as_u16(),
+                body: serde_json::Value::Null,
+            }));
+            return results;
+        }
+        Err(e) => operation_result(0, Err(e)),
+    };
+
+    // The statement failed for every entity
+    let failed = failed.await;
+    for index in indices {
+        results.push(OperationResult {
+            index,
+            ..failed.clone()
+        });
+    }
+
+    results
+}
+
+/// Fails like the delete endpoint of the entity, if it does not exist or can not be deleted
+async fn require_deletable<'a, C>(entity: BatchEntity, id: i32, db: &'a C) -> Result<(), ApiError>
+where
+    C: ConnectionTrait<'a>,
+{
+    let exists = match entity {
+        BatchEntity::Groups => Group::get(id, db).await?.is_some(),
+        BatchEntity::Roles => match Role::get(id, db).await? {
+            // Deleting system roles by condition would silently skip them
+            Some(role) if role.is_system => return Err(ApiError::Rbac(RbacError::SystemRole)),
+            role => role.is_some(),
+        },
+        BatchEntity::Users => User::get(id, db).await?.is_some(),
+    };
+
+    match exists {
+        true => Ok(()),
+        false => Err(ApiError::NotFound),
+    }
+}
+
+async fn delete_ids<'a, C>(entity: BatchEntity, ids: Vec<i32>, db: &'a C) -> Result<(), ApiError>
+where
+    C: ConnectionTrait<'a>,
+{
+    match entity {
+        BatchEntity::Groups => {
+            Group::delete_condition(group::Column::Id.is_in(ids).into_condition(), db).await?
+        }
+        BatchEntity::Roles => {
+            Role::delete_condition(role::Column::Id.is_in(ids).into_condition(), db).await?
+        }
+        BatchEntity::Users => {
+            User::delete_condition(user::Column::Id.is_in(ids).into_condition(), db).await?
+        }
+    };
+
+    Ok(())
+}
+
+/// Grants or revokes related entities. Unknown ids are ignored and listed in the result.
+async fn run_relation_operation<'a, C>(
+    operation: RelationOperation,
+    grant: bool,
+    db: &'a C,
+) -> Result<serde_json::Value, ApiError>
+where
+    C: ConnectionTrait<'a>,
+{
+    let RelationOperation {
+        entity,
+        id,
+        relation,
+        ids,
+        validity,
+    } = operation;
+
+    if validity != GrantValidity::default() && !(grant && entity == BatchEntity::Users) {
+        return Err(BatchError::UnsupportedValidity.into());
+    }
+
+    let exists = match entity {
+        BatchEntity::Groups => Group::get(id, db).await?.is_some(),
+        BatchEntity::Roles => Role::get(id, db).await?.is_some(),
+        BatchEntity::Users => User::get(id, db).await?.is_some(),
+    };
+    if !exists {
+        return Err(ApiError::NotFound);
+    }
+
+    let (ids, ignored) = match relation {
+        BatchRelation::Permissions => {
+            partition_ids::<permission::Entity, _>(permission::Column::Id, ids, db).await?
+        }
+        BatchRelation::Roles => partition_ids::<role::Entity, _>(role::Column::Id, ids, db).await?,
+        BatchRelation::Members | BatchRelation::Users => {
+            partition_ids::<user::Entity, _>(user::Column::Id, ids, db).await?
+        }
+    };
+
+    let changes = match (entity, relation, grant) {
+        (BatchEntity::Groups, BatchRelation::Members, true) => {
+            Group::add_members(id, ids, db).await?
+        }
+        (BatchEntity::Groups, BatchRelation::Members, false) => {
+            Group::remove_members(id, ids, db).await?
+        }
+        (BatchEntity::Groups, BatchRelation::Roles, true) => {
+            Group::grant_roles(id, ids, db).await?
+        }
+        (BatchEntity::Groups, BatchRelation::Roles, false) => {
+            Group::revoke_roles(id, ids, db).await?
+        }
+        (BatchEntity::Roles, BatchRelation::Permissions, true) => {
+            Role::grant_permissions(id, ids, db).await?
+        }
+        (BatchEntity::Roles, BatchRelation::Permissions, false) => {
+            Role::revoke_permissions(id, ids, db).await?
+        }
+        (BatchEntity::Roles, BatchRelation::Users, true) => Role::add_users(id, ids, db).await?,
+        (BatchEntity::Roles, BatchRelation::Users, false) => {
+            Role::remove_users(id, ids, db).await?
+        }
+        (BatchEntity::Users, BatchRelation::Permissions, true) => {
+            User::grant_permissions(id, ids, validity, db).await?
+        }
+        (BatchEntity::Users, BatchRelation::Permissions, false) => {
+            User::revoke_permissions(id, ids, db).await?
+        }
+        (BatchEntity::Users, BatchRelation::Roles, true) => {
+            User::grant_roles(id, ids, validity, db).await?
+        }
+        (BatchEntity::Users, BatchRelation::Roles, false) => {
+            User::revoke_roles(id, ids, db).await?
+        }
+        _ => return Err(BatchError::UnsupportedRelation.into()),
+    };
+    debug!("Changed grants {:?}", changes);
+
+    if !ignored.is_empty() {
+        debug!("Ignored unknown ids {:?}", ignored);
+    }
+
+    Ok(json!(IgnoredIds { ignored }))
+}
+
+fn from_data<T>(data: serde_json::Value) -> Result<T, ApiError>
+where
+    T: DeserializeOwned,
+{
+    serde_json::from_value(data).map_err(|e| BatchError::InvalidData(e.to_string()).into())
+}
+
+/// Result of an operation, errors are rendered like the response of the single endpoint
+fn operation_result(
+    index: usize,
+    result: Result<serde_json::Value, ApiError>,
+) -> impl Future<Output = OperationResult> {
+    // Rendered before awaiting, errors are not `Send`
+    let response = result.map_err(IntoResponse::into_response);
+
+    async move {
+        let response = match response {
+            Ok(body) => {
+                return OperationResult {
+                    index,
+                    status: StatusCode::OK.as_u16(),
+                    body,
+                }
+            }
+            Err(response) => response,
+        };
+
+        let status = response.status().as_u16();
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .ok()
+            .and_then(|body| serde_json::from_slice(&body).ok())
+            .unwrap_or_default();
+
+        OperationResult {
+            index,
+            status,
+            body,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_operations, BatchEntity, BatchMode, Operation, Rollback};
+    use crate::api::users::controller::create_user;
+    use crate::application::tests::TestDatabase;
+    use serde_json::json;
+    use taskrs_core::logic::ReadModelTrait;
+    use taskrs_core::models::grant::GrantValidity;
+    use taskrs_core::models::group::Group;
+    use taskrs_core::models::user::{User, UserCreate};
+    use taskrs_core::seeding::{seed_root_role, seed_root_user};
+    use taskrs_db::models::group;
+    use taskrs_db::sea_orm::sea_query::IntoCondition;
+    use taskrs_db::sea_orm::{ColumnTrait, DbConn};
+
+    /// Creates a group and updates a group that does not exist
+    fn create_and_fail() -> Vec<(usize, Operation)> {
+        vec![
+            (
+                0,
+                Operation::Create {
+                    entity: BatchEntity::Groups,
+                    data: json!({ "name": "batch-group" }),
+                },
+            ),
+            (
+                1,
+                Operation::Update {
+                    entity: BatchEntity::Groups,
+                    id: i32::MAX,
+                    data: json!({ "name": "missing" }),
+                },
+            ),
+        ]
+    }
+
+    async fn group_exists(db: &DbConn) -> bool {
+        let condition = group::Column::Name.eq("batch-group").into_condition();
+        !Group::find(condition, db).await.unwrap().is_empty()
+    }
+
+    #[tokio::test]
+    async fn atomic_batches_roll_back_on_the_first_failure() {
+        let test = TestDatabase::sqlite().await;
+
+        let failed = match run_operations(create_and_fail(), BatchMode::Atomic, &test.db).await {
+            Err(Rollback::Failed(failed)) => failed,
+            _ => panic!("Atomic batch with a failed operation was committed"),
+        };
+
+        assert_eq!(failed.len(), 1);
+        assert_eq!((failed[0].index, failed[0].status), (1, 404));
+        assert!(!group_exists(&test.db).await);
+    }
+
+    #[tokio::test]
+    async fn best_effort_batches_keep_successful_operations() {
+        let test = TestDatabase::sqlite().await;
+
+        let results = match run_operations(create_and_fail(), BatchMode::BestEffort, &test.db).await
+        {
+            Ok(results) => results,
+            _ => panic!("Best effort batch failed"),
+        };
+
+        let statuses: Vec<(usize, u16)> = results
+            .iter()
+            .map(|result| (result.index, result.status))
+            .collect();
+        assert_eq!(statuses, vec![(0, 200), (1, 404)]);
+        assert!(group_exists(&test.db).await);
+    }
+
+    #[tokio::test]
+    async fn best_effort_deletes_fail_one_at_a_time() {
+        let test = TestDatabase::sqlite().await;
+        let db = test.db.as_ref();
+        let role = seed_root_role(db).await.unwrap();
+        let root = seed_root_user("root@taskrs.test".into(), "root".into(), None, None, db)
+            .await
+            .unwrap();
+        User::grant_roles(root.id, vec![role.id], GrantValidity::default(), db)
+            .await
+            .unwrap();
+        let user = create_user(
+            UserCreate {
+                email: "user@taskrs.test".into(),
+                password: "password".into(),
+                ..Default::default()
+            },
+            db,
+        )
+        .await
+        .unwrap_or_else(|_| panic!("User can be created"));
+
+        let deletes = [user.id, root.id]
+            .into_iter()
+            .enumerate()
+            .map(|(index, id)| {
+                let entity = BatchEntity::Users;
+                (index, Operation::Delete { entity, id })
+            })
+            .collect();
+        let results = match run_operations(deletes, BatchMode::BestEffort, db).await {
+            Ok(results) => results,
+            _ => panic!("Best effort batch failed"),
+        };
+
+        // Deleting the last root user fails on its own
+        let statuses: Vec<(usize, u16)> = results
+            .iter()
+            .map(|result| (result.index, result.status))
+            .collect();
+        assert_eq!(statuses, vec![(0, 200), (1, 409)]);
+        assert!(User::get(user.id, db).await.unwrap().is_none());
+        assert!(User::get(root.id, db).await.unwrap().is_some());
+    }
+}
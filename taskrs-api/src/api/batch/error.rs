@@ -0,0 +1,47 @@
+use crate::api::error::Problem;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+#[derive(Debug)]
+pub enum BatchError {
+    InvalidData(String),
+    TooManyOperations(usize),
+    UnsupportedRelation,
+    UnsupportedValidity,
+}
+
+impl BatchError {
+    pub fn problem(&self) -> Problem {
+        match self {
+            BatchError::InvalidData(e) => Problem::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "batch.invalid_data",
+                "Invalid operation data",
+            )
+            .detail(e.clone()),
+            BatchError::TooManyOperations(max) => Problem::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "batch.too_many_operations",
+                "Too many operations",
+            )
+            .detail(format!("A batch can contain at most {} operations", max))
+            .extension("maxOperations", max),
+            BatchError::UnsupportedRelation => Problem::new(
+                StatusCode::BAD_REQUEST,
+                "batch.unsupported_relation",
+                "Relation is not supported by the entity",
+            ),
+            BatchError::UnsupportedValidity => Problem::new(
+                StatusCode::BAD_REQUEST,
+                "batch.unsupported_validity",
+                "Validity is only supported by grants to users",
+            ),
+        }
+    }
+}
+
+impl IntoResponse for BatchError {
+    fn into_response(self) -> Response {
+        self.problem().into_response()
+    }
+}
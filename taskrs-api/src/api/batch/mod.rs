@@ -0,0 +1,9 @@
+mod controller;
+pub mod error;
+
+use axum::routing::post;
+use axum::Router;
+
+pub fn get_router() -> Router {
+    Router::new().route("/", post(controller::batch))
+}
@@ -0,0 +1,219 @@
+use crate::api::error::ApiError;
+use axum::http::header::{ETAG, IF_MATCH, LINK};
+use axum::http::{HeaderMap, HeaderValue, Uri};
+use serde::{Deserialize, Serialize};
+use taskrs_core::models::grant::GrantValidity;
+use taskrs_core::models::pagination::CursorPage;
+
+const DEFAULT_LIMIT: usize = 25;
+const MAX_LIMIT: usize = 100;
+
+/// Query parameters of paginated requests. Pages start at 0.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PaginationQuery {
+    #[serde(default)]
+    pub page: usize,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+impl PaginationQuery {
+    /// Limit clamped to `1..=MAX_LIMIT`
+    pub fn limit(&self) -> usize {
+        self.limit.clamp(1, MAX_LIMIT)
+    }
+}
+
+fn default_limit() -> usize {
+    DEFAULT_LIMIT
+}
+
+/// A single page of entities
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: usize,
+    pub page: usize,
+    pub limit: usize,
+}
+
+impl<T> Page<T> {
+    /// `Link` header (RFC 8288) with the first, previous, next and last page of the request `uri`.
+    /// Other query parameters like filters and sort keys are kept.
+    pub fn link_header(&self, uri: &Uri) -> HeaderMap {
+        let link = |page: usize| format!("page={}&limit={}", page, self.limit);
+
+        let last_page = match self.limit {
+            0 => 0,
+            limit => (self.total.max(1) - 1) / limit,
+        };
+        let mut links = vec![(link(0), "first")];
+        if self.page > 0 {
+            links.push((link(self.page.min(last_page + 1) - 1), "prev"));
+        }
+        if self.page < last_page {
+            links.push((link(self.page + 1), "next"));
+        }
+        links.push((link(last_page), "last"));
+
+        link_header(uri, &["page", "limit"], links)
+    }
+}
+
+/// Query parameters of keyset paginated requests.
+/// Lists are paginated by cursor instead of pages if `cursor` is present,
+/// an empty cursor starts at the beginning of the list.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CursorQuery {
+    pub cursor: Option<String>,
+    /// Count all entities, which is slow on large tables
+    #[serde(default)]
+    pub count: bool,
+}
+
+impl CursorQuery {
+    /// Cursor to continue from, `None` if the list starts at the beginning
+    pub fn cursor(&self) -> Option<String> {
+        self.cursor.clone().filter(|cursor| !cursor.is_empty())
+    }
+}
+
+/// `Link` header (RFC 8288) with the first, previous and next page of a keyset paginated
+/// request `uri`. Other query parameters like filters and sort keys are kept.
+pub fn cursor_link_header<T>(page: &CursorPage<T>, uri: &Uri, limit: usize) -> HeaderMap {
+    let link = |cursor: &str| format!("cursor={}&limit={}", cursor, limit);
+
+    let mut links = vec![(link(""), "first")];
+    if let Some(prev) = &page.prev {
+        links.push((link(prev), "prev"));
+    }
+    if let Some(next) = &page.next {
+        links.push((link(next), "next"));
+    }
+
+    link_header(uri, &["cursor", "limit", "page"], links)
+}
+
+/// Builds a `Link` header with links to `uri`, where the query parameters in `replaced`
+/// are replaced by the pagination query of each link
+fn link_header(uri: &Uri, replaced: &[&str], links: Vec<(String, &str)>) -> HeaderMap {
+    let params: Vec<&str> = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|param| {
+            let key = param.split('=').next().unwrap_or_default();
+            !param.is_empty() && !replaced.contains(&key)
+        })
+        .collect();
+    let mut query = params.join("&");
+    if !query.is_empty() {
+        query.push('&');
+    }
+
+    let links: Vec<String> = links
+        .into_iter()
+        .map(|(pagination, rel)| {
+            format!("<{}?{}{}>; rel=\"{}\"", uri.path(), query, pagination, rel)
+        })
+        .collect();
+
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(&links.join(", ")) {
+        headers.insert(LINK, value);
+    }
+    headers
+}
+
+/// Strong `ETag` header derived from the version of an entity
+pub fn etag_header(version: i32) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(&format!("\"{}\"", version)) {
+        headers.insert(ETAG, value);
+    }
+    headers
+}
+
+/// Version expected by the `If-Match` header of a request, as set by [`etag_header`].
+/// Returns `None` if the header is absent or `*`.
+/// Fails if the header can not match a single version, e.g. because of weak or multiple tags.
+pub fn if_match_version(headers: &HeaderMap) -> Result<Option<i32>, ApiError> {
+    let value = match headers.get(IF_MATCH) {
+        Some(value) => value
+            .to_str()
+            .map_err(|_| ApiError::PreconditionFailed)?
+            .trim(),
+        None => return Ok(None),
+    };
+
+    if value == "*" {
+        return Ok(None);
+    }
+
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .and_then(|version| version.parse().ok())
+        .map(Some)
+        .ok_or(ApiError::PreconditionFailed)
+}
+
+/// Request body for endpoints that take a list of ids
+#[derive(Clone, Debug, Deserialize)]
+pub struct IdList {
+    pub ids: Vec<i32>,
+}
+
+/// Request body for endpoints that grant roles or permissions for a period
+#[derive(Clone, Debug, Deserialize)]
+pub struct GrantList {
+    pub ids: Vec<i32>,
+    #[serde(flatten)]
+    pub validity: GrantValidity,
+}
+
+/// Response of mutations that take a list of ids.
+/// Ids that do not belong to an existing entity are ignored and listed here.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct IgnoredIds {
+    pub ignored: Vec<i32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{etag_header, if_match_version};
+    use crate::api::error::ApiError;
+    use axum::http::header::{ETAG, IF_MATCH};
+    use axum::http::{HeaderMap, HeaderValue};
+
+    fn if_match(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(IF_MATCH, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn if_match_takes_the_version_of_the_etag() {
+        let mut headers = HeaderMap::new();
+        headers.insert(IF_MATCH, etag_header(3)[ETAG].clone());
+
+        assert!(matches!(if_match_version(&headers), Ok(Some(3))));
+        assert!(matches!(if_match_version(&HeaderMap::new()), Ok(None)));
+        assert!(matches!(if_match_version(&if_match("*")), Ok(None)));
+    }
+
+    #[test]
+    fn if_match_rejects_tags_that_match_no_single_version() {
+        for value in ["W/\"3\"", "\"3\", \"4\"", "3", "\"x\""] {
+            assert!(
+                matches!(
+                    if_match_version(&if_match(value)),
+                    Err(ApiError::PreconditionFailed)
+                ),
+                "{} was accepted",
+                value
+            );
+        }
+    }
+}
@@ -1,18 +1,111 @@
 use crate::api::auth::error::AuthError;
+use crate::api::batch::error::BatchError;
+use crate::api::users::error::UserError;
 use axum::response::{IntoResponse, Response};
 use axum::Json;
+use hyper::header::CONTENT_TYPE;
+use hyper::http::HeaderValue;
 use hyper::StatusCode;
-use serde_json::json;
-use taskrs_core::error::Error;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use taskrs_core::error::{
+    AuthError as CoreAuthError, ConcurrencyError, Error, FilterError, RbacError, ValidationError,
+};
+use taskrs_core::models::audit_event::AuditContext;
 use taskrs_db::sea_orm::DbErr;
 
+/// Media type of error responses
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Body of error responses, the problem details of RFC 7807.
+/// Clients tell errors apart by the stable `code`, like `auth.user_disabled`.
+/// `title` is the same for every occurrence of a code, `detail` explains this occurrence.
+/// Members specific to a code, like the fields of a validation error, are added as extensions.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: &'static str,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub code: &'static str,
+    /// Id of the request, also echoed in the `X-Request-Id` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(flatten)]
+    pub extensions: Map<String, Value>,
+}
+
+impl Problem {
+    /// Problem of the current request
+    pub fn new(status: StatusCode, code: &'static str, title: &'static str) -> Self {
+        Self {
+            problem_type: format!("urn:taskrs:problem:{}", code),
+            title,
+            status: status.as_u16(),
+            detail: None,
+            code,
+            request_id: AuditContext::current().request_id,
+            extensions: Map::new(),
+        }
+    }
+
+    /// Problem of a failure the client can not resolve.
+    /// The details are only shown by debug builds, they might contain internals.
+    pub fn internal(detail: impl FnOnce() -> String) -> Self {
+        let problem = Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "server.internal_error",
+            "Internal Server Error",
+        );
+        match cfg!(debug_assertions) {
+            true => problem.detail(detail()),
+            false => problem,
+        }
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn extension(mut self, name: &str, value: impl Serialize) -> Self {
+        let value = serde_json::to_value(value).unwrap_or(Value::Null);
+        self.extensions.insert(name.to_string(), value);
+        self
+    }
+}
+
+impl IntoResponse for Problem {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut response = (status, Json(self)).into_response();
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+        response
+    }
+}
+
 pub enum ApiError {
     Auth(AuthError),
+    Batch(BatchError),
+    Concurrency(ConcurrencyError),
+    Filter(FilterError),
+    Rbac(RbacError),
+    User(UserError),
+    Validation(ValidationError),
+    Forbidden,
+    NotFound,
+    PreconditionFailed,
 
     // External Errors
     Argon(Box<dyn std::error::Error>),
     Database(DbErr),
     JsonWebToken(Box<dyn std::error::Error>),
+    Mail(Box<dyn std::error::Error>),
 }
 
 impl From<AuthError> for ApiError {
@@ -21,57 +114,229 @@ impl From<AuthError> for ApiError {
     }
 }
 
+impl From<BatchError> for ApiError {
+    fn from(err: BatchError) -> Self {
+        Self::Batch(err)
+    }
+}
+
+impl From<FilterError> for ApiError {
+    fn from(err: FilterError) -> Self {
+        Self::Filter(err)
+    }
+}
+
+impl From<UserError> for ApiError {
+    fn from(err: UserError) -> Self {
+        Self::User(err)
+    }
+}
+
 impl From<taskrs_core::error::Error> for ApiError {
     fn from(err: taskrs_core::error::Error) -> Self {
         match err {
             Error::Argon(e) => Self::Argon(Box::new(e)),
-            Error::Auth(_) => Self::Auth(AuthError::Credentials),
+            Error::Auth(e) => match e {
+                CoreAuthError::UnknownEmail => Self::Auth(AuthError::User),
+                CoreAuthError::UserDisabled => Self::Auth(AuthError::UserDisabled),
+                CoreAuthError::WrongPassword => Self::Auth(AuthError::WrongPassword),
+                CoreAuthError::EmailTaken => Self::User(UserError::EmailTaken),
+                CoreAuthError::StaleEmailChange => Self::Auth(AuthError::EmailChangeToken),
+            },
+            Error::Concurrency(e) => Self::Concurrency(e),
             Error::Database(e) => Self::Database(e),
+            Error::Filter(e) => Self::Filter(e),
             Error::JsonWebToken(e) => Self::JsonWebToken(Box::new(e)),
+            Error::Rbac(e) => Self::Rbac(e),
+            Error::Validation(e) => Self::Validation(e),
         }
     }
 }
 
-impl IntoResponse for ApiError {
-    fn into_response(self) -> Response {
+impl ApiError {
+    /// Problem details the error is responded with
+    pub fn problem(&self) -> Problem {
         match self {
-            ApiError::Auth(e) => e.into_response(),
-            ApiError::Argon(e) => match cfg!(debug_assertions) {
-                true => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({ "error": format!("Argon error: {}", e) })),
+            ApiError::Auth(e) => e.problem(),
+            ApiError::Batch(e) => e.problem(),
+            ApiError::User(e) => e.problem(),
+            ApiError::Concurrency(e) => {
+                let ConcurrencyError::VersionMismatch { expected, actual } = e;
+                Problem::new(
+                    StatusCode::CONFLICT,
+                    "concurrency.version_mismatch",
+                    "Entity was changed in the meantime",
                 )
-                    .into_response(),
-                false => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Internal Server Error"})),
-                )
-                    .into_response(),
-            },
-            ApiError::Database(e) => match cfg!(debug_assertions) {
-                true => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({ "error": format!("Database error: {}", e) })),
-                )
-                    .into_response(),
-                false => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Internal Server Error"})),
-                )
-                    .into_response(),
-            },
-            ApiError::JsonWebToken(e) => match cfg!(debug_assertions) {
-                true => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({ "error": format!("JsonWebToken error: {}", e) })),
-                )
-                    .into_response(),
-                false => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Internal Server Error"})),
-                )
-                    .into_response(),
-            },
+                .detail(e.to_string())
+                .extension("expectedVersion", expected)
+                .extension("currentVersion", actual)
+            }
+            ApiError::Filter(e) => filter_problem(e).detail(e.to_string()),
+            ApiError::Rbac(e) => {
+                let (code, title) = match e {
+                    RbacError::SystemRole => ("rbac.system_role", "System role can not be changed"),
+                    RbacError::LastRootUser => (
+                        "rbac.last_root_user",
+                        "Last enabled root user can not be removed",
+                    ),
+                };
+                Problem::new(StatusCode::CONFLICT, code, title).detail(e.to_string())
+            }
+            ApiError::Validation(e) => Problem::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "validation.failed",
+                "Invalid fields",
+            )
+            .extension("fields", &e.fields),
+            ApiError::Forbidden => Problem::new(
+                StatusCode::FORBIDDEN,
+                "permission.missing",
+                "Missing permission",
+            ),
+            ApiError::NotFound => Problem::new(StatusCode::NOT_FOUND, "not_found", "Not found"),
+            ApiError::PreconditionFailed => Problem::new(
+                StatusCode::PRECONDITION_FAILED,
+                "concurrency.precondition_failed",
+                "If-Match header does not match a version",
+            ),
+            ApiError::Database(e) if is_unique_violation(e) => {
+                let problem = unique_violation_problem(e);
+                match cfg!(debug_assertions) {
+                    true => problem.detail(format!("Database error: {}", e)),
+                    false => problem,
+                }
+            }
+            ApiError::Argon(e) => Problem::internal(|| format!("Argon error: {}", e)),
+            ApiError::Database(e) => Problem::internal(|| format!("Database error: {}", e)),
+            ApiError::JsonWebToken(e) => Problem::internal(|| format!("JsonWebToken error: {}", e)),
+            ApiError::Mail(e) => Problem::internal(|| format!("Mail error: {}", e)),
         }
     }
 }
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        self.problem().into_response()
+    }
+}
+
+fn filter_problem(err: &FilterError) -> Problem {
+    let (code, title) = match err {
+        FilterError::MalformedParameter(_) => (
+            "filter.malformed_parameter",
+            "Malformed filter or sort parameter",
+        ),
+        FilterError::UnknownOperator(_) => ("filter.unknown_operator", "Unknown filter operator"),
+        FilterError::UnknownFilterField(_) => {
+            ("filter.unknown_filter_field", "Field can not be filtered")
+        }
+        FilterError::UnknownSortField(_) => {
+            ("filter.unknown_sort_field", "Field can not be sorted by")
+        }
+        FilterError::OperatorNotAllowed { .. } => (
+            "filter.operator_not_allowed",
+            "Operator is not allowed for the field",
+        ),
+        FilterError::InvalidValue { .. } => {
+            ("filter.invalid_value", "Value does not fit the field")
+        }
+        FilterError::InvalidCursor => ("filter.invalid_cursor", "Invalid pagination cursor"),
+        FilterError::UnsupportedCursorSort(_) => (
+            "filter.unsupported_cursor_sort",
+            "Field can not order cursor paginated lists",
+        ),
+    };
+    Problem::new(StatusCode::BAD_REQUEST, code, title)
+}
+
+/// Whether the statement failed on a unique constraint or primary key.
+/// The drivers only report it in their messages, which differ by backend.
+fn is_unique_violation(err: &DbErr) -> bool {
+    match err {
+        DbErr::Exec(message) | DbErr::Query(message) => {
+            message.contains("UNIQUE constraint failed")
+                || message.contains("duplicate key value violates unique constraint")
+                || message.contains("Duplicate entry")
+        }
+        _ => false,
+    }
+}
+
+/// Unique constraint of an entity that has its own conflict problem
+struct UniqueConstraint {
+    /// Columns as listed by SQLite, like `users.email`
+    columns: &'static str,
+    /// Names Postgres and MySQL report for the constraint.
+    /// MySQL 8 prefixes the name with the table, older versions only report the name.
+    names: &'static [&'static str],
+    problem: fn() -> Problem,
+}
+
+const UNIQUE_CONSTRAINTS: &[UniqueConstraint] = &[
+    UniqueConstraint {
+        columns: "users.email",
+        names: &["users_email_key", "users.email", "email"],
+        problem: || UserError::EmailTaken.problem(),
+    },
+    UniqueConstraint {
+        columns: "permissions.group, permissions.name",
+        names: &[
+            "idx-permissions-group-name",
+            "permissions.idx-permissions-group-name",
+        ],
+        problem: || {
+            Problem::new(
+                StatusCode::CONFLICT,
+                "permission.name_taken",
+                "Name is already taken in the group",
+            )
+        },
+    },
+    UniqueConstraint {
+        columns: "roles.name",
+        names: &["roles_name_key", "roles.name"],
+        problem: || {
+            Problem::new(
+                StatusCode::CONFLICT,
+                "role.name_taken",
+                "Name is already taken",
+            )
+        },
+    },
+    UniqueConstraint {
+        columns: "groups.name",
+        names: &["groups_name_key", "groups.name"],
+        problem: || {
+            Problem::new(
+                StatusCode::CONFLICT,
+                "group.name_taken",
+                "Name is already taken",
+            )
+        },
+    },
+];
+
+/// Problem of the entity whose unique constraint the statement failed on,
+/// a generic conflict for constraints without their own problem.
+fn unique_violation_problem(err: &DbErr) -> Problem {
+    let message = match err {
+        DbErr::Exec(message) | DbErr::Query(message) => message.as_str(),
+        _ => "",
+    };
+    let violated = |constraint: &&UniqueConstraint| {
+        message.ends_with(&format!("UNIQUE constraint failed: {}", constraint.columns))
+            || constraint.names.iter().any(|name| {
+                message.contains(&format!("\"{}\"", name))
+                    || message.contains(&format!("for key '{}'", name))
+            })
+    };
+
+    match UNIQUE_CONSTRAINTS.iter().find(violated) {
+        Some(constraint) => (constraint.problem)(),
+        None => Problem::new(
+            StatusCode::CONFLICT,
+            "database.unique_violation",
+            "Conflicts with an existing entry",
+        ),
+    }
+}
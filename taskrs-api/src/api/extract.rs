@@ -0,0 +1,117 @@
+use crate::api::error::Problem;
+use axum::async_trait;
+use axum::body::HttpBody;
+use axum::extract::{FromRequest, RequestParts};
+use axum::response::{IntoResponse, Response};
+use axum::BoxError;
+use hyper::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// JSON body like [`axum::Json`], rejected with problem details instead of plain text.
+/// Responds like [`axum::Json`] as well, so controllers use it for both directions.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Json<T>(pub T);
+
+/// Path parameters like [`axum::extract::Path`], rejected with problem details
+#[derive(Clone, Copy, Debug)]
+pub struct Path<T>(pub T);
+
+/// Query parameters like [`axum::extract::Query`], rejected with problem details
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Query<T>(pub T);
+
+#[async_trait]
+impl<T, B> FromRequest<B> for Json<T>
+where
+    T: DeserializeOwned,
+    B: HttpBody + Send,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = Problem;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        axum::Json::from_request(req)
+            .await
+            .map(|axum::Json(value)| Self(value))
+            .map_err(|rejection| {
+                rejection_problem(rejection, "request.invalid_body", "Invalid request body")
+            })
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+#[async_trait]
+impl<T, B> FromRequest<B> for Path<T>
+where
+    T: DeserializeOwned + Send,
+    B: Send,
+{
+    type Rejection = Problem;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        axum::extract::Path::from_request(req)
+            .await
+            .map(|axum::extract::Path(value)| Self(value))
+            .map_err(|rejection| {
+                rejection_problem(rejection, "request.invalid_path", "Invalid path parameters")
+            })
+    }
+}
+
+#[async_trait]
+impl<T, B> FromRequest<B> for Query<T>
+where
+    T: DeserializeOwned,
+    B: Send,
+{
+    type Rejection = Problem;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        axum::extract::Query::from_request(req)
+            .await
+            .map(|axum::extract::Query(value)| Self(value))
+            .map_err(|rejection| {
+                rejection_problem(
+                    rejection,
+                    "request.invalid_query",
+                    "Invalid query parameters",
+                )
+            })
+    }
+}
+
+/// Problem of a rejected extractor with the status chosen by axum.
+/// Server side failures, like an extractor running twice, are internal errors.
+fn rejection_problem(
+    rejection: impl IntoResponse + std::error::Error,
+    code: &'static str,
+    title: &'static str,
+) -> Problem {
+    // The rejections keep the cause, like the field serde failed on, in their sources
+    let mut detail = rejection.to_string();
+    let mut source = rejection.source();
+    while let Some(cause) = source {
+        let cause_detail = cause.to_string();
+        if !detail.ends_with(&cause_detail) {
+            detail = format!("{}: {}", detail, cause_detail);
+        }
+        source = cause.source();
+    }
+    match rejection.into_response().status() {
+        status if status.is_server_error() => Problem::internal(|| detail),
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => Problem::new(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "request.unsupported_media_type",
+            "Unsupported media type",
+        )
+        .detail(detail),
+        status => Problem::new(status, code, title).detail(detail),
+    }
+}
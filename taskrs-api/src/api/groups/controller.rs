@@ -0,0 +1,285 @@
+use crate::api::common::{
+    cursor_link_header, etag_header, if_match_version, CursorQuery, IdList, Page, PaginationQuery,
+};
+use crate::api::error::ApiError;
+use crate::api::extract::{Json, Path, Query};
+use crate::api::requester::Requester;
+use axum::extract::{Extension, OriginalUri};
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Response};
+use std::sync::Arc;
+use taskrs_core::logic::{
+    CreateModelTrait, DeleteModelTrait, FilterableModelTrait, ReadModelTrait, UpdateModelTrait,
+};
+use taskrs_core::models::filter::ListQuery;
+use taskrs_core::models::group::{Group, GroupCreate, GroupUpdate};
+use taskrs_core::models::role::Role;
+use taskrs_core::models::user::User;
+use taskrs_core::permissions::Permission;
+use taskrs_db::sea_orm::DbConn;
+
+#[instrument(name = "list_groups", level = "debug", skip_all)]
+pub async fn list(
+    OriginalUri(uri): OriginalUri,
+    Query(pagination): Query<PaginationQuery>,
+    Query(cursor): Query<CursorQuery>,
+    Query(params): Query<Vec<(String, String)>>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Response, ApiError> {
+    requester
+        .require_permission(Permission::GroupsRead, db.as_ref())
+        .await?;
+
+    let query = ListQuery::from_pairs(params)?;
+    let limit = pagination.limit();
+
+    if cursor.cursor.is_some() {
+        let page =
+            Group::find_by_query_cursor(query, cursor.cursor(), limit, cursor.count, db.as_ref())
+                .await?;
+        return Ok((cursor_link_header(&page, &uri, limit), Json(page)).into_response());
+    }
+
+    let (items, total) = Group::find_by_query(query, pagination.page, limit, db.as_ref()).await?;
+
+    let page = Page {
+        items,
+        total,
+        page: pagination.page,
+        limit,
+    };
+
+    Ok((page.link_header(&uri), Json(page)).into_response())
+}
+
+#[instrument(name = "get_group", level = "debug", skip_all, fields(group_id = id))]
+pub async fn get(
+    Path(id): Path<i32>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(HeaderMap, Json<Group>), ApiError> {
+    requester
+        .require_permission(Permission::GroupsRead, db.as_ref())
+        .await?;
+
+    Group::get(id, db.as_ref())
+        .await?
+        .map(|group| (etag_header(group.version), Json(group)))
+        .ok_or(ApiError::NotFound)
+}
+
+#[instrument(name = "create_group", level = "debug", skip_all)]
+pub async fn create(
+    Json(group): Json<GroupCreate>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<Group>, ApiError> {
+    requester
+        .require_permission(Permission::GroupsCreate, db.as_ref())
+        .await?;
+
+    debug!("Creating group {}", group.name);
+    let group = Group::create(group, db.as_ref()).await?;
+
+    Ok(Json(group))
+}
+
+#[instrument(name = "update_group", level = "debug", skip_all, fields(group_id = id))]
+pub async fn update(
+    Path(id): Path<i32>,
+    Json(mut group): Json<GroupUpdate>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+    // Extracted last, taking the headers would fail the other extractors
+    headers: HeaderMap,
+) -> Result<(HeaderMap, Json<Group>), ApiError> {
+    requester
+        .require_permission(Permission::GroupsUpdate, db.as_ref())
+        .await?;
+
+    if Group::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    group.id = id;
+    if let Some(version) = if_match_version(&headers)? {
+        group.version = Some(version);
+    }
+    let group = Group::update(group, db.as_ref()).await?;
+
+    Ok((etag_header(group.version), Json(group)))
+}
+
+#[instrument(name = "delete_group", level = "debug", skip_all, fields(group_id = id))]
+pub async fn delete(
+    Path(id): Path<i32>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(), ApiError> {
+    requester
+        .require_permission(Permission::GroupsDelete, db.as_ref())
+        .await?;
+
+    if Group::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    Group::delete(id, db.as_ref()).await?;
+
+    Ok(())
+}
+
+#[instrument(name = "list_group_members", level = "debug", skip_all, fields(group_id = id))]
+pub async fn members(
+    Path(id): Path<i32>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<Vec<User>>, ApiError> {
+    requester
+        .require_permission(Permission::GroupsRead, db.as_ref())
+        .await?;
+
+    if Group::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    Ok(Json(Group::members(id, db.as_ref()).await?))
+}
+
+#[instrument(name = "add_group_members", level = "debug", skip_all, fields(group_id = id))]
+pub async fn add_members(
+    Path(id): Path<i32>,
+    Json(user_ids): Json<IdList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(), ApiError> {
+    requester
+        .require_permission(Permission::GroupsManageMembers, db.as_ref())
+        .await?;
+
+    if Group::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    Group::add_members(id, user_ids.ids, db.as_ref()).await?;
+
+    Ok(())
+}
+
+#[instrument(name = "remove_group_members", level = "debug", skip_all, fields(group_id = id))]
+pub async fn remove_members(
+    Path(id): Path<i32>,
+    Json(user_ids): Json<IdList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(), ApiError> {
+    requester
+        .require_permission(Permission::GroupsManageMembers, db.as_ref())
+        .await?;
+
+    if Group::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    Group::remove_members(id, user_ids.ids, db.as_ref()).await?;
+
+    Ok(())
+}
+
+#[instrument(name = "set_group_members", level = "debug", skip_all, fields(group_id = id))]
+pub async fn set_members(
+    Path(id): Path<i32>,
+    Json(user_ids): Json<IdList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(), ApiError> {
+    requester
+        .require_permission(Permission::GroupsManageMembers, db.as_ref())
+        .await?;
+
+    if Group::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    Group::set_members(id, user_ids.ids, db.as_ref()).await?;
+
+    Ok(())
+}
+
+#[instrument(name = "list_group_roles", level = "debug", skip_all, fields(group_id = id))]
+pub async fn roles(
+    Path(id): Path<i32>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<Vec<Role>>, ApiError> {
+    requester
+        .require_permission(Permission::GroupsRead, db.as_ref())
+        .await?;
+
+    if Group::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    Ok(Json(Group::roles(id, db.as_ref()).await?))
+}
+
+#[instrument(name = "grant_group_roles", level = "debug", skip_all, fields(group_id = id))]
+pub async fn grant_roles(
+    Path(id): Path<i32>,
+    Json(role_ids): Json<IdList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(), ApiError> {
+    requester
+        .require_permission(Permission::GroupsManageRoles, db.as_ref())
+        .await?;
+
+    if Group::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    Group::grant_roles(id, role_ids.ids, db.as_ref()).await?;
+
+    Ok(())
+}
+
+#[instrument(name = "revoke_group_roles", level = "debug", skip_all, fields(group_id = id))]
+pub async fn revoke_roles(
+    Path(id): Path<i32>,
+    Json(role_ids): Json<IdList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(), ApiError> {
+    requester
+        .require_permission(Permission::GroupsManageRoles, db.as_ref())
+        .await?;
+
+    if Group::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    Group::revoke_roles(id, role_ids.ids, db.as_ref()).await?;
+
+    Ok(())
+}
+
+#[instrument(name = "set_group_roles", level = "debug", skip_all, fields(group_id = id))]
+pub async fn set_roles(
+    Path(id): Path<i32>,
+    Json(role_ids): Json<IdList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(), ApiError> {
+    requester
+        .require_permission(Permission::GroupsManageRoles, db.as_ref())
+        .await?;
+
+    if Group::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    Group::set_roles(id, role_ids.ids, db.as_ref()).await?;
+
+    Ok(())
+}
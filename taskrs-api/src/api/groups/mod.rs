@@ -0,0 +1,29 @@
+mod controller;
+
+use axum::routing::get;
+use axum::Router;
+
+pub fn get_router() -> Router {
+    Router::new()
+        .route("/", get(controller::list).post(controller::create))
+        .route(
+            "/:id",
+            get(controller::get)
+                .patch(controller::update)
+                .delete(controller::delete),
+        )
+        .route(
+            "/:id/members",
+            get(controller::members)
+                .post(controller::add_members)
+                .put(controller::set_members)
+                .delete(controller::remove_members),
+        )
+        .route(
+            "/:id/roles",
+            get(controller::roles)
+                .post(controller::grant_roles)
+                .put(controller::set_roles)
+                .delete(controller::revoke_roles),
+        )
+}
@@ -0,0 +1,147 @@
+use crate::api::auth::error::AuthError;
+use crate::api::auth::REFRESH_TOKEN_COOKIE;
+use crate::api::error::ApiError;
+use crate::api::extract::{Json, Query};
+use crate::api::requester::Requester;
+use crate::application::ApplicationState;
+use axum::extract::Extension;
+use hyper::StatusCode;
+use serde::Serialize;
+use std::sync::Arc;
+use taskrs_core::logic::validation::Validate;
+use taskrs_core::logic::{ReadModelTrait, UpdateModelTrait};
+use taskrs_core::models::auth::{EmailChange, EmailChangeTokenData, PasswordChange, Token};
+use taskrs_core::models::permission::Permission;
+use taskrs_core::models::role::AssignedRole;
+use taskrs_core::models::user::{ProfileUpdate, User};
+use taskrs_db::sea_orm::DbConn;
+use tower_cookies::Cookies;
+
+/// The logged in user with everything the UI needs to know about them
+#[derive(Clone, Debug, Serialize)]
+pub struct Me {
+    #[serde(flatten)]
+    pub user: User,
+    pub roles: Vec<AssignedRole>,
+    pub permissions: Vec<Permission>,
+}
+
+#[instrument(name = "get_me", level = "debug", skip_all)]
+pub async fn get(
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<Me>, ApiError> {
+    let user = User::get(requester.id, db.as_ref())
+        .await?
+        .ok_or(AuthError::User)?;
+
+    Ok(Json(Me {
+        roles: User::roles(user.id, db.as_ref()).await?,
+        permissions: User::permissions(user.id, db.as_ref()).await?,
+        user,
+    }))
+}
+
+#[instrument(name = "update_me", level = "debug", skip_all)]
+pub async fn update(
+    Json(profile): Json<ProfileUpdate>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<User>, ApiError> {
+    let user = User::update(profile.into_user_update(requester.id), db.as_ref()).await?;
+
+    Ok(Json(user))
+}
+
+#[instrument(name = "change_password", level = "debug", skip_all)]
+pub async fn change_password(
+    Json(password_change): Json<PasswordChange>,
+    requester: Requester,
+    cookies: Cookies,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(), ApiError> {
+    // Keep the session of this request
+    let refresh_token = cookies
+        .get(REFRESH_TOKEN_COOKIE)
+        .map(|cookie| cookie.value().to_string());
+
+    let revoked = password_change
+        .change(requester.id, refresh_token, db.as_ref())
+        .await?;
+    debug!("Revoked {} other sessions", revoked);
+
+    Ok(())
+}
+
+#[instrument(name = "request_email_change", level = "debug", skip_all)]
+pub async fn request_email_change(
+    Json(email_change): Json<EmailChange>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+    Extension(state): Extension<ApplicationState>,
+) -> Result<StatusCode, ApiError> {
+    let email = email_change.email.trim().to_string();
+
+    let user = User::get(requester.id, db.as_ref())
+        .await?
+        .ok_or(AuthError::User)?;
+
+    debug!("Validate new email");
+    EmailChange {
+        email: email.clone(),
+    }
+    .validate(db.as_ref())
+    .await?;
+
+    debug!("Generate email change token");
+    let config = &state.config;
+    let token_data = EmailChangeTokenData::new(
+        user.id,
+        user.email,
+        email.clone(),
+        config.authentication.email_change_expiration_time,
+    );
+    let token = taskrs_core::logic::auth::encode_token(
+        &token_data,
+        config.authentication.email_change_secret.as_bytes(),
+    )
+    .map_err(|e| ApiError::JsonWebToken(Box::new(e)))?;
+
+    debug!("Send confirmation link to new email");
+    let link = format!(
+        "{}/api/me/email/confirm?token={}",
+        config.mail.public_url.trim_end_matches('/'),
+        token
+    );
+    state
+        .mailer
+        .send(
+            &email,
+            "Confirm your new email address",
+            format!(
+                "Open the following link to use this address for your taskrs account:\n\n{}\n",
+                link
+            ),
+        )
+        .await
+        .map_err(ApiError::Mail)?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[instrument(name = "confirm_email_change", level = "debug", skip_all)]
+pub async fn confirm_email_change(
+    Query(token): Query<Token>,
+    Extension(db): Extension<Arc<DbConn>>,
+    Extension(state): Extension<ApplicationState>,
+) -> Result<Json<User>, ApiError> {
+    let (token_data_result, _) = taskrs_core::logic::auth::validate_token::<EmailChangeTokenData>(
+        &token.token,
+        state.config.authentication.email_change_secret.as_bytes(),
+    );
+    let token_data = token_data_result.map_err(|_| AuthError::EmailChangeToken)?;
+
+    let user = token_data.claims.confirm(db.as_ref()).await?;
+
+    Ok(Json(user))
+}
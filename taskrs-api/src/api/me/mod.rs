@@ -0,0 +1,12 @@
+mod controller;
+
+use axum::routing::{get, post};
+use axum::Router;
+
+pub fn get_router() -> Router {
+    Router::new()
+        .route("/", get(controller::get).patch(controller::update))
+        .route("/password", post(controller::change_password))
+        .route("/email", post(controller::request_email_change))
+        .route("/email/confirm", get(controller::confirm_email_change))
+}
@@ -1,6 +1,16 @@
-mod auth;
+mod audit;
+pub mod auth;
+mod batch;
+mod common;
 pub mod error;
+mod extract;
+mod groups;
+mod me;
+mod permissions;
 mod requester;
+mod roles;
+mod search;
+mod users;
 
 use axum::routing::get;
 use axum::Router;
@@ -8,7 +18,15 @@ use axum::Router;
 pub fn get_api_router() -> Router {
     Router::new()
         .route("/status", get(status))
+        .nest("/audit", audit::get_router())
         .nest("/auth", auth::get_router())
+        .nest("/batch", batch::get_router())
+        .nest("/groups", groups::get_router())
+        .nest("/me", me::get_router())
+        .nest("/permissions", permissions::get_router())
+        .nest("/roles", roles::get_router())
+        .nest("/search", search::get_router())
+        .nest("/users", users::get_router())
 }
 
 async fn status() -> &'static str {
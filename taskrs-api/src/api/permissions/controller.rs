@@ -0,0 +1,20 @@
+use crate::api::error::ApiError;
+use crate::api::extract::Json;
+use crate::api::requester::Requester;
+use axum::extract::Extension;
+use std::sync::Arc;
+use taskrs_core::models::permission::{Permission as PermissionModel, PermissionGroup};
+use taskrs_core::permissions::Permission;
+use taskrs_db::sea_orm::DbConn;
+
+#[instrument(name = "list_permissions", level = "debug", skip_all)]
+pub async fn list(
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<Vec<PermissionGroup>>, ApiError> {
+    requester
+        .require_permission(Permission::PermissionsRead, db.as_ref())
+        .await?;
+
+    Ok(Json(PermissionModel::all_grouped(db.as_ref()).await?))
+}
@@ -0,0 +1,8 @@
+mod controller;
+
+use axum::routing::get;
+use axum::Router;
+
+pub fn get_router() -> Router {
+    Router::new().route("/", get(controller::list))
+}
@@ -8,9 +8,11 @@ use axum::response::{IntoResponse, Response};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use taskrs_core::logic::ReadModelTrait;
+use taskrs_core::models::audit_event::AuditContext;
 use taskrs_core::models::auth::{AccessTokenData, RefreshTokenData};
 use taskrs_core::models::refresh_token::RefreshToken;
 use taskrs_core::models::user::User;
+use taskrs_core::permissions::Permission;
 use taskrs_db::sea_orm::sea_query::IntoCondition;
 use taskrs_db::sea_orm::{ColumnTrait, DbConn};
 use time::Duration;
@@ -32,6 +34,27 @@ impl From<User> for Requester {
     }
 }
 
+impl Requester {
+    /// Fails with `ApiError::Forbidden` if the permission is not effective for the requester
+    pub async fn require_permission(
+        &self,
+        permission: Permission,
+        db: &DbConn,
+    ) -> Result<(), ApiError> {
+        match User::has_permission(self.id, permission, db).await? {
+            true => Ok(()),
+            false => {
+                debug!(
+                    "Requester is missing permission {}.{}",
+                    permission.group(),
+                    permission.name()
+                );
+                Err(ApiError::Forbidden)
+            }
+        }
+    }
+}
+
 #[async_trait]
 impl<B> FromRequest<B> for Requester
 where
@@ -72,8 +95,9 @@ where
         if let Ok(token_data) = token_data_result {
             debug!("Valid access token");
             let requester: Requester = token_data.claims.user.into();
-            tracing::Span::current().record("requester_id", &field::display(requester.id));
-            tracing::Span::current().record("requester_email", &field::display(&requester.email));
+            tracing::Span::current().record("requester_id", field::display(requester.id));
+            tracing::Span::current().record("requester_email", field::display(&requester.email));
+            AuditContext::set_actor(requester.id);
             return Ok(requester);
         }
 
@@ -98,7 +122,7 @@ where
                 db.as_ref(),
             )
             .await
-            .map_err(|e| ApiError::Database(e).into_response())?
+            .map_err(|e| ApiError::from(e).into_response())?
             .ok_or_else(|| AuthError::RefreshToken.into_response())?;
 
             trace!("Validate refresh token");
@@ -115,11 +139,11 @@ where
             trace!("Get user from database");
             let user = User::get(user_id, db.as_ref())
                 .await
-                .map_err(|e| ApiError::Database(e).into_response())?
+                .map_err(|e| ApiError::from(e).into_response())?
                 .ok_or_else(|| AuthError::User.into_response())?;
 
-            tracing::Span::current().record("requester_id", &field::display(user.id));
-            tracing::Span::current().record("requester_email", &field::display(&user.email));
+            tracing::Span::current().record("requester_id", field::display(user.id));
+            tracing::Span::current().record("requester_email", field::display(&user.email));
 
             trace!("Check if user is enabled");
             if !user.enabled {
@@ -147,6 +171,7 @@ where
             );
 
             // Return requester
+            AuditContext::set_actor(user.id);
             return Ok(user.into());
         }
 
@@ -0,0 +1,440 @@
+use crate::api::common::{
+    cursor_link_header, etag_header, if_match_version, CursorQuery, IdList, IgnoredIds, Page,
+    PaginationQuery,
+};
+use crate::api::error::ApiError;
+use crate::api::extract::{Json, Path, Query};
+use crate::api::requester::Requester;
+use axum::extract::{Extension, OriginalUri};
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use std::sync::Arc;
+use taskrs_core::logic::{
+    events, partition_ids, CreateModelTrait, DeleteModelTrait, FilterableModelTrait,
+    ReadModelTrait, UpdateModelTrait,
+};
+use taskrs_core::models::filter::ListQuery;
+use taskrs_core::models::permission::Permission as PermissionModel;
+use taskrs_core::models::role::{Role, RoleCreate, RoleUpdate};
+use taskrs_core::models::user::User;
+use taskrs_core::permissions::Permission;
+use taskrs_db::models::{permission, user};
+use taskrs_db::sea_orm::DbConn;
+
+/// Permission rules of a role
+#[derive(Clone, Debug, Serialize)]
+pub struct RolePermissions {
+    pub allowed: Vec<PermissionModel>,
+    pub denied: Vec<PermissionModel>,
+}
+
+#[instrument(name = "list_roles", level = "debug", skip_all)]
+pub async fn list(
+    OriginalUri(uri): OriginalUri,
+    Query(pagination): Query<PaginationQuery>,
+    Query(cursor): Query<CursorQuery>,
+    Query(params): Query<Vec<(String, String)>>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Response, ApiError> {
+    requester
+        .require_permission(Permission::RolesRead, db.as_ref())
+        .await?;
+
+    let query = ListQuery::from_pairs(params)?;
+    let limit = pagination.limit();
+
+    if cursor.cursor.is_some() {
+        let page =
+            Role::find_by_query_cursor(query, cursor.cursor(), limit, cursor.count, db.as_ref())
+                .await?;
+        return Ok((cursor_link_header(&page, &uri, limit), Json(page)).into_response());
+    }
+
+    let (items, total) = Role::find_by_query(query, pagination.page, limit, db.as_ref()).await?;
+
+    let page = Page {
+        items,
+        total,
+        page: pagination.page,
+        limit,
+    };
+
+    Ok((page.link_header(&uri), Json(page)).into_response())
+}
+
+#[instrument(name = "get_role", level = "debug", skip_all, fields(role_id = id))]
+pub async fn get(
+    Path(id): Path<i32>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(HeaderMap, Json<Role>), ApiError> {
+    requester
+        .require_permission(Permission::RolesRead, db.as_ref())
+        .await?;
+
+    Role::get(id, db.as_ref())
+        .await?
+        .map(|role| (etag_header(role.version), Json(role)))
+        .ok_or(ApiError::NotFound)
+}
+
+#[instrument(name = "create_role", level = "debug", skip_all)]
+pub async fn create(
+    Json(role): Json<RoleCreate>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<Role>, ApiError> {
+    requester
+        .require_permission(Permission::RolesCreate, db.as_ref())
+        .await?;
+
+    debug!("Creating role {}", role.name);
+    let role = Role::create(role, db.as_ref()).await?;
+
+    Ok(Json(role))
+}
+
+#[instrument(name = "update_role", level = "debug", skip_all, fields(role_id = id))]
+pub async fn update(
+    Path(id): Path<i32>,
+    Json(mut role): Json<RoleUpdate>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+    // Extracted last, taking the headers would fail the other extractors
+    headers: HeaderMap,
+) -> Result<(HeaderMap, Json<Role>), ApiError> {
+    requester
+        .require_permission(Permission::RolesUpdate, db.as_ref())
+        .await?;
+
+    if Role::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    role.id = id;
+    if let Some(version) = if_match_version(&headers)? {
+        role.version = Some(version);
+    }
+    let role = Role::update(role, db.as_ref()).await?;
+
+    Ok((etag_header(role.version), Json(role)))
+}
+
+#[instrument(name = "delete_role", level = "debug", skip_all, fields(role_id = id))]
+pub async fn delete(
+    Path(id): Path<i32>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(), ApiError> {
+    requester
+        .require_permission(Permission::RolesDelete, db.as_ref())
+        .await?;
+
+    if Role::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    Role::delete(id, db.as_ref()).await?;
+
+    Ok(())
+}
+
+#[instrument(name = "list_role_permissions", level = "debug", skip_all, fields(role_id = id))]
+pub async fn permissions(
+    Path(id): Path<i32>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<RolePermissions>, ApiError> {
+    requester
+        .require_permission(Permission::RolesRead, db.as_ref())
+        .await?;
+
+    if Role::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    Ok(Json(RolePermissions {
+        allowed: Role::permissions(id, db.as_ref()).await?,
+        denied: Role::denied_permissions(id, db.as_ref()).await?,
+    }))
+}
+
+#[instrument(name = "list_role_users", level = "debug", skip_all, fields(role_id = id))]
+pub async fn users(
+    Path(id): Path<i32>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<Vec<User>>, ApiError> {
+    requester
+        .require_permission(Permission::RolesRead, db.as_ref())
+        .await?;
+
+    if Role::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    Ok(Json(Role::users(id, db.as_ref()).await?))
+}
+
+#[instrument(name = "grant_role_permissions", level = "debug", skip_all, fields(role_id = id))]
+pub async fn grant_permissions(
+    Path(id): Path<i32>,
+    Json(permission_ids): Json<IdList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<IgnoredIds>, ApiError> {
+    requester
+        .require_permission(Permission::RolesManagePermissions, db.as_ref())
+        .await?;
+
+    if Role::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    // Transaction
+    let ignored = events::transaction(db.as_ref(), |txn| {
+        Box::pin(async move {
+            let (permission_ids, ignored) = partition_ids::<permission::Entity, _>(
+                permission::Column::Id,
+                permission_ids.ids,
+                txn,
+            )
+            .await?;
+
+            Role::grant_permissions(id, permission_ids, txn).await?;
+
+            Ok(ignored)
+        })
+    })
+    .await?;
+
+    if !ignored.is_empty() {
+        debug!("Ignored unknown ids {:?}", ignored);
+    }
+
+    Ok(Json(IgnoredIds { ignored }))
+}
+
+#[instrument(name = "deny_role_permissions", level = "debug", skip_all, fields(role_id = id))]
+pub async fn deny_permissions(
+    Path(id): Path<i32>,
+    Json(permission_ids): Json<IdList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<IgnoredIds>, ApiError> {
+    requester
+        .require_permission(Permission::RolesManagePermissions, db.as_ref())
+        .await?;
+
+    if Role::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    // Transaction
+    let ignored = events::transaction(db.as_ref(), |txn| {
+        Box::pin(async move {
+            let (permission_ids, ignored) = partition_ids::<permission::Entity, _>(
+                permission::Column::Id,
+                permission_ids.ids,
+                txn,
+            )
+            .await?;
+
+            Role::deny_permissions(id, permission_ids, txn).await?;
+
+            Ok(ignored)
+        })
+    })
+    .await?;
+
+    if !ignored.is_empty() {
+        debug!("Ignored unknown ids {:?}", ignored);
+    }
+
+    Ok(Json(IgnoredIds { ignored }))
+}
+
+#[instrument(name = "revoke_role_permissions", level = "debug", skip_all, fields(role_id = id))]
+pub async fn revoke_permissions(
+    Path(id): Path<i32>,
+    Json(permission_ids): Json<IdList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<IgnoredIds>, ApiError> {
+    requester
+        .require_permission(Permission::RolesManagePermissions, db.as_ref())
+        .await?;
+
+    if Role::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    // Transaction
+    let ignored = events::transaction(db.as_ref(), |txn| {
+        Box::pin(async move {
+            let (permission_ids, ignored) = partition_ids::<permission::Entity, _>(
+                permission::Column::Id,
+                permission_ids.ids,
+                txn,
+            )
+            .await?;
+
+            Role::revoke_permissions(id, permission_ids, txn).await?;
+
+            Ok(ignored)
+        })
+    })
+    .await?;
+
+    if !ignored.is_empty() {
+        debug!("Ignored unknown ids {:?}", ignored);
+    }
+
+    Ok(Json(IgnoredIds { ignored }))
+}
+
+#[instrument(name = "set_role_permissions", level = "debug", skip_all, fields(role_id = id))]
+pub async fn set_permissions(
+    Path(id): Path<i32>,
+    Json(permission_ids): Json<IdList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<IgnoredIds>, ApiError> {
+    requester
+        .require_permission(Permission::RolesManagePermissions, db.as_ref())
+        .await?;
+
+    if Role::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    // Transaction
+    let ignored = events::transaction(db.as_ref(), |txn| {
+        Box::pin(async move {
+            let (permission_ids, ignored) = partition_ids::<permission::Entity, _>(
+                permission::Column::Id,
+                permission_ids.ids,
+                txn,
+            )
+            .await?;
+
+            Role::set_permissions(id, permission_ids, txn).await?;
+
+            Ok(ignored)
+        })
+    })
+    .await?;
+
+    if !ignored.is_empty() {
+        debug!("Ignored unknown ids {:?}", ignored);
+    }
+
+    Ok(Json(IgnoredIds { ignored }))
+}
+
+#[instrument(name = "add_role_users", level = "debug", skip_all, fields(role_id = id))]
+pub async fn add_users(
+    Path(id): Path<i32>,
+    Json(user_ids): Json<IdList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<IgnoredIds>, ApiError> {
+    requester
+        .require_permission(Permission::RolesManageUsers, db.as_ref())
+        .await?;
+
+    if Role::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    // Transaction
+    let ignored = events::transaction(db.as_ref(), |txn| {
+        Box::pin(async move {
+            let (user_ids, ignored) =
+                partition_ids::<user::Entity, _>(user::Column::Id, user_ids.ids, txn).await?;
+
+            Role::add_users(id, user_ids, txn).await?;
+
+            Ok(ignored)
+        })
+    })
+    .await?;
+
+    if !ignored.is_empty() {
+        debug!("Ignored unknown ids {:?}", ignored);
+    }
+
+    Ok(Json(IgnoredIds { ignored }))
+}
+
+#[instrument(name = "remove_role_users", level = "debug", skip_all, fields(role_id = id))]
+pub async fn remove_users(
+    Path(id): Path<i32>,
+    Json(user_ids): Json<IdList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<IgnoredIds>, ApiError> {
+    requester
+        .require_permission(Permission::RolesManageUsers, db.as_ref())
+        .await?;
+
+    if Role::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    // Transaction
+    let ignored = events::transaction(db.as_ref(), |txn| {
+        Box::pin(async move {
+            let (user_ids, ignored) =
+                partition_ids::<user::Entity, _>(user::Column::Id, user_ids.ids, txn).await?;
+
+            Role::remove_users(id, user_ids, txn).await?;
+
+            Ok(ignored)
+        })
+    })
+    .await?;
+
+    if !ignored.is_empty() {
+        debug!("Ignored unknown ids {:?}", ignored);
+    }
+
+    Ok(Json(IgnoredIds { ignored }))
+}
+
+#[instrument(name = "set_role_users", level = "debug", skip_all, fields(role_id = id))]
+pub async fn set_users(
+    Path(id): Path<i32>,
+    Json(user_ids): Json<IdList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<IgnoredIds>, ApiError> {
+    requester
+        .require_permission(Permission::RolesManageUsers, db.as_ref())
+        .await?;
+
+    if Role::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    // Transaction
+    let ignored = events::transaction(db.as_ref(), |txn| {
+        Box::pin(async move {
+            let (user_ids, ignored) =
+                partition_ids::<user::Entity, _>(user::Column::Id, user_ids.ids, txn).await?;
+
+            Role::set_users(id, user_ids, txn).await?;
+
+            Ok(ignored)
+        })
+    })
+    .await?;
+
+    if !ignored.is_empty() {
+        debug!("Ignored unknown ids {:?}", ignored);
+    }
+
+    Ok(Json(IgnoredIds { ignored }))
+}
@@ -0,0 +1,30 @@
+mod controller;
+
+use axum::routing::{get, post};
+use axum::Router;
+
+pub fn get_router() -> Router {
+    Router::new()
+        .route("/", get(controller::list).post(controller::create))
+        .route(
+            "/:id",
+            get(controller::get)
+                .patch(controller::update)
+                .delete(controller::delete),
+        )
+        .route(
+            "/:id/permissions",
+            get(controller::permissions)
+                .post(controller::grant_permissions)
+                .put(controller::set_permissions)
+                .delete(controller::revoke_permissions),
+        )
+        .route("/:id/permissions/deny", post(controller::deny_permissions))
+        .route(
+            "/:id/users",
+            get(controller::users)
+                .post(controller::add_users)
+                .put(controller::set_users)
+                .delete(controller::remove_users),
+        )
+}
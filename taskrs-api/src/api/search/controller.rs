@@ -0,0 +1,71 @@
+use crate::api::error::ApiError;
+use crate::api::extract::{Json, Query};
+use crate::api::requester::Requester;
+use axum::extract::Extension;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use taskrs_core::models::search::SearchHit;
+use taskrs_core::models::user::User;
+use taskrs_core::permissions::Permission;
+use taskrs_db::sea_orm::DbConn;
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+
+/// Query parameters of a search
+#[derive(Clone, Debug, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+fn default_limit() -> usize {
+    DEFAULT_LIMIT
+}
+
+/// Search hit tagged with the kind of the found entity
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Hit {
+    User(SearchHit<User>),
+}
+
+impl Hit {
+    fn rank(&self) -> f64 {
+        match self {
+            Self::User(hit) => hit.rank,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SearchResults {
+    pub hits: Vec<Hit>,
+}
+
+/// Searches all entities the requester is allowed to read.
+/// Kinds without read permission are left out instead of failing the request.
+///
+/// Only users are indexed so far. Whether roles and groups are searched as well is not agreed on yet,
+/// each of them would need an index migration per backend and a variant of [`Hit`].
+#[instrument(name = "search", level = "debug", skip_all)]
+pub async fn search(
+    Query(query): Query<SearchQuery>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<SearchResults>, ApiError> {
+    let limit = query.limit.clamp(1, MAX_LIMIT);
+    let mut hits = vec![];
+
+    if User::has_permission(requester.id, Permission::UsersRead, db.as_ref()).await? {
+        debug!("Search users");
+        let users = User::search(&query.q, limit, db.as_ref()).await?;
+        hits.extend(users.into_iter().map(Hit::User));
+    }
+
+    hits.sort_by(|a, b| b.rank().total_cmp(&a.rank()));
+    hits.truncate(limit);
+
+    Ok(Json(SearchResults { hits }))
+}
@@ -0,0 +1,483 @@
+use crate::api::common::{
+    cursor_link_header, etag_header, if_match_version, CursorQuery, GrantList, IdList, Page,
+    PaginationQuery,
+};
+use crate::api::error::ApiError;
+use crate::api::extract::{Json, Path, Query};
+use crate::api::requester::Requester;
+use crate::application::ApplicationState;
+use axum::extract::{Extension, OriginalUri};
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use taskrs_core::logic::validation::Validate;
+use taskrs_core::logic::{
+    CreateModelTrait, DeleteModelTrait, FilterableModelTrait, ReadModelTrait, UpdateModelTrait,
+};
+use taskrs_core::models::filter::ListQuery;
+use taskrs_core::models::permission::Permission as PermissionModel;
+use taskrs_core::models::role::AssignedRole;
+use taskrs_core::models::user::{User, UserCreate, UserUpdate};
+use taskrs_core::models::user_permission::UserPermission;
+use taskrs_core::permissions::Permission;
+use taskrs_db::sea_orm::sea_query::IntoCondition;
+use taskrs_db::sea_orm::{ColumnTrait, ConnectionTrait, DbConn};
+
+/// Query parameters of the user list
+#[derive(Clone, Debug, Deserialize)]
+pub struct DeletedQuery {
+    /// Include soft deleted users
+    #[serde(default)]
+    pub with_deleted: bool,
+}
+
+/// Response of purging deleted users
+#[derive(Clone, Debug, Serialize)]
+pub struct Purged {
+    pub purged: u64,
+}
+
+#[instrument(name = "list_users", level = "debug", skip_all)]
+pub async fn list(
+    OriginalUri(uri): OriginalUri,
+    Query(pagination): Query<PaginationQuery>,
+    Query(deleted): Query<DeletedQuery>,
+    Query(cursor): Query<CursorQuery>,
+    Query(params): Query<Vec<(String, String)>>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Response, ApiError> {
+    requester
+        .require_permission(Permission::UsersRead, db.as_ref())
+        .await?;
+
+    let query = ListQuery::from_pairs(params)?;
+    let limit = pagination.limit();
+
+    if cursor.cursor.is_some() {
+        let db = db.as_ref();
+        let page = if deleted.with_deleted {
+            User::find_by_query_cursor_with_deleted(query, cursor.cursor(), limit, cursor.count, db)
+                .await?
+        } else {
+            User::find_by_query_cursor(query, cursor.cursor(), limit, cursor.count, db).await?
+        };
+        return Ok((cursor_link_header(&page, &uri, limit), Json(page)).into_response());
+    }
+
+    let (items, total) = if deleted.with_deleted {
+        User::find_by_query_with_deleted(query, pagination.page, limit, db.as_ref()).await?
+    } else {
+        User::find_by_query(query, pagination.page, limit, db.as_ref()).await?
+    };
+
+    let page = Page {
+        items,
+        total,
+        page: pagination.page,
+        limit,
+    };
+
+    Ok((page.link_header(&uri), Json(page)).into_response())
+}
+
+#[instrument(name = "get_user", level = "debug", skip_all, fields(user_id = id))]
+pub async fn get(
+    Path(id): Path<i32>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(HeaderMap, Json<User>), ApiError> {
+    requester
+        .require_permission(Permission::UsersRead, db.as_ref())
+        .await?;
+
+    User::get(id, db.as_ref())
+        .await?
+        .map(|user| (etag_header(user.version), Json(user)))
+        .ok_or(ApiError::NotFound)
+}
+
+#[instrument(name = "create_user", level = "debug", skip_all, fields(email = %user.email))]
+pub async fn create(
+    Json(user): Json<UserCreate>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<User>, ApiError> {
+    requester
+        .require_permission(Permission::UsersCreate, db.as_ref())
+        .await?;
+
+    Ok(Json(create_user(user, db.as_ref()).await?))
+}
+
+/// Validates a new user, hashes their password and creates them
+pub(crate) async fn create_user<'a, C>(user: UserCreate, db: &'a C) -> Result<User, ApiError>
+where
+    C: ConnectionTrait<'a>,
+{
+    // Validated before hashing, the password can not be checked afterwards
+    debug!("Validate user");
+    user.validate(db).await?;
+
+    debug!("Hash password");
+    let user = user
+        .hash_password()
+        .map_err(|e| ApiError::Argon(Box::new(e)))?;
+
+    Ok(User::create(user, db).await?)
+}
+
+#[instrument(name = "update_user", level = "debug", skip_all, fields(user_id = id))]
+pub async fn update(
+    Path(id): Path<i32>,
+    Json(mut user): Json<UserUpdate>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+    // Extracted last, taking the headers would fail the other extractors
+    headers: HeaderMap,
+) -> Result<(HeaderMap, Json<User>), ApiError> {
+    requester
+        .require_permission(Permission::UsersUpdate, db.as_ref())
+        .await?;
+
+    user.id = id;
+    if let Some(version) = if_match_version(&headers)? {
+        user.version = Some(version);
+    }
+    let user = update_user(user, db.as_ref()).await?;
+
+    Ok((etag_header(user.version), Json(user)))
+}
+
+/// Validates the changes of a user, hashes a new password and updates them
+pub(crate) async fn update_user<'a, C>(user: UserUpdate, db: &'a C) -> Result<User, ApiError>
+where
+    C: ConnectionTrait<'a>,
+{
+    User::get(user.id, db).await?.ok_or(ApiError::NotFound)?;
+
+    // Validated before hashing, the password can not be checked afterwards
+    debug!("Validate changes");
+    user.validate(db).await?;
+
+    debug!("Hash password");
+    let user = user
+        .hash_password()
+        .map_err(|e| ApiError::Argon(Box::new(e)))?;
+
+    Ok(User::update(user, db).await?)
+}
+
+#[instrument(name = "delete_user", level = "debug", skip_all, fields(user_id = id))]
+pub async fn delete(
+    Path(id): Path<i32>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(), ApiError> {
+    requester
+        .require_permission(Permission::UsersDelete, db.as_ref())
+        .await?;
+
+    if User::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    User::delete(id, db.as_ref()).await?;
+
+    Ok(())
+}
+
+#[instrument(name = "restore_user", level = "debug", skip_all, fields(user_id = id))]
+pub async fn restore(
+    Path(id): Path<i32>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<User>, ApiError> {
+    requester
+        .require_permission(Permission::UsersRestore, db.as_ref())
+        .await?;
+
+    User::restore(id, db.as_ref())
+        .await?
+        .map(Json)
+        .ok_or(ApiError::NotFound)
+}
+
+#[instrument(name = "purge_users", level = "debug", skip_all)]
+pub async fn purge(
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+    Extension(state): Extension<ApplicationState>,
+) -> Result<Json<Purged>, ApiError> {
+    requester
+        .require_permission(Permission::UsersPurge, db.as_ref())
+        .await?;
+
+    let retention = chrono::Duration::seconds(state.config.retention.deleted_users as i64);
+    let purged = User::purge_deleted(retention, db.as_ref()).await?;
+
+    Ok(Json(Purged { purged }))
+}
+
+#[instrument(name = "enable_user", level = "debug", skip_all, fields(user_id = id))]
+pub async fn enable(
+    Path(id): Path<i32>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+    // Extracted last, taking the headers would fail the other extractors
+    headers: HeaderMap,
+) -> Result<(HeaderMap, Json<User>), ApiError> {
+    let version = if_match_version(&headers)?;
+    set_enabled(id, true, version, requester, db.as_ref()).await
+}
+
+#[instrument(name = "disable_user", level = "debug", skip_all, fields(user_id = id))]
+pub async fn disable(
+    Path(id): Path<i32>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+    // Extracted last, taking the headers would fail the other extractors
+    headers: HeaderMap,
+) -> Result<(HeaderMap, Json<User>), ApiError> {
+    let version = if_match_version(&headers)?;
+    set_enabled(id, false, version, requester, db.as_ref()).await
+}
+
+/// Enables or disables the user like an update of only `enabled`.
+/// An `If-Match` header is optional as for [`update`], but checked against the version if sent,
+/// so a client acting on a stale user gets a version conflict instead of a silent overwrite.
+async fn set_enabled(
+    id: i32,
+    enabled: bool,
+    version: Option<i32>,
+    requester: Requester,
+    db: &DbConn,
+) -> Result<(HeaderMap, Json<User>), ApiError> {
+    requester
+        .require_permission(Permission::UsersUpdate, db)
+        .await?;
+
+    if User::get(id, db).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    let user = User::update(
+        UserUpdate {
+            id,
+            enabled: Some(enabled),
+            version,
+            ..Default::default()
+        },
+        db,
+    )
+    .await?;
+
+    Ok((etag_header(user.version), Json(user)))
+}
+
+#[instrument(name = "list_user_roles", level = "debug", skip_all, fields(user_id = id))]
+pub async fn roles(
+    Path(id): Path<i32>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<Vec<AssignedRole>>, ApiError> {
+    requester
+        .require_permission(Permission::UsersRead, db.as_ref())
+        .await?;
+
+    if User::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    Ok(Json(User::roles(id, db.as_ref()).await?))
+}
+
+#[instrument(name = "grant_user_roles", level = "debug", skip_all, fields(user_id = id))]
+pub async fn grant_roles(
+    Path(id): Path<i32>,
+    Json(grant): Json<GrantList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(), ApiError> {
+    requester
+        .require_permission(Permission::UsersManageRoles, db.as_ref())
+        .await?;
+
+    if User::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    User::grant_roles(id, grant.ids, grant.validity, db.as_ref()).await?;
+
+    Ok(())
+}
+
+#[instrument(name = "revoke_user_roles", level = "debug", skip_all, fields(user_id = id))]
+pub async fn revoke_roles(
+    Path(id): Path<i32>,
+    Json(role_ids): Json<IdList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(), ApiError> {
+    requester
+        .require_permission(Permission::UsersManageRoles, db.as_ref())
+        .await?;
+
+    if User::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    User::revoke_roles(id, role_ids.ids, db.as_ref()).await?;
+
+    Ok(())
+}
+
+#[instrument(name = "set_user_roles", level = "debug", skip_all, fields(user_id = id))]
+pub async fn set_roles(
+    Path(id): Path<i32>,
+    Json(role_ids): Json<IdList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(), ApiError> {
+    requester
+        .require_permission(Permission::UsersManageRoles, db.as_ref())
+        .await?;
+
+    if User::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    User::set_roles(id, role_ids.ids, db.as_ref()).await?;
+
+    Ok(())
+}
+
+/// Direct permission rules of the user
+#[instrument(name = "list_user_permissions", level = "debug", skip_all, fields(user_id = id))]
+pub async fn permissions(
+    Path(id): Path<i32>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<Vec<UserPermission>>, ApiError> {
+    requester
+        .require_permission(Permission::UsersRead, db.as_ref())
+        .await?;
+
+    if User::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    let user_permissions = UserPermission::find(
+        taskrs_db::models::user_permission::Column::UserId
+            .eq(id)
+            .into_condition(),
+        db.as_ref(),
+    )
+    .await?;
+
+    Ok(Json(user_permissions))
+}
+
+/// Permissions that are effective for the user, granted directly or through roles
+#[instrument(
+    name = "list_effective_user_permissions",
+    level = "debug",
+    skip_all,
+    fields(user_id = id)
+)]
+pub async fn effective_permissions(
+    Path(id): Path<i32>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<Json<Vec<PermissionModel>>, ApiError> {
+    requester
+        .require_permission(Permission::UsersRead, db.as_ref())
+        .await?;
+
+    if User::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    Ok(Json(User::permissions(id, db.as_ref()).await?))
+}
+
+#[instrument(name = "grant_user_permissions", level = "debug", skip_all, fields(user_id = id))]
+pub async fn grant_permissions(
+    Path(id): Path<i32>,
+    Json(grant): Json<GrantList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(), ApiError> {
+    requester
+        .require_permission(Permission::UsersManagePermissions, db.as_ref())
+        .await?;
+
+    if User::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    User::grant_permissions(id, grant.ids, grant.validity, db.as_ref()).await?;
+
+    Ok(())
+}
+
+#[instrument(name = "deny_user_permissions", level = "debug", skip_all, fields(user_id = id))]
+pub async fn deny_permissions(
+    Path(id): Path<i32>,
+    Json(permission_ids): Json<IdList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(), ApiError> {
+    requester
+        .require_permission(Permission::UsersManagePermissions, db.as_ref())
+        .await?;
+
+    if User::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    User::deny_permissions(id, permission_ids.ids, db.as_ref()).await?;
+
+    Ok(())
+}
+
+#[instrument(name = "revoke_user_permissions", level = "debug", skip_all, fields(user_id = id))]
+pub async fn revoke_permissions(
+    Path(id): Path<i32>,
+    Json(permission_ids): Json<IdList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(), ApiError> {
+    requester
+        .require_permission(Permission::UsersManagePermissions, db.as_ref())
+        .await?;
+
+    if User::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    User::revoke_permissions(id, permission_ids.ids, db.as_ref()).await?;
+
+    Ok(())
+}
+
+#[instrument(name = "set_user_permissions", level = "debug", skip_all, fields(user_id = id))]
+pub async fn set_permissions(
+    Path(id): Path<i32>,
+    Json(permission_ids): Json<IdList>,
+    requester: Requester,
+    Extension(db): Extension<Arc<DbConn>>,
+) -> Result<(), ApiError> {
+    requester
+        .require_permission(Permission::UsersManagePermissions, db.as_ref())
+        .await?;
+
+    if User::get(id, db.as_ref()).await?.is_none() {
+        return Err(ApiError::NotFound);
+    }
+
+    User::set_permissions(id, permission_ids.ids, db.as_ref()).await?;
+
+    Ok(())
+}
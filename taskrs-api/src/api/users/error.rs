@@ -0,0 +1,26 @@
+use crate::api::error::Problem;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+#[derive(Debug)]
+pub enum UserError {
+    EmailTaken,
+}
+
+impl UserError {
+    pub fn problem(&self) -> Problem {
+        match self {
+            UserError::EmailTaken => Problem::new(
+                StatusCode::CONFLICT,
+                "user.email_taken",
+                "Email is already taken",
+            ),
+        }
+    }
+}
+
+impl IntoResponse for UserError {
+    fn into_response(self) -> Response {
+        self.problem().into_response()
+    }
+}
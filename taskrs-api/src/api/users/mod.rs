@@ -0,0 +1,39 @@
+pub(super) mod controller;
+pub mod error;
+
+use axum::routing::{get, post};
+use axum::Router;
+
+pub fn get_router() -> Router {
+    Router::new()
+        .route("/", get(controller::list).post(controller::create))
+        .route(
+            "/:id",
+            get(controller::get)
+                .patch(controller::update)
+                .delete(controller::delete),
+        )
+        .route("/purge", post(controller::purge))
+        .route("/:id/restore", post(controller::restore))
+        .route("/:id/enable", post(controller::enable))
+        .route("/:id/disable", post(controller::disable))
+        .route(
+            "/:id/roles",
+            get(controller::roles)
+                .post(controller::grant_roles)
+                .put(controller::set_roles)
+                .delete(controller::revoke_roles),
+        )
+        .route(
+            "/:id/permissions",
+            get(controller::permissions)
+                .post(controller::grant_permissions)
+                .put(controller::set_permissions)
+                .delete(controller::revoke_permissions),
+        )
+        .route("/:id/permissions/deny", post(controller::deny_permissions))
+        .route(
+            "/:id/permissions/effective",
+            get(controller::effective_permissions),
+        )
+}
@@ -1,23 +1,29 @@
+use crate::api::error::Problem;
 use crate::config::{Config, DatabaseConfig, ServerConfig};
-use axum::routing::{get_service, IntoMakeService};
-use axum::{Json, Router, Server};
+use crate::idempotency::IdempotencyLayer;
+use crate::mail::Mailer;
+use crate::request_context::RequestContextLayer;
+use axum::extract::connect_info::IntoMakeServiceWithConnectInfo;
+use axum::routing::get_service;
+use axum::{Router, Server};
 use http_body::combinators::UnsyncBoxBody;
 use hyper::body::HttpBody as _;
 use hyper::header::{AUTHORIZATION, CONTENT_LENGTH};
 use hyper::http::HeaderValue;
 use hyper::server::conn::AddrIncoming;
-use hyper::{Response, StatusCode};
-use serde_json::json;
+use hyper::Response;
 use std::iter::once;
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::process::exit;
 use std::sync::Arc;
 use std::time::Duration;
+use taskrs_core::models::grant::GrantValidity;
 use taskrs_core::seeding::{
     seed_permissions, seed_root_role, seed_root_role_permissions, seed_root_user,
 };
 use taskrs_db::connection::ConnectionBuilder;
+use taskrs_db::migrations::MigrationLock;
 use taskrs_db::sea_orm::DbConn;
 use tower_cookies::CookieManagerLayer;
 use tower_http::add_extension::AddExtensionLayer;
@@ -33,13 +39,15 @@ use tracing_subscriber::reload::Handle;
 use tracing_subscriber::{EnvFilter, Registry};
 
 pub struct Application {
-    server: Server<AddrIncoming, IntoMakeService<Router>>,
+    server: Server<AddrIncoming, IntoMakeServiceWithConnectInfo<Router, SocketAddr>>,
     address: SocketAddr,
 }
 
 #[derive(Clone, Debug)]
 pub struct ApplicationState {
     pub config: Config,
+    pub mailer: Mailer,
+    #[allow(dead_code)]
     pub log_reload_handle: Handle<EnvFilter, Registry>,
 }
 
@@ -48,12 +56,25 @@ impl Application {
     pub async fn build(config: Config, log_reload_handle: Handle<EnvFilter, Registry>) -> Self {
         debug!("Building application and connect to database");
 
+        // Subscribe before seeding, so no change is missed
+        crate::events::register_subscribers();
+
         let db_connection = get_database_connection(&config.database).await;
         setup_database(&config, &db_connection).await;
+        let db_connection = Arc::new(db_connection);
+
+        crate::jobs::spawn_jobs(&config.jobs, db_connection.clone());
+
+        let mailer = Mailer::new(&config.mail).unwrap_or_else(|err| {
+            error!("Error while setting up mail delivery.");
+            error!("{}", err);
+            exit(-1);
+        });
 
         let bind_address = SocketAddr::new(config.server.bind_address, config.server.bind_port);
         let state = ApplicationState {
             config,
+            mailer,
             log_reload_handle,
         };
         let server = build_server(bind_address, state, db_connection);
@@ -74,11 +95,17 @@ impl Application {
 fn build_server(
     address: SocketAddr,
     state: ApplicationState,
-    db: DbConn,
-) -> Server<AddrIncoming, IntoMakeService<Router>> {
+    db: Arc<DbConn>,
+) -> Server<AddrIncoming, IntoMakeServiceWithConnectInfo<Router, SocketAddr>> {
     let router = build_router(&state.config.server)
         // Mark the `Authorization` request header as sensitive so it doesn't show in logs
         .layer(SetSensitiveHeadersLayer::new(once(AUTHORIZATION)))
+        // Manager Layer for cookies, inside the idempotency layer so that responses setting cookies are not stored
+        .layer(CookieManagerLayer::new())
+        // Run requests with an `Idempotency-Key` at most once
+        .layer(IdempotencyLayer)
+        // Attribute changes to the request for the audit log
+        .layer(RequestContextLayer)
         // High level logging of requests and responses
         .layer(TraceLayer::new_for_http().on_response(DefaultOnResponse::new().level(Level::INFO)))
         // CORS
@@ -93,18 +120,16 @@ fn build_server(
             CONTENT_LENGTH,
             content_length_from_response,
         ))
-        // Manager Layer for cookies
-        .layer(CookieManagerLayer::new())
         // Wrap application state for extraction
         .layer(AddExtensionLayer::new(state))
         // Wrap database connection for extraction
-        .layer(AddExtensionLayer::new(Arc::new(db)));
+        .layer(AddExtensionLayer::new(db));
 
     // Compress responses only in release mode
     #[cfg(not(debug_assertions))]
     let router = router.layer(CompressionLayer::new());
 
-    Server::bind(&address).serve(router.into_make_service())
+    Server::bind(&address).serve(router.into_make_service_with_connect_info::<SocketAddr, _>())
 }
 
 fn build_router(server_config: &ServerConfig) -> Router {
@@ -121,11 +146,8 @@ fn build_router(server_config: &ServerConfig) -> Router {
                     .precompressed_gzip()
                     .precompressed_deflate(),
             )
-            .handle_error(|_: std::io::Error| async move {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Internal Server Error"})),
-                )
+            .handle_error(|err: std::io::Error| async move {
+                Problem::internal(|| format!("IO error: {}", err))
             }),
         )
         .fallback(
@@ -135,16 +157,13 @@ fn build_router(server_config: &ServerConfig) -> Router {
                     .precompressed_gzip()
                     .precompressed_deflate(),
             )
-            .handle_error(|_: std::io::Error| async move {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(json!({"error": "Internal Server Error"})),
-                )
+            .handle_error(|err: std::io::Error| async move {
+                Problem::internal(|| format!("IO error: {}", err))
             }),
         )
 }
 
-async fn get_database_connection(database_config: &DatabaseConfig) -> DbConn {
+pub(crate) async fn get_database_connection(database_config: &DatabaseConfig) -> DbConn {
     debug!("Building connection options from config");
     let builder = ConnectionBuilder::new(database_config.url.clone())
         .max_connections(Some(database_config.max_connections))
@@ -166,34 +185,52 @@ async fn get_database_connection(database_config: &DatabaseConfig) -> DbConn {
 }
 
 async fn setup_database(config: &Config, db: &DbConn) {
+    // Other instances starting at the same time wait until this one is done
+    let lock = acquire_migration_lock(&config.database, db).await;
+    // Released before exiting on errors, so other instances do not wait for it
+    let prepared = prepare_database(config, db).await;
+    release_migration_lock(lock, db).await;
+    if prepared.is_err() {
+        exit(-1);
+    }
+}
+
+/// Migrates and seeds the database, errors are logged
+async fn prepare_database(config: &Config, db: &DbConn) -> Result<(), ()> {
     // Migrations
     let migrations = taskrs_db::migrations::Migrations::new(None);
-    migrations.run(db).await.unwrap_or_else(|err| {
-        error!("Error while running database migrations.");
-        error!("{}", err);
-        exit(-1);
-    });
+    if config.database.auto_migrate {
+        migrations
+            .run(db)
+            .await
+            .map_err(log_error("Error while running database migrations."))?;
+    } else {
+        let pending = migrations
+            .plan(db)
+            .await
+            .map_err(log_error("Error while checking database migrations."))?;
+        if !pending.is_empty() {
+            error!(
+                "{} migrations are pending and auto migration is disabled, run `migrate up` first.",
+                pending.len()
+            );
+            return Err(());
+        }
+    }
+    check_changed_migrations(config, db).await?;
 
     // Seeding permissions
-    seed_permissions(db).await.unwrap_or_else(|err| {
-        error!("Error while seeding permissions.");
-        error!("{}", err);
-        exit(-1);
-    });
+    seed_permissions(db)
+        .await
+        .map_err(log_error("Error while seeding permissions."))?;
 
     // Seed root role and its permissions
-    let role = seed_root_role(db).await.unwrap_or_else(|err| {
-        error!("Error while seeding root role.");
-        error!("{}", err);
-        exit(-1);
-    });
+    let role = seed_root_role(db)
+        .await
+        .map_err(log_error("Error while seeding root role."))?;
     seed_root_role_permissions(role.id, db)
         .await
-        .unwrap_or_else(|err| {
-            error!("Error while seeding root role permissions.");
-            error!("{}", err);
-            exit(-1);
-        });
+        .map_err(log_error("Error while seeding root role permissions."))?;
 
     // Seed root user
     if config.seeding.seed_root_user {
@@ -205,22 +242,77 @@ async fn setup_database(config: &Config, db: &DbConn) {
             db,
         )
         .await
-        .unwrap_or_else(|err| {
-            error!("Error while seeding root user.");
-            error!("{}", err);
-            exit(-1);
-        });
+        .map_err(log_error("Error while seeding root user."))?;
 
         // Grant root role
         if config.seeding.grant_root_role {
-            taskrs_core::models::user::User::grant_roles(root_user.id, vec![role.id], db)
-                .await
-                .unwrap_or_else(|err| {
-                    error!("Database error while granting root role: {}", err);
-                    exit(-1);
-                });
+            taskrs_core::models::user::User::grant_roles(
+                root_user.id,
+                vec![role.id],
+                GrantValidity::default(),
+                db,
+            )
+            .await
+            .map_err(log_error("Database error while granting root role."))?;
         }
     }
+
+    Ok(())
+}
+
+/// Logs the error of a failed setup step
+fn log_error<E: std::fmt::Display>(message: &'static str) -> impl FnOnce(E) {
+    move |err| {
+        error!("{}", message);
+        error!("{}", err);
+    }
+}
+
+/// Waits for the lock serializing migrations and seeding, exits if another instance holds it too long
+pub(crate) async fn acquire_migration_lock(
+    database_config: &DatabaseConfig,
+    db: &DbConn,
+) -> MigrationLock {
+    let timeout = Duration::from_secs(database_config.migration_lock_timeout as u64);
+    MigrationLock::acquire(db, timeout)
+        .await
+        .unwrap_or_else(|err| {
+            error!("Error while acquiring the migration lock.");
+            error!("{}", err);
+            exit(-1);
+        })
+}
+
+/// Releases the migration lock, exits on errors
+pub(crate) async fn release_migration_lock(lock: MigrationLock, db: &DbConn) {
+    lock.release(db).await.unwrap_or_else(|err| {
+        error!("Error while releasing the migration lock.");
+        error!("{}", err);
+        exit(-1);
+    });
+}
+
+/// Warns about applied migrations whose statements changed, fails if configured to
+async fn check_changed_migrations(config: &Config, db: &DbConn) -> Result<(), ()> {
+    let changed = taskrs_db::migrations::Migrations::new(None)
+        .changed(db)
+        .await
+        .map_err(log_error("Error while verifying migration checksums."))?;
+
+    for mismatch in &changed {
+        warn!(
+            recorded = %mismatch.recorded,
+            current = %mismatch.current,
+            "Migration {} changed since it was applied",
+            mismatch.name
+        );
+    }
+    if !changed.is_empty() && config.database.fail_on_changed_migrations {
+        error!("Applied migrations changed, compare the schema with `schema check`.");
+        return Err(());
+    }
+
+    Ok(())
 }
 
 fn content_length_from_response(
@@ -232,3 +324,45 @@ fn content_length_from_response(
         .exact()
         .map(|size| HeaderValue::from_str(&size.to_string()).unwrap())
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use taskrs_db::migrations::Migrations;
+    use taskrs_db::sea_orm::{Database, DbConn};
+
+    /// Migrated SQLite database for tests, shared like the connection of the application.
+    /// The database is a file, so that every connection of the pool sees it, and is removed once dropped.
+    pub(crate) struct TestDatabase {
+        pub(crate) db: Arc<DbConn>,
+        path: PathBuf,
+    }
+
+    impl TestDatabase {
+        pub(crate) async fn sqlite() -> Self {
+            let path =
+                std::env::temp_dir().join(format!("taskrs-test-{}.db", uuid::Uuid::new_v4()));
+            let db = Database::connect(&format!("sqlite:{}?mode=rwc", path.display()))
+                .await
+                .expect("test database can be opened");
+            Migrations::new(None)
+                .run(&db)
+                .await
+                .expect("test database can be migrated");
+
+            Self {
+                db: Arc::new(db),
+                path,
+            }
+        }
+    }
+
+    impl Drop for TestDatabase {
+        fn drop(&mut self) {
+            for suffix in ["", "-journal", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{}{}", self.path.display(), suffix));
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use crate::config::Config;
+use clap::{Parser, Subcommand};
+use std::process::exit;
+use taskrs_db::migrations::{MigrationDirection, Migrations, PlannedMigration};
+use taskrs_db::sea_orm::{ConnectionTrait, DbConn};
+
+/// Server of taskrs
+#[derive(Debug, Parser)]
+#[clap(version, about)]
+pub struct Cli {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Runs the server, the default without a command
+    Serve,
+    /// Manages the migrations of the database
+    #[clap(subcommand)]
+    Migrate(MigrateCommand),
+    /// Inspects the schema of the database
+    #[clap(subcommand)]
+    Schema(SchemaCommand),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MigrateCommand {
+    /// Lists applied and pending migrations
+    Status,
+    /// Applies pending migrations, up to and including the target if given
+    Up {
+        /// Name of the last migration to apply
+        #[clap(long)]
+        target: Option<String>,
+        /// Prints the SQL instead of executing it
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Rolls back the migrations applied after the target
+    Down {
+        /// Name of the last migration to keep
+        #[clap(long)]
+        target: String,
+        /// Prints the SQL instead of executing it
+        #[clap(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SchemaCommand {
+    /// Compares the tables, columns, indexes and foreign keys with the entities, exits with 1 on differences
+    Check,
+}
+
+/// Runs a migrate command, exits the process on errors
+pub async fn migrate(command: MigrateCommand, config: &Config) {
+    let db = crate::application::get_database_connection(&config.database).await;
+
+    let (migrations, direction, dry_run) = match command {
+        MigrateCommand::Status => return print_status(&db).await,
+        MigrateCommand::Up { target, dry_run } => {
+            (Migrations::new(target), MigrationDirection::Up, dry_run)
+        }
+        MigrateCommand::Down { target, dry_run } => (
+            Migrations::new(Some(target)),
+            MigrationDirection::Down,
+            dry_run,
+        ),
+    };
+
+    let plan = migrations.plan(&db).await.unwrap_or_else(|err| {
+        error!("Error while planning migrations: {}", err);
+        exit(-1);
+    });
+    if plan.iter().any(|planned| planned.direction != direction) {
+        error!(
+            "The target can not be reached by migrating {}, use `migrate {}` instead",
+            direction_name(direction),
+            direction_name(opposite(direction))
+        );
+        exit(-1);
+    }
+    if plan.is_empty() {
+        println!("Nothing to migrate");
+        return;
+    }
+
+    if dry_run {
+        print_plan(&plan, &db);
+        return;
+    }
+
+    for planned in &plan {
+        println!(
+            "Migrating {} {}",
+            direction_name(planned.direction),
+            planned.migration.name()
+        );
+    }
+    drop(plan);
+    let lock = crate::application::acquire_migration_lock(&config.database, &db).await;
+    let migrated = migrations.run(&db).await;
+    // Released before exiting on errors, so other instances do not wait for it
+    crate::application::release_migration_lock(lock, &db).await;
+    if let Err(err) = migrated {
+        error!("Error while running database migrations: {}", err);
+        exit(-1);
+    }
+}
+
+async fn print_status(db: &DbConn) {
+    let statuses = Migrations::new(None)
+        .status(db)
+        .await
+        .unwrap_or_else(|err| {
+            error!("Error while reading migrations: {}", err);
+            exit(-1);
+        });
+
+    println!("{:>6}  {:<32}  Applied at", "Order", "Name");
+    for status in statuses {
+        let run_at = status
+            .run_at
+            .map(|run_at| run_at.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "pending".to_string());
+        let changed = match (status.changed, status.unverified) {
+            (true, _) => "  (changed)",
+            (_, true) => "  (unverified)",
+            _ => "",
+        };
+        println!(
+            "{:>6}  {:<32}  {}{}",
+            status.order, status.name, run_at, changed
+        );
+    }
+}
+
+/// Runs a schema command, exits the process on errors and differences
+pub async fn schema(command: SchemaCommand, config: &Config) {
+    let db = crate::application::get_database_connection(&config.database).await;
+
+    match command {
+        SchemaCommand::Check => {
+            let differences = taskrs_db::schema::check(&db).await.unwrap_or_else(|err| {
+                error!("Error while reading the schema: {}", err);
+                exit(-1);
+            });
+            if differences.is_empty() {
+                println!("Schema matches the entities");
+                return;
+            }
+            for difference in &differences {
+                println!("{}", difference);
+            }
+            exit(1);
+        }
+    }
+}
+
+/// Prints the statements of the plan for the backend of the database
+fn print_plan(plan: &[PlannedMigration], db: &DbConn) {
+    let backend = db.get_database_backend();
+    for planned in plan {
+        println!(
+            "-- {} {}",
+            direction_name(planned.direction),
+            planned.migration.name()
+        );
+        for statement in planned.statements(backend) {
+            println!("{};", statement);
+        }
+        println!();
+    }
+}
+
+fn direction_name(direction: MigrationDirection) -> &'static str {
+    match direction {
+        MigrationDirection::Up => "up",
+        MigrationDirection::Down => "down",
+    }
+}
+
+fn opposite(direction: MigrationDirection) -> MigrationDirection {
+    match direction {
+        MigrationDirection::Up => MigrationDirection::Down,
+        MigrationDirection::Down => MigrationDirection::Up,
+    }
+}
@@ -9,6 +9,9 @@ pub struct Config {
     pub server: ServerConfig,
     pub database: DatabaseConfig,
     pub logs: LogConfig,
+    pub jobs: JobsConfig,
+    pub mail: MailConfig,
+    pub retention: RetentionConfig,
 }
 
 impl Config {
@@ -49,6 +52,9 @@ impl Default for Config {
             server: ServerConfig::default(),
             database: DatabaseConfig::default(),
             logs: LogConfig::default(),
+            jobs: JobsConfig::default(),
+            mail: MailConfig::default(),
+            retention: RetentionConfig::default(),
         }
     }
 }
@@ -59,6 +65,8 @@ pub struct AuthenticationConfig {
     pub refresh_token_secret: String,
     pub access_token_expiration_time: u32,  // Seconds
     pub refresh_token_expiration_time: u32, // Seconds
+    pub email_change_secret: String,
+    pub email_change_expiration_time: u32, // Seconds
 }
 
 impl AuthenticationConfig {
@@ -79,6 +87,8 @@ impl Default for AuthenticationConfig {
             refresh_token_secret: "refresh_token_secret".to_string(),
             access_token_expiration_time: 900,
             refresh_token_expiration_time: 2592000,
+            email_change_secret: "email_change_secret".to_string(),
+            email_change_expiration_time: 86400,
         }
     }
 }
@@ -132,6 +142,13 @@ pub struct DatabaseConfig {
     pub max_connections: u32,
     pub connect_timeout: u32, // Seconds
     pub idle_timeout: u32,    // Seconds
+    /// Applies pending migrations at server start,
+    /// otherwise the server refuses to start until they are applied with `migrate up`
+    pub auto_migrate: bool,
+    /// Refuses to start if applied migrations changed since, otherwise only warns
+    pub fail_on_changed_migrations: bool,
+    /// Time to wait for other instances to finish migrating and seeding
+    pub migration_lock_timeout: u32, // Seconds
 }
 
 impl Default for DatabaseConfig {
@@ -142,6 +159,9 @@ impl Default for DatabaseConfig {
             max_connections: 100,
             connect_timeout: 10,
             idle_timeout: 10,
+            auto_migrate: true,
+            fail_on_changed_migrations: false,
+            migration_lock_timeout: 60,
         }
     }
 }
@@ -166,3 +186,62 @@ impl Default for LogConfig {
         }
     }
 }
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JobsConfig {
+    pub expired_grants_interval: u32,           // Seconds
+    pub expired_idempotency_keys_interval: u32, // Seconds
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            expired_grants_interval: 60,
+            expired_idempotency_keys_interval: 3600,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MailConfig {
+    /// Mails are only logged if disabled
+    pub enabled: bool,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    pub from: String,
+    /// URL under which the server is reachable, used for links in mails
+    pub public_url: String,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            smtp_host: "localhost".to_string(),
+            smtp_port: 587,
+            smtp_username: None,
+            smtp_password: None,
+            from: "taskrs <noreply@taskrs.com>".to_string(),
+            public_url: "http://localhost:8080".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RetentionConfig {
+    /// Soft deleted users can be purged after this period
+    pub deleted_users: u32, // Seconds
+    /// Responses of requests with an `Idempotency-Key` are replayed for this period
+    pub idempotency_keys: u32, // Seconds
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            deleted_users: 2592000,
+            idempotency_keys: 86400,
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use axum::async_trait;
+use taskrs_core::logic::events::{subscribe, Subscriber};
+use taskrs_core::models::domain_event::DomainEvent;
+
+/// Registers all subscribers of domain events
+pub fn register_subscribers() {
+    subscribe(LogSubscriber);
+}
+
+/// Logs every published event
+struct LogSubscriber;
+
+#[async_trait]
+impl Subscriber for LogSubscriber {
+    async fn handle(&self, event: &DomainEvent) {
+        debug!(target: "taskrs::events", ?event, "Domain event published");
+    }
+}
@@ -0,0 +1,569 @@
+use crate::api::auth::{ACCESS_TOKEN_COOKIE, REFRESH_TOKEN_COOKIE};
+use crate::api::error::{ApiError, Problem};
+use crate::application::ApplicationState;
+use axum::body::{boxed, Body, BoxBody, Bytes, Full, StreamBody};
+use axum::response::IntoResponse;
+use futures::future::BoxFuture;
+use futures::{stream, StreamExt};
+use http_body::Body as HttpBody;
+use hyper::header::{HeaderName, CONNECTION, CONTENT_TYPE, COOKIE, SET_COOKIE};
+use hyper::http::{HeaderMap, HeaderValue};
+use hyper::{Method, Request, Response, StatusCode};
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use taskrs_core::models::idempotency_key::{
+    IdempotencyClaim, IdempotencyKey, IdempotencyKeyCreate, StoredResponse,
+};
+use taskrs_db::sea_orm::DbConn;
+use tower::{Layer, Service};
+use tower_cookies::Cookie;
+
+const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+const IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// Longest idempotency key, fits the column of the key
+const MAX_KEY_LENGTH: usize = 255;
+
+/// Largest request and response bodies of requests with a key, both are held in memory.
+/// Larger responses are passed through without being stored.
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// How long a request keeps its key before a retry may take it over.
+/// Longer than any request runs, so that only keys of crashed requests are taken over.
+const LEASE: i64 = 60; // Seconds
+
+/// Headers of a connection instead of a response (RFC 7230, section 6.1) and credentials, which are never replayed.
+/// `Content-Type` is stored on its own and `Content-Length` follows from the body.
+/// Responses setting cookies are not stored at all.
+const UNSTORED_HEADERS: &[&str] = &[
+    "authorization",
+    "cookie",
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "content-type",
+    "content-length",
+];
+
+/// Runs POST and PATCH requests sent with an `Idempotency-Key` header at most once.
+/// The response is stored with the key, retries with the same key get it replayed
+/// and reusing a key for a different request fails with 422.
+/// Replays keep the status, body and headers of the response, except for hop-by-hop headers and credentials.
+///
+/// Keys are scoped to the session of the requester, so that responses are never replayed
+/// to other users. Requests without a session run as if they had no key.
+/// Server errors, responses setting cookies and responses larger than [`MAX_BODY_SIZE`] are not stored,
+/// a retry runs the request again. Cookies hold the session and are never written to the database.
+/// A retry while the request is still running fails with 409,
+/// unless the request did not finish within its [`LEASE`] and is taken over by the retry.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IdempotencyLayer;
+
+impl<S> Layer<S> for IdempotencyLayer {
+    type Service = Idempotency<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Idempotency { inner }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Idempotency<S> {
+    inner: S,
+}
+
+impl<S> Service<Request<Body>> for Idempotency<S>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // The ready service handles this request, a clone takes its place for the next one
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        let key = match (request.method(), request.headers().get(IDEMPOTENCY_KEY)) {
+            (&Method::POST | &Method::PATCH, Some(key)) => key.clone(),
+            _ => return Box::pin(inner.call(request)),
+        };
+        let scope = match session_scope(&request) {
+            Some(scope) => scope,
+            None => return Box::pin(inner.call(request)),
+        };
+
+        Box::pin(async move {
+            let key = match key.to_str() {
+                Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LENGTH => key.to_string(),
+                _ => {
+                    return Ok(Problem::new(
+                        StatusCode::BAD_REQUEST,
+                        "idempotency.invalid_key",
+                        "Invalid idempotency key",
+                    )
+                    .detail(format!(
+                        "Idempotency-Key has to be printable and at most {} characters long",
+                        MAX_KEY_LENGTH
+                    ))
+                    .into_response())
+                }
+            };
+
+            let (state, db) = match (
+                request.extensions().get::<ApplicationState>().cloned(),
+                request.extensions().get::<Arc<DbConn>>().cloned(),
+            ) {
+                (Some(state), Some(db)) => (state, db),
+                _ => return inner.call(request).await,
+            };
+
+            let (parts, body) = request.into_parts();
+            let body = match read_limited(body, MAX_BODY_SIZE).await {
+                Ok(LimitedBody::Complete(body)) => body,
+                Ok(LimitedBody::TooLarge(..)) => {
+                    return Ok(Problem::new(
+                        StatusCode::PAYLOAD_TOO_LARGE,
+                        "idempotency.request_too_large",
+                        "Request body is too large for an idempotency key",
+                    )
+                    .detail(format!(
+                        "Requests with an Idempotency-Key can have at most {} bytes",
+                        MAX_BODY_SIZE
+                    ))
+                    .into_response())
+                }
+                Err(err) => {
+                    return Ok(Problem::new(
+                        StatusCode::BAD_REQUEST,
+                        "request.invalid_body",
+                        "Request body could not be read",
+                    )
+                    .detail(err.to_string())
+                    .into_response())
+                }
+            };
+            let fingerprint = fingerprint(&parts.method, &parts.uri, &body);
+
+            let now = chrono::Utc::now().naive_utc();
+            let retention =
+                chrono::Duration::seconds(state.config.retention.idempotency_keys as i64);
+            let create = IdempotencyKeyCreate {
+                scope,
+                key,
+                fingerprint: fingerprint.clone(),
+                locked_until: now + chrono::Duration::seconds(LEASE),
+                expires_at: now + retention,
+            };
+            let claimed = match IdempotencyKey::claim(create, db.as_ref()).await {
+                Ok(IdempotencyClaim::Claimed(claimed)) => claimed,
+                Ok(IdempotencyClaim::Existing(existing)) => {
+                    return Ok(replay(existing, &fingerprint))
+                }
+                Err(err) => return Ok(ApiError::from(err).into_response()),
+            };
+
+            debug!("Running request of idempotency key {}", claimed.id);
+            let request = Request::from_parts(parts, Body::from(body));
+            let response = inner.call(request).await?;
+            Ok(store(claimed.id, response, db.as_ref()).await)
+        })
+    }
+}
+
+/// Hash of the session cookie, the refresh token outlives the access tokens issued with it.
+/// Read from the `Cookie` header, the cookie manager runs inside this layer
+/// so that responses setting cookies are recognized and not stored.
+fn session_scope(request: &Request<Body>) -> Option<String> {
+    let cookies: Vec<Cookie> = request
+        .headers()
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(';'))
+        .filter_map(|cookie| Cookie::parse_encoded(cookie.trim().to_string()).ok())
+        .collect();
+    let cookie = |name: &str| cookies.iter().find(|cookie| cookie.name() == name);
+    let session = cookie(REFRESH_TOKEN_COOKIE).or_else(|| cookie(ACCESS_TOKEN_COOKIE))?;
+
+    Some(format!("{:x}", Sha256::digest(session.value().as_bytes())))
+}
+
+/// Hash identifying a request, retries with the same key have to send the same request
+fn fingerprint(method: &Method, uri: &hyper::Uri, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_str().as_bytes());
+    hasher.update(b" ");
+    hasher.update(uri.to_string().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(body);
+
+    format!("{:x}", hasher.finalize())
+}
+
+/// Response to a request whose key was used before
+fn replay(existing: IdempotencyKey, fingerprint: &str) -> Response<BoxBody> {
+    if existing.fingerprint != fingerprint {
+        return Problem::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "idempotency.key_reused",
+            "Idempotency key was used for a different request",
+        )
+        .into_response();
+    }
+
+    let stored = match existing.response {
+        Some(stored) => stored,
+        None => {
+            return Problem::new(
+                StatusCode::CONFLICT,
+                "idempotency.in_progress",
+                "Request with the idempotency key is still running",
+            )
+            .into_response()
+        }
+    };
+
+    debug!("Replaying response of idempotency key {}", existing.id);
+    let status = StatusCode::from_u16(stored.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let mut response = Response::new(boxed(Full::from(stored.body)));
+    *response.status_mut() = status;
+    for (name, value) in stored.headers {
+        match (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            (Ok(name), Ok(value)) => {
+                response.headers_mut().append(name, value);
+            }
+            _ => warn!(
+                "Skipping invalid header {} of idempotency key {}",
+                name, existing.id
+            ),
+        }
+    }
+    if let Some(content_type) = stored
+        .content_type
+        .and_then(|content_type| HeaderValue::from_str(&content_type).ok())
+    {
+        response.headers_mut().insert(CONTENT_TYPE, content_type);
+    }
+    response
+        .headers_mut()
+        .insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+    response
+}
+
+/// Stores the response with the key.
+/// Server errors, responses setting cookies, bodies that are no text
+/// and bodies larger than [`MAX_BODY_SIZE`] release the key.
+async fn store(id: i32, response: Response<BoxBody>, db: &DbConn) -> Response<BoxBody> {
+    let (parts, body) = response.into_parts();
+    let body = match read_limited(body, MAX_BODY_SIZE).await {
+        Ok(LimitedBody::Complete(body)) => body,
+        Ok(LimitedBody::TooLarge(read, rest)) => {
+            debug!(
+                "Response of idempotency key {} is too large to be stored",
+                id
+            );
+            release(id, db).await;
+            let rest = stream::unfold(rest, |mut rest| async move {
+                rest.data().await.map(|chunk| (chunk, rest))
+            });
+            let body = stream::iter(read.into_iter().map(Ok)).chain(rest);
+            return Response::from_parts(parts, boxed(StreamBody::new(body)));
+        }
+        Err(err) => {
+            error!(
+                "Error while reading response of idempotency key {}: {}",
+                id, err
+            );
+            release(id, db).await;
+            return Problem::internal(|| format!("Response error: {}", err)).into_response();
+        }
+    };
+
+    let text = std::str::from_utf8(&body).ok();
+    let stored = !parts.status.is_server_error() && !parts.headers.contains_key(SET_COOKIE);
+    let result = match (stored, text) {
+        (true, Some(text)) => {
+            let stored = StoredResponse {
+                status: parts.status.as_u16(),
+                content_type: parts
+                    .headers
+                    .get(CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .map(String::from),
+                headers: stored_headers(&parts.headers),
+                body: text.to_string(),
+            };
+            IdempotencyKey::complete(id, stored, db).await
+        }
+        _ => IdempotencyKey::release(id, db).await,
+    };
+    if let Err(err) = result {
+        error!(
+            "Error while storing response of idempotency key {}: {}",
+            id, err
+        );
+    }
+
+    Response::from_parts(parts, boxed(Full::from(body)))
+}
+
+async fn release(id: i32, db: &DbConn) {
+    if let Err(err) = IdempotencyKey::release(id, db).await {
+        error!("Error while releasing idempotency key {}: {}", id, err);
+    }
+}
+
+/// Headers of the response that are replayed, without hop-by-hop headers, credentials
+/// and the headers the `Connection` header declares as hop-by-hop
+fn stored_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    let connection: Vec<String> = headers
+        .get_all(CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .collect();
+
+    headers
+        .iter()
+        .filter(|(name, _)| {
+            !UNSTORED_HEADERS.contains(&name.as_str())
+                && !connection.iter().any(|hop| hop == name.as_str())
+                && *name != IDEMPOTENT_REPLAYED
+        })
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+/// Body read up to a limit
+enum LimitedBody<B> {
+    Complete(Bytes),
+    /// The body exceeds the limit, with the chunks read so far and the unread rest
+    TooLarge(Vec<Bytes>, B),
+}
+
+async fn read_limited<B>(mut body: B, limit: usize) -> Result<LimitedBody<B>, B::Error>
+where
+    B: HttpBody<Data = Bytes> + Unpin,
+{
+    if body.size_hint().lower() > limit as u64 {
+        return Ok(LimitedBody::TooLarge(vec![], body));
+    }
+
+    let mut chunks = vec![];
+    let mut size = 0;
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        size += chunk.len();
+        chunks.push(chunk);
+        if size > limit {
+            return Ok(LimitedBody::TooLarge(chunks, body));
+        }
+    }
+
+    Ok(LimitedBody::Complete(Bytes::from(chunks.concat())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{stored_headers, IdempotencyLayer, IDEMPOTENCY_KEY, IDEMPOTENT_REPLAYED};
+    use crate::api::auth::REFRESH_TOKEN_COOKIE;
+    use crate::application::tests::TestDatabase;
+    use crate::application::ApplicationState;
+    use crate::config::Config;
+    use crate::mail::Mailer;
+    use axum::body::{boxed, Body, BoxBody, Full};
+    use hyper::header::{AUTHORIZATION, COOKIE, LOCATION, SET_COOKIE};
+    use hyper::http::{HeaderMap, HeaderValue};
+    use hyper::{Method, Request, Response, StatusCode};
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::sync::Notify;
+    use tower::{service_fn, Layer, ServiceExt};
+    use tracing_subscriber::{reload, EnvFilter, Registry};
+
+    /// Idempotency layer around a handler counting its calls
+    struct TestApp {
+        state: ApplicationState,
+        test_db: TestDatabase,
+        calls: Arc<AtomicUsize>,
+        /// Notified once `/slow` is running
+        started: Arc<Notify>,
+        /// Lets `/slow` finish
+        finish: Arc<Notify>,
+    }
+
+    impl TestApp {
+        async fn new() -> Self {
+            let config = Config::default();
+            let mailer = Mailer::new(&config.mail).expect("mails are disabled");
+            let (_, log_reload_handle) =
+                reload::Layer::<EnvFilter, Registry>::new(EnvFilter::default());
+
+            Self {
+                state: ApplicationState {
+                    config,
+                    mailer,
+                    log_reload_handle,
+                },
+                test_db: TestDatabase::sqlite().await,
+                calls: Default::default(),
+                started: Default::default(),
+                finish: Default::default(),
+            }
+        }
+
+        async fn send(&self, uri: &str, key: &str, body: &'static str) -> Response<BoxBody> {
+            let calls = self.calls.clone();
+            let started = self.started.clone();
+            let finish = self.finish.clone();
+            let handler = service_fn(move |request: Request<Body>| {
+                let calls = calls.clone();
+                let started = started.clone();
+                let finish = finish.clone();
+                async move {
+                    let call = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                    let mut response =
+                        Response::new(boxed(Full::from(format!("{{\"id\":{}}}", call))));
+                    *response.status_mut() = StatusCode::CREATED;
+                    response.headers_mut().insert(
+                        LOCATION,
+                        HeaderValue::from_str(&format!("/users/{}", call)).unwrap(),
+                    );
+                    match request.uri().path() {
+                        "/slow" => {
+                            started.notify_one();
+                            finish.notified().await;
+                        }
+                        "/cookie" => {
+                            response
+                                .headers_mut()
+                                .insert(SET_COOKIE, HeaderValue::from_static("refresh_token=new"));
+                        }
+                        _ => {}
+                    }
+                    Ok::<_, Infallible>(response)
+                }
+            });
+
+            let mut request = Request::builder()
+                .method(Method::POST)
+                .uri(uri)
+                .header(IDEMPOTENCY_KEY, key)
+                .header(COOKIE, format!("{}=session", REFRESH_TOKEN_COOKIE))
+                .body(Body::from(body))
+                .unwrap();
+            request.extensions_mut().insert(self.state.clone());
+            request.extensions_mut().insert(self.test_db.db.clone());
+
+            IdempotencyLayer
+                .layer(handler)
+                .oneshot(request)
+                .await
+                .unwrap()
+        }
+
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    async fn body(response: Response<BoxBody>) -> String {
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn retries_get_the_stored_response_replayed() {
+        let app = TestApp::new().await;
+
+        let first = app.send("/users", "create-1", r#"{"a":1}"#).await;
+        assert_eq!(first.status(), StatusCode::CREATED);
+        assert!(first.headers().get(IDEMPOTENT_REPLAYED).is_none());
+        assert_eq!(body(first).await, r#"{"id":1}"#);
+
+        let retry = app.send("/users", "create-1", r#"{"a":1}"#).await;
+        assert_eq!(retry.status(), StatusCode::CREATED);
+        assert_eq!(retry.headers()[IDEMPOTENT_REPLAYED], "true");
+        assert_eq!(retry.headers()[LOCATION], "/users/1");
+        assert_eq!(body(retry).await, r#"{"id":1}"#);
+        assert_eq!(app.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn reusing_a_key_for_another_request_fails() {
+        let app = TestApp::new().await;
+
+        app.send("/users", "create-1", r#"{"a":1}"#).await;
+        let reused = app.send("/users", "create-1", r#"{"a":2}"#).await;
+
+        assert_eq!(reused.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(body(reused).await.contains("idempotency.key_reused"));
+        assert_eq!(app.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn retries_of_running_requests_conflict() {
+        let app = Arc::new(TestApp::new().await);
+
+        let running = tokio::spawn({
+            let app = app.clone();
+            async move { app.send("/slow", "slow-1", "{}").await.status() }
+        });
+        app.started.notified().await;
+        let retry = app.send("/slow", "slow-1", "{}").await;
+        app.finish.notify_one();
+
+        assert_eq!(retry.status(), StatusCode::CONFLICT);
+        assert!(body(retry).await.contains("idempotency.in_progress"));
+        assert_eq!(running.await.unwrap(), StatusCode::CREATED);
+        assert_eq!(app.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn responses_setting_cookies_are_not_stored() {
+        let app = TestApp::new().await;
+
+        app.send("/cookie", "refresh-1", "{}").await;
+        let retry = app.send("/cookie", "refresh-1", "{}").await;
+
+        assert!(retry.headers().get(IDEMPOTENT_REPLAYED).is_none());
+        assert_eq!(retry.headers()[SET_COOKIE], "refresh_token=new");
+        assert_eq!(app.calls(), 2);
+    }
+
+    #[test]
+    fn stored_headers_leave_out_credentials_and_hop_by_hop_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(LOCATION, HeaderValue::from_static("/users/1"));
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer token"));
+        headers.insert("connection", HeaderValue::from_static("x-hop"));
+        headers.insert("x-hop", HeaderValue::from_static("1"));
+
+        assert_eq!(
+            stored_headers(&headers),
+            vec![("location".to_string(), "/users/1".to_string())]
+        );
+    }
+}
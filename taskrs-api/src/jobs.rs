@@ -0,0 +1,54 @@
+use crate::config::JobsConfig;
+use std::sync::Arc;
+use std::time::Duration;
+use taskrs_core::models::idempotency_key::IdempotencyKey;
+use taskrs_core::models::user::User;
+use taskrs_db::sea_orm::DbConn;
+
+/// Spawns all periodic background jobs
+pub fn spawn_jobs(config: &JobsConfig, db: Arc<DbConn>) {
+    tokio::spawn(sweep_expired_grants(
+        Duration::from_secs(config.expired_grants_interval as u64),
+        db.clone(),
+    ));
+    tokio::spawn(sweep_expired_idempotency_keys(
+        Duration::from_secs(config.expired_idempotency_keys_interval as u64),
+        db,
+    ));
+}
+
+/// Removes grants of roles and permissions whose validity has ended
+#[instrument(name = "sweep_expired_grants", skip_all)]
+async fn sweep_expired_grants(period: Duration, db: Arc<DbConn>) {
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        interval.tick().await;
+
+        trace!("Sweeping expired grants");
+        match User::revoke_expired_grants(db.as_ref()).await {
+            Ok(expired_grants) => debug!(
+                "Removed {} expired role grants and {} expired permission grants",
+                expired_grants.user_roles.len(),
+                expired_grants.user_permissions.len()
+            ),
+            Err(err) => error!("Error while removing expired grants: {}", err),
+        }
+    }
+}
+
+/// Removes idempotency keys whose responses are no longer replayed
+#[instrument(name = "sweep_expired_idempotency_keys", skip_all)]
+async fn sweep_expired_idempotency_keys(period: Duration, db: Arc<DbConn>) {
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        interval.tick().await;
+
+        trace!("Sweeping expired idempotency keys");
+        match IdempotencyKey::delete_expired(db.as_ref()).await {
+            Ok(count) => debug!("Removed {} expired idempotency keys", count),
+            Err(err) => error!("Error while removing expired idempotency keys: {}", err),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::config::MailConfig;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+/// Sends mails over SMTP. Mails are only logged if mail delivery is disabled.
+#[derive(Clone, Debug)]
+pub struct Mailer {
+    from: String,
+    transport: Option<AsyncSmtpTransport<Tokio1Executor>>,
+}
+
+impl Mailer {
+    pub fn new(config: &MailConfig) -> Result<Self, lettre::transport::smtp::Error> {
+        if !config.enabled {
+            return Ok(Self {
+                from: config.from.clone(),
+                transport: None,
+            });
+        }
+
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)?
+            .port(config.smtp_port);
+        if let (Some(username), Some(password)) = (&config.smtp_username, &config.smtp_password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self {
+            from: config.from.clone(),
+            transport: Some(builder.build()),
+        })
+    }
+
+    #[instrument(name = "send_mail", level = "debug", skip(self, body))]
+    pub async fn send(
+        &self,
+        to: &str,
+        subject: &str,
+        body: String,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let transport = match &self.transport {
+            Some(transport) => transport,
+            None => {
+                info!("Mail delivery is disabled, not sending mail");
+                debug!("{}", body);
+                return Ok(());
+            }
+        };
+
+        let message = Message::builder()
+            .from(self.from.parse::<Mailbox>()?)
+            .to(to.parse::<Mailbox>()?)
+            .subject(subject)
+            .body(body)?;
+
+        transport.send(message).await?;
+
+        Ok(())
+    }
+}
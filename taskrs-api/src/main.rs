@@ -3,11 +3,22 @@ extern crate tracing;
 
 mod api;
 mod application;
+mod cli;
 mod config;
+mod events;
+mod idempotency;
+mod jobs;
 mod logging;
+mod mail;
+mod request_context;
+
+use clap::Parser;
+use cli::{Cli, Command};
 
 #[tokio::main]
 async fn main() {
+    let cli = Cli::parse();
+
     // Config
     let config = config::Config::build();
 
@@ -15,7 +26,13 @@ async fn main() {
     let (subscriber, log_reload_handle, _guards) = logging::get_subscriber(&config.logs);
     logging::init_subscriber(subscriber);
 
-    // Application
-    let application = application::Application::build(config, log_reload_handle).await;
-    application.run().await.expect("Error while running server");
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => {
+            // Application
+            let application = application::Application::build(config, log_reload_handle).await;
+            application.run().await.expect("Error while running server");
+        }
+        Command::Migrate(command) => cli::migrate(command, &config).await,
+        Command::Schema(command) => cli::schema(command, &config).await,
+    }
 }
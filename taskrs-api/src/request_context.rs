@@ -0,0 +1,79 @@
+use axum::extract::ConnectInfo;
+use futures::future::BoxFuture;
+use hyper::header::HeaderName;
+use hyper::http::HeaderValue;
+use hyper::{Request, Response};
+use std::net::SocketAddr;
+use std::task::{Context, Poll};
+use taskrs_core::models::audit_event::AuditContext;
+use tower::{Layer, Service};
+use uuid::Uuid;
+
+const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest request id taken over from the client, longer ones are replaced
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+/// Runs every request inside an [`AuditContext`] carrying its request id and client ip.
+/// The request id is taken from the `X-Request-Id` header or generated
+/// and is echoed back in the response.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RequestContextLayer;
+
+impl<S> Layer<S> for RequestContextLayer {
+    type Service = RequestContext<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestContext { inner }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RequestContext<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for RequestContext<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let request_id = request
+            .headers()
+            .get(X_REQUEST_ID)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.is_empty() && value.len() <= MAX_REQUEST_ID_LENGTH)
+            .map(String::from)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        let ip_address = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(address)| address.ip().to_string());
+
+        let header_value = HeaderValue::from_str(&request_id).ok();
+        let context = AuditContext {
+            request_id: Some(request_id),
+            ip_address,
+            ..AuditContext::default()
+        };
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            let mut response = context.scope(future).await?;
+            if let Some(header_value) = header_value {
+                response.headers_mut().insert(X_REQUEST_ID, header_value);
+            }
+
+            Ok(response)
+        })
+    }
+}
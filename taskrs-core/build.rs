@@ -1,52 +1,106 @@
+#[path = "build/catalog.rs"]
+mod catalog;
+
+use std::fmt::Write as _;
 use std::fs::{File, OpenOptions};
 use std::io::{BufReader, Write};
+use std::path::PathBuf;
 
-use build_const::ConstWriter;
+use catalog::{variant_name, Permission};
 use glob::glob;
-use serde::{Deserialize, Serialize};
 
 fn main() {
     // Rerun if permission files changed
     println!("cargo:rerun-if-changed=permissions/");
 
-    let permissions: Vec<Permission> = glob("permissions/**/*.json")
+    let permissions: Vec<(PathBuf, Permission)> = glob("permissions/**/*.json")
         .unwrap()
-        .into_iter()
         .filter_map(|e| e.ok())
-        .map(|path| {
-            let file = File::open(path).unwrap();
+        .flat_map(|path| {
+            let file = File::open(&path).unwrap();
             let reader = BufReader::new(file);
 
-            serde_json::from_reader::<_, Vec<Permission>>(reader).unwrap()
+            let permissions = serde_json::from_reader::<_, Vec<Permission>>(reader)
+                .unwrap_or_else(|err| panic!("Invalid permission file {:?}: {}", path, err));
+
+            permissions
+                .into_iter()
+                .map(move |permission| (path.clone(), permission))
         })
-        .flatten()
         .collect();
 
-    let mut constants = ConstWriter::for_build("permissions")
-        .unwrap()
-        .finish_dependencies();
+    if let Err(err) = catalog::validate(&permissions) {
+        panic!("{}", err);
+    }
 
-    permissions.iter().cloned().for_each(|p| {
-        let name = format!("{}_{}", &p.group, &p.name).to_uppercase();
-        constants.add_value(&name, "(&str, &str)", (p.group, p.name))
-    });
-
-    let path = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("permissions.json");
+    let path = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("permissions.rs");
     let mut permission_file = OpenOptions::new()
         .write(true)
         .truncate(true)
         .create(true)
         .open(path)
         .unwrap();
-    let permissions_json = serde_json::to_string(&permissions).unwrap();
+    let permissions: Vec<Permission> = permissions.into_iter().map(|(_, p)| p).collect();
     permission_file
-        .write_all(permissions_json.as_bytes())
+        .write_all(generate(&permissions).as_bytes())
         .unwrap();
 }
 
-#[derive(Clone, Deserialize, Serialize)]
-pub struct Permission {
-    pub name: String,
-    pub group: String,
-    pub description: Option<String>,
+/// Generates the `Permission` enum
+fn generate(permissions: &[Permission]) -> String {
+    let variants: Vec<String> = permissions.iter().map(variant_name).collect();
+
+    let mut code = String::new();
+
+    writeln!(
+        code,
+        "/// Every permission defined in the permission catalog."
+    )
+    .unwrap();
+    writeln!(code, "#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]").unwrap();
+    writeln!(code, "pub enum Permission {{").unwrap();
+    for (permission, variant) in permissions.iter().zip(&variants) {
+        writeln!(code, "    /// {}", permission.description).unwrap();
+        writeln!(code, "    {},", variant).unwrap();
+    }
+    writeln!(code, "}}\n").unwrap();
+
+    writeln!(code, "impl Permission {{").unwrap();
+    writeln!(code, "    /// All permissions in catalog order").unwrap();
+    writeln!(code, "    pub const ALL: &'static [Permission] = &[").unwrap();
+    for variant in &variants {
+        writeln!(code, "        Permission::{},", variant).unwrap();
+    }
+    writeln!(code, "    ];\n").unwrap();
+
+    let functions: [(&str, &str, FieldAccessor); 3] = [
+        ("group", "Group of the permission", |p| &p.group),
+        ("name", "Name of the permission within its group", |p| {
+            &p.name
+        }),
+        ("description", "Description of the permission", |p| {
+            &p.description
+        }),
+    ];
+    for (function, doc, field) in functions {
+        writeln!(code, "    /// {}", doc).unwrap();
+        writeln!(code, "    pub fn {}(&self) -> &'static str {{", function).unwrap();
+        writeln!(code, "        match self {{").unwrap();
+        for (permission, variant) in permissions.iter().zip(&variants) {
+            writeln!(
+                code,
+                "            Permission::{} => {:?},",
+                variant,
+                field(permission)
+            )
+            .unwrap();
+        }
+        writeln!(code, "        }}").unwrap();
+        writeln!(code, "    }}\n").unwrap();
+    }
+    writeln!(code, "}}").unwrap();
+
+    code
 }
+
+type FieldAccessor = fn(&Permission) -> &String;
@@ -0,0 +1,139 @@
+//! Permission catalog of `permissions/**/*.json`, checked by the build script.
+//! Included by the library in tests, so the checks are tested like other code.
+
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// Permission as defined in the catalog files
+#[derive(Clone, Deserialize)]
+pub struct Permission {
+    pub name: String,
+    pub group: String,
+    pub description: String,
+}
+
+/// Checks the permissions of the catalog, fails on the first invalid or duplicate permission
+pub fn validate(permissions: &[(PathBuf, Permission)]) -> Result<(), String> {
+    let mut seen = HashSet::new();
+
+    for (path, permission) in permissions {
+        for (field, value) in [("group", &permission.group), ("name", &permission.name)] {
+            if !is_valid_identifier(value) {
+                return Err(format!(
+                    "Invalid permission {} {:?} in {:?}: must match ^[a-z][a-z0-9_]*$",
+                    field, value, path
+                ));
+            }
+        }
+
+        if permission.description.trim().is_empty() {
+            return Err(format!(
+                "Permission {}.{} in {:?} has no description",
+                permission.group, permission.name, path
+            ));
+        }
+
+        if !seen.insert(variant_name(permission)) {
+            return Err(format!(
+                "Duplicate permission {}.{} in {:?}",
+                permission.group, permission.name, path
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn is_valid_identifier(value: &str) -> bool {
+    let mut chars = value.chars();
+
+    matches!(chars.next(), Some('a'..='z'))
+        && chars.all(|c| matches!(c, 'a'..='z' | '0'..='9' | '_'))
+}
+
+/// Name of the enum variant, e.g. `UsersManageRoles` for `users.manage_roles`.
+/// Also used to detect duplicates, as two permissions must not map to the same variant.
+pub fn variant_name(permission: &Permission) -> String {
+    format!(
+        "{}{}",
+        to_pascal_case(&permission.group),
+        to_pascal_case(&permission.name)
+    )
+}
+
+fn to_pascal_case(value: &str) -> String {
+    value
+        .split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate, Permission};
+    use std::path::PathBuf;
+
+    fn permission(group: &str, name: &str, description: &str) -> (PathBuf, Permission) {
+        let permission = Permission {
+            name: name.to_string(),
+            group: group.to_string(),
+            description: description.to_string(),
+        };
+
+        (PathBuf::from("permissions/test.json"), permission)
+    }
+
+    fn error(permissions: &[(PathBuf, Permission)]) -> String {
+        validate(permissions).expect_err("catalog is invalid")
+    }
+
+    #[test]
+    fn accepts_the_catalog() {
+        let permissions = [
+            permission("users", "create", "Create users"),
+            permission("users", "manage_roles2", "Manage the roles of users"),
+        ];
+
+        assert!(validate(&permissions).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_identifiers() {
+        for (group, name) in [("Users", "create"), ("users", "2fa"), ("users", "")] {
+            let error = error(&[permission(group, name, "Description")]);
+
+            assert!(error.starts_with("Invalid permission"), "{}", error);
+        }
+        let error = error(&[permission("users", "manage-roles", "Description")]);
+        assert!(error.contains("name \"manage-roles\""), "{}", error);
+    }
+
+    #[test]
+    fn rejects_empty_descriptions() {
+        let error = error(&[permission("users", "create", " ")]);
+
+        assert!(error.contains("has no description"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_permissions_with_the_same_variant() {
+        // Both are `UsersManageRoles`
+        let error = error(&[
+            permission("users", "manage_roles", "Manage roles"),
+            permission("users_manage", "roles", "Manage roles"),
+        ]);
+
+        assert!(
+            error.starts_with("Duplicate permission users_manage.roles"),
+            "{}",
+            error
+        );
+    }
+}
@@ -1,9 +1,16 @@
+use serde::Serialize;
+use taskrs_db::sea_orm::{DbErr, TransactionError};
+
 #[derive(Debug)]
 pub enum Error {
     Argon(argon2::Error),
     Auth(AuthError),
-    Database(taskrs_db::sea_orm::DbErr),
+    Concurrency(ConcurrencyError),
+    Database(DbErr),
+    Filter(FilterError),
     JsonWebToken(jsonwebtoken::errors::Error),
+    Rbac(RbacError),
+    Validation(ValidationError),
 }
 
 impl std::error::Error for Error {}
@@ -13,8 +20,39 @@ impl std::fmt::Display for Error {
         match self {
             Self::Argon(e) => write!(f, "Password hashing/verifying error: {}", e),
             Self::Auth(e) => write!(f, "Auth Error: {}", e),
+            Self::Concurrency(e) => write!(f, "Concurrency error: {}", e),
             Self::Database(e) => write!(f, "Database Error: {}", e),
+            Self::Filter(e) => write!(f, "Filter error: {}", e),
             Self::JsonWebToken(e) => write!(f, "Error while creating/decoding JWTs: {}", e),
+            Self::Rbac(e) => write!(f, "Access control error: {}", e),
+            Self::Validation(e) => write!(f, "Validation error: {}", e),
+        }
+    }
+}
+
+impl From<DbErr> for Error {
+    fn from(err: DbErr) -> Self {
+        Self::Database(err)
+    }
+}
+
+impl From<FilterError> for Error {
+    fn from(err: FilterError) -> Self {
+        Self::Filter(err)
+    }
+}
+
+impl From<ValidationError> for Error {
+    fn from(err: ValidationError) -> Self {
+        Self::Validation(err)
+    }
+}
+
+impl From<TransactionError<Error>> for Error {
+    fn from(err: TransactionError<Error>) -> Self {
+        match err {
+            TransactionError::Connection(e) => Self::Database(e),
+            TransactionError::Transaction(e) => e,
         }
     }
 }
@@ -24,6 +62,10 @@ pub enum AuthError {
     UnknownEmail,
     UserDisabled,
     WrongPassword,
+    /// The email is already used by another user
+    EmailTaken,
+    /// The email change was requested for an email the user no longer has
+    StaleEmailChange,
 }
 
 impl std::error::Error for AuthError {}
@@ -34,6 +76,148 @@ impl std::fmt::Display for AuthError {
             Self::UnknownEmail => write!(f, "The provided email is not known"),
             Self::UserDisabled => write!(f, "The requested user is disabled"),
             Self::WrongPassword => write!(f, "The provided password is wrong"),
+            Self::EmailTaken => write!(f, "The provided email is already taken"),
+            Self::StaleEmailChange => write!(f, "The email of the user changed in the meantime"),
         }
     }
 }
+
+#[derive(Debug)]
+pub enum ConcurrencyError {
+    /// The entity was updated since the version the update is based on
+    VersionMismatch { expected: i32, actual: i32 },
+}
+
+impl std::error::Error for ConcurrencyError {}
+
+impl std::fmt::Display for ConcurrencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::VersionMismatch { expected, actual } => write!(
+                f,
+                "Expected version {} but the current version is {}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RbacError {
+    /// System roles can not be deleted, renamed or have their permissions changed
+    SystemRole,
+    /// The operation would leave no enabled user with the root role
+    LastRootUser,
+}
+
+impl std::error::Error for RbacError {}
+
+impl std::fmt::Display for RbacError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::SystemRole => write!(f, "System roles can not be modified"),
+            Self::LastRootUser => write!(
+                f,
+                "The operation would leave no enabled user with the root role"
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FilterError {
+    /// A filter or sort parameter does not follow the expected syntax
+    MalformedParameter(String),
+    UnknownOperator(String),
+    /// The field is not in the allow-list of filterable fields
+    UnknownFilterField(String),
+    /// The field is not in the allow-list of sortable fields
+    UnknownSortField(String),
+    /// The operator is not allowed for the field
+    OperatorNotAllowed {
+        field: String,
+        operator: String,
+    },
+    /// The value can not be converted to the type of the field
+    InvalidValue {
+        field: String,
+        value: String,
+    },
+    /// The pagination cursor is malformed or belongs to another order
+    InvalidCursor,
+    /// The field can not be used to order keyset paginated lists
+    UnsupportedCursorSort(String),
+}
+
+impl std::error::Error for FilterError {}
+
+impl std::fmt::Display for FilterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::MalformedParameter(p) => write!(f, "Malformed filter or sort parameter '{}'", p),
+            Self::UnknownOperator(o) => write!(f, "Unknown filter operator '{}'", o),
+            Self::UnknownFilterField(field) => write!(f, "Field '{}' can not be filtered", field),
+            Self::UnknownSortField(field) => write!(f, "Field '{}' can not be sorted by", field),
+            Self::OperatorNotAllowed { field, operator } => write!(
+                f,
+                "Operator '{}' is not allowed for field '{}'",
+                operator, field
+            ),
+            Self::InvalidValue { field, value } => {
+                write!(f, "Invalid value '{}' for field '{}'", value, field)
+            }
+            Self::InvalidCursor => write!(f, "Invalid pagination cursor"),
+            Self::UnsupportedCursorSort(field) => write!(
+                f,
+                "Field '{}' can not be used to sort lists paginated by cursor",
+                field
+            ),
+        }
+    }
+}
+
+/// Fields of a model that break validation rules.
+/// Every rule is checked, so all invalid fields are listed at once.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ValidationError {
+    pub fields: Vec<FieldError>,
+}
+
+impl std::error::Error for ValidationError {}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let fields: Vec<String> = self
+            .fields
+            .iter()
+            .map(|e| format!("'{}' {}", e.field, e.message))
+            .collect();
+        write!(f, "{}", fields.join(", "))
+    }
+}
+
+/// Rule broken by a field
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldError {
+    /// Path of the field in the JSON representation of the model, e.g. `firstName`
+    pub field: String,
+    pub code: ValidationCode,
+    pub message: String,
+}
+
+/// Stable identifier of a validation rule
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationCode {
+    /// The field is empty or only contains whitespace
+    Required,
+    /// The field is longer than allowed
+    Length,
+    /// The field is not an email address
+    Email,
+    /// The field does not match the pattern of the field
+    Pattern,
+    /// The value is already used by another entity
+    Unique,
+}
@@ -1,6 +1,10 @@
 #[macro_use]
 extern crate tracing;
 
+// Checks of the build script
+#[cfg(test)]
+#[path = "../build/catalog.rs"]
+mod catalog;
 pub mod error;
 pub mod logic;
 pub mod models;
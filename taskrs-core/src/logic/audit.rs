@@ -0,0 +1,213 @@
+use crate::error::Error;
+use crate::logic::{FilterableModelTrait, PaginatedModelTrait, ReadModelTrait};
+use crate::models::audit_event::{AuditAction, AuditContext, AuditEvent, AuditEventCreate};
+use crate::models::filter::{FilterField, FilterOperator, SortField};
+use crate::models::IntoActiveModel;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::cell::RefCell;
+use std::future::Future;
+use taskrs_db::models::audit_event;
+use taskrs_db::sea_orm::sea_query::SimpleExpr;
+use taskrs_db::sea_orm::{ActiveModelTrait, ConnectionTrait, IntoSimpleExpr, Order};
+
+tokio::task_local! {
+    static AUDIT_CONTEXT: RefCell<AuditContext>;
+}
+
+impl AuditContext {
+    /// Runs `future` with this context. Changes made by it are attributed to the context.
+    pub async fn scope<F>(self, future: F) -> F::Output
+    where
+        F: Future,
+    {
+        AUDIT_CONTEXT.scope(RefCell::new(self), future).await
+    }
+
+    /// Context of the current scope.
+    /// Outside of a scope changes are attributed to the system.
+    pub fn current() -> Self {
+        AUDIT_CONTEXT
+            .try_with(|context| context.borrow().clone())
+            .unwrap_or_default()
+    }
+
+    /// Sets the actor of the current scope, e.g. once the requester is authenticated.
+    /// Does nothing outside of a scope.
+    pub fn set_actor(actor_id: i32) {
+        let _ = AUDIT_CONTEXT.try_with(|context| context.borrow_mut().actor_id = Some(actor_id));
+    }
+}
+
+/// Records a change of an entity, attributed to the current [`AuditContext`].
+/// Only fields that differ between `before` and `after` are stored,
+/// nothing is recorded if both are equal.
+pub(crate) async fn record<'a, C>(
+    action: AuditAction,
+    entity_type: &str,
+    entity_id: i32,
+    before: Value,
+    after: Value,
+    db: &'a C,
+) -> Result<(), Error>
+where
+    C: ConnectionTrait<'a>,
+{
+    let changes = match diff(before, after) {
+        Some(changes) => changes,
+        None => return Ok(()),
+    };
+
+    AuditEventCreate {
+        action,
+        entity_type: entity_type.to_string(),
+        entity_id,
+        changes,
+        context: AuditContext::current(),
+    }
+    .into_active_model()
+    .insert(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Records a change of an entity given by its state before and after the change.
+/// The entity is identified by its `id` field.
+pub(crate) async fn record_model<'a, T, C>(
+    action: AuditAction,
+    entity_type: &str,
+    before: Option<&T>,
+    after: Option<&T>,
+    db: &'a C,
+) -> Result<(), Error>
+where
+    T: Serialize + Sync,
+    C: ConnectionTrait<'a>,
+{
+    let before = serde_json::to_value(before).unwrap_or_default();
+    let after = serde_json::to_value(after).unwrap_or_default();
+    let entity_id = [&after, &before]
+        .iter()
+        .find_map(|state| state.get("id").and_then(Value::as_i64))
+        .unwrap_or_default() as i32;
+
+    record(action, entity_type, entity_id, before, after, db).await
+}
+
+/// State of a relation keyed by the id of the related entity, e.g. the role grants of a user.
+/// Used as `before` and `after` of [`record`], so that only changed grants are stored.
+pub(crate) fn snapshot<T, K>(items: &[T], key: K) -> Value
+where
+    T: Serialize,
+    K: Fn(&T) -> i32,
+{
+    Value::Object(
+        items
+            .iter()
+            .map(|item| {
+                let state = serde_json::to_value(item).unwrap_or_default();
+                (key(item).to_string(), state)
+            })
+            .collect(),
+    )
+}
+
+/// Reduces two JSON objects to the fields that differ.
+/// Other values are kept as they are, e.g. `null` before the creation of an entity.
+fn diff(before: Value, after: Value) -> Option<Value> {
+    match (before, after) {
+        (Value::Object(mut before), Value::Object(mut after)) => {
+            let mut keys: Vec<String> = before.keys().chain(after.keys()).cloned().collect();
+            keys.sort();
+            keys.dedup();
+
+            let (mut changed_before, mut changed_after) = (Map::new(), Map::new());
+            for key in keys {
+                let (old, new) = (before.remove(&key), after.remove(&key));
+                if old == new {
+                    continue;
+                }
+                if let Some(old) = old {
+                    changed_before.insert(key.clone(), old);
+                }
+                if let Some(new) = new {
+                    changed_after.insert(key, new);
+                }
+            }
+
+            match changed_before.is_empty() && changed_after.is_empty() {
+                true => None,
+                false => Some(json!({ "before": changed_before, "after": changed_after })),
+            }
+        }
+        (before, after) if before == after => None,
+        (before, after) => Some(json!({ "before": before, "after": after })),
+    }
+}
+
+impl ReadModelTrait<audit_event::Entity> for AuditEvent {}
+
+impl PaginatedModelTrait<audit_event::Entity, audit_event::ActiveModel> for AuditEvent {
+    /// Newest events first
+    fn default_order() -> (SimpleExpr, Order) {
+        (audit_event::Column::Id.into_simple_expr(), Order::Desc)
+    }
+}
+
+impl FilterableModelTrait<audit_event::Entity, audit_event::ActiveModel> for AuditEvent {
+    fn filter_fields() -> Vec<FilterField<audit_event::Column>> {
+        vec![
+            FilterField::new("id", audit_event::Column::Id, FilterOperator::COMPARISON),
+            FilterField::new(
+                "actor_id",
+                audit_event::Column::ActorId,
+                FilterOperator::COMPARISON,
+            )
+            .nullable(),
+            FilterField::new(
+                "impersonator_id",
+                audit_event::Column::ImpersonatorId,
+                FilterOperator::EQUALITY,
+            )
+            .nullable(),
+            FilterField::new("action", audit_event::Column::Action, FilterOperator::TEXT),
+            FilterField::new(
+                "entity_type",
+                audit_event::Column::EntityType,
+                FilterOperator::TEXT,
+            ),
+            FilterField::new(
+                "entity_id",
+                audit_event::Column::EntityId,
+                FilterOperator::COMPARISON,
+            ),
+            FilterField::new(
+                "request_id",
+                audit_event::Column::RequestId,
+                FilterOperator::EQUALITY,
+            )
+            .nullable(),
+            FilterField::new(
+                "ip_address",
+                audit_event::Column::IpAddress,
+                FilterOperator::TEXT,
+            )
+            .nullable(),
+            FilterField::new(
+                "inserted_at",
+                audit_event::Column::InsertedAt,
+                FilterOperator::COMPARISON,
+            ),
+        ]
+    }
+
+    fn sort_fields() -> Vec<SortField<audit_event::Column>> {
+        vec![
+            SortField::new("id", audit_event::Column::Id),
+            SortField::new("action", audit_event::Column::Action),
+            SortField::new("entity_type", audit_event::Column::EntityType),
+            SortField::new("inserted_at", audit_event::Column::InsertedAt),
+        ]
+    }
+}
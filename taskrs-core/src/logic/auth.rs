@@ -1,13 +1,21 @@
 use crate::error::{AuthError, Error};
-use crate::logic::{CreateModelTrait, DeleteModelTrait};
-use crate::models::auth::{AccessTokenData, Auth, AuthSettings, AuthTokens, RefreshTokenData};
+use crate::logic::validation::{Validate, Validator};
+use crate::logic::{events, CreateModelTrait, DeleteModelTrait, ReadModelTrait, UpdateModelTrait};
+use crate::models::auth::{
+    AccessTokenData, Auth, AuthSettings, AuthTokens, EmailChange, EmailChangeTokenData,
+    PasswordChange, RefreshTokenData,
+};
 use crate::models::refresh_token::{RefreshToken, RefreshTokenCreate};
+use crate::models::user::{User, UserUpdate};
+use async_trait::async_trait;
 use jsonwebtoken::errors::ErrorKind;
 use jsonwebtoken::TokenData;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use taskrs_db::sea_orm::sea_query::IntoCondition;
-use taskrs_db::sea_orm::{ColumnTrait, DbConn, EntityTrait, QueryFilter};
+use taskrs_db::sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, DbConn, EntityTrait, QueryFilter,
+};
 
 impl Auth {
     pub async fn login(self, settings: &AuthSettings, db: &DbConn) -> Result<AuthTokens, Error> {
@@ -18,10 +26,9 @@ impl Auth {
             .await
             .map_err(Error::Database)?;
 
-        let user = user.ok_or(Error::Auth(AuthError::UnknownEmail))?;
-        if !user.enabled {
-            return Err(Error::Auth(AuthError::UserDisabled));
-        }
+        let user = user
+            .filter(|user| user.deleted_at.is_none())
+            .ok_or(Error::Auth(AuthError::UnknownEmail))?;
 
         debug!("Verify password of user");
         let matches = argon2::verify_encoded(&user.password_hash, self.password.as_bytes())
@@ -30,6 +37,11 @@ impl Auth {
             return Err(Error::Auth(AuthError::WrongPassword));
         }
 
+        // Checked after the password to only tell the user that the account is disabled
+        if !user.enabled {
+            return Err(Error::Auth(AuthError::UserDisabled));
+        }
+
         debug!("Generating tokens");
         let user_id = user.id;
         let access_token_data =
@@ -57,8 +69,7 @@ impl Auth {
             },
             db,
         )
-        .await
-        .map_err(Error::Database)?;
+        .await?;
 
         Ok(AuthTokens {
             access_token,
@@ -75,7 +86,118 @@ impl Auth {
         )
         .await
         .map(|r| r.rows_affected)
-        .map_err(Error::Database)
+    }
+}
+
+impl PasswordChange {
+    /// Changes the password of the user if the current password matches.
+    /// Revokes every refresh token of the user except `keep_refresh_token`,
+    /// which ends all other sessions once their access tokens expire.
+    /// Returns the number of revoked refresh tokens.
+    pub async fn change<'a, C>(
+        self,
+        user_id: i32,
+        keep_refresh_token: Option<String>,
+        db: &'a C,
+    ) -> Result<u64, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        self.validate(db).await?;
+
+        let user = taskrs_db::models::user::Entity::find_by_id(user_id)
+            .one(db)
+            .await
+            .map_err(Error::Database)?
+            .ok_or(Error::Auth(AuthError::UnknownEmail))?;
+
+        debug!("Verify current password of user");
+        let matches = argon2::verify_encoded(&user.password_hash, self.current_password.as_bytes())
+            .map_err(Error::Argon)?;
+        if !matches {
+            return Err(Error::Auth(AuthError::WrongPassword));
+        }
+
+        let user_update = UserUpdate {
+            id: user_id,
+            password: Some(self.new_password),
+            ..Default::default()
+        }
+        .hash_password()
+        .map_err(Error::Argon)?;
+
+        // Transaction
+        let revoked = events::transaction(db, |txn| {
+            Box::pin(async move {
+                User::update(user_update, txn).await?;
+
+                let mut condition = Condition::all()
+                    .add(taskrs_db::models::refresh_token::Column::UserId.eq(user_id));
+                if let Some(token) = keep_refresh_token {
+                    condition =
+                        condition.add(taskrs_db::models::refresh_token::Column::Token.ne(token));
+                }
+
+                RefreshToken::delete_condition(condition, txn)
+                    .await
+                    .map(|r| r.rows_affected)
+            })
+        })
+        .await?;
+
+        Ok(revoked)
+    }
+}
+
+#[async_trait]
+impl Validate for PasswordChange {
+    async fn rules<'a, C>(&self, validator: &mut Validator, _db: &'a C) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        validator.required("newPassword", &self.new_password);
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Validate for EmailChange {
+    async fn rules<'a, C>(&self, validator: &mut Validator, db: &'a C) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        User::validate_email(&self.email, None, validator, db).await
+    }
+}
+
+impl EmailChangeTokenData {
+    /// Applies the email change if the user still has the old email
+    /// and the new email was not taken in the meantime.
+    pub async fn confirm<'a, C>(self, db: &'a C) -> Result<User, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let user = User::get(self.user_id, db)
+            .await?
+            .ok_or(Error::Auth(AuthError::UnknownEmail))?;
+
+        if user.email != self.old_email {
+            return Err(Error::Auth(AuthError::StaleEmailChange));
+        }
+        if User::email_exists(&self.new_email, db).await? {
+            return Err(Error::Auth(AuthError::EmailTaken));
+        }
+
+        User::update(
+            UserUpdate {
+                id: user.id,
+                email: Some(self.new_email),
+                ..Default::default()
+            },
+            db,
+        )
+        .await
     }
 }
 
@@ -0,0 +1,312 @@
+use crate::error::FilterError;
+use crate::logic::filter::parse_value;
+use crate::models::pagination::Cursor;
+use taskrs_db::sea_orm::sea_query::{ColumnRef, ColumnSpec, IntoCondition, SimpleExpr};
+use taskrs_db::sea_orm::{
+    ColumnTrait, Condition, DbBackend, EntityTrait, IdenStatic, IntoSimpleExpr, Iterable,
+    ModelTrait, Order, PrimaryKeyToColumn, Schema, Value,
+};
+
+/// Encodes a cursor into an opaque, URL safe string
+pub(crate) fn encode(cursor: &Cursor) -> String {
+    let json = serde_json::to_vec(cursor).unwrap_or_default();
+    base64::encode_config(json, base64::URL_SAFE_NO_PAD)
+}
+
+/// Decodes a cursor created by [`encode`]
+pub(crate) fn decode(cursor: &str) -> Result<Cursor, FilterError> {
+    base64::decode_config(cursor, base64::URL_SAFE_NO_PAD)
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or(FilterError::InvalidCursor)
+}
+
+/// Column of an order expression, e.g. of the default order
+pub(crate) fn order_column<E>(expr: &SimpleExpr) -> Option<E::Column>
+where
+    E: EntityTrait,
+{
+    let name = match expr {
+        SimpleExpr::Column(ColumnRef::Column(column))
+        | SimpleExpr::Column(ColumnRef::TableColumn(_, column)) => column.to_string(),
+        _ => return None,
+    };
+
+    E::Column::iter().find(|column| column.as_str() == name)
+}
+
+/// Columns that define the position of a row: the sort column followed by the primary key
+pub(crate) fn key_columns<E>(sort_column: E::Column) -> Vec<E::Column>
+where
+    E: EntityTrait,
+{
+    let mut columns = vec![sort_column];
+    for column in E::PrimaryKey::iter().map(|key| key.into_column()) {
+        if column.as_str() != sort_column.as_str() {
+            columns.push(column);
+        }
+    }
+
+    columns
+}
+
+/// Whether the column of the entity allows `NULL`
+pub(crate) fn nullable<E>(column: E::Column) -> bool
+where
+    E: EntityTrait,
+{
+    Schema::new(DbBackend::Sqlite)
+        .create_table_from_entity(E::default())
+        .get_columns()
+        .iter()
+        .find(|def| def.get_column_name() == column.as_str())
+        .map(|def| {
+            !def.get_column_spec()
+                .iter()
+                .any(|spec| matches!(spec, ColumnSpec::NotNull | ColumnSpec::PrimaryKey))
+        })
+        .unwrap_or(false)
+}
+
+/// Values of the key columns of a model, `None` for `NULL`
+pub(crate) fn model_keys<M>(
+    model: &M,
+    columns: &[<M::Entity as EntityTrait>::Column],
+) -> Result<Vec<Option<String>>, FilterError>
+where
+    M: ModelTrait,
+{
+    columns
+        .iter()
+        .map(|column| {
+            let value = model.get(*column);
+            if is_null(&value) {
+                return Ok(None);
+            }
+            value_to_string(value)
+                .map(Some)
+                .ok_or_else(|| FilterError::UnsupportedCursorSort(column.as_str().to_string()))
+        })
+        .collect()
+}
+
+/// Expressions to order the key columns by, in the given order.
+/// A nullable sort column is first ordered by whether it is `NULL`,
+/// so that `NULL` comes last in ascending and first in descending order on every backend.
+pub(crate) fn key_order<C>(columns: &[C], nullable: bool, order: Order) -> Vec<(SimpleExpr, Order)>
+where
+    C: ColumnTrait,
+{
+    let mut exprs = Vec::new();
+    for (i, column) in columns.iter().enumerate() {
+        if i == 0 && nullable {
+            exprs.push((column.is_null(), order));
+        }
+        exprs.push((column.into_simple_expr(), order));
+    }
+
+    exprs
+}
+
+/// Condition matching rows that come after the keys in the order of [`key_order`], i.e.
+/// `(c1 > v1) OR (c1 = v1 AND c2 > v2) OR ...` for ascending order.
+/// Only the first column is `nullable`, the following ones are the primary key.
+pub(crate) fn keyset_condition<C>(
+    columns: &[C],
+    nullable: bool,
+    keys: &[Option<String>],
+    order: Order,
+) -> Result<Condition, FilterError>
+where
+    C: ColumnTrait,
+{
+    if columns.len() != keys.len() {
+        return Err(FilterError::InvalidCursor);
+    }
+
+    let values = columns
+        .iter()
+        .zip(keys)
+        .enumerate()
+        .map(|(i, (column, key))| match key {
+            Some(key) => parse_value(column.def().get_column_type(), key)
+                .map(Some)
+                .ok_or(FilterError::InvalidCursor),
+            None if i == 0 && nullable => Ok(None),
+            None => Err(FilterError::InvalidCursor),
+        })
+        .collect::<Result<Vec<Option<Value>>, FilterError>>()?;
+
+    let ascending = !matches!(order, Order::Desc);
+    let mut condition = Condition::any();
+    for (i, column) in columns.iter().enumerate() {
+        let mut position = Condition::all();
+        for (previous, value) in columns.iter().zip(&values).take(i) {
+            position = position.add(match value {
+                Some(value) => previous.eq(value.clone()),
+                None => previous.is_null(),
+            });
+        }
+
+        // `NULL` is last in ascending and first in descending order
+        let after = match (&values[i], ascending) {
+            (Some(value), true) if i == 0 && nullable => Condition::any()
+                .add(column.gt(value.clone()))
+                .add(column.is_null()),
+            (Some(value), true) => column.gt(value.clone()).into_condition(),
+            (Some(value), false) => column.lt(value.clone()).into_condition(),
+            (None, true) => continue,
+            (None, false) => column.is_not_null().into_condition(),
+        };
+        condition = condition.add(position.add(after));
+    }
+
+    Ok(condition)
+}
+
+/// Whether the value is `NULL`
+fn is_null(value: &Value) -> bool {
+    match value {
+        Value::Bool(v) => v.is_none(),
+        Value::TinyInt(v) => v.is_none(),
+        Value::SmallInt(v) => v.is_none(),
+        Value::Int(v) => v.is_none(),
+        Value::BigInt(v) => v.is_none(),
+        Value::TinyUnsigned(v) => v.is_none(),
+        Value::SmallUnsigned(v) => v.is_none(),
+        Value::Unsigned(v) => v.is_none(),
+        Value::BigUnsigned(v) => v.is_none(),
+        Value::Float(v) => v.is_none(),
+        Value::Double(v) => v.is_none(),
+        Value::String(v) => v.is_none(),
+        Value::DateTime(v) => v.is_none(),
+        _ => false,
+    }
+}
+
+/// Converts a value of a key column into the string stored in cursors
+fn value_to_string(value: Value) -> Option<String> {
+    match value {
+        Value::Bool(v) => v.map(|v| v.to_string()),
+        Value::TinyInt(v) => v.map(|v| v.to_string()),
+        Value::SmallInt(v) => v.map(|v| v.to_string()),
+        Value::Int(v) => v.map(|v| v.to_string()),
+        Value::BigInt(v) => v.map(|v| v.to_string()),
+        Value::TinyUnsigned(v) => v.map(|v| v.to_string()),
+        Value::SmallUnsigned(v) => v.map(|v| v.to_string()),
+        Value::Unsigned(v) => v.map(|v| v.to_string()),
+        Value::BigUnsigned(v) => v.map(|v| v.to_string()),
+        Value::Float(v) => v.map(|v| v.to_string()),
+        Value::Double(v) => v.map(|v| v.to_string()),
+        Value::String(v) => v.map(|v| *v),
+        Value::DateTime(v) => v.map(|v| v.format("%Y-%m-%dT%H:%M:%S%.f").to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::tests::TestDatabase;
+    use crate::logic::{CreateModelTrait, PaginatedModelTrait};
+    use crate::models::group::{Group, GroupCreate};
+    use crate::models::pagination::CursorPage;
+    use taskrs_db::models::group;
+    use taskrs_db::sea_orm::sea_query::IntoCondition;
+    use taskrs_db::sea_orm::{ColumnTrait, DbConn, Order};
+
+    /// Ids of groups by their description, ordered ascending with `NULL` last
+    async fn create_groups(db: &DbConn) -> Vec<i32> {
+        let descriptions = [Some("b"), None, Some("a"), None, Some("b"), Some("c"), None];
+        let run = rand::random::<u32>();
+
+        let mut groups = Vec::new();
+        for (i, description) in descriptions.into_iter().enumerate() {
+            let group = GroupCreate {
+                name: format!("cursor-{}-{}", run, i),
+                description: description.map(String::from),
+                ..Default::default()
+            };
+            groups.push((description, Group::create(group, db).await.unwrap().id));
+        }
+
+        groups.sort_by_key(|(description, id)| (description.is_none(), *description, *id));
+        groups.into_iter().map(|(_, id)| id).collect()
+    }
+
+    async fn page(
+        groups: &[i32],
+        order: Order,
+        cursor: Option<String>,
+        limit: usize,
+        db: &DbConn,
+    ) -> CursorPage<Group> {
+        Group::find_by_cursor(
+            group::Column::Id.is_in(groups.to_vec()).into_condition(),
+            Some((group::Column::Description, order)),
+            cursor,
+            limit,
+            false,
+            db,
+        )
+        .await
+        .unwrap()
+    }
+
+    fn ids(page: &CursorPage<Group>) -> Vec<i32> {
+        page.items.iter().map(|group| group.id).collect()
+    }
+
+    /// Pages through the groups with `next`, then back from the last page with `prev`,
+    /// no group may be skipped or repeated
+    async fn assert_pages(order: Order, expected: &[i32], db: &DbConn) {
+        for limit in 1..=3 {
+            let mut forward = Vec::new();
+            let mut last = page(expected, order, None, limit, db).await;
+            assert!(last.prev.is_none());
+            forward.extend(ids(&last));
+            while let Some(next) = last.next.clone() {
+                last = page(expected, order, Some(next), limit, db).await;
+                forward.extend(ids(&last));
+            }
+            assert_eq!(forward, expected, "forward with limit {}", limit);
+
+            let mut backward = ids(&last);
+            let mut prev = last.prev.clone();
+            while let Some(cursor) = prev {
+                let page = page(expected, order, Some(cursor), limit, db).await;
+                backward.splice(0..0, ids(&page));
+                prev = page.prev;
+            }
+            assert_eq!(backward, expected, "backward with limit {}", limit);
+        }
+    }
+
+    async fn assert_null_sort_keys(db: &DbConn) {
+        let ascending = create_groups(db).await;
+        let descending: Vec<i32> = ascending.iter().rev().copied().collect();
+
+        assert_pages(Order::Asc, &ascending, db).await;
+        assert_pages(Order::Desc, &descending, db).await;
+    }
+
+    #[tokio::test]
+    async fn pages_cover_null_sort_keys_once() {
+        let test = TestDatabase::sqlite().await;
+
+        assert_null_sort_keys(&test.db).await;
+    }
+
+    #[tokio::test]
+    async fn pages_cover_null_sort_keys_once_on_postgres() {
+        if let Some(test) = TestDatabase::from_env("TASKRS_TEST_POSTGRES_URL").await {
+            assert_null_sort_keys(&test.db).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn pages_cover_null_sort_keys_once_on_mysql() {
+        if let Some(test) = TestDatabase::from_env("TASKRS_TEST_MYSQL_URL").await {
+            assert_null_sort_keys(&test.db).await;
+        }
+    }
+}
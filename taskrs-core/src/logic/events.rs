@@ -0,0 +1,262 @@
+//! In-process publishing of [`DomainEvent`]s.
+//!
+//! Changes emit events while they run, but events are only handed to subscribers once the
+//! change is committed. Logic that changes data runs inside [`after_commit`] or
+//! [`transaction`], nested calls leave publishing to the outermost one.
+
+use crate::error::Error;
+use crate::models::domain_event::DomainEvent;
+use async_trait::async_trait;
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{PoisonError, RwLock};
+use taskrs_db::sea_orm::{ConnectionTrait, DatabaseTransaction};
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+tokio::task_local! {
+    static PENDING_EVENTS: RefCell<Vec<DomainEvent>>;
+}
+
+static SUBSCRIBERS: RwLock<Vec<UnboundedSender<DomainEvent>>> = RwLock::new(Vec::new());
+
+/// Handler of published events
+#[async_trait]
+pub trait Subscriber: Send + Sync + 'static {
+    async fn handle(&self, event: &DomainEvent);
+}
+
+/// Registers a subscriber for all events published from now on.
+/// Every subscriber runs in its own task and receives events in the order they were published,
+/// so a slow subscriber holds up neither changes nor other subscribers.
+/// Has to be called inside a tokio runtime.
+pub fn subscribe<S>(subscriber: S)
+where
+    S: Subscriber,
+{
+    let (sender, mut receiver) = mpsc::unbounded_channel::<DomainEvent>();
+
+    tokio::spawn(async move {
+        while let Some(event) = receiver.recv().await {
+            subscriber.handle(&event).await;
+        }
+    });
+
+    SUBSCRIBERS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(sender);
+}
+
+fn publish(events: Vec<DomainEvent>) {
+    if events.is_empty() {
+        return;
+    }
+
+    let subscribers = SUBSCRIBERS.read().unwrap_or_else(PoisonError::into_inner);
+    for event in events {
+        for subscriber in subscribers.iter() {
+            // A closed channel means the subscriber task has ended
+            let _ = subscriber.send(event.clone());
+        }
+    }
+}
+
+/// Queues an event until the surrounding [`after_commit`] succeeded.
+/// Outside of it the event is published right away.
+pub(crate) fn emit(event: DomainEvent) {
+    match PENDING_EVENTS.try_with(|_| ()) {
+        Ok(()) => PENDING_EVENTS.with(|pending| pending.borrow_mut().push(event)),
+        Err(_) => publish(vec![event]),
+    }
+}
+
+/// Runs `future` and publishes the events it emitted once it succeeded.
+/// Events of a failed future are discarded.
+/// Wrap transactions in it, so their events are only published once committed.
+pub async fn after_commit<F, T, E>(future: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    // Nested, the outermost call publishes
+    if let Ok(queued) = PENDING_EVENTS.try_with(|pending| pending.borrow().len()) {
+        let result = future.await;
+        if result.is_err() {
+            PENDING_EVENTS.with(|pending| pending.borrow_mut().truncate(queued));
+        }
+        return result;
+    }
+
+    let (result, events) = PENDING_EVENTS
+        .scope(RefCell::new(Vec::new()), async move {
+            let result = future.await;
+            (result, PENDING_EVENTS.with(RefCell::take))
+        })
+        .await;
+
+    if result.is_ok() {
+        publish(events);
+    }
+
+    result
+}
+
+/// Runs `callback` in a transaction and publishes the events it emitted once committed
+pub async fn transaction<'a, C, F, T>(db: &'a C, callback: F) -> Result<T, Error>
+where
+    C: ConnectionTrait<'a>,
+    F: for<'c> FnOnce(
+            &'c DatabaseTransaction,
+        ) -> Pin<Box<dyn Future<Output = Result<T, Error>> + Send + 'c>>
+        + Send,
+    T: Send,
+{
+    after_commit(async move { db.transaction(callback).await.map_err(Error::from) }).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{after_commit, subscribe, transaction, Subscriber};
+    use crate::error::Error;
+    use crate::logic::tests::TestDatabase;
+    use crate::logic::{CreateModelTrait, ReadModelTrait};
+    use crate::models::domain_event::DomainEvent;
+    use crate::models::group::{Group, GroupCreate};
+    use async_trait::async_trait;
+    use std::time::Duration;
+    use taskrs_db::models::group;
+    use taskrs_db::sea_orm::sea_query::IntoCondition;
+    use taskrs_db::sea_orm::{ColumnTrait, ConnectionTrait, DbConn, DbErr};
+    use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+    /// Forwards the names of created groups starting with the marker of a test,
+    /// subscribers see the events of every test
+    struct CreatedGroups {
+        marker: String,
+        sender: UnboundedSender<String>,
+    }
+
+    #[async_trait]
+    impl Subscriber for CreatedGroups {
+        async fn handle(&self, event: &DomainEvent) {
+            if let DomainEvent::GroupCreated(group) = event {
+                if group.name.starts_with(&self.marker) {
+                    let _ = self.sender.send(group.name.clone());
+                }
+            }
+        }
+    }
+
+    /// Marker for the group names of a test and the names of its published groups
+    fn subscribe_groups() -> (String, UnboundedReceiver<String>) {
+        let marker = format!("events-{:016x}", rand::random::<u64>());
+        let (sender, receiver) = mpsc::unbounded_channel();
+        subscribe(CreatedGroups {
+            marker: marker.clone(),
+            sender,
+        });
+
+        (marker, receiver)
+    }
+
+    /// Names published so far, after giving the subscriber time to handle them
+    async fn published(receiver: &mut UnboundedReceiver<String>) -> Vec<String> {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let mut names = Vec::new();
+        while let Ok(name) = receiver.try_recv() {
+            names.push(name);
+        }
+        names
+    }
+
+    async fn create_group<'a, C>(name: &str, db: &'a C) -> Result<Group, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let group = GroupCreate {
+            name: name.to_string(),
+            ..Default::default()
+        };
+
+        Group::create(group, db).await
+    }
+
+    async fn group_exists(name: &str, db: &DbConn) -> bool {
+        let condition = group::Column::Name.eq(name).into_condition();
+        Group::find_one(condition, db).await.unwrap().is_some()
+    }
+
+    fn failure() -> Error {
+        Error::Database(DbErr::Custom("failed on purpose".to_string()))
+    }
+
+    #[tokio::test]
+    async fn events_are_published_once_committed() {
+        let test = TestDatabase::sqlite().await;
+        let (marker, receiver) = subscribe_groups();
+        let name = format!("{}-committed", marker);
+
+        let (mut receiver, before_commit) = transaction(&test.db, {
+            let name = name.clone();
+            |txn| {
+                Box::pin(async move {
+                    let mut receiver = receiver;
+                    create_group(&name, txn).await?;
+                    let before_commit = published(&mut receiver).await;
+                    Ok((receiver, before_commit))
+                })
+            }
+        })
+        .await
+        .unwrap();
+
+        assert!(before_commit.is_empty());
+        assert_eq!(published(&mut receiver).await, vec![name]);
+    }
+
+    #[tokio::test]
+    async fn events_of_rolled_back_transactions_are_discarded() {
+        let test = TestDatabase::sqlite().await;
+        let (marker, mut receiver) = subscribe_groups();
+        let name = format!("{}-rolled-back", marker);
+
+        let result = transaction(&test.db, {
+            let name = name.clone();
+            |txn| {
+                Box::pin(async move {
+                    create_group(&name, txn).await?;
+                    Err::<(), Error>(failure())
+                })
+            }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert!(published(&mut receiver).await.is_empty());
+        assert!(!group_exists(&name, &test.db).await);
+    }
+
+    #[tokio::test]
+    async fn failed_nested_scopes_discard_only_their_events() {
+        let test = TestDatabase::sqlite().await;
+        let (marker, mut receiver) = subscribe_groups();
+        let failed = format!("{}-inner", marker);
+        let kept = format!("{}-outer", marker);
+
+        let result = after_commit(async {
+            let inner = after_commit(async {
+                create_group(&failed, &test.db).await?;
+                Err::<(), Error>(failure())
+            })
+            .await;
+            assert!(inner.is_err());
+
+            create_group(&kept, &test.db).await
+        })
+        .await;
+
+        assert!(result.is_ok());
+        assert_eq!(published(&mut receiver).await, vec![kept]);
+    }
+}
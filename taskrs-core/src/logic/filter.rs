@@ -0,0 +1,172 @@
+use crate::error::FilterError;
+use crate::models::filter::{Filter, FilterField, FilterOperator, Sort, SortField};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use taskrs_db::sea_orm::sea_query::{IntoCondition, SimpleExpr};
+use taskrs_db::sea_orm::{ColumnTrait, ColumnType, Condition, IntoSimpleExpr, Order, Value};
+
+/// Builds the condition of the filters, validated against the allow-list `fields`
+pub(crate) fn filter_condition<C>(
+    filters: &[Filter],
+    fields: &[FilterField<C>],
+) -> Result<Condition, FilterError>
+where
+    C: ColumnTrait,
+{
+    let mut condition = Condition::all();
+
+    for filter in filters {
+        let field = fields
+            .iter()
+            .find(|field| field.name == filter.field)
+            .ok_or_else(|| FilterError::UnknownFilterField(filter.field.clone()))?;
+
+        if !field.allows(filter.operator) {
+            return Err(FilterError::OperatorNotAllowed {
+                field: filter.field.clone(),
+                operator: filter.operator.as_str().to_string(),
+            });
+        }
+
+        let column = field.column;
+        let parse = |value: &str| {
+            parse_value(column.def().get_column_type(), value).ok_or_else(|| {
+                FilterError::InvalidValue {
+                    field: filter.field.clone(),
+                    value: value.to_string(),
+                }
+            })
+        };
+
+        let expr = match filter.operator {
+            FilterOperator::Eq => column.eq(parse(&filter.value)?),
+            FilterOperator::Ne => column.ne(parse(&filter.value)?),
+            FilterOperator::Lt => column.lt(parse(&filter.value)?),
+            FilterOperator::Gt => column.gt(parse(&filter.value)?),
+            FilterOperator::In => column.is_in(
+                filter
+                    .value
+                    .split(',')
+                    .map(|value| parse(value.trim()))
+                    .collect::<Result<Vec<Value>, FilterError>>()?,
+            ),
+            FilterOperator::Like => column.like(&filter.value),
+            FilterOperator::IsNull => match filter.value.as_str() {
+                "true" | "" => column.is_null(),
+                "false" => column.is_not_null(),
+                _ => {
+                    return Err(FilterError::InvalidValue {
+                        field: filter.field.clone(),
+                        value: filter.value.clone(),
+                    })
+                }
+            },
+        };
+
+        condition = condition.add(expr.into_condition());
+    }
+
+    Ok(condition)
+}
+
+/// Builds the order of the sort keys, validated against the allow-list `fields`
+pub(crate) fn sort_order<C>(
+    sort: &[Sort],
+    fields: &[SortField<C>],
+) -> Result<Vec<(SimpleExpr, Order)>, FilterError>
+where
+    C: ColumnTrait,
+{
+    sort.iter()
+        .map(|sort| {
+            fields
+                .iter()
+                .find(|field| field.name == sort.field)
+                .map(|field| (field.column.into_simple_expr(), sort.order()))
+                .ok_or_else(|| FilterError::UnknownSortField(sort.field.clone()))
+        })
+        .collect()
+}
+
+/// Converts a query string value into the type of the column
+pub(crate) fn parse_value(column_type: &ColumnType, value: &str) -> Option<Value> {
+    match column_type {
+        ColumnType::TinyInteger | ColumnType::SmallInteger | ColumnType::Integer => {
+            value.parse::<i32>().ok().map(Value::from)
+        }
+        ColumnType::BigInteger => value.parse::<i64>().ok().map(Value::from),
+        ColumnType::Float | ColumnType::Double => value.parse::<f64>().ok().map(Value::from),
+        ColumnType::Boolean => value.parse::<bool>().ok().map(Value::from),
+        ColumnType::DateTime | ColumnType::Timestamp | ColumnType::TimestampWithTimeZone => {
+            parse_date_time(value).map(Value::from)
+        }
+        _ => Some(Value::from(value.to_string())),
+    }
+}
+
+/// Accepts RFC 3339 timestamps, timestamps without offset (UTC) and plain dates
+fn parse_date_time(value: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|date_time| date_time.naive_utc())
+        .or_else(|_| value.parse::<NaiveDateTime>())
+        .ok()
+        .or_else(|| {
+            value
+                .parse::<NaiveDate>()
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::filter_condition;
+    use crate::error::FilterError;
+    use crate::models::filter::{Filter, FilterField, FilterOperator};
+    use taskrs_db::models::user;
+
+    fn fields() -> Vec<FilterField<user::Column>> {
+        vec![
+            FilterField::new("id", user::Column::Id, FilterOperator::COMPARISON),
+            FilterField::new("enabled", user::Column::Enabled, FilterOperator::EQUALITY),
+        ]
+    }
+
+    fn filter(field: &str, operator: FilterOperator, value: &str) -> Filter {
+        Filter {
+            field: field.to_string(),
+            operator,
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn rejects_operators_the_field_does_not_allow() {
+        let filters = [filter("enabled", FilterOperator::Lt, "true")];
+
+        let result = filter_condition(&filters, &fields());
+
+        assert!(matches!(
+            result,
+            Err(FilterError::OperatorNotAllowed { field, operator })
+                if field == "enabled" && operator == "lt"
+        ));
+    }
+
+    #[test]
+    fn rejects_fields_outside_the_allow_list() {
+        let filters = [filter("password_hash", FilterOperator::Eq, "x")];
+
+        let result = filter_condition(&filters, &fields());
+
+        assert!(matches!(result, Err(FilterError::UnknownFilterField(f)) if f == "password_hash"));
+    }
+
+    #[test]
+    fn rejects_values_of_another_type() {
+        let filters = [filter("id", FilterOperator::In, "1,x")];
+
+        let result = filter_condition(&filters, &fields());
+
+        assert!(matches!(result, Err(FilterError::InvalidValue { value, .. }) if value == "x"));
+    }
+}
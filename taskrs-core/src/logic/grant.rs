@@ -0,0 +1,383 @@
+//! Atomic changes of grants, the rows of join tables like the roles of a user.
+//!
+//! A [`GrantUnitOfWork`] changes the grants of one owner in a transaction begun by its caller.
+//! Every grant is inserted, updated or removed by its own statement that only touches it
+//! if it is not in the requested state yet. Concurrent changes of the same grants therefore
+//! neither fail on the primary key nor get reported twice,
+//! the collected [`GrantChanges`] only list what this unit of work actually changed.
+
+use crate::error::Error;
+use crate::logic::insert_ignoring_conflicts;
+use crate::models::grant::GrantChanges;
+use crate::models::IntoActiveModel;
+use std::collections::HashSet;
+use taskrs_db::sea_orm::sea_query::{sea_value_to_json_value, Expr};
+use taskrs_db::sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait, ActiveValue, ColumnTrait, Condition, ConnectionTrait,
+    DatabaseTransaction, DbErr, EntityTrait, IdenStatic, Iterable, QueryFilter, QuerySelect,
+    QueryTrait, Value,
+};
+use taskrs_db::utils::QueryId;
+
+/// Grants of one owner, e.g. the roles of user 1, changed in an explicit transaction
+pub(crate) struct GrantUnitOfWork<'t, E>
+where
+    E: EntityTrait,
+{
+    txn: &'t DatabaseTransaction,
+    owner_column: E::Column,
+    owner_id: i32,
+    target_column: E::Column,
+    changes: GrantChanges,
+}
+
+impl<'t, E> GrantUnitOfWork<'t, E>
+where
+    E: EntityTrait,
+{
+    /// Changes the grants whose `owner_column` is `owner_id`.
+    /// Grants are identified by the id in `target_column`, like the role id of a user role.
+    pub(crate) fn new(
+        txn: &'t DatabaseTransaction,
+        owner_column: E::Column,
+        owner_id: i32,
+        target_column: E::Column,
+    ) -> Self {
+        Self {
+            txn,
+            owner_column,
+            owner_id,
+            target_column,
+            changes: GrantChanges::default(),
+        }
+    }
+
+    /// Target ids of the grants of the owner matching `condition`
+    pub(crate) async fn granted_ids(&self, condition: Condition) -> Result<HashSet<i32>, Error> {
+        let ids = E::find()
+            .select_only()
+            .column_as(self.target_column, QueryId::Id)
+            .filter(self.owner_column.eq(self.owner_id))
+            .filter(condition)
+            .into_values::<_, QueryId>()
+            .all(self.txn)
+            .await?;
+
+        Ok(ids.into_iter().collect())
+    }
+
+    /// Inserts grants that do not exist yet, existing grants are kept as they are
+    pub(crate) async fn insert<M, A>(&mut self, grants: Vec<M>) -> Result<(), Error>
+    where
+        M: IntoActiveModel<A>,
+        A: ActiveModelTrait<Entity = E> + ActiveModelBehavior + Send,
+    {
+        for grant in grants {
+            self.save(grant.into_active_model(), false).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Inserts grants that do not exist yet and overwrites the attributes
+    /// of existing grants, like their effect or validity
+    pub(crate) async fn upsert<M, A>(&mut self, grants: Vec<M>) -> Result<(), Error>
+    where
+        M: IntoActiveModel<A>,
+        A: ActiveModelTrait<Entity = E> + ActiveModelBehavior + Send,
+    {
+        for grant in grants {
+            self.save(grant.into_active_model(), true).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes the grants of the targets
+    pub(crate) async fn remove<I>(&mut self, target_ids: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = i32>,
+    {
+        for target_id in target_ids {
+            let result = E::delete_many()
+                .filter(self.owner_column.eq(self.owner_id))
+                .filter(self.target_column.eq(target_id))
+                .exec(self.txn)
+                .await?;
+
+            if result.rows_affected > 0 {
+                self.changes.removed.push(target_id);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the grants matching `condition` by `grants`.
+    /// Grants that are kept get the attributes of their replacement.
+    pub(crate) async fn replace<M, A>(
+        &mut self,
+        condition: Condition,
+        grants: Vec<M>,
+    ) -> Result<(), Error>
+    where
+        M: IntoActiveModel<A>,
+        A: ActiveModelTrait<Entity = E> + ActiveModelBehavior + Send,
+    {
+        let models: Vec<A> = grants.into_iter().map(|g| g.into_active_model()).collect();
+        let kept_ids: HashSet<i32> = models
+            .iter()
+            .filter_map(|model| self.target_id(model))
+            .collect();
+
+        let granted_ids = self.granted_ids(condition).await?;
+        self.remove(&granted_ids - &kept_ids).await?;
+
+        for model in models {
+            self.save(model, true).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Changes made so far, in ascending order of the target ids
+    pub(crate) fn into_changes(self) -> GrantChanges {
+        let mut changes = self.changes;
+        changes.added.sort_unstable();
+        changes.updated.sort_unstable();
+        changes.removed.sort_unstable();
+        changes
+    }
+
+    fn target_id<A>(&self, model: &A) -> Option<i32>
+    where
+        A: ActiveModelTrait<Entity = E>,
+    {
+        match model.get(self.target_column).into_value() {
+            Some(Value::Int(Some(id))) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// Inserts the grant if it does not exist yet,
+    /// otherwise updates its attributes if `update` is set and they differ
+    async fn save<A>(&mut self, model: A, update: bool) -> Result<(), Error>
+    where
+        A: ActiveModelTrait<Entity = E> + ActiveModelBehavior + Send,
+    {
+        let target_id = self
+            .target_id(&model)
+            .ok_or_else(|| DbErr::Custom("Grant has no target id".to_string()))?;
+
+        // Attributes are collected before timestamps are set, those are never overwritten
+        let key = [self.owner_column.as_str(), self.target_column.as_str()];
+        let attributes: Vec<(E::Column, Value)> = E::Column::iter()
+            .filter(|column| !key.contains(&column.as_str()))
+            .filter_map(|column| match model.get(column) {
+                ActiveValue::Set(value) => Some((column, value)),
+                _ => None,
+            })
+            .collect();
+
+        // Insert and ignore existing grants
+        let model = ActiveModelBehavior::before_save(model, true)?;
+        let statement = insert_ignoring_conflicts(
+            self.txn.get_database_backend(),
+            &E::insert(model).into_query(),
+        );
+
+        if self.txn.execute(statement).await?.rows_affected() > 0 {
+            self.changes.added.push(target_id);
+            return Ok(());
+        }
+
+        if !update || attributes.is_empty() {
+            return Ok(());
+        }
+
+        // Update existing grant, unless it already has the attributes
+        let mut differs = Condition::any();
+        let mut query = E::update_many()
+            .filter(self.owner_column.eq(self.owner_id))
+            .filter(self.target_column.eq(target_id));
+        for (column, value) in attributes {
+            differs = differs.add(value_differs(column, value.clone()));
+            query = query.col_expr(column, Expr::value(value));
+        }
+
+        if query.filter(differs).exec(self.txn).await?.rows_affected > 0 {
+            self.changes.updated.push(target_id);
+        }
+
+        Ok(())
+    }
+}
+
+/// Condition matching rows whose `column` is not `value`, treating NULL as a regular value
+fn value_differs<C>(column: C, value: Value) -> Condition
+where
+    C: ColumnTrait,
+{
+    if is_null(&value) {
+        Condition::all().add(column.is_not_null())
+    } else {
+        Condition::any().add(column.is_null()).add(column.ne(value))
+    }
+}
+
+/// Whether `value` is NULL, whatever its type
+fn is_null(value: &Value) -> bool {
+    match value {
+        // Rendered as the string "NULL" by the JSON conversion
+        Value::Date(None)
+        | Value::Time(None)
+        | Value::DateTime(None)
+        | Value::DateTimeWithTimeZone(None) => true,
+        value => sea_value_to_json_value(value).is_null(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{Error, RbacError};
+    use crate::logic::tests::TestDatabase;
+    use crate::logic::CreateModelTrait;
+    use crate::models::grant::GrantValidity;
+    use crate::models::role::{Role, RoleCreate};
+    use crate::models::user::{User, UserCreate};
+    use crate::seeding;
+    use futures::future::join_all;
+    use taskrs_db::sea_orm::DbConn;
+
+    async fn create_user(email: &str, db: &DbConn) -> i32 {
+        let user = UserCreate {
+            email: email.to_string(),
+            password: "password".to_string(),
+            enabled: true,
+            ..Default::default()
+        };
+
+        User::create(user, db).await.unwrap().id
+    }
+
+    async fn create_role(name: &str, db: &DbConn) -> i32 {
+        let role = RoleCreate {
+            name: name.to_string(),
+            ..Default::default()
+        };
+
+        Role::create(role, db).await.unwrap().id
+    }
+
+    async fn role_ids(user_id: i32, db: &DbConn) -> Vec<i32> {
+        let mut ids: Vec<i32> = User::roles(user_id, db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|role| role.role.id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_grants_of_the_same_role_add_it_once() {
+        let test = TestDatabase::sqlite().await;
+        let db = &test.db;
+        let user_id = create_user("user@taskrs.com", db).await;
+        let role_id = create_role("role", db).await;
+
+        let results = join_all(
+            (0..8).map(|_| User::grant_roles(user_id, vec![role_id], GrantValidity::default(), db)),
+        )
+        .await;
+
+        let added: usize = results
+            .into_iter()
+            .map(|changes| changes.unwrap().added.len())
+            .sum();
+        assert_eq!(added, 1);
+        assert_eq!(role_ids(user_id, db).await, vec![role_id]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_grants_of_different_roles_lose_none() {
+        let test = TestDatabase::sqlite().await;
+        let db = &test.db;
+        let user_id = create_user("user@taskrs.com", db).await;
+        let mut expected = Vec::new();
+        for i in 0..8 {
+            expected.push(create_role(&format!("role-{}", i), db).await);
+        }
+
+        let results = join_all(expected.iter().map(|role_id| {
+            User::grant_roles(user_id, vec![*role_id], GrantValidity::default(), db)
+        }))
+        .await;
+
+        for changes in results {
+            assert_eq!(changes.unwrap().added.len(), 1);
+        }
+        assert_eq!(role_ids(user_id, db).await, expected);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_revokes_of_the_same_role_remove_it_once() {
+        let test = TestDatabase::sqlite().await;
+        let db = &test.db;
+        let user_id = create_user("user@taskrs.com", db).await;
+        let role_id = create_role("role", db).await;
+        let kept_id = create_role("kept", db).await;
+        User::grant_roles(
+            user_id,
+            vec![role_id, kept_id],
+            GrantValidity::default(),
+            db,
+        )
+        .await
+        .unwrap();
+
+        let results =
+            join_all((0..8).map(|_| User::revoke_roles(user_id, vec![role_id], db))).await;
+
+        let removed: usize = results
+            .into_iter()
+            .map(|changes| changes.unwrap().removed.len())
+            .sum();
+        assert_eq!(removed, 1);
+        assert_eq!(role_ids(user_id, db).await, vec![kept_id]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_revokes_keep_the_last_root_user() {
+        let test = TestDatabase::sqlite().await;
+        let db = &test.db;
+        let root_role_id = seeding::seed_root_role(db).await.unwrap().id;
+        let first_id = create_user("first@taskrs.com", db).await;
+        let second_id = create_user("second@taskrs.com", db).await;
+        for user_id in [first_id, second_id] {
+            User::grant_roles(user_id, vec![root_role_id], GrantValidity::default(), db)
+                .await
+                .unwrap();
+        }
+
+        let results = join_all(
+            [first_id, second_id]
+                .into_iter()
+                .map(|user_id| User::revoke_roles(user_id, vec![root_role_id], db)),
+        )
+        .await;
+
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+        assert_eq!(Role::root_user_count(db).await.unwrap(), 1);
+
+        let remaining_id = if role_ids(first_id, db).await.is_empty() {
+            second_id
+        } else {
+            first_id
+        };
+        let result = User::revoke_roles(remaining_id, vec![root_role_id], db).await;
+        assert!(matches!(result, Err(Error::Rbac(RbacError::LastRootUser))));
+        assert_eq!(role_ids(remaining_id, db).await, vec![root_role_id]);
+    }
+}
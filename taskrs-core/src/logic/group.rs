@@ -0,0 +1,581 @@
+use crate::error::Error;
+use crate::logic::grant::GrantUnitOfWork;
+use crate::logic::validation::{Validate, Validator, MAX_STRING_LENGTH};
+use crate::logic::{audit, events};
+use crate::logic::{
+    AuditedModelTrait, CreateModelTrait, DeleteModelTrait, EventedModelTrait, FilterableModelTrait,
+    PaginatedModelTrait, ReadModelTrait, UpdateModelTrait,
+};
+use crate::models::audit_event::AuditAction;
+use crate::models::domain_event::{DomainEvent, Lifecycle};
+use crate::models::filter::{FilterField, FilterOperator, SortField};
+use crate::models::grant::GrantChanges;
+use crate::models::group::{Group, GroupCreate, GroupUpdate};
+use crate::models::group_member::GroupMember;
+use crate::models::group_role::GroupRole;
+use crate::models::role::Role;
+use crate::models::user::User;
+use async_trait::async_trait;
+use std::collections::HashSet;
+use taskrs_db::models::{group, group_member, group_role, role, user};
+use taskrs_db::sea_orm::prelude::*;
+use taskrs_db::sea_orm::sea_query::{IntoCondition, SimpleExpr};
+use taskrs_db::sea_orm::{
+    Condition, ConnectionTrait, DatabaseTransaction, DeleteResult, IntoSimpleExpr, JoinType, Order,
+    QuerySelect,
+};
+
+const AUDIT_ENTITY_TYPE: &str = "group";
+
+impl Group {
+    pub async fn members<'a, C>(group_id: i32, db: &'a C) -> Result<Vec<User>, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        user::Entity::find()
+            .filter(User::not_deleted_condition())
+            .filter(group_member::Column::GroupId.eq(group_id))
+            .join_rev(JoinType::InnerJoin, group_member::Relation::User.def())
+            .all(db)
+            .await
+            .map(|models| models.into_iter().map(User::from).collect())
+            .map_err(Error::Database)
+    }
+
+    /// Adds users to the group, existing members are kept
+    pub async fn add_members<'a, C>(
+        group_id: i32,
+        user_ids: Vec<i32>,
+        db: &'a C,
+    ) -> Result<GrantChanges, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        // Transaction
+        events::transaction(db, |txn| {
+            Box::pin(async move {
+                let before = Self::members_state(group_id, txn).await?;
+
+                // Insert only new members
+                let group_members: Vec<GroupMember> = user_ids
+                    .into_iter()
+                    .collect::<HashSet<i32>>()
+                    .into_iter()
+                    .map(|user_id| GroupMember {
+                        group_id,
+                        user_id,
+                        inserted_at: None,
+                    })
+                    .collect();
+
+                let mut grants = Self::member_grants(group_id, txn);
+                grants.insert(group_members).await?;
+                let changes = grants.into_changes();
+
+                let after = Self::members_state(group_id, txn).await?;
+                audit::record(
+                    AuditAction::AddMembers,
+                    AUDIT_ENTITY_TYPE,
+                    group_id,
+                    before,
+                    after,
+                    txn,
+                )
+                .await?;
+                if !changes.is_empty() {
+                    events::emit(DomainEvent::GroupMembersChanged { group_id });
+                }
+
+                Ok(changes)
+            })
+        })
+        .await
+    }
+
+    /// Removes members from the group.
+    /// Fails if no enabled user with the root role would be left.
+    pub async fn remove_members<'a, C>(
+        group_id: i32,
+        user_ids: Vec<i32>,
+        db: &'a C,
+    ) -> Result<GrantChanges, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        // Transaction
+        events::transaction(db, |txn| {
+            Box::pin(async move {
+                let root_user_count = Role::root_user_count(txn).await?;
+                let before = Self::members_state(group_id, txn).await?;
+
+                let mut grants = Self::member_grants(group_id, txn);
+                grants
+                    .remove(user_ids.into_iter().collect::<HashSet<i32>>())
+                    .await?;
+                let changes = grants.into_changes();
+
+                Role::ensure_root_user_remains(root_user_count, txn).await?;
+
+                let after = Self::members_state(group_id, txn).await?;
+                audit::record(
+                    AuditAction::RemoveMembers,
+                    AUDIT_ENTITY_TYPE,
+                    group_id,
+                    before,
+                    after,
+                    txn,
+                )
+                .await?;
+                if !changes.is_empty() {
+                    events::emit(DomainEvent::GroupMembersChanged { group_id });
+                }
+
+                Ok(changes)
+            })
+        })
+        .await
+    }
+
+    /// Replaces the members of the group.
+    /// Fails if no enabled user with the root role would be left.
+    pub async fn set_members<'a, C>(
+        group_id: i32,
+        user_ids: Vec<i32>,
+        db: &'a C,
+    ) -> Result<GrantChanges, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        // Create models for inserting
+        let new_group_members: Vec<GroupMember> = user_ids
+            .into_iter()
+            .collect::<HashSet<i32>>()
+            .into_iter()
+            .map(|user_id| GroupMember {
+                group_id,
+                user_id,
+                inserted_at: None,
+            })
+            .collect();
+
+        // Transaction
+        events::transaction(db, |txn| {
+            Box::pin(async move {
+                let root_user_count = Role::root_user_count(txn).await?;
+                let before = Self::members_state(group_id, txn).await?;
+
+                // Replace all members
+                let mut grants = Self::member_grants(group_id, txn);
+                grants.replace(Condition::all(), new_group_members).await?;
+                let changes = grants.into_changes();
+
+                Role::ensure_root_user_remains(root_user_count, txn).await?;
+
+                let after = Self::members_state(group_id, txn).await?;
+                audit::record(
+                    AuditAction::SetMembers,
+                    AUDIT_ENTITY_TYPE,
+                    group_id,
+                    before,
+                    after,
+                    txn,
+                )
+                .await?;
+                if !changes.is_empty() {
+                    events::emit(DomainEvent::GroupMembersChanged { group_id });
+                }
+
+                Ok(changes)
+            })
+        })
+        .await
+    }
+
+    pub async fn roles<'a, C>(group_id: i32, db: &'a C) -> Result<Vec<Role>, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        role::Entity::find()
+            .filter(group_role::Column::GroupId.eq(group_id))
+            .join_rev(JoinType::InnerJoin, group_role::Relation::Role.def())
+            .all(db)
+            .await
+            .map(|models| models.into_iter().map(Role::from).collect())
+            .map_err(Error::Database)
+    }
+
+    /// Grants roles to the group, roles that are already granted are kept
+    pub async fn grant_roles<'a, C>(
+        group_id: i32,
+        role_ids: Vec<i32>,
+        db: &'a C,
+    ) -> Result<GrantChanges, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        // Transaction
+        events::transaction(db, |txn| {
+            Box::pin(async move {
+                let before = Self::roles_state(group_id, txn).await?;
+
+                // Insert only new roles
+                let group_roles: Vec<GroupRole> = role_ids
+                    .into_iter()
+                    .collect::<HashSet<i32>>()
+                    .into_iter()
+                    .map(|role_id| GroupRole {
+                        group_id,
+                        role_id,
+                        inserted_at: None,
+                    })
+                    .collect();
+
+                let mut grants = Self::role_grants(group_id, txn);
+                grants.insert(group_roles).await?;
+                let changes = grants.into_changes();
+
+                let after = Self::roles_state(group_id, txn).await?;
+                audit::record(
+                    AuditAction::GrantRoles,
+                    AUDIT_ENTITY_TYPE,
+                    group_id,
+                    before,
+                    after,
+                    txn,
+                )
+                .await?;
+                if !changes.is_empty() {
+                    events::emit(DomainEvent::GroupRolesChanged { group_id });
+                }
+
+                Ok(changes)
+            })
+        })
+        .await
+    }
+
+    /// Revokes roles of the group.
+    /// Fails if no enabled user with the root role would be left.
+    pub async fn revoke_roles<'a, C>(
+        group_id: i32,
+        role_ids: Vec<i32>,
+        db: &'a C,
+    ) -> Result<GrantChanges, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        // Transaction
+        events::transaction(db, |txn| {
+            Box::pin(async move {
+                let root_user_count = Role::root_user_count(txn).await?;
+                let before = Self::roles_state(group_id, txn).await?;
+
+                let mut grants = Self::role_grants(group_id, txn);
+                grants
+                    .remove(role_ids.into_iter().collect::<HashSet<i32>>())
+                    .await?;
+                let changes = grants.into_changes();
+
+                Role::ensure_root_user_remains(root_user_count, txn).await?;
+
+                let after = Self::roles_state(group_id, txn).await?;
+                audit::record(
+                    AuditAction::RevokeRoles,
+                    AUDIT_ENTITY_TYPE,
+                    group_id,
+                    before,
+                    after,
+                    txn,
+                )
+                .await?;
+                if !changes.is_empty() {
+                    events::emit(DomainEvent::GroupRolesChanged { group_id });
+                }
+
+                Ok(changes)
+            })
+        })
+        .await
+    }
+
+    /// Replaces the roles of the group.
+    /// Fails if no enabled user with the root role would be left.
+    pub async fn set_roles<'a, C>(
+        group_id: i32,
+        role_ids: Vec<i32>,
+        db: &'a C,
+    ) -> Result<GrantChanges, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        // Create models for inserting
+        let new_group_roles: Vec<GroupRole> = role_ids
+            .into_iter()
+            .collect::<HashSet<i32>>()
+            .into_iter()
+            .map(|role_id| GroupRole {
+                group_id,
+                role_id,
+                inserted_at: None,
+            })
+            .collect();
+
+        // Transaction
+        events::transaction(db, |txn| {
+            Box::pin(async move {
+                let root_user_count = Role::root_user_count(txn).await?;
+                let before = Self::roles_state(group_id, txn).await?;
+
+                // Replace all group roles
+                let mut grants = Self::role_grants(group_id, txn);
+                grants.replace(Condition::all(), new_group_roles).await?;
+                let changes = grants.into_changes();
+
+                Role::ensure_root_user_remains(root_user_count, txn).await?;
+
+                let after = Self::roles_state(group_id, txn).await?;
+                audit::record(
+                    AuditAction::SetRoles,
+                    AUDIT_ENTITY_TYPE,
+                    group_id,
+                    before,
+                    after,
+                    txn,
+                )
+                .await?;
+                if !changes.is_empty() {
+                    events::emit(DomainEvent::GroupRolesChanged { group_id });
+                }
+
+                Ok(changes)
+            })
+        })
+        .await
+    }
+
+    /// Members of the group, changed in the transaction
+    fn member_grants(
+        group_id: i32,
+        txn: &DatabaseTransaction,
+    ) -> GrantUnitOfWork<'_, group_member::Entity> {
+        GrantUnitOfWork::new(
+            txn,
+            group_member::Column::GroupId,
+            group_id,
+            group_member::Column::UserId,
+        )
+    }
+
+    /// Role grants of the group, changed in the transaction
+    fn role_grants(
+        group_id: i32,
+        txn: &DatabaseTransaction,
+    ) -> GrantUnitOfWork<'_, group_role::Entity> {
+        GrantUnitOfWork::new(
+            txn,
+            group_role::Column::GroupId,
+            group_id,
+            group_role::Column::RoleId,
+        )
+    }
+
+    /// Members of the group keyed by user id, recorded as state of audit events
+    async fn members_state<'a, C>(group_id: i32, db: &'a C) -> Result<serde_json::Value, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let members = GroupMember::find(
+            group_member::Column::GroupId.eq(group_id).into_condition(),
+            db,
+        )
+        .await?;
+
+        Ok(audit::snapshot(&members, |member| member.user_id))
+    }
+
+    /// Roles of the group keyed by role id, recorded as state of audit events
+    async fn roles_state<'a, C>(group_id: i32, db: &'a C) -> Result<serde_json::Value, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let roles = GroupRole::find(
+            group_role::Column::GroupId.eq(group_id).into_condition(),
+            db,
+        )
+        .await?;
+
+        Ok(audit::snapshot(&roles, |role| role.role_id))
+    }
+}
+
+impl AuditedModelTrait for Group {
+    fn audit_entity_type() -> Option<&'static str> {
+        Some(AUDIT_ENTITY_TYPE)
+    }
+}
+
+impl EventedModelTrait for Group {
+    fn lifecycle_event() -> Option<fn(Lifecycle, Self) -> DomainEvent> {
+        Some(|lifecycle, group| match lifecycle {
+            Lifecycle::Created => DomainEvent::GroupCreated(group),
+            Lifecycle::Updated => DomainEvent::GroupUpdated(group),
+            Lifecycle::Deleted => DomainEvent::GroupDeleted(group),
+        })
+    }
+}
+
+impl CreateModelTrait<group::Entity, group::ActiveModel, GroupCreate> for Group {}
+
+impl ReadModelTrait<group::Entity> for Group {}
+
+impl UpdateModelTrait<group::Entity, group::ActiveModel, GroupUpdate> for Group {
+    fn version_column() -> Option<group::Column> {
+        Some(group::Column::Version)
+    }
+}
+
+#[async_trait]
+impl Validate for GroupCreate {
+    async fn rules<'a, C>(&self, validator: &mut Validator, _db: &'a C) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        validator
+            .required("name", &self.name)
+            .max_length("name", &self.name, MAX_STRING_LENGTH);
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Validate for GroupUpdate {
+    async fn rules<'a, C>(&self, validator: &mut Validator, _db: &'a C) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        if let Some(name) = &self.name {
+            validator
+                .required("name", name)
+                .max_length("name", name, MAX_STRING_LENGTH);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DeleteModelTrait<group::Entity, group::ActiveModel> for Group {
+    /// Delete a group.
+    /// Fails if no enabled user with the root role would be left.
+    async fn delete<'a, C>(id: i32, db: &'a C) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        // Transaction
+        events::transaction(db, |txn| {
+            Box::pin(async move {
+                let root_user_count = Role::root_user_count(txn).await?;
+
+                let model = group::Entity::find_by_id(id).one(txn).await?;
+                if let Some(model) = model {
+                    let group = Self::from(model.clone());
+                    model.delete(txn).await?;
+                    audit::record_model(
+                        AuditAction::Delete,
+                        AUDIT_ENTITY_TYPE,
+                        Some(&group),
+                        None,
+                        txn,
+                    )
+                    .await?;
+                    events::emit(DomainEvent::GroupDeleted(group));
+                }
+
+                Role::ensure_root_user_remains(root_user_count, txn).await
+            })
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Delete groups using a condition.
+    /// Fails if no enabled user with the root role would be left.
+    async fn delete_condition<'a, C>(condition: Condition, db: &'a C) -> Result<DeleteResult, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        // Transaction
+        let result = events::transaction(db, |txn| {
+            Box::pin(async move {
+                let root_user_count = Role::root_user_count(txn).await?;
+
+                let groups = Self::find(condition.clone(), txn).await?;
+                let result = group::Entity::delete_many()
+                    .filter(condition)
+                    .exec(txn)
+                    .await?;
+
+                Role::ensure_root_user_remains(root_user_count, txn).await?;
+
+                for group in &groups {
+                    audit::record_model(
+                        AuditAction::Delete,
+                        AUDIT_ENTITY_TYPE,
+                        Some(group),
+                        None,
+                        txn,
+                    )
+                    .await?;
+                    events::emit(DomainEvent::GroupDeleted(group.clone()));
+                }
+
+                Ok(result)
+            })
+        })
+        .await?;
+
+        Ok(result)
+    }
+}
+
+impl PaginatedModelTrait<group::Entity, group::ActiveModel> for Group {
+    fn default_order() -> (SimpleExpr, Order) {
+        (group::Column::Id.into_simple_expr(), Order::Asc)
+    }
+}
+
+impl FilterableModelTrait<group::Entity, group::ActiveModel> for Group {
+    fn filter_fields() -> Vec<FilterField<group::Column>> {
+        vec![
+            FilterField::new("id", group::Column::Id, FilterOperator::COMPARISON),
+            FilterField::new("name", group::Column::Name, FilterOperator::TEXT),
+            FilterField::new(
+                "description",
+                group::Column::Description,
+                FilterOperator::TEXT,
+            )
+            .nullable(),
+            FilterField::new(
+                "inserted_at",
+                group::Column::InsertedAt,
+                FilterOperator::COMPARISON,
+            ),
+            FilterField::new(
+                "updated_at",
+                group::Column::UpdatedAt,
+                FilterOperator::COMPARISON,
+            ),
+        ]
+    }
+
+    fn sort_fields() -> Vec<SortField<group::Column>> {
+        vec![
+            SortField::new("id", group::Column::Id),
+            SortField::new("name", group::Column::Name),
+            SortField::new("inserted_at", group::Column::InsertedAt),
+            SortField::new("updated_at", group::Column::UpdatedAt),
+        ]
+    }
+}
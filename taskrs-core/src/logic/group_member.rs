@@ -0,0 +1,21 @@
+use crate::logic::validation::Validate;
+use crate::logic::{
+    AuditedModelTrait, CreateModelTrait, DeleteModelTrait, EventedModelTrait, ReadModelTrait,
+};
+use crate::models::group_member::GroupMember;
+use taskrs_db::models::group_member;
+
+impl AuditedModelTrait for GroupMember {}
+
+impl EventedModelTrait for GroupMember {}
+
+impl Validate for GroupMember {}
+
+impl CreateModelTrait<group_member::Entity, group_member::ActiveModel, GroupMember>
+    for GroupMember
+{
+}
+
+impl ReadModelTrait<group_member::Entity> for GroupMember {}
+
+impl DeleteModelTrait<group_member::Entity, group_member::ActiveModel> for GroupMember {}
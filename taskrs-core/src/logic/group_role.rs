@@ -0,0 +1,18 @@
+use crate::logic::validation::Validate;
+use crate::logic::{
+    AuditedModelTrait, CreateModelTrait, DeleteModelTrait, EventedModelTrait, ReadModelTrait,
+};
+use crate::models::group_role::GroupRole;
+use taskrs_db::models::group_role;
+
+impl AuditedModelTrait for GroupRole {}
+
+impl EventedModelTrait for GroupRole {}
+
+impl Validate for GroupRole {}
+
+impl CreateModelTrait<group_role::Entity, group_role::ActiveModel, GroupRole> for GroupRole {}
+
+impl ReadModelTrait<group_role::Entity> for GroupRole {}
+
+impl DeleteModelTrait<group_role::Entity, group_role::ActiveModel> for GroupRole {}
@@ -0,0 +1,129 @@
+use crate::error::Error;
+use crate::logic::insert_ignoring_conflicts;
+use crate::models::idempotency_key::{
+    IdempotencyClaim, IdempotencyKey, IdempotencyKeyCreate, StoredResponse,
+};
+use crate::models::IntoActiveModel;
+use taskrs_db::models::idempotency_key;
+use taskrs_db::sea_orm::sea_query::Expr;
+use taskrs_db::sea_orm::{
+    ActiveModelBehavior, ColumnTrait, Condition, ConnectionTrait, DbErr, EntityTrait, QueryFilter,
+    QueryTrait,
+};
+
+impl IdempotencyKey {
+    /// Claims the key for a request, unless an earlier request already did.
+    /// Concurrent claims of the same key are decided by the unique index on scope and key.
+    /// Expired keys can be claimed again, as can keys whose request outlived its lease
+    /// without storing a response, e.g. because the server crashed while running it.
+    pub async fn claim<'a, C>(
+        create: IdempotencyKeyCreate,
+        db: &'a C,
+    ) -> Result<IdempotencyClaim, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let now = chrono::Utc::now().naive_utc();
+        let condition = idempotency_key::Column::Scope
+            .eq(create.scope.clone())
+            .and(idempotency_key::Column::Key.eq(create.key.clone()));
+
+        let abandoned = idempotency_key::Column::ResponseStatus
+            .is_null()
+            .and(idempotency_key::Column::LockedUntil.lte(now));
+        idempotency_key::Entity::delete_many()
+            .filter(condition.clone())
+            .filter(
+                Condition::any()
+                    .add(idempotency_key::Column::ExpiresAt.lte(now))
+                    .add(abandoned),
+            )
+            .exec(db)
+            .await?;
+
+        let model = ActiveModelBehavior::before_save(create.into_active_model(), true)?;
+        let statement = insert_ignoring_conflicts(
+            db.get_database_backend(),
+            &idempotency_key::Entity::insert(model).into_query(),
+        );
+        let claimed = db.execute(statement).await?.rows_affected() > 0;
+
+        let key = idempotency_key::Entity::find()
+            .filter(condition)
+            .one(db)
+            .await?
+            .map(IdempotencyKey::from)
+            .ok_or_else(|| {
+                Error::Database(DbErr::RecordNotFound(
+                    "Idempotency key was removed while it was claimed".to_string(),
+                ))
+            })?;
+
+        match claimed {
+            true => Ok(IdempotencyClaim::Claimed(key)),
+            false => Ok(IdempotencyClaim::Existing(key)),
+        }
+    }
+
+    /// Stores the response of the request that claimed the key and ends its lease.
+    /// Nothing is stored if another request took over the key after the lease ended.
+    pub async fn complete<'a, C>(id: i32, response: StoredResponse, db: &'a C) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let headers = serde_json::to_string(&response.headers).unwrap_or_default();
+        idempotency_key::Entity::update_many()
+            .col_expr(
+                idempotency_key::Column::ResponseStatus,
+                Expr::value(response.status as i32),
+            )
+            .col_expr(
+                idempotency_key::Column::ResponseContentType,
+                Expr::value(response.content_type),
+            )
+            .col_expr(
+                idempotency_key::Column::ResponseBody,
+                Expr::value(response.body),
+            )
+            .col_expr(
+                idempotency_key::Column::ResponseHeaders,
+                Expr::value(headers),
+            )
+            .col_expr(
+                idempotency_key::Column::LockedUntil,
+                Expr::value(Option::<chrono::NaiveDateTime>::None),
+            )
+            .filter(idempotency_key::Column::Id.eq(id))
+            .exec(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Gives up the key without storing a response, so that a retry runs the request again
+    pub async fn release<'a, C>(id: i32, db: &'a C) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        idempotency_key::Entity::delete_many()
+            .filter(idempotency_key::Column::Id.eq(id))
+            .exec(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Removes expired keys, returns how many were removed
+    pub async fn delete_expired<'a, C>(db: &'a C) -> Result<u64, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let now = chrono::Utc::now().naive_utc();
+        let result = idempotency_key::Entity::delete_many()
+            .filter(idempotency_key::Column::ExpiresAt.lte(now))
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected)
+    }
+}
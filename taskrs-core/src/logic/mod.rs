@@ -1,21 +1,128 @@
+mod audit;
 pub mod auth;
+mod cursor;
+pub mod events;
+mod filter;
+mod grant;
+mod group;
+mod group_member;
+mod group_role;
+mod idempotency_key;
 mod permission;
 mod refresh_token;
 mod role;
 mod role_permission;
+mod search;
 mod user;
 mod user_permission;
 mod user_role;
+pub mod validation;
 
+use crate::error::{ConcurrencyError, Error, FilterError};
+use crate::logic::validation::Validate;
+use crate::models::audit_event::AuditAction;
+use crate::models::domain_event::{DomainEvent, Lifecycle};
+use crate::models::filter::{FilterField, ListQuery, SortField};
+use crate::models::pagination::{Cursor, CursorDirection, CursorPage};
 use crate::models::IntoActiveModel;
 use async_trait::async_trait;
 use futures::try_join;
-use taskrs_db::sea_orm::sea_query::SimpleExpr;
+use serde::Serialize;
+use std::collections::HashSet;
+use taskrs_db::sea_orm::sea_query::{FromValueTuple, InsertStatement, SimpleExpr};
 use taskrs_db::sea_orm::{
-    ActiveModelBehavior, ActiveModelTrait, Condition, ConnectionTrait, DbErr, DeleteResult,
-    EntityTrait, InsertResult, ModelTrait, Order, PaginatorTrait, PrimaryKeyTrait, QueryFilter,
-    QueryOrder,
+    ActiveModelBehavior, ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DbBackend,
+    DbErr, DeleteResult, EntityTrait, InsertResult, Iterable, ModelTrait, Order, PaginatorTrait,
+    PrimaryKeyToColumn, PrimaryKeyTrait, QueryFilter, QueryOrder, QuerySelect, Statement, Value,
 };
+use taskrs_db::utils::QueryId;
+
+/// Condition matching grants that are valid right now
+pub(crate) fn active_grant_condition<C>(valid_from: C, valid_until: C) -> Condition
+where
+    C: ColumnTrait,
+{
+    let now = chrono::Utc::now().naive_utc();
+
+    Condition::all()
+        .add(
+            Condition::any()
+                .add(valid_from.is_null())
+                .add(valid_from.lte(now)),
+        )
+        .add(
+            Condition::any()
+                .add(valid_until.is_null())
+                .add(valid_until.gt(now)),
+        )
+}
+
+/// Builds `query` so that rows conflicting with a unique key are skipped instead of failing
+pub(crate) fn insert_ignoring_conflicts(backend: DbBackend, query: &InsertStatement) -> Statement {
+    let mut statement = backend.build(query);
+    statement.sql = match backend {
+        DbBackend::MySql => statement.sql.replacen("INSERT", "INSERT IGNORE", 1),
+        DbBackend::Postgres | DbBackend::Sqlite => {
+            format!("{} ON CONFLICT DO NOTHING", statement.sql)
+        }
+    };
+    statement
+}
+
+/// Splits ids into ids of existing entities and unknown ids, keeping their order.
+/// `column` has to be the integer primary key of the entity.
+pub async fn partition_ids<'a, E, C>(
+    column: E::Column,
+    ids: Vec<i32>,
+    db: &'a C,
+) -> Result<(Vec<i32>, Vec<i32>), Error>
+where
+    E: EntityTrait,
+    C: ConnectionTrait<'a>,
+{
+    let existing_ids: HashSet<i32> = E::find()
+        .select_only()
+        .column_as(column, QueryId::Id)
+        .filter(column.is_in(ids.clone()))
+        .into_values::<_, QueryId>()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect();
+
+    Ok(ids.into_iter().partition(|id| existing_ids.contains(id)))
+}
+
+/// Entities whose changes can be recorded in the audit log.
+/// The create, update and delete traits record changes of audited entities
+/// in the same transaction as the change itself.
+pub trait AuditedModelTrait: Serialize {
+    /// Entity type of audit events, changes are not recorded if `None`.
+    /// Audited entities are identified by their `id` field.
+    fn audit_entity_type() -> Option<&'static str> {
+        None
+    }
+}
+
+/// Entities whose lifecycle is published as [`DomainEvent`]s.
+/// The create, update and delete traits publish the events once the change is committed.
+pub trait EventedModelTrait: Sized {
+    /// Builds the event of an entity reaching a stage of its lifecycle,
+    /// nothing is published if `None`
+    fn lifecycle_event() -> Option<fn(Lifecycle, Self) -> DomainEvent> {
+        None
+    }
+}
+
+/// Emits the lifecycle event of an entity, if it has one
+fn emit_lifecycle_event<M>(lifecycle: Lifecycle, model: &M)
+where
+    M: EventedModelTrait + Clone,
+{
+    if let Some(event) = M::lifecycle_event() {
+        events::emit(event(lifecycle, model.clone()));
+    }
+}
 
 #[async_trait]
 pub trait CreateModelTrait<E, A, CM>
@@ -23,25 +130,93 @@ where
     E: EntityTrait,
     <E as EntityTrait>::Model: taskrs_db::sea_orm::IntoActiveModel<A> + Sync,
     A: ActiveModelTrait<Entity = E> + ActiveModelBehavior + Send + 'static,
-    CM: IntoActiveModel<A> + Send + 'static,
-    Self: From<<E as EntityTrait>::Model> + Sized,
+    CM: IntoActiveModel<A> + Validate + Send + 'static,
+    Self: AuditedModelTrait
+        + EventedModelTrait
+        + Clone
+        + From<<E as EntityTrait>::Model>
+        + Send
+        + Sync
+        + Sized,
 {
     /// Create a new entity
-    async fn create<'a, C>(model: CM, db: &'a C) -> Result<Self, DbErr>
+    async fn create<'a, C>(model: CM, db: &'a C) -> Result<Self, Error>
     where
         C: ConnectionTrait<'a>,
     {
-        model.into_active_model().insert(db).await.map(Self::from)
+        model.validate(db).await?;
+
+        events::after_commit(async move {
+            let created = match Self::audit_entity_type() {
+                Some(entity_type) => {
+                    let txn = db.begin().await?;
+                    let created = Self::from(model.into_active_model().insert(&txn).await?);
+                    audit::record_model(
+                        AuditAction::Create,
+                        entity_type,
+                        None,
+                        Some(&created),
+                        &txn,
+                    )
+                    .await?;
+                    txn.commit().await?;
+                    created
+                }
+                None => Self::from(model.into_active_model().insert(db).await?),
+            };
+
+            emit_lifecycle_event(Lifecycle::Created, &created);
+
+            Ok(created)
+        })
+        .await
     }
 
     /// Create multiple new entities
-    async fn create_many<'a, C>(models: Vec<CM>, db: &'a C) -> Result<InsertResult<A>, DbErr>
+    async fn create_many<'a, C>(models: Vec<CM>, db: &'a C) -> Result<InsertResult<A>, Error>
     where
         C: ConnectionTrait<'a>,
     {
-        let active_models = models.into_iter().map(|m| m.into_active_model());
+        for model in &models {
+            model.validate(db).await?;
+        }
 
-        E::insert_many(active_models).exec(db).await
+        let entity_type = match Self::audit_entity_type() {
+            Some(entity_type) if !models.is_empty() => entity_type,
+            _ => {
+                let active_models = models.into_iter().map(|m| m.into_active_model());
+
+                return E::insert_many(active_models)
+                    .exec(db)
+                    .await
+                    .map_err(Error::Database);
+            }
+        };
+
+        // Audited entities are inserted one by one to record their ids
+        events::after_commit(async move {
+            let txn = db.begin().await?;
+            let mut last_insert_id = None;
+            for model in models {
+                let created = model.into_active_model().insert(&txn).await?;
+                last_insert_id =
+                    taskrs_db::sea_orm::IntoActiveModel::into_active_model(created.clone())
+                        .get_primary_key_value();
+
+                let created = Self::from(created);
+                audit::record_model(AuditAction::Create, entity_type, None, Some(&created), &txn)
+                    .await?;
+                emit_lifecycle_event(Lifecycle::Created, &created);
+            }
+            txn.commit().await?;
+
+            let last_insert_id = last_insert_id
+                .ok_or_else(|| DbErr::Exec("Inserted entity has no primary key".to_string()))?;
+            Ok(InsertResult {
+                last_insert_id: FromValueTuple::from_value_tuple(last_insert_id),
+            })
+        })
+        .await
     }
 }
 
@@ -51,22 +226,41 @@ where
     E: EntityTrait,
     Self: From<<E as EntityTrait>::Model> + Sized,
 {
+    /// Condition matching entities that are not soft deleted.
+    /// Defaults to matching everything for entities without soft deletion.
+    fn not_deleted_condition() -> Condition {
+        Condition::all()
+    }
+
     /// Get all entities
-    async fn all<'a, C>(db: &'a C) -> Result<Vec<Self>, DbErr>
+    async fn all<'a, C>(db: &'a C) -> Result<Vec<Self>, Error>
     where
         C: ConnectionTrait<'a>,
     {
-        E::find()
-            .all(db)
-            .await
-            .map(|res| res.into_iter().map(Self::from).collect())
+        Self::find_with_deleted(Self::not_deleted_condition(), db).await
     }
 
     /// Get a single entity by its id
     async fn get<'a, C>(
         id: <<E as EntityTrait>::PrimaryKey as PrimaryKeyTrait>::ValueType,
         db: &'a C,
-    ) -> Result<Option<Self>, DbErr>
+    ) -> Result<Option<Self>, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        E::find_by_id(id)
+            .filter(Self::not_deleted_condition())
+            .one(db)
+            .await
+            .map(|res| res.map(Self::from))
+            .map_err(Error::Database)
+    }
+
+    /// Get a single entity by its id, even if it is soft deleted
+    async fn get_with_deleted<'a, C>(
+        id: <<E as EntityTrait>::PrimaryKey as PrimaryKeyTrait>::ValueType,
+        db: &'a C,
+    ) -> Result<Option<Self>, Error>
     where
         C: ConnectionTrait<'a>,
     {
@@ -74,10 +268,25 @@ where
             .one(db)
             .await
             .map(|res| res.map(Self::from))
+            .map_err(Error::Database)
     }
 
     /// Find multiple entities using a condition
-    async fn find<'a, C>(condition: Condition, db: &'a C) -> Result<Vec<Self>, DbErr>
+    async fn find<'a, C>(condition: Condition, db: &'a C) -> Result<Vec<Self>, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        Self::find_with_deleted(
+            Condition::all()
+                .add(condition)
+                .add(Self::not_deleted_condition()),
+            db,
+        )
+        .await
+    }
+
+    /// Find multiple entities using a condition, including soft deleted ones
+    async fn find_with_deleted<'a, C>(condition: Condition, db: &'a C) -> Result<Vec<Self>, Error>
     where
         C: ConnectionTrait<'a>,
     {
@@ -86,10 +295,28 @@ where
             .all(db)
             .await
             .map(|res| res.into_iter().map(Self::from).collect())
+            .map_err(Error::Database)
     }
 
     /// Find single entity using a condition
-    async fn find_one<'a, C>(condition: Condition, db: &'a C) -> Result<Option<Self>, DbErr>
+    async fn find_one<'a, C>(condition: Condition, db: &'a C) -> Result<Option<Self>, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        Self::find_one_with_deleted(
+            Condition::all()
+                .add(condition)
+                .add(Self::not_deleted_condition()),
+            db,
+        )
+        .await
+    }
+
+    /// Find single entity using a condition, including soft deleted ones
+    async fn find_one_with_deleted<'a, C>(
+        condition: Condition,
+        db: &'a C,
+    ) -> Result<Option<Self>, Error>
     where
         C: ConnectionTrait<'a>,
     {
@@ -98,6 +325,7 @@ where
             .one(db)
             .await
             .map(|res| res.map(Self::from))
+            .map_err(Error::Database)
     }
 }
 
@@ -107,15 +335,147 @@ where
     E: EntityTrait,
     <E as EntityTrait>::Model: taskrs_db::sea_orm::IntoActiveModel<A> + Sync,
     A: ActiveModelTrait<Entity = E> + ActiveModelBehavior + Send + 'static,
-    UM: IntoActiveModel<A> + Send + 'static,
-    Self: From<<E as EntityTrait>::Model> + Sized,
+    UM: IntoActiveModel<A> + Validate + Send + 'static,
+    Self: AuditedModelTrait
+        + EventedModelTrait
+        + Clone
+        + From<<E as EntityTrait>::Model>
+        + Send
+        + Sync
+        + Sized,
 {
+    /// Column holding the version of the entity, if it uses optimistic concurrency control
+    fn version_column() -> Option<E::Column> {
+        None
+    }
+
     /// Update an entity
-    async fn update<'a, C>(model: UM, db: &'a C) -> Result<Self, DbErr>
+    async fn update<'a, C>(model: UM, db: &'a C) -> Result<Self, Error>
     where
         C: ConnectionTrait<'a>,
     {
-        model.into_active_model().update(db).await.map(Self::from)
+        model.validate(db).await?;
+        Self::update_active_model(model.into_active_model(), db).await
+    }
+
+    /// Update an entity using an active model.
+    /// For versioned entities, a set version is the version the update is based on.
+    /// See [`update_versioned`].
+    async fn update_active_model<'a, C>(model: A, db: &'a C) -> Result<Self, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        events::after_commit(async move {
+            let updated = match Self::audit_entity_type() {
+                Some(entity_type) => {
+                    let txn = db.begin().await?;
+                    let before = E::find()
+                        .filter(primary_key_condition(&model))
+                        .one(&txn)
+                        .await?
+                        .map(Self::from);
+                    let updated =
+                        Self::from(update_model(model, Self::version_column(), &txn).await?);
+                    audit::record_model(
+                        AuditAction::Update,
+                        entity_type,
+                        before.as_ref(),
+                        Some(&updated),
+                        &txn,
+                    )
+                    .await?;
+                    txn.commit().await?;
+                    updated
+                }
+                None => Self::from(update_model(model, Self::version_column(), db).await?),
+            };
+
+            emit_lifecycle_event(Lifecycle::Updated, &updated);
+
+            Ok(updated)
+        })
+        .await
+    }
+}
+
+/// Updates an entity, versioned if the entity has a version column
+async fn update_model<'a, E, A, C>(
+    model: A,
+    version_column: Option<E::Column>,
+    db: &'a C,
+) -> Result<E::Model, Error>
+where
+    E: EntityTrait,
+    E::Model: taskrs_db::sea_orm::IntoActiveModel<A>,
+    A: ActiveModelTrait<Entity = E> + ActiveModelBehavior + Send + 'a,
+    C: ConnectionTrait<'a>,
+{
+    match version_column {
+        Some(column) => update_versioned(model, column, db).await,
+        None => model.update(db).await.map_err(Error::Database),
+    }
+}
+
+/// Condition matching the entity of an active model by its primary key
+fn primary_key_condition<E, A>(model: &A) -> Condition
+where
+    E: EntityTrait,
+    A: ActiveModelTrait<Entity = E>,
+{
+    E::PrimaryKey::iter().fold(Condition::all(), |condition, key| {
+        let key = key.into_column();
+        match model.get(key).into_value() {
+            Some(value) => condition.add(key.eq(value)),
+            None => condition,
+        }
+    })
+}
+
+/// Updates an entity and increments its version.
+/// If the version of `model` is set, the update only succeeds if the entity still has that
+/// version and fails with [`ConcurrencyError::VersionMismatch`] otherwise.
+/// If it is not set, the update is based on the current version.
+pub(crate) async fn update_versioned<'a, E, A, C>(
+    model: A,
+    column: E::Column,
+    db: &'a C,
+) -> Result<E::Model, Error>
+where
+    E: EntityTrait,
+    E::Model: taskrs_db::sea_orm::IntoActiveModel<A>,
+    A: ActiveModelTrait<Entity = E> + ActiveModelBehavior + Send,
+    C: ConnectionTrait<'a>,
+{
+    let primary_key = primary_key_condition(&model);
+    let current_version = |model: E::Model| match model.get(column) {
+        Value::Int(Some(version)) => version,
+        _ => 0,
+    };
+
+    let mut model = model;
+    let expected = match model.get(column).into_value() {
+        Some(Value::Int(Some(version))) => version,
+        _ => match E::find().filter(primary_key.clone()).one(db).await? {
+            Some(current) => current_version(current),
+            None => return Err(DbErr::RecordNotFound("Entity does not exist".to_string()).into()),
+        },
+    };
+    model.set(column, Value::Int(Some(expected + 1)));
+
+    let model = ActiveModelBehavior::before_save(model, false)?;
+    match E::update(model).filter(column.eq(expected)).exec(db).await {
+        Ok(model) => Ok(A::after_save(model, false)?),
+        Err(DbErr::RecordNotFound(e)) => {
+            // Distinguish an outdated version from a missing entity
+            match E::find().filter(primary_key).one(db).await? {
+                Some(current) => Err(Error::Concurrency(ConcurrencyError::VersionMismatch {
+                    expected,
+                    actual: current_version(current),
+                })),
+                None => Err(DbErr::RecordNotFound(e).into()),
+            }
+        }
+        Err(e) => Err(e.into()),
     }
 }
 
@@ -125,31 +485,84 @@ where
     E: EntityTrait,
     <E as EntityTrait>::Model: taskrs_db::sea_orm::IntoActiveModel<A> + Sync,
     A: ActiveModelTrait<Entity = E> + ActiveModelBehavior + Send + 'static,
-    Self: From<<E as EntityTrait>::Model> + Sized,
+    Self: AuditedModelTrait
+        + EventedModelTrait
+        + Clone
+        + From<<E as EntityTrait>::Model>
+        + Send
+        + Sync
+        + Sized,
 {
     /// Delete entity using its id
     async fn delete<'a, C>(
         id: <<E as EntityTrait>::PrimaryKey as PrimaryKeyTrait>::ValueType,
         db: &'a C,
-    ) -> Result<(), DbErr>
+    ) -> Result<(), Error>
     where
         C: ConnectionTrait<'a>,
     {
-        let model = E::find_by_id(id).one(db).await?;
-
-        if let Some(model) = model {
-            model.delete(db).await?;
-        }
+        events::after_commit(async move {
+            let txn = db.begin().await?;
+            if let Some(model) = E::find_by_id(id).one(&txn).await? {
+                let deleted = Self::from(model.clone());
+                model.delete(&txn).await?;
+                if let Some(entity_type) = Self::audit_entity_type() {
+                    audit::record_model(
+                        AuditAction::Delete,
+                        entity_type,
+                        Some(&deleted),
+                        None,
+                        &txn,
+                    )
+                    .await?;
+                }
+                emit_lifecycle_event(Lifecycle::Deleted, &deleted);
+            }
+            txn.commit().await?;
 
-        Ok(())
+            Ok(())
+        })
+        .await
     }
 
     /// Delete entities using a condition
-    async fn delete_condition<'a, C>(condition: Condition, db: &'a C) -> Result<DeleteResult, DbErr>
+    async fn delete_condition<'a, C>(condition: Condition, db: &'a C) -> Result<DeleteResult, Error>
     where
         C: ConnectionTrait<'a>,
     {
-        E::delete_many().filter(condition).exec(db).await
+        let entity_type = Self::audit_entity_type();
+        if entity_type.is_none() && Self::lifecycle_event().is_none() {
+            return E::delete_many()
+                .filter(condition)
+                .exec(db)
+                .await
+                .map_err(Error::Database);
+        }
+
+        // Deleted entities are needed for the audit log and events
+        events::after_commit(async move {
+            let txn = db.begin().await?;
+            let models = E::find().filter(condition.clone()).all(&txn).await?;
+            let result = E::delete_many().filter(condition).exec(&txn).await?;
+            for model in models {
+                let deleted = Self::from(model);
+                if let Some(entity_type) = entity_type {
+                    audit::record_model(
+                        AuditAction::Delete,
+                        entity_type,
+                        Some(&deleted),
+                        None,
+                        &txn,
+                    )
+                    .await?;
+                }
+                emit_lifecycle_event(Lifecycle::Deleted, &deleted);
+            }
+            txn.commit().await?;
+
+            Ok(result)
+        })
+        .await
     }
 }
 
@@ -159,7 +572,7 @@ where
     E: EntityTrait,
     <E as EntityTrait>::Model: taskrs_db::sea_orm::IntoActiveModel<A> + Sync,
     A: ActiveModelTrait<Entity = E> + ActiveModelBehavior + Send + 'static,
-    Self: From<<E as EntityTrait>::Model> + Sized,
+    Self: ReadModelTrait<E> + From<<E as EntityTrait>::Model> + Sized,
 {
     /// Default order used in paginated requests
     fn default_order() -> (SimpleExpr, Order);
@@ -170,11 +583,48 @@ where
         limit: usize,
         order: Option<Vec<(SimpleExpr, Order)>>,
         db: &'a C,
-    ) -> Result<(Vec<Self>, usize), DbErr>
+    ) -> Result<(Vec<Self>, usize), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        Self::find_paginated(Condition::all(), page, limit, order, db).await
+    }
+
+    /// Find entities paginated
+    async fn find_paginated<'a, C>(
+        condition: Condition,
+        page: usize,
+        limit: usize,
+        order: Option<Vec<(SimpleExpr, Order)>>,
+        db: &'a C,
+    ) -> Result<(Vec<Self>, usize), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        Self::find_paginated_with_deleted(
+            Condition::all()
+                .add(condition)
+                .add(Self::not_deleted_condition()),
+            page,
+            limit,
+            order,
+            db,
+        )
+        .await
+    }
+
+    /// Find entities paginated, including soft deleted ones
+    async fn find_paginated_with_deleted<'a, C>(
+        condition: Condition,
+        page: usize,
+        limit: usize,
+        order: Option<Vec<(SimpleExpr, Order)>>,
+        db: &'a C,
+    ) -> Result<(Vec<Self>, usize), Error>
     where
         C: ConnectionTrait<'a>,
     {
-        let mut query = E::find();
+        let mut query = E::find().filter(condition);
 
         let mut ordered = false;
         if let Some(order) = order {
@@ -196,36 +646,410 @@ where
         Ok((models.into_iter().map(Self::from).collect(), count))
     }
 
-    /// Find entities paginated
-    async fn find_paginated<'a, C>(
+    /// Find entities using keyset pagination instead of offsets.
+    /// Entities are ordered by `order` or the default order, with the primary key as tie-breaker.
+    /// `cursor` continues the list from the `next` or `prev` cursor of a previous page.
+    /// All entities matching `condition` are only counted if `count` is set.
+    async fn find_by_cursor<'a, C>(
+        condition: Condition,
+        order: Option<(E::Column, Order)>,
+        cursor: Option<String>,
+        limit: usize,
+        count: bool,
+        db: &'a C,
+    ) -> Result<CursorPage<Self>, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        Self::find_by_cursor_with_deleted(
+            Condition::all()
+                .add(condition)
+                .add(Self::not_deleted_condition()),
+            order,
+            cursor,
+            limit,
+            count,
+            db,
+        )
+        .await
+    }
+
+    /// Find entities using keyset pagination, including soft deleted ones
+    async fn find_by_cursor_with_deleted<'a, C>(
         condition: Condition,
+        order: Option<(E::Column, Order)>,
+        cursor: Option<String>,
+        limit: usize,
+        count: bool,
+        db: &'a C,
+    ) -> Result<CursorPage<Self>, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let (column, order) = match order {
+            Some(order) => order,
+            None => {
+                let (expr, order) = Self::default_order();
+                let column = cursor::order_column::<E>(&expr)
+                    .ok_or_else(|| FilterError::UnsupportedCursorSort(format!("{:?}", expr)))?;
+                (column, order)
+            }
+        };
+        let columns = cursor::key_columns::<E>(column);
+        let nullable = cursor::nullable::<E>(column);
+        let cursor = cursor.as_deref().map(cursor::decode).transpose()?;
+
+        // Pages before the cursor are fetched in reverse order
+        let backward = matches!(&cursor, Some(cursor) if cursor.direction == CursorDirection::Prev);
+        let query_order = match (backward, order) {
+            (true, Order::Asc) => Order::Desc,
+            (true, _) => Order::Asc,
+            (false, order) => order,
+        };
+
+        let mut page_condition = Condition::all().add(condition.clone());
+        if let Some(cursor) = &cursor {
+            page_condition = page_condition.add(cursor::keyset_condition(
+                &columns,
+                nullable,
+                &cursor.keys,
+                query_order,
+            )?);
+        }
+
+        let mut query = E::find().filter(page_condition);
+        for (expr, order) in cursor::key_order(&columns, nullable, query_order) {
+            query = query.order_by(expr, order);
+        }
+
+        // Fetch one more entity to know if there is another page
+        let mut models = query.limit(limit as u64 + 1).all(db).await?;
+        let has_more = models.len() > limit;
+        models.truncate(limit);
+        if backward {
+            models.reverse();
+        }
+
+        let total = match count {
+            true => Some(
+                PaginatorTrait::paginate(E::find().filter(condition), db, 1)
+                    .num_items()
+                    .await?,
+            ),
+            false => None,
+        };
+
+        let has_next = if backward { cursor.is_some() } else { has_more };
+        let has_prev = if backward { has_more } else { cursor.is_some() };
+        let next = match models.last() {
+            Some(model) if has_next => Some(cursor::encode(&Cursor {
+                keys: cursor::model_keys(model, &columns)?,
+                direction: CursorDirection::Next,
+            })),
+            _ => None,
+        };
+        let prev = match models.first() {
+            Some(model) if has_prev => Some(cursor::encode(&Cursor {
+                keys: cursor::model_keys(model, &columns)?,
+                direction: CursorDirection::Prev,
+            })),
+            _ => None,
+        };
+
+        Ok(CursorPage {
+            items: models.into_iter().map(Self::from).collect(),
+            next,
+            prev,
+            total,
+        })
+    }
+}
+
+/// Filtering and sorting of paginated requests by the fields of an allow-list
+#[async_trait]
+pub trait FilterableModelTrait<E, A>: PaginatedModelTrait<E, A>
+where
+    E: EntityTrait,
+    <E as EntityTrait>::Model: taskrs_db::sea_orm::IntoActiveModel<A> + Sync,
+    A: ActiveModelTrait<Entity = E> + ActiveModelBehavior + Send + 'static,
+    Self: ReadModelTrait<E> + From<<E as EntityTrait>::Model> + Sized,
+{
+    /// Fields that can be filtered, with their allowed operators
+    fn filter_fields() -> Vec<FilterField<E::Column>>;
+
+    /// Fields that can be sorted by
+    fn sort_fields() -> Vec<SortField<E::Column>>;
+
+    /// Converts a list query into a condition and an order.
+    /// The default order is appended to keep pages stable if sort keys are not unique.
+    fn query_condition_and_order(
+        query: &ListQuery,
+    ) -> Result<(Condition, Vec<(SimpleExpr, Order)>), Error> {
+        let condition = filter::filter_condition(&query.filters, &Self::filter_fields())?;
+        let mut order = filter::sort_order(&query.sort, &Self::sort_fields())?;
+        if !order.is_empty() {
+            order.push(Self::default_order());
+        }
+
+        Ok((condition, order))
+    }
+
+    /// Find entities paginated, filtered and sorted by a list query
+    async fn find_by_query<'a, C>(
+        query: ListQuery,
         page: usize,
         limit: usize,
-        order: Option<Vec<(SimpleExpr, Order)>>,
         db: &'a C,
-    ) -> Result<(Vec<Self>, usize), DbErr>
+    ) -> Result<(Vec<Self>, usize), Error>
     where
         C: ConnectionTrait<'a>,
     {
-        let mut query = E::find().filter(condition);
+        let (condition, order) = Self::query_condition_and_order(&query)?;
+        Self::find_paginated(condition, page, limit, Some(order), db).await
+    }
 
-        let mut ordered = false;
-        if let Some(order) = order {
-            ordered = !order.is_empty();
-            for (col, ord) in order {
-                query = query.order_by(col, ord);
+    /// Find entities paginated, filtered and sorted by a list query, including soft deleted ones
+    async fn find_by_query_with_deleted<'a, C>(
+        query: ListQuery,
+        page: usize,
+        limit: usize,
+        db: &'a C,
+    ) -> Result<(Vec<Self>, usize), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let (condition, order) = Self::query_condition_and_order(&query)?;
+        Self::find_paginated_with_deleted(condition, page, limit, Some(order), db).await
+    }
+
+    /// Converts the sort keys of a list query into the order of keyset pagination,
+    /// which supports a single sort key
+    fn query_cursor_order(query: &ListQuery) -> Result<Option<(E::Column, Order)>, Error> {
+        let sort = match query.sort.as_slice() {
+            [] => return Ok(None),
+            [sort] => sort,
+            [_, sort, ..] => {
+                return Err(FilterError::UnsupportedCursorSort(sort.field.clone()).into())
             }
+        };
+
+        Self::sort_fields()
+            .into_iter()
+            .find(|field| field.name == sort.field)
+            .map(|field| Some((field.column, sort.order())))
+            .ok_or_else(|| FilterError::UnknownSortField(sort.field.clone()).into())
+    }
+
+    /// Find entities filtered by a list query using keyset pagination
+    async fn find_by_query_cursor<'a, C>(
+        query: ListQuery,
+        cursor: Option<String>,
+        limit: usize,
+        count: bool,
+        db: &'a C,
+    ) -> Result<CursorPage<Self>, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let condition = filter::filter_condition(&query.filters, &Self::filter_fields())?;
+        let order = Self::query_cursor_order(&query)?;
+        Self::find_by_cursor(condition, order, cursor, limit, count, db).await
+    }
+
+    /// Find entities filtered by a list query using keyset pagination, including soft deleted ones
+    async fn find_by_query_cursor_with_deleted<'a, C>(
+        query: ListQuery,
+        cursor: Option<String>,
+        limit: usize,
+        count: bool,
+        db: &'a C,
+    ) -> Result<CursorPage<Self>, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let condition = filter::filter_condition(&query.filters, &Self::filter_fields())?;
+        let order = Self::query_cursor_order(&query)?;
+        Self::find_by_cursor_with_deleted(condition, order, cursor, limit, count, db).await
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::error::{ConcurrencyError, Error};
+    use crate::logic::{CreateModelTrait, ReadModelTrait, UpdateModelTrait};
+    use crate::models::grant::GrantValidity;
+    use crate::models::group::{Group, GroupCreate, GroupUpdate};
+    use crate::models::refresh_token::{RefreshToken, RefreshTokenCreate};
+    use crate::models::role::{Role, RoleCreate};
+    use crate::models::user::{User, UserCreate};
+    use std::path::PathBuf;
+    use taskrs_db::migrations::Migrations;
+    use taskrs_db::models::{refresh_token, user, user_role};
+    use taskrs_db::sea_orm::{
+        ColumnTrait, Database, DbConn, EntityTrait, PaginatorTrait, QueryFilter,
+    };
+
+    /// Migrated database for tests.
+    /// SQLite databases are files instead of memory, so that every connection of the pool
+    /// sees the same database, and are removed once dropped.
+    pub(crate) struct TestDatabase {
+        pub(crate) db: DbConn,
+        path: Option<PathBuf>,
+    }
+
+    impl TestDatabase {
+        pub(crate) async fn sqlite() -> Self {
+            let path =
+                std::env::temp_dir().join(format!("taskrs-test-{:016x}.db", rand::random::<u64>()));
+            let mut test = Self::connect(&format!("sqlite:{}?mode=rwc", path.display())).await;
+            test.path = Some(path);
+            test
         }
 
-        if !ordered {
-            let (col, ord) = Self::default_order();
-            query = query.order_by(col, ord);
+        /// Database given by the URL in the environment variable `var`, e.g. of a Postgres server.
+        /// Tests against other backends are skipped without it.
+        /// The database is kept, tests have to tell their rows from the ones of earlier runs.
+        pub(crate) async fn from_env(var: &str) -> Option<Self> {
+            match std::env::var(var) {
+                Ok(url) => Some(Self::connect(&url).await),
+                Err(_) => {
+                    eprintln!("{} is not set, skipping", var);
+                    None
+                }
+            }
         }
 
-        let paginator = PaginatorTrait::paginate(query, db, limit);
+        async fn connect(url: &str) -> Self {
+            let db = Database::connect(url)
+                .await
+                .expect("test database can be opened");
+            Migrations::new(None)
+                .run(&db)
+                .await
+                .expect("test database can be migrated");
 
-        let (models, count) = try_join!(paginator.fetch_page(page), paginator.num_items())?;
+            Self { db, path: None }
+        }
+    }
 
-        Ok((models.into_iter().map(Self::from).collect(), count))
+    impl Drop for TestDatabase {
+        fn drop(&mut self) {
+            if let Some(path) = &self.path {
+                for suffix in ["", "-journal", "-wal", "-shm"] {
+                    let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+                }
+            }
+        }
+    }
+
+    /// Asserts that the migrated schema, including its foreign keys, matches the entities
+    /// and that removing a user removes its relations with it
+    async fn assert_relations(db: &DbConn) {
+        assert_eq!(taskrs_db::schema::check(db).await.unwrap(), vec![]);
+
+        let user = User::create(
+            UserCreate {
+                email: format!("u{:08x}@example.com", rand::random::<u32>()),
+                password: "password".to_string(),
+                enabled: true,
+                ..Default::default()
+            },
+            db,
+        )
+        .await
+        .unwrap();
+        let role = Role::create(
+            RoleCreate {
+                name: format!("role{:08x}", rand::random::<u32>()),
+                ..Default::default()
+            },
+            db,
+        )
+        .await
+        .unwrap();
+        User::grant_roles(user.id, vec![role.id], GrantValidity::default(), db)
+            .await
+            .unwrap();
+        RefreshToken::create(
+            RefreshTokenCreate {
+                user_id: user.id,
+                token: format!("token{:08x}", rand::random::<u32>()),
+                ..Default::default()
+            },
+            db,
+        )
+        .await
+        .unwrap();
+
+        user::Entity::delete_many()
+            .filter(user::Column::Id.eq(user.id))
+            .exec(db)
+            .await
+            .unwrap();
+
+        let roles = user_role::Entity::find()
+            .filter(user_role::Column::UserId.eq(user.id))
+            .count(db)
+            .await
+            .unwrap();
+        let tokens = refresh_token::Entity::find()
+            .filter(refresh_token::Column::UserId.eq(user.id))
+            .count(db)
+            .await
+            .unwrap();
+        assert_eq!((roles, tokens), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn relations_cascade_on_sqlite() {
+        let test = TestDatabase::sqlite().await;
+        assert_relations(&test.db).await;
+    }
+
+    #[tokio::test]
+    async fn relations_cascade_on_postgres() {
+        if let Some(test) = TestDatabase::from_env("TASKRS_TEST_POSTGRES_URL").await {
+            assert_relations(&test.db).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn relations_cascade_on_mysql() {
+        if let Some(test) = TestDatabase::from_env("TASKRS_TEST_MYSQL_URL").await {
+            assert_relations(&test.db).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn stale_versions_do_not_overwrite_updates() {
+        let test = TestDatabase::sqlite().await;
+        let db = &test.db;
+        let group = GroupCreate {
+            name: "versioned".to_string(),
+            ..Default::default()
+        };
+        let group = Group::create(group, db).await.unwrap();
+        let update = |name: &str| GroupUpdate {
+            id: group.id,
+            name: Some(name.to_string()),
+            version: Some(group.version),
+            ..Default::default()
+        };
+
+        let updated = Group::update(update("first"), db).await.unwrap();
+        assert_eq!(updated.version, group.version + 1);
+
+        let stale = Group::update(update("second"), db).await;
+        assert!(matches!(
+            stale,
+            Err(Error::Concurrency(ConcurrencyError::VersionMismatch { expected, actual }))
+                if expected == group.version && actual == updated.version
+        ));
+
+        let current = Group::get(group.id, db).await.unwrap().unwrap();
+        assert_eq!(
+            (current.name, current.version),
+            ("first".to_string(), updated.version)
+        );
     }
 }
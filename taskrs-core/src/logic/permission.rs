@@ -1,10 +1,53 @@
+use crate::error::{Error, ValidationCode};
+use crate::logic::validation::{Validate, Validator, MAX_STRING_LENGTH};
 use crate::logic::{
-    CreateModelTrait, DeleteModelTrait, PaginatedModelTrait, ReadModelTrait, UpdateModelTrait,
+    AuditedModelTrait, CreateModelTrait, DeleteModelTrait, EventedModelTrait, PaginatedModelTrait,
+    ReadModelTrait, UpdateModelTrait,
 };
-use crate::models::permission::{Permission, PermissionCreate, PermissionUpdate};
+use crate::models::permission::{Permission, PermissionCreate, PermissionGroup, PermissionUpdate};
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use regex::Regex;
 use taskrs_db::models::permission;
 use taskrs_db::sea_orm::sea_query::SimpleExpr;
-use taskrs_db::sea_orm::{IntoSimpleExpr, Order};
+use taskrs_db::sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, IntoSimpleExpr, Order, QueryFilter, QueryOrder,
+};
+
+static IDENTIFIER_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new("^[a-z][a-z0-9_]*$").unwrap());
+
+impl Permission {
+    /// All permissions grouped by their group, both ordered by name
+    pub async fn all_grouped<'a, C>(db: &'a C) -> Result<Vec<PermissionGroup>, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let permissions = permission::Entity::find()
+            .order_by_asc(permission::Column::Group)
+            .order_by_asc(permission::Column::Name)
+            .all(db)
+            .await?;
+
+        let mut groups: Vec<PermissionGroup> = vec![];
+        for permission in permissions.into_iter().map(Permission::from) {
+            match groups.last_mut() {
+                Some(group) if group.group == permission.group => {
+                    group.permissions.push(permission)
+                }
+                _ => groups.push(PermissionGroup {
+                    group: permission.group.clone(),
+                    permissions: vec![permission],
+                }),
+            }
+        }
+
+        Ok(groups)
+    }
+}
+
+impl AuditedModelTrait for Permission {}
+
+impl EventedModelTrait for Permission {}
 
 impl CreateModelTrait<permission::Entity, permission::ActiveModel, PermissionCreate>
     for Permission
@@ -18,6 +61,56 @@ impl UpdateModelTrait<permission::Entity, permission::ActiveModel, PermissionUpd
 {
 }
 
+#[async_trait]
+impl Validate for PermissionCreate {
+    async fn rules<'a, C>(&self, validator: &mut Validator, db: &'a C) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        validate_identifier("name", &self.name, validator);
+        validate_identifier("group", &self.group, validator);
+
+        if validator.is_valid("name") && validator.is_valid("group") {
+            validate_unique(&self.group, &self.name, None, validator, db).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Validate for PermissionUpdate {
+    async fn rules<'a, C>(&self, validator: &mut Validator, db: &'a C) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        if let Some(name) = &self.name {
+            validate_identifier("name", name, validator);
+        }
+        if let Some(group) = &self.group {
+            validate_identifier("group", group, validator);
+        }
+
+        let renamed = self.name.is_some() || self.group.is_some();
+        if renamed && validator.is_valid("name") && validator.is_valid("group") {
+            if let Some(current) = permission::Entity::find_by_id(self.id).one(db).await? {
+                let name = self.name.as_ref().unwrap_or(&current.name);
+                let group = self.group.as_ref().unwrap_or(&current.group);
+                validate_unique(group, name, Some(self.id), validator, db).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Names and groups follow the rules of the permission catalog
+fn validate_identifier(field: &str, value: &str, validator: &mut Validator) {
+    validator
+        .pattern(field, value, &IDENTIFIER_REGEX)
+        .max_length(field, value, MAX_STRING_LENGTH);
+}
+
 impl DeleteModelTrait<permission::Entity, permission::ActiveModel> for Permission {}
 
 impl PaginatedModelTrait<permission::Entity, permission::ActiveModel> for Permission {
@@ -25,3 +118,32 @@ impl PaginatedModelTrait<permission::Entity, permission::ActiveModel> for Permis
         (permission::Column::Id.into_simple_expr(), Order::Asc)
     }
 }
+
+/// Names are unique within their group
+async fn validate_unique<'a, C>(
+    group: &str,
+    name: &str,
+    permission_id: Option<i32>,
+    validator: &mut Validator,
+    db: &'a C,
+) -> Result<(), Error>
+where
+    C: ConnectionTrait<'a>,
+{
+    let mut query = permission::Entity::find()
+        .filter(permission::Column::Group.eq(group))
+        .filter(permission::Column::Name.eq(name));
+    if let Some(permission_id) = permission_id {
+        query = query.filter(permission::Column::Id.ne(permission_id));
+    }
+
+    if query.one(db).await?.is_some() {
+        validator.add(
+            "name",
+            ValidationCode::Unique,
+            "is already taken in the group",
+        );
+    }
+
+    Ok(())
+}
@@ -1,11 +1,17 @@
+use crate::logic::validation::Validate;
 use crate::logic::{
-    CreateModelTrait, DeleteModelTrait, PaginatedModelTrait, ReadModelTrait, UpdateModelTrait,
+    AuditedModelTrait, CreateModelTrait, DeleteModelTrait, EventedModelTrait, PaginatedModelTrait,
+    ReadModelTrait, UpdateModelTrait,
 };
 use crate::models::refresh_token::{RefreshToken, RefreshTokenCreate, RefreshTokenUpdate};
 use taskrs_db::models::refresh_token;
 use taskrs_db::sea_orm::sea_query::SimpleExpr;
 use taskrs_db::sea_orm::{IntoSimpleExpr, Order};
 
+impl AuditedModelTrait for RefreshToken {}
+
+impl EventedModelTrait for RefreshToken {}
+
 impl CreateModelTrait<refresh_token::Entity, refresh_token::ActiveModel, RefreshTokenCreate>
     for RefreshToken
 {
@@ -18,6 +24,10 @@ impl UpdateModelTrait<refresh_token::Entity, refresh_token::ActiveModel, Refresh
 {
 }
 
+impl Validate for RefreshTokenCreate {}
+
+impl Validate for RefreshTokenUpdate {}
+
 impl DeleteModelTrait<refresh_token::Entity, refresh_token::ActiveModel> for RefreshToken {}
 
 impl PaginatedModelTrait<refresh_token::Entity, refresh_token::ActiveModel> for RefreshToken {
@@ -1,27 +1,68 @@
+use crate::error::{Error, RbacError};
+use crate::logic::grant::GrantUnitOfWork;
+use crate::logic::validation::{Validate, Validator, MAX_STRING_LENGTH};
 use crate::logic::{
-    CreateModelTrait, DeleteModelTrait, PaginatedModelTrait, ReadModelTrait, UpdateModelTrait,
+    active_grant_condition, AuditedModelTrait, CreateModelTrait, DeleteModelTrait,
+    EventedModelTrait, FilterableModelTrait, PaginatedModelTrait, ReadModelTrait, UpdateModelTrait,
 };
+use crate::logic::{audit, events};
+use crate::models::audit_event::AuditAction;
+use crate::models::domain_event::{DomainEvent, Lifecycle};
+use crate::models::filter::{FilterField, FilterOperator, SortField};
+use crate::models::grant::{GrantChanges, GrantValidity};
 use crate::models::permission::Permission;
 use crate::models::role::{Role, RoleCreate, RoleUpdate};
 use crate::models::role_permission::RolePermission;
 use crate::models::user::User;
 use crate::models::user_role::UserRole;
+use crate::models::IntoActiveModel;
+use crate::seeding::ROOT_ROLE_NAME;
+use async_trait::async_trait;
 use std::collections::HashSet;
-use taskrs_db::models::{permission, role, role_permission, user, user_role};
+use taskrs_db::models::permission::PermissionEffect;
+use taskrs_db::models::{
+    group_member, group_role, permission, role, role_permission, user, user_role,
+};
 use taskrs_db::sea_orm::prelude::*;
-use taskrs_db::sea_orm::sea_query::{IntoCondition, SimpleExpr};
+use taskrs_db::sea_orm::sea_query::{IntoCondition, Query, SimpleExpr};
 use taskrs_db::sea_orm::{
-    Condition, ConnectionTrait, IntoSimpleExpr, JoinType, Order, QuerySelect, TransactionError,
+    Condition, ConnectionTrait, DatabaseTransaction, DeleteResult, IntoSimpleExpr, JoinType, Order,
+    PaginatorTrait, QuerySelect,
 };
-use taskrs_db::utils::QueryId;
+
+const AUDIT_ENTITY_TYPE: &str = "role";
 
 impl Role {
-    pub async fn permissions<'a, C>(role_id: i32, db: &'a C) -> Result<Vec<Permission>, DbErr>
+    /// Permissions allowed by the role
+    pub async fn permissions<'a, C>(role_id: i32, db: &'a C) -> Result<Vec<Permission>, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        Self::permissions_with_effect(role_id, PermissionEffect::Allow, db).await
+    }
+
+    /// Permissions denied by the role
+    pub async fn denied_permissions<'a, C>(
+        role_id: i32,
+        db: &'a C,
+    ) -> Result<Vec<Permission>, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        Self::permissions_with_effect(role_id, PermissionEffect::Deny, db).await
+    }
+
+    async fn permissions_with_effect<'a, C>(
+        role_id: i32,
+        effect: PermissionEffect,
+        db: &'a C,
+    ) -> Result<Vec<Permission>, Error>
     where
         C: ConnectionTrait<'a>,
     {
         permission::Entity::find()
             .filter(role_permission::Column::RoleId.eq(role_id))
+            .filter(role_permission::Column::Effect.eq(effect))
             .join_rev(
                 JoinType::InnerJoin,
                 role_permission::Relation::Permission.def(),
@@ -29,219 +70,766 @@ impl Role {
             .all(db)
             .await
             .map(|models| models.into_iter().map(Permission::from).collect())
+            .map_err(Error::Database)
     }
 
+    /// Grants permissions to the role.
+    /// Existing deny rules for the same permissions are turned into allow rules.
     pub async fn grant_permissions<'a, C>(
         role_id: i32,
         permission_ids: Vec<i32>,
         db: &'a C,
-    ) -> Result<(), DbErr>
+    ) -> Result<GrantChanges, Error>
     where
         C: ConnectionTrait<'a>,
     {
-        // Get direct permissions of role
-        let current_permission_ids: HashSet<i32> = role_permission::Entity::find()
-            .select_only()
-            .column_as(role_permission::Column::PermissionId, QueryId::Id)
-            .filter(role_permission::Column::RoleId.eq(role_id))
-            .into_values::<_, QueryId>()
-            .all(db)
-            .await?
-            .into_iter()
-            .collect();
+        Self::ensure_not_system(role_id, db).await?;
+
+        // Transaction
+        events::transaction(db, |txn| {
+            Box::pin(Self::assign_permissions(
+                role_id,
+                permission_ids,
+                PermissionEffect::Allow,
+                txn,
+            ))
+        })
+        .await
+    }
+
+    /// Denies permissions to every user of the role, even if another role
+    /// or a direct user permission allows them.
+    /// Existing allow rules for the same permissions are turned into deny rules.
+    pub async fn deny_permissions<'a, C>(
+        role_id: i32,
+        permission_ids: Vec<i32>,
+        db: &'a C,
+    ) -> Result<GrantChanges, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        Self::ensure_not_system(role_id, db).await?;
 
-        // Filter only new permissions
-        let new_permission_ids: HashSet<i32> = permission_ids.into_iter().collect();
-        let role_permissions: Vec<RolePermission> = (&new_permission_ids - &current_permission_ids)
+        // Transaction
+        events::transaction(db, |txn| {
+            Box::pin(Self::assign_permissions(
+                role_id,
+                permission_ids,
+                PermissionEffect::Deny,
+                txn,
+            ))
+        })
+        .await
+    }
+
+    /// Assigns permission rules in the transaction without checking for system roles.
+    /// Only used directly by seeding.
+    pub(crate) async fn assign_permissions(
+        role_id: i32,
+        permission_ids: Vec<i32>,
+        effect: PermissionEffect,
+        txn: &DatabaseTransaction,
+    ) -> Result<GrantChanges, Error> {
+        let before = Self::permission_rules_state(role_id, txn).await?;
+
+        // Insert new rules and switch rules with the opposite effect
+        let role_permissions: Vec<RolePermission> = permission_ids
+            .into_iter()
+            .collect::<HashSet<i32>>()
             .into_iter()
             .map(|permission_id| RolePermission {
                 role_id,
                 permission_id,
+                effect,
                 inserted_at: None,
             })
             .collect();
 
-        // Insert models
-        if !role_permissions.is_empty() {
-            RolePermission::create_many(role_permissions, db).await?;
+        let mut grants = Self::permission_grants(role_id, txn);
+        grants.upsert(role_permissions).await?;
+        let changes = grants.into_changes();
+
+        let action = match effect {
+            PermissionEffect::Allow => AuditAction::GrantPermissions,
+            PermissionEffect::Deny => AuditAction::DenyPermissions,
+        };
+        let after = Self::permission_rules_state(role_id, txn).await?;
+        audit::record(action, AUDIT_ENTITY_TYPE, role_id, before, after, txn).await?;
+        if !changes.is_empty() {
+            events::emit(DomainEvent::RolePermissionsChanged { role_id });
         }
 
-        Ok(())
+        Ok(changes)
     }
 
     pub async fn revoke_permissions<'a, C>(
         role_id: i32,
         permission_ids: Vec<i32>,
         db: &'a C,
-    ) -> Result<(), DbErr>
+    ) -> Result<GrantChanges, Error>
     where
         C: ConnectionTrait<'a>,
     {
-        RolePermission::delete_condition(
-            Condition::all()
-                .add(role_permission::Column::RoleId.eq(role_id))
-                .add(role_permission::Column::PermissionId.is_in(permission_ids)),
-            db,
+        Self::ensure_not_system(role_id, db).await?;
+
+        // Transaction
+        events::transaction(db, |txn| {
+            Box::pin(Self::remove_permissions(role_id, permission_ids, txn))
+        })
+        .await
+    }
+
+    /// Removes permission rules in the transaction without checking for system roles.
+    /// Only used directly by seeding.
+    pub(crate) async fn remove_permissions(
+        role_id: i32,
+        permission_ids: Vec<i32>,
+        txn: &DatabaseTransaction,
+    ) -> Result<GrantChanges, Error> {
+        let before = Self::permission_rules_state(role_id, txn).await?;
+
+        let mut grants = Self::permission_grants(role_id, txn);
+        grants
+            .remove(permission_ids.into_iter().collect::<HashSet<i32>>())
+            .await?;
+        let changes = grants.into_changes();
+
+        let after = Self::permission_rules_state(role_id, txn).await?;
+        audit::record(
+            AuditAction::RevokePermissions,
+            AUDIT_ENTITY_TYPE,
+            role_id,
+            before,
+            after,
+            txn,
         )
         .await?;
+        if !changes.is_empty() {
+            events::emit(DomainEvent::RolePermissionsChanged { role_id });
+        }
 
-        Ok(())
+        Ok(changes)
     }
 
+    /// Replaces the allow rules of the role.
+    /// Deny rules are kept and win over the provided permissions.
     pub async fn set_permissions<'a, C>(
         role_id: i32,
         permission_ids: Vec<i32>,
         db: &'a C,
-    ) -> Result<(), TransactionError<DbErr>>
+    ) -> Result<GrantChanges, Error>
     where
         C: ConnectionTrait<'a>,
     {
-        // Create models for inserting
-        let new_role_permissions: Vec<RolePermission> = permission_ids
-            .into_iter()
-            .map(|permission_id| RolePermission {
-                role_id,
-                permission_id,
-                inserted_at: None,
-            })
-            .collect();
+        Self::ensure_not_system(role_id, db).await?;
 
         // Transaction
-        db.transaction::<_, (), DbErr>(|txn| {
+        events::transaction(db, |txn| {
             Box::pin(async move {
-                // Delete all role permissions
-                RolePermission::delete_condition(
-                    role_permission::Column::RoleId.eq(role_id).into_condition(),
+                let before = Self::permission_rules_state(role_id, txn).await?;
+                let mut grants = Self::permission_grants(role_id, txn);
+
+                // Get denied permissions, they can not be allowed at the same time
+                let denied_permission_ids = grants
+                    .granted_ids(
+                        role_permission::Column::Effect
+                            .eq(PermissionEffect::Deny)
+                            .into_condition(),
+                    )
+                    .await?;
+
+                // Create models for inserting
+                let new_role_permissions: Vec<RolePermission> = permission_ids
+                    .into_iter()
+                    .collect::<HashSet<i32>>()
+                    .into_iter()
+                    .filter(|id| !denied_permission_ids.contains(id))
+                    .map(|permission_id| RolePermission {
+                        role_id,
+                        permission_id,
+                        effect: PermissionEffect::Allow,
+                        inserted_at: None,
+                    })
+                    .collect();
+
+                // Replace allowed role permissions
+                grants
+                    .replace(
+                        role_permission::Column::Effect
+                            .eq(PermissionEffect::Allow)
+                            .into_condition(),
+                        new_role_permissions,
+                    )
+                    .await?;
+                let changes = grants.into_changes();
+
+                let after = Self::permission_rules_state(role_id, txn).await?;
+                audit::record(
+                    AuditAction::SetPermissions,
+                    AUDIT_ENTITY_TYPE,
+                    role_id,
+                    before,
+                    after,
                     txn,
                 )
                 .await?;
-
-                // Insert new permissions
-                if !new_role_permissions.is_empty() {
-                    RolePermission::create_many(new_role_permissions, txn).await?;
+                if !changes.is_empty() {
+                    events::emit(DomainEvent::RolePermissionsChanged { role_id });
                 }
 
-                Ok(())
+                Ok(changes)
             })
         })
         .await
     }
 
-    pub async fn users<'a, C>(role_id: i32, db: &'a C) -> Result<Vec<User>, DbErr>
+    pub async fn users<'a, C>(role_id: i32, db: &'a C) -> Result<Vec<User>, Error>
     where
         C: ConnectionTrait<'a>,
     {
         user::Entity::find()
+            .filter(User::not_deleted_condition())
             .filter(user_role::Column::RoleId.eq(role_id))
+            .filter(active_grant_condition(
+                user_role::Column::ValidFrom,
+                user_role::Column::ValidUntil,
+            ))
             .join_rev(JoinType::InnerJoin, user_role::Relation::User.def())
             .all(db)
             .await
             .map(|models| models.into_iter().map(User::from).collect())
+            .map_err(Error::Database)
     }
 
-    pub async fn add_users<'a, C>(role_id: i32, user_ids: Vec<i32>, db: &'a C) -> Result<(), DbErr>
+    /// Grants the role to users indefinitely.
+    /// Users that already have the role keep their period.
+    pub async fn add_users<'a, C>(
+        role_id: i32,
+        user_ids: Vec<i32>,
+        db: &'a C,
+    ) -> Result<GrantChanges, Error>
     where
         C: ConnectionTrait<'a>,
     {
-        // Get users of role
-        let current_user_ids: HashSet<i32> = user_role::Entity::find()
-            .select_only()
-            .column_as(user_role::Column::UserId, QueryId::Id)
-            .filter(user_role::Column::RoleId.eq(role_id))
-            .into_values::<_, QueryId>()
-            .all(db)
-            .await?
-            .into_iter()
-            .collect();
+        // Transaction
+        events::transaction(db, |txn| {
+            Box::pin(async move {
+                let before = Self::user_grants_state(role_id, txn).await?;
 
-        // Filter only new users
-        let new_user_ids: HashSet<i32> = user_ids.into_iter().collect();
-        let user_roles: Vec<UserRole> = (&new_user_ids - &current_user_ids)
-            .into_iter()
-            .map(|user_id| UserRole {
-                role_id,
-                user_id,
-                inserted_at: None,
-            })
-            .collect();
+                // Insert only new users
+                let user_roles: Vec<UserRole> = user_ids
+                    .into_iter()
+                    .collect::<HashSet<i32>>()
+                    .into_iter()
+                    .map(|user_id| UserRole {
+                        role_id,
+                        user_id,
+                        validity: GrantValidity::default(),
+                        inserted_at: None,
+                    })
+                    .collect();
 
-        // Insert models
-        if !user_roles.is_empty() {
-            UserRole::create_many(user_roles, db).await?;
-        }
+                let mut grants = Self::user_grants(role_id, txn);
+                grants.insert(user_roles).await?;
+                let changes = grants.into_changes();
 
-        Ok(())
+                let after = Self::user_grants_state(role_id, txn).await?;
+                audit::record(
+                    AuditAction::AddUsers,
+                    AUDIT_ENTITY_TYPE,
+                    role_id,
+                    before,
+                    after,
+                    txn,
+                )
+                .await?;
+                if !changes.is_empty() {
+                    events::emit(DomainEvent::RoleUsersChanged { role_id });
+                }
+
+                Ok(changes)
+            })
+        })
+        .await
     }
 
+    /// Removes users from the role.
+    /// Fails if no enabled user with the root role would be left.
     pub async fn remove_users<'a, C>(
         role_id: i32,
         user_ids: Vec<i32>,
         db: &'a C,
-    ) -> Result<(), DbErr>
+    ) -> Result<GrantChanges, Error>
     where
         C: ConnectionTrait<'a>,
     {
-        UserRole::delete_condition(
-            Condition::all()
-                .add(user_role::Column::RoleId.eq(role_id))
-                .add(user_role::Column::UserId.is_in(user_ids)),
-            db,
-        )
-        .await?;
+        // Transaction
+        events::transaction(db, |txn| {
+            Box::pin(async move {
+                let root_user_count = Self::root_user_count(txn).await?;
+                let before = Self::user_grants_state(role_id, txn).await?;
 
-        Ok(())
+                let mut grants = Self::user_grants(role_id, txn);
+                grants
+                    .remove(user_ids.into_iter().collect::<HashSet<i32>>())
+                    .await?;
+                let changes = grants.into_changes();
+
+                Self::ensure_root_user_remains(root_user_count, txn).await?;
+
+                let after = Self::user_grants_state(role_id, txn).await?;
+                audit::record(
+                    AuditAction::RemoveUsers,
+                    AUDIT_ENTITY_TYPE,
+                    role_id,
+                    before,
+                    after,
+                    txn,
+                )
+                .await?;
+                if !changes.is_empty() {
+                    events::emit(DomainEvent::RoleUsersChanged { role_id });
+                }
+
+                Ok(changes)
+            })
+        })
+        .await
     }
 
+    /// Replaces the users of the role, all of them get the role indefinitely.
+    /// Fails if no enabled user with the root role would be left.
     pub async fn set_users<'a, C>(
         role_id: i32,
         user_ids: Vec<i32>,
         db: &'a C,
-    ) -> Result<(), TransactionError<DbErr>>
+    ) -> Result<GrantChanges, Error>
     where
         C: ConnectionTrait<'a>,
     {
         // Create models for inserting
         let new_user_roles: Vec<UserRole> = user_ids
+            .into_iter()
+            .collect::<HashSet<i32>>()
             .into_iter()
             .map(|user_id| UserRole {
                 role_id,
                 user_id,
+                validity: GrantValidity::default(),
                 inserted_at: None,
             })
             .collect();
 
         // Transaction
-        db.transaction::<_, (), DbErr>(|txn| {
+        events::transaction(db, |txn| {
             Box::pin(async move {
-                // Delete all user roles
-                RolePermission::delete_condition(
-                    user_role::Column::RoleId.eq(role_id).into_condition(),
+                let root_user_count = Self::root_user_count(txn).await?;
+                let before = Self::user_grants_state(role_id, txn).await?;
+
+                // Replace all user roles
+                let mut grants = Self::user_grants(role_id, txn);
+                grants.replace(Condition::all(), new_user_roles).await?;
+                let changes = grants.into_changes();
+
+                Self::ensure_root_user_remains(root_user_count, txn).await?;
+
+                let after = Self::user_grants_state(role_id, txn).await?;
+                audit::record(
+                    AuditAction::SetUsers,
+                    AUDIT_ENTITY_TYPE,
+                    role_id,
+                    before,
+                    after,
                     txn,
                 )
                 .await?;
-
-                // Insert new users
-                if !new_user_roles.is_empty() {
-                    UserRole::create_many(new_user_roles, txn).await?;
+                if !changes.is_empty() {
+                    events::emit(DomainEvent::RoleUsersChanged { role_id });
                 }
 
-                Ok(())
+                Ok(changes)
             })
         })
         .await
     }
+
+    /// Permission rules of the role, changed in the transaction
+    fn permission_grants(
+        role_id: i32,
+        txn: &DatabaseTransaction,
+    ) -> GrantUnitOfWork<'_, role_permission::Entity> {
+        GrantUnitOfWork::new(
+            txn,
+            role_permission::Column::RoleId,
+            role_id,
+            role_permission::Column::PermissionId,
+        )
+    }
+
+    /// Grants of the role to users, changed in the transaction
+    fn user_grants(
+        role_id: i32,
+        txn: &DatabaseTransaction,
+    ) -> GrantUnitOfWork<'_, user_role::Entity> {
+        GrantUnitOfWork::new(
+            txn,
+            user_role::Column::RoleId,
+            role_id,
+            user_role::Column::UserId,
+        )
+    }
+
+    /// Permission rules of the role keyed by permission id, recorded as state of audit events
+    async fn permission_rules_state<'a, C>(
+        role_id: i32,
+        db: &'a C,
+    ) -> Result<serde_json::Value, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let rules = RolePermission::find(
+            role_permission::Column::RoleId.eq(role_id).into_condition(),
+            db,
+        )
+        .await?;
+
+        Ok(audit::snapshot(&rules, |rule| rule.permission_id))
+    }
+
+    /// Grants of the role keyed by user id, recorded as state of audit events
+    async fn user_grants_state<'a, C>(role_id: i32, db: &'a C) -> Result<serde_json::Value, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let grants =
+            UserRole::find(user_role::Column::RoleId.eq(role_id).into_condition(), db).await?;
+
+        Ok(audit::snapshot(&grants, |grant| grant.user_id))
+    }
+
+    /// Fails if the role is a system role
+    async fn ensure_not_system<'a, C>(role_id: i32, db: &'a C) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let role = role::Entity::find_by_id(role_id).one(db).await?;
+
+        match role {
+            Some(role) if role.is_system => Err(Error::Rbac(RbacError::SystemRole)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Number of enabled users that currently have the root role,
+    /// either granted directly or inherited through a group
+    pub(crate) async fn root_user_count<'a, C>(db: &'a C) -> Result<usize, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let root_role_ids = Query::select()
+            .column((role::Entity, role::Column::Id))
+            .from(role::Entity)
+            .cond_where(
+                Condition::all()
+                    .add(role::Column::Name.eq(ROOT_ROLE_NAME))
+                    .add(role::Column::IsSystem.eq(true)),
+            )
+            .to_owned();
+
+        let root_user_ids = Query::select()
+            .column((user_role::Entity, user_role::Column::UserId))
+            .from(user_role::Entity)
+            .cond_where(
+                Condition::all()
+                    .add(user_role::Column::RoleId.in_subquery(root_role_ids.clone()))
+                    .add(active_grant_condition(
+                        user_role::Column::ValidFrom,
+                        user_role::Column::ValidUntil,
+                    )),
+            )
+            .to_owned();
+
+        // Users that inherit the root role through a group
+        let root_group_ids = Query::select()
+            .column((group_role::Entity, group_role::Column::GroupId))
+            .from(group_role::Entity)
+            .cond_where(group_role::Column::RoleId.in_subquery(root_role_ids))
+            .to_owned();
+
+        let root_member_ids = Query::select()
+            .column((group_member::Entity, group_member::Column::UserId))
+            .from(group_member::Entity)
+            .cond_where(group_member::Column::GroupId.in_subquery(root_group_ids))
+            .to_owned();
+
+        user::Entity::find()
+            .filter(
+                Condition::all()
+                    .add(user::Column::Enabled.eq(true))
+                    .add(User::not_deleted_condition())
+                    .add(
+                        Condition::any()
+                            .add(user::Column::Id.in_subquery(root_user_ids))
+                            .add(user::Column::Id.in_subquery(root_member_ids)),
+                    ),
+            )
+            .count(db)
+            .await
+            .map_err(Error::Database)
+    }
+
+    /// Fails if there were enabled root users before an operation, but none are left.
+    /// Used inside the transaction of the operation so it can be rolled back.
+    pub(crate) async fn ensure_root_user_remains<'a, C>(
+        previous_count: usize,
+        db: &'a C,
+    ) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        if previous_count > 0 && Self::root_user_count(db).await? == 0 {
+            return Err(Error::Rbac(RbacError::LastRootUser));
+        }
+
+        Ok(())
+    }
+}
+
+impl AuditedModelTrait for Role {
+    fn audit_entity_type() -> Option<&'static str> {
+        Some(AUDIT_ENTITY_TYPE)
+    }
+}
+
+impl EventedModelTrait for Role {
+    fn lifecycle_event() -> Option<fn(Lifecycle, Self) -> DomainEvent> {
+        Some(|lifecycle, role| match lifecycle {
+            Lifecycle::Created => DomainEvent::RoleCreated(role),
+            Lifecycle::Updated => DomainEvent::RoleUpdated(role),
+            Lifecycle::Deleted => DomainEvent::RoleDeleted(role),
+        })
+    }
 }
 
 impl CreateModelTrait<role::Entity, role::ActiveModel, RoleCreate> for Role {}
 
 impl ReadModelTrait<role::Entity> for Role {}
 
-impl UpdateModelTrait<role::Entity, role::ActiveModel, RoleUpdate> for Role {}
+#[async_trait]
+impl UpdateModelTrait<role::Entity, role::ActiveModel, RoleUpdate> for Role {
+    fn version_column() -> Option<role::Column> {
+        Some(role::Column::Version)
+    }
+
+    /// Update a role. System roles can not be renamed.
+    async fn update<'a, C>(model: RoleUpdate, db: &'a C) -> Result<Self, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        model.validate(db).await?;
+
+        // Transaction, so the role is not renamed between the check and the update
+        events::transaction(db, |txn| {
+            Box::pin(async move {
+                if let Some(name) = &model.name {
+                    let role = role::Entity::find_by_id(model.id).one(txn).await?;
 
-impl DeleteModelTrait<role::Entity, role::ActiveModel> for Role {}
+                    if matches!(role, Some(role) if role.is_system && &role.name != name) {
+                        return Err(Error::Rbac(RbacError::SystemRole));
+                    }
+                }
+
+                Self::update_active_model(model.into_active_model(), txn).await
+            })
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl Validate for RoleCreate {
+    async fn rules<'a, C>(&self, validator: &mut Validator, _db: &'a C) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        validator
+            .required("name", &self.name)
+            .max_length("name", &self.name, MAX_STRING_LENGTH);
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Validate for RoleUpdate {
+    async fn rules<'a, C>(&self, validator: &mut Validator, _db: &'a C) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        if let Some(name) = &self.name {
+            validator
+                .required("name", name)
+                .max_length("name", name, MAX_STRING_LENGTH);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DeleteModelTrait<role::Entity, role::ActiveModel> for Role {
+    /// Delete a role. System roles can not be deleted.
+    async fn delete<'a, C>(id: i32, db: &'a C) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        // Transaction
+        events::transaction(db, |txn| {
+            Box::pin(async move {
+                let model = role::Entity::find_by_id(id).one(txn).await?;
+
+                if let Some(model) = model {
+                    if model.is_system {
+                        return Err(Error::Rbac(RbacError::SystemRole));
+                    }
+
+                    let role = Self::from(model.clone());
+                    model.delete(txn).await?;
+                    audit::record_model(
+                        AuditAction::Delete,
+                        AUDIT_ENTITY_TYPE,
+                        Some(&role),
+                        None,
+                        txn,
+                    )
+                    .await?;
+                    events::emit(DomainEvent::RoleDeleted(role));
+                }
+
+                Ok(())
+            })
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Delete roles using a condition. System roles are never deleted.
+    async fn delete_condition<'a, C>(condition: Condition, db: &'a C) -> Result<DeleteResult, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let condition = Condition::all()
+            .add(condition)
+            .add(role::Column::IsSystem.eq(false));
+
+        // Transaction
+        let result = events::transaction(db, |txn| {
+            Box::pin(async move {
+                let roles = Self::find(condition.clone(), txn).await?;
+                let result = role::Entity::delete_many()
+                    .filter(condition)
+                    .exec(txn)
+                    .await?;
+
+                for role in &roles {
+                    audit::record_model(
+                        AuditAction::Delete,
+                        AUDIT_ENTITY_TYPE,
+                        Some(role),
+                        None,
+                        txn,
+                    )
+                    .await?;
+                    events::emit(DomainEvent::RoleDeleted(role.clone()));
+                }
+
+                Ok(result)
+            })
+        })
+        .await?;
+
+        Ok(result)
+    }
+}
 
 impl PaginatedModelTrait<role::Entity, role::ActiveModel> for Role {
     fn default_order() -> (SimpleExpr, Order) {
         (role::Column::Id.into_simple_expr(), Order::Asc)
     }
 }
+
+impl FilterableModelTrait<role::Entity, role::ActiveModel> for Role {
+    fn filter_fields() -> Vec<FilterField<role::Column>> {
+        vec![
+            FilterField::new("id", role::Column::Id, FilterOperator::COMPARISON),
+            FilterField::new("name", role::Column::Name, FilterOperator::TEXT),
+            FilterField::new(
+                "description",
+                role::Column::Description,
+                FilterOperator::TEXT,
+            )
+            .nullable(),
+            FilterField::new(
+                "is_system",
+                role::Column::IsSystem,
+                FilterOperator::EQUALITY,
+            ),
+            FilterField::new(
+                "inserted_at",
+                role::Column::InsertedAt,
+                FilterOperator::COMPARISON,
+            ),
+            FilterField::new(
+                "updated_at",
+                role::Column::UpdatedAt,
+                FilterOperator::COMPARISON,
+            ),
+        ]
+    }
+
+    fn sort_fields() -> Vec<SortField<role::Column>> {
+        vec![
+            SortField::new("id", role::Column::Id),
+            SortField::new("name", role::Column::Name),
+            SortField::new("is_system", role::Column::IsSystem),
+            SortField::new("inserted_at", role::Column::InsertedAt),
+            SortField::new("updated_at", role::Column::UpdatedAt),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{Error, RbacError};
+    use crate::logic::tests::TestDatabase;
+    use crate::logic::{ReadModelTrait, UpdateModelTrait};
+    use crate::models::role::{Role, RoleUpdate};
+    use crate::seeding::{self, ROOT_ROLE_NAME};
+
+    #[tokio::test]
+    async fn system_roles_keep_their_name() {
+        let test = TestDatabase::sqlite().await;
+        let role = seeding::seed_root_role(&test.db).await.unwrap();
+
+        let renamed = RoleUpdate {
+            id: role.id,
+            name: Some("renamed".to_string()),
+            ..Default::default()
+        };
+        let result = Role::update(renamed, &test.db).await;
+        assert!(matches!(result, Err(Error::Rbac(RbacError::SystemRole))));
+
+        let described = RoleUpdate {
+            id: role.id,
+            name: Some(ROOT_ROLE_NAME.to_string()),
+            description: Some(Some("Everything".to_string())),
+            ..Default::default()
+        };
+        Role::update(described, &test.db).await.unwrap();
+
+        let current = Role::get(role.id, &test.db).await.unwrap().unwrap();
+        assert_eq!(current.name, ROOT_ROLE_NAME);
+        assert_eq!(current.description.as_deref(), Some("Everything"));
+    }
+}
@@ -1,7 +1,16 @@
-use crate::logic::{CreateModelTrait, DeleteModelTrait, ReadModelTrait};
+use crate::logic::validation::Validate;
+use crate::logic::{
+    AuditedModelTrait, CreateModelTrait, DeleteModelTrait, EventedModelTrait, ReadModelTrait,
+};
 use crate::models::role_permission::RolePermission;
 use taskrs_db::models::role_permission;
 
+impl AuditedModelTrait for RolePermission {}
+
+impl EventedModelTrait for RolePermission {}
+
+impl Validate for RolePermission {}
+
 impl CreateModelTrait<role_permission::Entity, role_permission::ActiveModel, RolePermission>
     for RolePermission
 {
@@ -0,0 +1,335 @@
+use crate::error::Error;
+use crate::logic::ReadModelTrait;
+use crate::models::search::SearchHit;
+use crate::models::user::User;
+use std::collections::HashMap;
+use taskrs_db::models::user;
+use taskrs_db::sea_orm::{ColumnTrait, Condition, ConnectionTrait, DbBackend, Statement};
+
+/// Maximum number of terms of a search query, further terms are ignored
+const MAX_TERMS: usize = 10;
+
+impl User {
+    /// Searches users by email and names using the full-text index of the database.
+    /// Every term of the query has to match the start of a word. Soft deleted users are excluded.
+    #[instrument(name = "search_users", level = "debug", skip_all)]
+    pub async fn search<'a, C>(
+        query: &str,
+        limit: usize,
+        db: &'a C,
+    ) -> Result<Vec<SearchHit<User>>, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let terms = search_terms(query);
+        if terms.is_empty() || limit == 0 {
+            return Ok(vec![]);
+        }
+
+        let backend = db.get_database_backend();
+        let limit = limit as i64;
+        let statement = match backend {
+            DbBackend::Sqlite => Statement::from_sql_and_values(
+                backend,
+                "SELECT users.id AS id, -bm25(users_search) AS score \
+                FROM users_search JOIN users ON users.id = users_search.rowid \
+                WHERE users_search MATCH ? AND users.deleted_at IS NULL \
+                ORDER BY score DESC, users.id LIMIT ?",
+                vec![fts5_query(&terms).into(), limit.into()],
+            ),
+            DbBackend::Postgres => Statement::from_sql_and_values(
+                backend,
+                "SELECT id, ts_rank(search, to_tsquery('simple', $1))::float8 AS score \
+                FROM users \
+                WHERE search @@ to_tsquery('simple', $1) AND deleted_at IS NULL \
+                ORDER BY score DESC, id LIMIT $2",
+                vec![tsquery(&terms).into(), limit.into()],
+            ),
+            DbBackend::MySql => Statement::from_sql_and_values(
+                backend,
+                "SELECT id, MATCH(email, first_name, last_name) AGAINST (? IN BOOLEAN MODE) AS score \
+                FROM users \
+                WHERE MATCH(email, first_name, last_name) AGAINST (? IN BOOLEAN MODE) \
+                AND deleted_at IS NULL \
+                ORDER BY score DESC, id LIMIT ?",
+                vec![
+                    boolean_query(&terms).into(),
+                    boolean_query(&terms).into(),
+                    limit.into(),
+                ],
+            ),
+        };
+
+        let ranks: Vec<(i32, f64)> = db
+            .query_all(statement)
+            .await?
+            .iter()
+            .map(|row| Ok((row.try_get("", "id")?, row.try_get("", "score")?)))
+            .collect::<Result<_, Error>>()?;
+        if ranks.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut users: HashMap<i32, User> = Self::find(
+            Condition::all().add(user::Column::Id.is_in(ranks.iter().map(|(id, _)| *id))),
+            db,
+        )
+        .await?
+        .into_iter()
+        .map(|user| (user.id, user))
+        .collect();
+
+        Ok(ranks
+            .into_iter()
+            .filter_map(|(id, rank)| users.remove(&id).map(|user| (user, rank)))
+            .map(|(user, rank)| {
+                let fields = [
+                    ("email", Some(&user.email)),
+                    ("firstName", user.first_name.as_ref()),
+                    ("lastName", user.last_name.as_ref()),
+                ];
+                let highlights = fields
+                    .into_iter()
+                    .filter_map(|(name, value)| {
+                        value
+                            .and_then(|value| highlight(value, &terms))
+                            .map(|value| (name.to_string(), value))
+                    })
+                    .collect();
+
+                SearchHit {
+                    rank,
+                    highlights,
+                    item: user,
+                }
+            })
+            .collect())
+    }
+}
+
+/// Splits a search query into lowercase terms of letters and digits.
+/// Everything else separates terms, like the tokenizers of the full-text indexes do.
+fn search_terms(query: &str) -> Vec<String> {
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(str::to_lowercase)
+        .take(MAX_TERMS)
+        .collect()
+}
+
+/// SQLite FTS5 query matching all terms as prefixes, e.g. `"ann"* "corp"*`
+fn fts5_query(terms: &[String]) -> String {
+    terms
+        .iter()
+        .map(|term| format!("\"{}\"*", term))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Postgres tsquery matching all terms as prefixes, e.g. `ann:* & corp:*`
+fn tsquery(terms: &[String]) -> String {
+    terms
+        .iter()
+        .map(|term| format!("{}:*", term))
+        .collect::<Vec<_>>()
+        .join(" & ")
+}
+
+/// MySQL boolean mode query matching all terms as prefixes, e.g. `+ann* +corp*`
+fn boolean_query(terms: &[String]) -> String {
+    terms
+        .iter()
+        .map(|term| format!("+{}*", term))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// HTML escapes `text` and wraps words starting with one of the terms in `<mark>` tags.
+/// Returns `None` if no term matches.
+fn highlight(text: &str, terms: &[String]) -> Option<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut highlighted = String::with_capacity(text.len());
+    let mut matched = false;
+
+    let mut i = 0;
+    while i < chars.len() {
+        let word_start = i == 0 || !chars[i - 1].is_alphanumeric();
+        let length = match word_start {
+            true => terms
+                .iter()
+                .filter(|term| starts_with_ignore_case(&chars[i..], term))
+                .map(|term| term.chars().count())
+                .max(),
+            false => None,
+        };
+
+        match length {
+            Some(length) => {
+                matched = true;
+                highlighted.push_str("<mark>");
+                chars[i..i + length]
+                    .iter()
+                    .for_each(|c| push_escaped(&mut highlighted, *c));
+                highlighted.push_str("</mark>");
+                i += length;
+            }
+            None => {
+                push_escaped(&mut highlighted, chars[i]);
+                i += 1;
+            }
+        }
+    }
+
+    matched.then_some(highlighted)
+}
+
+fn starts_with_ignore_case(chars: &[char], term: &str) -> bool {
+    let term: Vec<char> = term.chars().collect();
+    chars.len() >= term.len()
+        && chars
+            .iter()
+            .zip(&term)
+            .all(|(c, t)| c.to_lowercase().eq(t.to_lowercase()))
+}
+
+fn push_escaped(s: &mut String, c: char) {
+    match c {
+        '&' => s.push_str("&amp;"),
+        '<' => s.push_str("&lt;"),
+        '>' => s.push_str("&gt;"),
+        '"' => s.push_str("&quot;"),
+        '\'' => s.push_str("&#39;"),
+        c => s.push(c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{boolean_query, fts5_query, highlight, search_terms, tsquery};
+    use crate::logic::tests::TestDatabase;
+    use crate::logic::{CreateModelTrait, DeleteModelTrait, UpdateModelTrait};
+    use crate::models::user::{User, UserCreate, UserUpdate};
+    use taskrs_db::sea_orm::DbConn;
+
+    #[test]
+    fn terms_are_lowercase_words() {
+        assert_eq!(
+            search_terms("Ann  O'Neil@Corp.com"),
+            vec!["ann", "o", "neil", "corp", "com"]
+        );
+        assert!(search_terms(" *-\"").is_empty());
+    }
+
+    #[test]
+    fn queries_match_every_term_as_prefix() {
+        let terms = vec!["ann".to_string(), "corp".to_string()];
+
+        assert_eq!(fts5_query(&terms), "\"ann\"* \"corp\"*");
+        assert_eq!(tsquery(&terms), "ann:* & corp:*");
+        assert_eq!(boolean_query(&terms), "+ann* +corp*");
+    }
+
+    #[test]
+    fn highlights_are_escaped_and_only_mark_word_starts() {
+        let terms = vec!["an".to_string()];
+
+        assert_eq!(
+            highlight("Ann <Joanna>", &terms).as_deref(),
+            Some("<mark>An</mark>n &lt;Joanna&gt;")
+        );
+        assert_eq!(highlight("Joanna", &terms), None);
+    }
+
+    async fn create_user(first_name: &str, last_name: &str, db: &DbConn) -> i32 {
+        let user = UserCreate {
+            email: format!("u{:08x}.{}@example.com", rand::random::<u32>(), last_name),
+            password: "password".to_string(),
+            first_name: Some(first_name.to_string()),
+            last_name: Some(last_name.to_string()),
+            enabled: true,
+            ..Default::default()
+        };
+
+        User::create(user, db).await.unwrap().id
+    }
+
+    async fn search_ids(query: &str, db: &DbConn) -> Vec<i32> {
+        let mut ids: Vec<i32> = User::search(query, 10, db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.item.id)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Searches users of a fresh last name, so that users of earlier runs do not match
+    async fn assert_search(db: &DbConn) {
+        let family = format!("fam{:08x}", rand::random::<u32>());
+        let ann = create_user("Ann", &family, db).await;
+        let annabel = create_user("Annabel", &family, db).await;
+        let bob = create_user("Bob", &family, db).await;
+        let anna = create_user("Anna", &family, db).await;
+        User::delete(anna, db).await.unwrap();
+
+        // Deleted users are left out
+        assert_eq!(search_ids(&family, db).await, vec![ann, annabel, bob]);
+        // Every term has to match the start of a word
+        assert_eq!(
+            search_ids(&format!("ann {}", family), db).await,
+            vec![ann, annabel]
+        );
+        assert!(search_ids(&format!("nn {}", family), db).await.is_empty());
+        // Emails are split into words
+        assert_eq!(
+            search_ids(&format!("bob example {}", family), db).await,
+            vec![bob]
+        );
+
+        let hits = User::search(&format!("annab {}", family), 10, db)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].highlights["firstName"], "<mark>Annab</mark>el");
+        assert_eq!(
+            hits[0].highlights["lastName"],
+            format!("<mark>{}</mark>", family)
+        );
+
+        // Changes are indexed
+        User::update(
+            UserUpdate {
+                id: bob,
+                first_name: Some(Some("Carl".to_string())),
+                ..Default::default()
+            },
+            db,
+        )
+        .await
+        .unwrap();
+        assert!(search_ids(&format!("bob {}", family), db).await.is_empty());
+        assert_eq!(search_ids(&format!("carl {}", family), db).await, vec![bob]);
+    }
+
+    #[tokio::test]
+    async fn searches_users_on_sqlite() {
+        let test = TestDatabase::sqlite().await;
+        assert_search(&test.db).await;
+    }
+
+    #[tokio::test]
+    async fn searches_users_on_postgres() {
+        if let Some(test) = TestDatabase::from_env("TASKRS_TEST_POSTGRES_URL").await {
+            assert_search(&test.db).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn searches_users_on_mysql() {
+        if let Some(test) = TestDatabase::from_env("TASKRS_TEST_MYSQL_URL").await {
+            assert_search(&test.db).await;
+        }
+    }
+}
@@ -1,22 +1,47 @@
+use crate::error::{Error, ValidationCode};
+use crate::logic::grant::GrantUnitOfWork;
+use crate::logic::validation::{Validate, Validator, MAX_STRING_LENGTH};
 use crate::logic::{
-    CreateModelTrait, DeleteModelTrait, PaginatedModelTrait, ReadModelTrait, UpdateModelTrait,
+    active_grant_condition, AuditedModelTrait, CreateModelTrait, DeleteModelTrait,
+    EventedModelTrait, FilterableModelTrait, PaginatedModelTrait, ReadModelTrait, UpdateModelTrait,
 };
-use crate::models::permission::Permission;
-use crate::models::role::Role;
+use crate::logic::{audit, events};
+use crate::models::audit_event::AuditAction;
+use crate::models::domain_event::{DomainEvent, Lifecycle};
+use crate::models::filter::{FilterField, FilterOperator, SortField};
+use crate::models::grant::{ExpiredGrants, GrantChanges, GrantValidity};
+use crate::models::group::Group;
+use crate::models::group_member::GroupMember;
+use crate::models::permission::{
+    Permission, PermissionExplanation, PermissionRule, PermissionRuleSource,
+};
+use crate::models::refresh_token::RefreshToken;
+use crate::models::role::{AssignedRole, Role, RoleSource};
 use crate::models::user::{User, UserCreate, UserUpdate};
 use crate::models::user_permission::UserPermission;
 use crate::models::user_role::UserRole;
-use std::collections::HashSet;
-use taskrs_db::models::{permission, role, role_permission, user, user_permission, user_role};
+use crate::models::IntoActiveModel;
+use crate::permissions;
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+use taskrs_db::models::permission::PermissionEffect;
+use taskrs_db::models::{
+    group, group_member, group_role, permission, refresh_token, role, role_permission, user,
+    user_permission, user_role,
+};
 use taskrs_db::sea_orm::prelude::*;
-use taskrs_db::sea_orm::sea_query::{Expr, IntoCondition, Query, SimpleExpr};
-use taskrs_db::sea_orm::{Condition, ConnectionTrait, JoinType, QuerySelect, TransactionError};
+use taskrs_db::sea_orm::sea_query::{Expr, IntoCondition, Query, SelectStatement, SimpleExpr};
+use taskrs_db::sea_orm::{
+    Condition, ConnectionTrait, DatabaseTransaction, DeleteResult, QueryOrder,
+};
 use taskrs_db::sea_orm::{IntoSimpleExpr, Order};
-use taskrs_db::utils::QueryId;
+
+const AUDIT_ENTITY_TYPE: &str = "user";
 
 impl User {
-    /// Checks if an user with email already exists
-    pub async fn email_exists<'a, C>(email: &str, db: &'a C) -> Result<bool, DbErr>
+    /// Checks if an user with email already exists.
+    /// Soft deleted users keep their email, so they are included.
+    pub async fn email_exists<'a, C>(email: &str, db: &'a C) -> Result<bool, Error>
     where
         C: ConnectionTrait<'a>,
     {
@@ -25,13 +50,64 @@ impl User {
             .one(db)
             .await
             .map(|res| res.is_some())
+            .map_err(Error::Database)
+    }
+
+    /// Validates an email, which has to be unique among all users except `user_id`
+    pub(crate) async fn validate_email<'a, C>(
+        email: &str,
+        user_id: Option<i32>,
+        validator: &mut Validator,
+        db: &'a C,
+    ) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        validator
+            .required("email", email)
+            .max_length("email", email, MAX_STRING_LENGTH)
+            .email("email", email);
+
+        if validator.is_valid("email") {
+            let mut query = user::Entity::find().filter(user::Column::Email.eq(email));
+            if let Some(user_id) = user_id {
+                query = query.filter(user::Column::Id.ne(user_id));
+            }
+
+            if query.one(db).await?.is_some() {
+                validator.add("email", ValidationCode::Unique, "is already taken");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks if a permission of the catalog is effective for the user
+    pub async fn has_permission<'a, C>(
+        user_id: i32,
+        permission: permissions::Permission,
+        db: &'a C,
+    ) -> Result<bool, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        Self::permissions_query(user_id)
+            .filter(
+                Condition::all()
+                    .add(permission::Column::Group.eq(permission.group()))
+                    .add(permission::Column::Name.eq(permission.name())),
+            )
+            .one(db)
+            .await
+            .map(|model| model.is_some())
+            .map_err(Error::Database)
     }
 
     pub async fn has_one_permission<'a, C>(
         user_id: i32,
         permission_id: i32,
         db: &'a C,
-    ) -> Result<bool, DbErr>
+    ) -> Result<bool, Error>
     where
         C: ConnectionTrait<'a>,
     {
@@ -40,13 +116,14 @@ impl User {
             .one(db)
             .await
             .map(|model| model.is_some())
+            .map_err(Error::Database)
     }
 
     pub async fn has_any_permission<'a, C>(
         user_id: i32,
         permission_ids: Vec<i32>,
         db: &'a C,
-    ) -> Result<bool, DbErr>
+    ) -> Result<bool, Error>
     where
         C: ConnectionTrait<'a>,
     {
@@ -55,13 +132,14 @@ impl User {
             .one(db)
             .await
             .map(|model| model.is_some())
+            .map_err(Error::Database)
     }
 
     pub async fn has_all_permissions<'a, C>(
         user_id: i32,
         permission_ids: Vec<i32>,
         db: &'a C,
-    ) -> Result<bool, DbErr>
+    ) -> Result<bool, Error>
     where
         C: ConnectionTrait<'a>,
     {
@@ -72,9 +150,10 @@ impl User {
             .all(db)
             .await
             .map(|models| models.len() == total)
+            .map_err(Error::Database)
     }
 
-    pub async fn permissions<'a, C>(user_id: i32, db: &'a C) -> Result<Vec<Permission>, DbErr>
+    pub async fn permissions<'a, C>(user_id: i32, db: &'a C) -> Result<Vec<Permission>, Error>
     where
         C: ConnectionTrait<'a>,
     {
@@ -82,273 +161,1061 @@ impl User {
             .all(db)
             .await
             .map(|models| models.into_iter().map(Permission::from).collect())
+            .map_err(Error::Database)
     }
 
+    /// Grants permissions to the user directly for the provided period.
+    /// Existing rules for the same permissions are turned into allow rules with the new period.
     pub async fn grant_permissions<'a, C>(
         user_id: i32,
         permission_ids: Vec<i32>,
+        validity: GrantValidity,
         db: &'a C,
-    ) -> Result<(), DbErr>
+    ) -> Result<GrantChanges, Error>
     where
         C: ConnectionTrait<'a>,
     {
-        // Get direct permissions of user
-        let current_permission_ids: HashSet<i32> = user_permission::Entity::find()
-            .select_only()
-            .column_as(user_permission::Column::PermissionId, QueryId::Id)
-            .filter(user_permission::Column::UserId.eq(user_id))
-            .into_values::<_, QueryId>()
-            .all(db)
-            .await?
-            .into_iter()
-            .collect();
+        Self::assign_permissions(
+            user_id,
+            permission_ids,
+            PermissionEffect::Allow,
+            validity,
+            db,
+        )
+        .await
+    }
 
-        // Filter only new permissions
-        let new_permission_ids: HashSet<i32> = permission_ids.into_iter().collect();
-        let user_permissions: Vec<UserPermission> = (&new_permission_ids - &current_permission_ids)
-            .into_iter()
-            .map(|permission_id| UserPermission {
-                user_id,
-                permission_id,
-                inserted_at: None,
-            })
-            .collect();
+    /// Denies permissions to the user directly. A deny overrides every allow,
+    /// regardless of whether the allow is direct or granted through a role.
+    /// Existing allow rules for the same permissions are turned into permanent deny rules.
+    pub async fn deny_permissions<'a, C>(
+        user_id: i32,
+        permission_ids: Vec<i32>,
+        db: &'a C,
+    ) -> Result<GrantChanges, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        Self::assign_permissions(
+            user_id,
+            permission_ids,
+            PermissionEffect::Deny,
+            GrantValidity::default(),
+            db,
+        )
+        .await
+    }
 
-        // Insert models
-        if !user_permissions.is_empty() {
-            UserPermission::create_many(user_permissions, db).await?;
-        }
+    async fn assign_permissions<'a, C>(
+        user_id: i32,
+        permission_ids: Vec<i32>,
+        effect: PermissionEffect,
+        validity: GrantValidity,
+        db: &'a C,
+    ) -> Result<GrantChanges, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        // Transaction
+        events::transaction(db, |txn| {
+            Box::pin(async move {
+                let before = Self::permission_rules_state(user_id, txn).await?;
 
-        Ok(())
+                // Insert new rules and overwrite existing ones
+                let user_permissions: Vec<UserPermission> = permission_ids
+                    .into_iter()
+                    .collect::<HashSet<i32>>()
+                    .into_iter()
+                    .map(|permission_id| UserPermission {
+                        user_id,
+                        permission_id,
+                        effect,
+                        validity,
+                        inserted_at: None,
+                    })
+                    .collect();
+
+                let mut grants = Self::permission_grants(user_id, txn);
+                grants.upsert(user_permissions).await?;
+                let changes = grants.into_changes();
+
+                let action = match effect {
+                    PermissionEffect::Allow => AuditAction::GrantPermissions,
+                    PermissionEffect::Deny => AuditAction::DenyPermissions,
+                };
+                let after = Self::permission_rules_state(user_id, txn).await?;
+                audit::record(action, AUDIT_ENTITY_TYPE, user_id, before, after, txn).await?;
+                if !changes.is_empty() {
+                    events::emit(DomainEvent::UserPermissionsChanged { user_id });
+                }
+
+                Ok(changes)
+            })
+        })
+        .await
     }
 
     pub async fn revoke_permissions<'a, C>(
         user_id: i32,
         permission_ids: Vec<i32>,
         db: &'a C,
-    ) -> Result<(), DbErr>
+    ) -> Result<GrantChanges, Error>
     where
         C: ConnectionTrait<'a>,
     {
-        UserPermission::delete_condition(
-            Condition::all()
-                .add(user_permission::Column::UserId.eq(user_id))
-                .add(user_permission::Column::PermissionId.is_in(permission_ids)),
-            db,
-        )
-        .await?;
+        // Transaction
+        events::transaction(db, |txn| {
+            Box::pin(async move {
+                let before = Self::permission_rules_state(user_id, txn).await?;
 
-        Ok(())
+                let mut grants = Self::permission_grants(user_id, txn);
+                grants
+                    .remove(permission_ids.into_iter().collect::<HashSet<i32>>())
+                    .await?;
+                let changes = grants.into_changes();
+
+                let after = Self::permission_rules_state(user_id, txn).await?;
+                audit::record(
+                    AuditAction::RevokePermissions,
+                    AUDIT_ENTITY_TYPE,
+                    user_id,
+                    before,
+                    after,
+                    txn,
+                )
+                .await?;
+                if !changes.is_empty() {
+                    events::emit(DomainEvent::UserPermissionsChanged { user_id });
+                }
+
+                Ok(changes)
+            })
+        })
+        .await
     }
 
+    /// Replaces the direct allow rules of the user, all of them are valid indefinitely.
+    /// Deny rules are kept and win over the provided permissions.
     pub async fn set_permissions<'a, C>(
         user_id: i32,
         permission_ids: Vec<i32>,
         db: &'a C,
-    ) -> Result<(), TransactionError<DbErr>>
+    ) -> Result<GrantChanges, Error>
     where
         C: ConnectionTrait<'a>,
     {
-        // Create models for inserting
-        let new_user_permissions: Vec<UserPermission> = permission_ids
-            .into_iter()
-            .map(|permission_id| UserPermission {
-                user_id,
-                permission_id,
-                inserted_at: None,
-            })
-            .collect();
-
         // Transaction
-        db.transaction::<_, (), DbErr>(|txn| {
+        events::transaction(db, |txn| {
             Box::pin(async move {
-                // Delete all user permissions
-                UserPermission::delete_condition(
-                    user_permission::Column::UserId.eq(user_id).into_condition(),
+                let before = Self::permission_rules_state(user_id, txn).await?;
+                let mut grants = Self::permission_grants(user_id, txn);
+
+                // Get denied permissions, they can not be allowed at the same time
+                let denied_permission_ids = grants
+                    .granted_ids(
+                        user_permission::Column::Effect
+                            .eq(PermissionEffect::Deny)
+                            .into_condition(),
+                    )
+                    .await?;
+
+                // Create models for inserting
+                let new_user_permissions: Vec<UserPermission> = permission_ids
+                    .into_iter()
+                    .collect::<HashSet<i32>>()
+                    .into_iter()
+                    .filter(|id| !denied_permission_ids.contains(id))
+                    .map(|permission_id| UserPermission {
+                        user_id,
+                        permission_id,
+                        effect: PermissionEffect::Allow,
+                        validity: GrantValidity::default(),
+                        inserted_at: None,
+                    })
+                    .collect();
+
+                // Replace allowed user permissions
+                grants
+                    .replace(
+                        user_permission::Column::Effect
+                            .eq(PermissionEffect::Allow)
+                            .into_condition(),
+                        new_user_permissions,
+                    )
+                    .await?;
+                let changes = grants.into_changes();
+
+                let after = Self::permission_rules_state(user_id, txn).await?;
+                audit::record(
+                    AuditAction::SetPermissions,
+                    AUDIT_ENTITY_TYPE,
+                    user_id,
+                    before,
+                    after,
                     txn,
                 )
                 .await?;
-
-                // Insert new permissions
-                if !new_user_permissions.is_empty() {
-                    UserPermission::create_many(new_user_permissions, txn).await?;
+                if !changes.is_empty() {
+                    events::emit(DomainEvent::UserPermissionsChanged { user_id });
                 }
 
-                Ok(())
+                Ok(changes)
             })
         })
         .await
     }
 
-    pub async fn roles<'a, C>(user_id: i32, db: &'a C) -> Result<Vec<Role>, DbErr>
+    /// Explains why a permission is or is not granted to the user,
+    /// listing every allow rule and every deny rule that applies.
+    pub async fn explain_permission<'a, C>(
+        user_id: i32,
+        permission_id: i32,
+        db: &'a C,
+    ) -> Result<PermissionExplanation, Error>
     where
         C: ConnectionTrait<'a>,
     {
-        role::Entity::find()
-            .filter(user_role::Column::UserId.eq(user_id))
-            .join_rev(JoinType::InnerJoin, user_role::Relation::Role.def())
+        // Direct rules
+        let direct_rules = user_permission::Entity::find()
+            .filter(user_permission::Column::UserId.eq(user_id))
+            .filter(user_permission::Column::PermissionId.eq(permission_id))
+            .filter(active_grant_condition(
+                user_permission::Column::ValidFrom,
+                user_permission::Column::ValidUntil,
+            ))
             .all(db)
-            .await
-            .map(|models| models.into_iter().map(Role::from).collect())
+            .await?
+            .into_iter()
+            .map(|model| PermissionRule {
+                effect: model.effect,
+                source: PermissionRuleSource::Direct,
+            });
+
+        // Rules of the user's roles
+        let role_rules = role_permission::Entity::find()
+            .find_also_related(role::Entity)
+            .filter(role_permission::Column::PermissionId.eq(permission_id))
+            .filter(role_permission::Column::RoleId.in_subquery(Self::role_ids_query(user_id)))
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|(model, role)| {
+                role.map(|role| PermissionRule {
+                    effect: model.effect,
+                    source: PermissionRuleSource::Role {
+                        id: role.id,
+                        name: role.name,
+                    },
+                })
+            });
+
+        let (denied_by, allowed_by): (Vec<PermissionRule>, Vec<PermissionRule>) = direct_rules
+            .chain(role_rules)
+            .partition(|rule| rule.effect == PermissionEffect::Deny);
+
+        Ok(PermissionExplanation {
+            permission_id,
+            granted: !allowed_by.is_empty() && denied_by.is_empty(),
+            allowed_by,
+            denied_by,
+        })
     }
 
-    pub async fn grant_roles<'a, C>(
-        user_id: i32,
-        role_ids: Vec<i32>,
-        db: &'a C,
-    ) -> Result<(), DbErr>
+    /// Roles currently granted to the user, together with
+    /// whether they are granted directly or inherited through groups
+    pub async fn roles<'a, C>(user_id: i32, db: &'a C) -> Result<Vec<AssignedRole>, Error>
     where
         C: ConnectionTrait<'a>,
     {
-        // Get direct roles of user
-        let current_role_ids: HashSet<i32> = user_role::Entity::find()
-            .select_only()
-            .column_as(user_role::Column::RoleId, QueryId::Id)
-            .filter(user_role::Column::UserId.eq(user_id))
-            .into_values::<_, QueryId>()
+        let mut assigned_roles: Vec<AssignedRole> = role::Entity::find()
+            .filter(role::Column::Id.in_subquery(Self::direct_role_ids_query(user_id)))
+            .order_by_asc(role::Column::Id)
             .all(db)
             .await?
             .into_iter()
+            .map(|model| AssignedRole {
+                role: Role::from(model),
+                sources: vec![RoleSource::Direct],
+            })
             .collect();
 
-        // Filter only new roles
-        let new_role_ids: HashSet<i32> = role_ids.into_iter().collect();
-        let user_roles: Vec<UserRole> = (&new_role_ids - &current_role_ids)
+        // Roles of the user's groups
+        let group_roles = group_role::Entity::find()
+            .find_also_related(role::Entity)
+            .filter(group_role::Column::GroupId.in_subquery(Self::group_ids_query(user_id)))
+            .order_by_asc(group_role::Column::RoleId)
+            .all(db)
+            .await?;
+
+        let groups: HashMap<i32, String> = group::Entity::find()
+            .filter(group::Column::Id.in_subquery(Self::group_ids_query(user_id)))
+            .all(db)
+            .await?
             .into_iter()
-            .map(|role_id| UserRole {
-                user_id,
-                role_id,
-                inserted_at: None,
-            })
+            .map(|model| (model.id, model.name))
             .collect();
 
-        // Insert models
-        if !user_roles.is_empty() {
-            UserRole::create_many(user_roles, db).await?;
+        for (group_role, role) in group_roles {
+            let (role, group_name) = match (role, groups.get(&group_role.group_id)) {
+                (Some(role), Some(group_name)) => (role, group_name),
+                _ => continue,
+            };
+
+            let source = RoleSource::Group {
+                id: group_role.group_id,
+                name: group_name.clone(),
+            };
+
+            match assigned_roles.iter_mut().find(|r| r.role.id == role.id) {
+                Some(assigned_role) => assigned_role.sources.push(source),
+                None => assigned_roles.push(AssignedRole {
+                    role: Role::from(role),
+                    sources: vec![source],
+                }),
+            }
         }
 
-        Ok(())
+        Ok(assigned_roles)
+    }
+
+    /// Groups the user is a member of
+    pub async fn groups<'a, C>(user_id: i32, db: &'a C) -> Result<Vec<Group>, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        group::Entity::find()
+            .filter(group::Column::Id.in_subquery(Self::group_ids_query(user_id)))
+            .all(db)
+            .await
+            .map(|models| models.into_iter().map(Group::from).collect())
+            .map_err(Error::Database)
+    }
+
+    /// Grants roles to the user for the provided period.
+    /// Roles that are already granted get the new period.
+    pub async fn grant_roles<'a, C>(
+        user_id: i32,
+        role_ids: Vec<i32>,
+        validity: GrantValidity,
+        db: &'a C,
+    ) -> Result<GrantChanges, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        // Transaction
+        events::transaction(db, |txn| {
+            Box::pin(async move {
+                let before = Self::role_grants_state(user_id, txn).await?;
+
+                // Insert new roles and overwrite the period of existing ones
+                let user_roles: Vec<UserRole> = role_ids
+                    .into_iter()
+                    .collect::<HashSet<i32>>()
+                    .into_iter()
+                    .map(|role_id| UserRole {
+                        user_id,
+                        role_id,
+                        validity,
+                        inserted_at: None,
+                    })
+                    .collect();
+
+                let mut grants = Self::role_grants(user_id, txn);
+                grants.upsert(user_roles).await?;
+                let changes = grants.into_changes();
+
+                let after = Self::role_grants_state(user_id, txn).await?;
+                audit::record(
+                    AuditAction::GrantRoles,
+                    AUDIT_ENTITY_TYPE,
+                    user_id,
+                    before,
+                    after,
+                    txn,
+                )
+                .await?;
+                if !changes.is_empty() {
+                    events::emit(DomainEvent::UserRolesChanged { user_id });
+                }
+
+                Ok(changes)
+            })
+        })
+        .await
     }
 
+    /// Revokes roles of the user.
+    /// Fails if no enabled user with the root role would be left.
     pub async fn revoke_roles<'a, C>(
         user_id: i32,
         role_ids: Vec<i32>,
         db: &'a C,
-    ) -> Result<(), DbErr>
+    ) -> Result<GrantChanges, Error>
     where
         C: ConnectionTrait<'a>,
     {
-        UserRole::delete_condition(
-            Condition::all()
-                .add(user_role::Column::UserId.eq(user_id))
-                .add(user_role::Column::RoleId.is_in(role_ids)),
-            db,
-        )
-        .await?;
+        // Transaction
+        events::transaction(db, |txn| {
+            Box::pin(async move {
+                let root_user_count = Role::root_user_count(txn).await?;
+                let before = Self::role_grants_state(user_id, txn).await?;
 
-        Ok(())
+                let mut grants = Self::role_grants(user_id, txn);
+                grants
+                    .remove(role_ids.into_iter().collect::<HashSet<i32>>())
+                    .await?;
+                let changes = grants.into_changes();
+
+                Role::ensure_root_user_remains(root_user_count, txn).await?;
+
+                let after = Self::role_grants_state(user_id, txn).await?;
+                audit::record(
+                    AuditAction::RevokeRoles,
+                    AUDIT_ENTITY_TYPE,
+                    user_id,
+                    before,
+                    after,
+                    txn,
+                )
+                .await?;
+                if !changes.is_empty() {
+                    events::emit(DomainEvent::UserRolesChanged { user_id });
+                }
+
+                Ok(changes)
+            })
+        })
+        .await
     }
 
+    /// Replaces the roles of the user, all of them are granted indefinitely.
+    /// Fails if no enabled user with the root role would be left.
     pub async fn set_roles<'a, C>(
         user_id: i32,
         role_ids: Vec<i32>,
         db: &'a C,
-    ) -> Result<(), TransactionError<DbErr>>
+    ) -> Result<GrantChanges, Error>
     where
         C: ConnectionTrait<'a>,
     {
         // Create models for inserting
         let new_user_roles: Vec<UserRole> = role_ids
+            .into_iter()
+            .collect::<HashSet<i32>>()
             .into_iter()
             .map(|role_id| UserRole {
                 user_id,
                 role_id,
+                validity: GrantValidity::default(),
                 inserted_at: None,
             })
             .collect();
 
         // Transaction
-        db.transaction::<_, (), DbErr>(|txn| {
+        events::transaction(db, |txn| {
             Box::pin(async move {
-                // Delete all user roles
-                UserRole::delete_condition(
-                    user_role::Column::UserId.eq(user_id).into_condition(),
+                let root_user_count = Role::root_user_count(txn).await?;
+                let before = Self::role_grants_state(user_id, txn).await?;
+
+                // Replace all user roles
+                let mut grants = Self::role_grants(user_id, txn);
+                grants.replace(Condition::all(), new_user_roles).await?;
+                let changes = grants.into_changes();
+
+                Role::ensure_root_user_remains(root_user_count, txn).await?;
+
+                let after = Self::role_grants_state(user_id, txn).await?;
+                audit::record(
+                    AuditAction::SetRoles,
+                    AUDIT_ENTITY_TYPE,
+                    user_id,
+                    before,
+                    after,
                     txn,
                 )
                 .await?;
-
-                // Insert new roles
-                if !new_user_roles.is_empty() {
-                    UserRole::create_many(new_user_roles, txn).await?;
+                if !changes.is_empty() {
+                    events::emit(DomainEvent::UserRolesChanged { user_id });
                 }
 
-                Ok(())
+                Ok(changes)
             })
         })
         .await
     }
 
-    fn permissions_query(user_id: i32) -> Select<permission::Entity> {
-        permission::Entity::find().filter(
-            permission::Column::Id.in_subquery(
-                Query::select()
-                    .column((permission::Entity, permission::Column::Id))
-                    .from(user::Entity)
-                    .inner_join(
-                        user_permission::Entity,
-                        Expr::tbl(user::Entity, user::Column::Id)
-                            .equals(user_permission::Entity, user_permission::Column::UserId),
-                    )
-                    .inner_join(
-                        user_role::Entity,
-                        Expr::tbl(user::Entity, user::Column::Id)
-                            .equals(user_role::Entity, user_role::Column::UserId),
-                    )
-                    .inner_join(
-                        role::Entity,
-                        Expr::tbl(user_role::Entity, user_role::Column::RoleId)
-                            .equals(role::Entity, role::Column::Id),
+    /// Restores a soft deleted user. Returns `None` if the user does not exist.
+    pub async fn restore<'a, C>(user_id: i32, db: &'a C) -> Result<Option<User>, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let user = match Self::get_with_deleted(user_id, db).await? {
+            Some(user) => user,
+            None => return Ok(None),
+        };
+
+        if user.deleted_at.is_none() {
+            return Ok(Some(user));
+        }
+
+        Self::update(
+            UserUpdate {
+                id: user_id,
+                deleted_at: Some(None),
+                ..Default::default()
+            },
+            db,
+        )
+        .await
+        .map(Some)
+    }
+
+    /// Permanently removes users that were soft deleted longer than `retention` ago,
+    /// together with their grants, group memberships and sessions.
+    /// Returns the number of removed users.
+    #[instrument(name = "purge_deleted_users", level = "debug", skip_all)]
+    pub async fn purge_deleted<'a, C>(retention: chrono::Duration, db: &'a C) -> Result<u64, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let deleted_before = chrono::Utc::now().naive_utc() - retention;
+
+        // Transaction
+        let user_ids = events::transaction(db, |txn| {
+            Box::pin(async move {
+                let users = Self::find_with_deleted(
+                    user::Column::DeletedAt.lte(deleted_before).into_condition(),
+                    txn,
+                )
+                .await?;
+                let user_ids: Vec<i32> = users.iter().map(|user| user.id).collect();
+
+                if user_ids.is_empty() {
+                    return Ok(user_ids);
+                }
+
+                UserRole::delete_condition(
+                    user_role::Column::UserId
+                        .is_in(user_ids.clone())
+                        .into_condition(),
+                    txn,
+                )
+                .await?;
+                UserPermission::delete_condition(
+                    user_permission::Column::UserId
+                        .is_in(user_ids.clone())
+                        .into_condition(),
+                    txn,
+                )
+                .await?;
+                GroupMember::delete_condition(
+                    group_member::Column::UserId
+                        .is_in(user_ids.clone())
+                        .into_condition(),
+                    txn,
+                )
+                .await?;
+                RefreshToken::delete_condition(
+                    refresh_token::Column::UserId
+                        .is_in(user_ids.clone())
+                        .into_condition(),
+                    txn,
+                )
+                .await?;
+
+                user::Entity::delete_many()
+                    .filter(user::Column::Id.is_in(user_ids.clone()))
+                    .exec(txn)
+                    .await?;
+
+                for user in &users {
+                    audit::record_model(
+                        AuditAction::Purge,
+                        AUDIT_ENTITY_TYPE,
+                        Some(user),
+                        None,
+                        txn,
                     )
-                    .inner_join(
-                        role_permission::Entity,
-                        Expr::tbl(role::Entity, role::Column::Id)
-                            .equals(role_permission::Entity, role_permission::Column::RoleId),
+                    .await?;
+                    events::emit(DomainEvent::UserPurged { user_id: user.id });
+                }
+
+                Ok(user_ids)
+            })
+        })
+        .await?;
+
+        Ok(user_ids.len() as u64)
+    }
+
+    /// Removes all role and permission grants whose validity has ended
+    /// and returns the removed grants. Emits an audit event for every removed grant.
+    #[instrument(name = "revoke_expired_grants", level = "debug", skip_all)]
+    pub async fn revoke_expired_grants<'a, C>(db: &'a C) -> Result<ExpiredGrants, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let now = chrono::Utc::now().naive_utc();
+
+        // Transaction
+        let expired_grants = events::transaction(db, |txn| {
+            Box::pin(async move {
+                let user_roles_condition = user_role::Column::ValidUntil.lte(now).into_condition();
+                let user_permissions_condition = user_permission::Column::ValidUntil
+                    .lte(now)
+                    .into_condition();
+
+                let user_roles = UserRole::find(user_roles_condition.clone(), txn).await?;
+                let user_permissions =
+                    UserPermission::find(user_permissions_condition.clone(), txn).await?;
+
+                if !user_roles.is_empty() {
+                    UserRole::delete_condition(user_roles_condition, txn).await?;
+                }
+                if !user_permissions.is_empty() {
+                    UserPermission::delete_condition(user_permissions_condition, txn).await?;
+                }
+
+                for user_role in &user_roles {
+                    audit::record(
+                        AuditAction::ExpireRoles,
+                        AUDIT_ENTITY_TYPE,
+                        user_role.user_id,
+                        audit::snapshot(std::slice::from_ref(user_role), |grant| grant.role_id),
+                        serde_json::json!({}),
+                        txn,
                     )
-                    .inner_join(
-                        permission::Entity,
-                        Condition::any()
-                            .add(
-                                Expr::tbl(
-                                    user_permission::Entity,
-                                    user_permission::Column::PermissionId,
-                                )
-                                .equals(permission::Entity, permission::Column::Id),
-                            )
-                            .add(
-                                Expr::tbl(
-                                    role_permission::Entity,
-                                    role_permission::Column::PermissionId,
-                                )
-                                .equals(permission::Entity, permission::Column::Id),
-                            ),
+                    .await?;
+                    events::emit(DomainEvent::UserRolesChanged {
+                        user_id: user_role.user_id,
+                    });
+                }
+                for user_permission in &user_permissions {
+                    audit::record(
+                        AuditAction::ExpirePermissions,
+                        AUDIT_ENTITY_TYPE,
+                        user_permission.user_id,
+                        audit::snapshot(std::slice::from_ref(user_permission), |rule| {
+                            rule.permission_id
+                        }),
+                        serde_json::json!({}),
+                        txn,
                     )
-                    .and_where(user::Column::Id.eq(user_id))
-                    .to_owned(),
-            ),
+                    .await?;
+                    events::emit(DomainEvent::UserPermissionsChanged {
+                        user_id: user_permission.user_id,
+                    });
+                }
+
+                Ok(ExpiredGrants {
+                    user_roles,
+                    user_permissions,
+                })
+            })
+        })
+        .await?;
+
+        Ok(expired_grants)
+    }
+
+    /// Direct permission rules of the user, changed in the transaction
+    fn permission_grants(
+        user_id: i32,
+        txn: &DatabaseTransaction,
+    ) -> GrantUnitOfWork<'_, user_permission::Entity> {
+        GrantUnitOfWork::new(
+            txn,
+            user_permission::Column::UserId,
+            user_id,
+            user_permission::Column::PermissionId,
+        )
+    }
+
+    /// Direct role grants of the user, changed in the transaction
+    fn role_grants(
+        user_id: i32,
+        txn: &DatabaseTransaction,
+    ) -> GrantUnitOfWork<'_, user_role::Entity> {
+        GrantUnitOfWork::new(
+            txn,
+            user_role::Column::UserId,
+            user_id,
+            user_role::Column::RoleId,
+        )
+    }
+
+    /// Role grants of the user keyed by role id, recorded as state of audit events
+    async fn role_grants_state<'a, C>(user_id: i32, db: &'a C) -> Result<serde_json::Value, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let grants =
+            UserRole::find(user_role::Column::UserId.eq(user_id).into_condition(), db).await?;
+
+        Ok(audit::snapshot(&grants, |grant| grant.role_id))
+    }
+
+    /// Direct permission rules of the user keyed by permission id,
+    /// recorded as state of audit events
+    async fn permission_rules_state<'a, C>(
+        user_id: i32,
+        db: &'a C,
+    ) -> Result<serde_json::Value, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let rules = UserPermission::find(
+            user_permission::Column::UserId.eq(user_id).into_condition(),
+            db,
+        )
+        .await?;
+
+        Ok(audit::snapshot(&rules, |rule| rule.permission_id))
+    }
+
+    /// Query for all effective permissions of a user.
+    /// A permission is effective if it is allowed directly or through a role
+    /// and not denied directly or through a role.
+    fn permissions_query(user_id: i32) -> Select<permission::Entity> {
+        // Conditions have to be combined up front, as chaining `filter` onto
+        // an `any` condition would add to the `any` condition.
+        permission::Entity::find().filter(
+            Condition::all()
+                .add(
+                    Condition::any()
+                        .add(
+                            permission::Column::Id.in_subquery(Self::direct_permission_ids_query(
+                                user_id,
+                                PermissionEffect::Allow,
+                            )),
+                        )
+                        .add(
+                            permission::Column::Id.in_subquery(Self::role_permission_ids_query(
+                                user_id,
+                                PermissionEffect::Allow,
+                            )),
+                        ),
+                )
+                .add(
+                    permission::Column::Id.not_in_subquery(Self::direct_permission_ids_query(
+                        user_id,
+                        PermissionEffect::Deny,
+                    )),
+                )
+                .add(
+                    permission::Column::Id.not_in_subquery(Self::role_permission_ids_query(
+                        user_id,
+                        PermissionEffect::Deny,
+                    )),
+                ),
         )
     }
+
+    fn direct_permission_ids_query(user_id: i32, effect: PermissionEffect) -> SelectStatement {
+        Query::select()
+            .column((
+                user_permission::Entity,
+                user_permission::Column::PermissionId,
+            ))
+            .from(user_permission::Entity)
+            .cond_where(
+                Condition::all()
+                    .add(user_permission::Column::UserId.eq(user_id))
+                    .add(user_permission::Column::Effect.eq(effect))
+                    .add(active_grant_condition(
+                        user_permission::Column::ValidFrom,
+                        user_permission::Column::ValidUntil,
+                    )),
+            )
+            .to_owned()
+    }
+
+    fn role_permission_ids_query(user_id: i32, effect: PermissionEffect) -> SelectStatement {
+        Query::select()
+            .column((
+                role_permission::Entity,
+                role_permission::Column::PermissionId,
+            ))
+            .from(role_permission::Entity)
+            .and_where(role_permission::Column::RoleId.in_subquery(Self::role_ids_query(user_id)))
+            .and_where(role_permission::Column::Effect.eq(effect))
+            .to_owned()
+    }
+
+    /// Query for the ids of all roles of a user,
+    /// granted directly or inherited through a group
+    fn role_ids_query(user_id: i32) -> SelectStatement {
+        Query::select()
+            .column((role::Entity, role::Column::Id))
+            .from(role::Entity)
+            .cond_where(
+                Condition::any()
+                    .add(role::Column::Id.in_subquery(Self::direct_role_ids_query(user_id)))
+                    .add(role::Column::Id.in_subquery(Self::group_role_ids_query(user_id))),
+            )
+            .to_owned()
+    }
+
+    fn direct_role_ids_query(user_id: i32) -> SelectStatement {
+        Query::select()
+            .column((user_role::Entity, user_role::Column::RoleId))
+            .from(user_role::Entity)
+            .cond_where(
+                Condition::all()
+                    .add(user_role::Column::UserId.eq(user_id))
+                    .add(active_grant_condition(
+                        user_role::Column::ValidFrom,
+                        user_role::Column::ValidUntil,
+                    )),
+            )
+            .to_owned()
+    }
+
+    fn group_role_ids_query(user_id: i32) -> SelectStatement {
+        Query::select()
+            .column((group_role::Entity, group_role::Column::RoleId))
+            .from(group_role::Entity)
+            .cond_where(group_role::Column::GroupId.in_subquery(Self::group_ids_query(user_id)))
+            .to_owned()
+    }
+
+    fn group_ids_query(user_id: i32) -> SelectStatement {
+        Query::select()
+            .column((group_member::Entity, group_member::Column::GroupId))
+            .from(group_member::Entity)
+            .cond_where(group_member::Column::UserId.eq(user_id))
+            .to_owned()
+    }
+}
+
+impl AuditedModelTrait for User {
+    fn audit_entity_type() -> Option<&'static str> {
+        Some(AUDIT_ENTITY_TYPE)
+    }
+}
+
+impl EventedModelTrait for User {
+    fn lifecycle_event() -> Option<fn(Lifecycle, Self) -> DomainEvent> {
+        Some(|lifecycle, user| match lifecycle {
+            Lifecycle::Created => DomainEvent::UserCreated(user),
+            Lifecycle::Updated => DomainEvent::UserUpdated(user),
+            Lifecycle::Deleted => DomainEvent::UserDeleted(user),
+        })
+    }
 }
 
 impl CreateModelTrait<user::Entity, user::ActiveModel, UserCreate> for User {}
 
-impl ReadModelTrait<user::Entity> for User {}
+impl ReadModelTrait<user::Entity> for User {
+    fn not_deleted_condition() -> Condition {
+        user::Column::DeletedAt.is_null().into_condition()
+    }
+}
+
+#[async_trait]
+impl UpdateModelTrait<user::Entity, user::ActiveModel, UserUpdate> for User {
+    fn version_column() -> Option<user::Column> {
+        Some(user::Column::Version)
+    }
+
+    /// Update a user.
+    /// Disabling fails if no enabled user with the root role would be left.
+    async fn update<'a, C>(model: UserUpdate, db: &'a C) -> Result<Self, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        model.validate(db).await?;
+
+        if model.enabled != Some(false) {
+            return Self::update_active_model(model.into_active_model(), db).await;
+        }
+
+        // Transaction
+        let user = events::transaction(db, |txn| {
+            Box::pin(async move {
+                let root_user_count = Role::root_user_count(txn).await?;
+
+                let user = Self::update_active_model(model.into_active_model(), txn).await?;
+
+                Role::ensure_root_user_remains(root_user_count, txn).await?;
+
+                Ok(user)
+            })
+        })
+        .await?;
+
+        Ok(user)
+    }
+}
+
+#[async_trait]
+impl Validate for UserCreate {
+    async fn rules<'a, C>(&self, validator: &mut Validator, db: &'a C) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        User::validate_email(&self.email, None, validator, db).await?;
+        validator.required("password", &self.password);
+        if let Some(first_name) = &self.first_name {
+            validator.max_length("firstName", first_name, MAX_STRING_LENGTH);
+        }
+        if let Some(last_name) = &self.last_name {
+            validator.max_length("lastName", last_name, MAX_STRING_LENGTH);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Validate for UserUpdate {
+    async fn rules<'a, C>(&self, validator: &mut Validator, db: &'a C) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        if let Some(email) = &self.email {
+            User::validate_email(email, Some(self.id), validator, db).await?;
+        }
+        if let Some(password) = &self.password {
+            validator.required("password", password);
+        }
+        if let Some(Some(first_name)) = &self.first_name {
+            validator.max_length("firstName", first_name, MAX_STRING_LENGTH);
+        }
+        if let Some(Some(last_name)) = &self.last_name {
+            validator.max_length("lastName", last_name, MAX_STRING_LENGTH);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DeleteModelTrait<user::Entity, user::ActiveModel> for User {
+    /// Soft delete a user and revoke their sessions.
+    /// Fails if no enabled user with the root role would be left.
+    async fn delete<'a, C>(id: i32, db: &'a C) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        Self::delete_condition(user::Column::Id.eq(id).into_condition(), db).await?;
+
+        Ok(())
+    }
+
+    /// Soft delete users using a condition and revoke their sessions.
+    /// Fails if no enabled user with the root role would be left.
+    async fn delete_condition<'a, C>(condition: Condition, db: &'a C) -> Result<DeleteResult, Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let now = chrono::Utc::now().naive_utc();
+
+        // Transaction
+        let result = events::transaction(db, |txn| {
+            Box::pin(async move {
+                let root_user_count = Role::root_user_count(txn).await?;
+
+                let users = Self::find(condition, txn).await?;
+                let user_ids: Vec<i32> = users.iter().map(|user| user.id).collect();
 
-impl UpdateModelTrait<user::Entity, user::ActiveModel, UserUpdate> for User {}
+                if user_ids.is_empty() {
+                    return Ok(DeleteResult { rows_affected: 0 });
+                }
+
+                let result = user::Entity::update_many()
+                    .col_expr(user::Column::DeletedAt, Expr::value(now))
+                    .col_expr(
+                        user::Column::Version,
+                        Expr::col(user::Column::Version).add(1),
+                    )
+                    .filter(user::Column::Id.is_in(user_ids.clone()))
+                    .exec(txn)
+                    .await?;
+
+                // Revoke sessions
+                RefreshToken::delete_condition(
+                    refresh_token::Column::UserId
+                        .is_in(user_ids)
+                        .into_condition(),
+                    txn,
+                )
+                .await?;
+
+                Role::ensure_root_user_remains(root_user_count, txn).await?;
+
+                for user in &users {
+                    let deleted = User {
+                        deleted_at: Some(now),
+                        version: user.version + 1,
+                        ..user.clone()
+                    };
+                    audit::record_model(
+                        AuditAction::Delete,
+                        AUDIT_ENTITY_TYPE,
+                        Some(user),
+                        Some(&deleted),
+                        txn,
+                    )
+                    .await?;
+                    events::emit(DomainEvent::UserDeleted(deleted));
+                }
+
+                Ok(DeleteResult {
+                    rows_affected: result.rows_affected,
+                })
+            })
+        })
+        .await?;
 
-impl DeleteModelTrait<user::Entity, user::ActiveModel> for User {}
+        Ok(result)
+    }
+}
 
 impl PaginatedModelTrait<user::Entity, user::ActiveModel> for User {
     fn default_order() -> (SimpleExpr, Order) {
         (user::Column::Id.into_simple_expr(), Order::Asc)
     }
 }
+
+impl FilterableModelTrait<user::Entity, user::ActiveModel> for User {
+    fn filter_fields() -> Vec<FilterField<user::Column>> {
+        vec![
+            FilterField::new("id", user::Column::Id, FilterOperator::COMPARISON),
+            FilterField::new("email", user::Column::Email, FilterOperator::TEXT),
+            FilterField::new("first_name", user::Column::FirstName, FilterOperator::TEXT)
+                .nullable(),
+            FilterField::new("last_name", user::Column::LastName, FilterOperator::TEXT).nullable(),
+            FilterField::new("enabled", user::Column::Enabled, FilterOperator::EQUALITY),
+            FilterField::new(
+                "inserted_at",
+                user::Column::InsertedAt,
+                FilterOperator::COMPARISON,
+            ),
+            FilterField::new(
+                "updated_at",
+                user::Column::UpdatedAt,
+                FilterOperator::COMPARISON,
+            ),
+            FilterField::new(
+                "deleted_at",
+                user::Column::DeletedAt,
+                FilterOperator::COMPARISON,
+            )
+            .nullable(),
+        ]
+    }
+
+    fn sort_fields() -> Vec<SortField<user::Column>> {
+        vec![
+            SortField::new("id", user::Column::Id),
+            SortField::new("email", user::Column::Email),
+            SortField::new("first_name", user::Column::FirstName),
+            SortField::new("last_name", user::Column::LastName),
+            SortField::new("enabled", user::Column::Enabled),
+            SortField::new("inserted_at", user::Column::InsertedAt),
+            SortField::new("updated_at", user::Column::UpdatedAt),
+            SortField::new("deleted_at", user::Column::DeletedAt),
+        ]
+    }
+}
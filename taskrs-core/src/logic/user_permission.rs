@@ -1,7 +1,16 @@
-use crate::logic::{CreateModelTrait, DeleteModelTrait, ReadModelTrait};
+use crate::logic::validation::Validate;
+use crate::logic::{
+    AuditedModelTrait, CreateModelTrait, DeleteModelTrait, EventedModelTrait, ReadModelTrait,
+};
 use crate::models::user_permission::UserPermission;
 use taskrs_db::models::user_permission;
 
+impl AuditedModelTrait for UserPermission {}
+
+impl EventedModelTrait for UserPermission {}
+
+impl Validate for UserPermission {}
+
 impl CreateModelTrait<user_permission::Entity, user_permission::ActiveModel, UserPermission>
     for UserPermission
 {
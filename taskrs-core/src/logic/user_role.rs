@@ -1,7 +1,16 @@
-use crate::logic::{CreateModelTrait, DeleteModelTrait, ReadModelTrait};
+use crate::logic::validation::Validate;
+use crate::logic::{
+    AuditedModelTrait, CreateModelTrait, DeleteModelTrait, EventedModelTrait, ReadModelTrait,
+};
 use crate::models::user_role::UserRole;
 use taskrs_db::models::user_role;
 
+impl AuditedModelTrait for UserRole {}
+
+impl EventedModelTrait for UserRole {}
+
+impl Validate for UserRole {}
+
 impl CreateModelTrait<user_role::Entity, user_role::ActiveModel, UserRole> for UserRole {}
 
 impl ReadModelTrait<user_role::Entity> for UserRole {}
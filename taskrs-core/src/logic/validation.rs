@@ -0,0 +1,120 @@
+//! Validation of create and update models.
+//!
+//! Models implement [`Validate`] by checking their fields with a [`Validator`].
+//! The create and update traits validate models before they touch the database,
+//! so invalid models fail with [`Error::Validation`] listing every broken rule.
+
+use crate::error::{Error, FieldError, ValidationCode, ValidationError};
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use taskrs_db::sea_orm::ConnectionTrait;
+
+/// Maximum length of string columns
+pub const MAX_STRING_LENGTH: usize = 256;
+
+/// Pragmatic check for `local@domain.tld`, delivery is the only real check of an address
+static EMAIL_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[^\s@]+@[^\s@]+\.[^\s@.]+$").unwrap());
+
+/// Models that are validated before they are created or updated
+#[async_trait]
+pub trait Validate: Sync {
+    /// Checks the fields of the model. Rules that need the database,
+    /// like uniqueness checks, are skipped for fields that already broke a rule.
+    async fn rules<'a, C>(&self, _validator: &mut Validator, _db: &'a C) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        Ok(())
+    }
+
+    /// Fails with [`Error::Validation`] if the model breaks any rule
+    async fn validate<'a, C>(&self, db: &'a C) -> Result<(), Error>
+    where
+        C: ConnectionTrait<'a>,
+    {
+        let mut validator = Validator::default();
+        self.rules(&mut validator, db).await?;
+        validator.finish()
+    }
+}
+
+/// Collects the rules broken by the fields of a model
+#[derive(Clone, Debug, Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    /// The value contains more than whitespace
+    pub fn required(&mut self, field: &str, value: &str) -> &mut Self {
+        if value.trim().is_empty() {
+            self.add(field, ValidationCode::Required, "must not be empty");
+        }
+        self
+    }
+
+    /// The value has at most `max` characters
+    pub fn max_length(&mut self, field: &str, value: &str, max: usize) -> &mut Self {
+        if value.chars().count() > max {
+            self.add(
+                field,
+                ValidationCode::Length,
+                format!("must have at most {} characters", max),
+            );
+        }
+        self
+    }
+
+    /// The value looks like an email address
+    pub fn email(&mut self, field: &str, value: &str) -> &mut Self {
+        if !EMAIL_REGEX.is_match(value) {
+            self.add(field, ValidationCode::Email, "must be an email address");
+        }
+        self
+    }
+
+    /// The value matches `regex`
+    pub fn pattern(&mut self, field: &str, value: &str, regex: &Regex) -> &mut Self {
+        if !regex.is_match(value) {
+            self.add(
+                field,
+                ValidationCode::Pattern,
+                format!("must match {}", regex.as_str()),
+            );
+        }
+        self
+    }
+
+    /// Records a broken rule, e.g. of a custom check
+    pub fn add(
+        &mut self,
+        field: &str,
+        code: ValidationCode,
+        message: impl Into<String>,
+    ) -> &mut Self {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            code,
+            message: message.into(),
+        });
+        self
+    }
+
+    /// Whether the field broke no rule so far
+    pub fn is_valid(&self, field: &str) -> bool {
+        !self.errors.iter().any(|e| e.field == field)
+    }
+
+    /// Fails with [`Error::Validation`] if any rule was broken
+    pub fn finish(self) -> Result<(), Error> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError {
+                fields: self.errors,
+            }
+            .into())
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use crate::models::IntoActiveModel;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use taskrs_db::models::audit_event;
+use taskrs_db::sea_orm::ActiveValue;
+
+/// Recorded change of an audited entity
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEvent {
+    pub id: i32,
+    /// User that made the change, `None` for changes made by the system, e.g. seeding and jobs
+    pub actor_id: Option<i32>,
+    /// User that acted on behalf of the actor
+    pub impersonator_id: Option<i32>,
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: i32,
+    /// Changed fields as `{"before": {...}, "after": {...}}`.
+    /// `before` is `null` for created and `after` for deleted entities.
+    pub changes: serde_json::Value,
+    pub request_id: Option<String>,
+    pub ip_address: Option<String>,
+    pub inserted_at: Option<NaiveDateTime>,
+}
+
+impl From<audit_event::Model> for AuditEvent {
+    fn from(model: audit_event::Model) -> Self {
+        Self {
+            id: model.id,
+            actor_id: model.actor_id,
+            impersonator_id: model.impersonator_id,
+            action: model.action,
+            entity_type: model.entity_type,
+            entity_id: model.entity_id,
+            changes: serde_json::from_str(&model.changes).unwrap_or_default(),
+            request_id: model.request_id,
+            ip_address: model.ip_address,
+            inserted_at: model.inserted_at,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AuditEventCreate {
+    pub action: AuditAction,
+    pub entity_type: String,
+    pub entity_id: i32,
+    pub changes: serde_json::Value,
+    pub context: AuditContext,
+}
+
+impl IntoActiveModel<audit_event::ActiveModel> for AuditEventCreate {
+    fn into_active_model(self) -> audit_event::ActiveModel {
+        audit_event::ActiveModel {
+            actor_id: ActiveValue::Set(self.context.actor_id),
+            impersonator_id: ActiveValue::Set(self.context.impersonator_id),
+            action: ActiveValue::Set(self.action.as_str().to_string()),
+            entity_type: ActiveValue::Set(self.entity_type),
+            entity_id: ActiveValue::Set(self.entity_id),
+            changes: ActiveValue::Set(self.changes.to_string()),
+            request_id: ActiveValue::Set(self.context.request_id),
+            ip_address: ActiveValue::Set(self.context.ip_address),
+            ..Default::default()
+        }
+    }
+}
+
+/// Who made a change and through which request.
+/// Set for the duration of a request, see [`AuditContext::scope`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AuditContext {
+    pub actor_id: Option<i32>,
+    pub impersonator_id: Option<i32>,
+    pub request_id: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+/// Kind of change recorded by an audit event
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Purge,
+    GrantRoles,
+    RevokeRoles,
+    SetRoles,
+    ExpireRoles,
+    GrantPermissions,
+    DenyPermissions,
+    RevokePermissions,
+    SetPermissions,
+    ExpirePermissions,
+    AddUsers,
+    RemoveUsers,
+    SetUsers,
+    AddMembers,
+    RemoveMembers,
+    SetMembers,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Create => "create",
+            Self::Update => "update",
+            Self::Delete => "delete",
+            Self::Purge => "purge",
+            Self::GrantRoles => "grant_roles",
+            Self::RevokeRoles => "revoke_roles",
+            Self::SetRoles => "set_roles",
+            Self::ExpireRoles => "expire_roles",
+            Self::GrantPermissions => "grant_permissions",
+            Self::DenyPermissions => "deny_permissions",
+            Self::RevokePermissions => "revoke_permissions",
+            Self::SetPermissions => "set_permissions",
+            Self::ExpirePermissions => "expire_permissions",
+            Self::AddUsers => "add_users",
+            Self::RemoveUsers => "remove_users",
+            Self::SetUsers => "set_users",
+            Self::AddMembers => "add_members",
+            Self::RemoveMembers => "remove_members",
+            Self::SetMembers => "set_members",
+        }
+    }
+}
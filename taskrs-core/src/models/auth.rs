@@ -56,6 +56,44 @@ impl RefreshTokenData {
     }
 }
 
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordChange {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailChange {
+    pub email: String,
+}
+
+/// Claims of the token sent to the new address to confirm an email change.
+/// Holds the old email, so the token is useless once the email changed.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailChangeTokenData {
+    pub iat: i64,
+    pub exp: i64,
+    pub user_id: i32,
+    pub old_email: String,
+    pub new_email: String,
+}
+
+impl EmailChangeTokenData {
+    pub fn new(user_id: i32, old_email: String, new_email: String, exp: u32) -> Self {
+        let now = Utc::now().timestamp();
+        Self {
+            iat: now,
+            exp: now + exp as i64,
+            user_id,
+            old_email,
+            new_email,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct AuthSettings {
     pub access_token_secret: String,
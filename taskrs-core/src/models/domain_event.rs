@@ -0,0 +1,59 @@
+use crate::models::group::Group;
+use crate::models::role::Role;
+use crate::models::user::User;
+use serde::Serialize;
+
+/// Change of an entity or its relations, published once the change is committed.
+/// Relation events only carry the id of the changed entity,
+/// subscribers read the current relations if they need them.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum DomainEvent {
+    UserCreated(User),
+    UserUpdated(User),
+    UserDeleted(User),
+    #[serde(rename_all = "camelCase")]
+    UserPurged {
+        user_id: i32,
+    },
+    #[serde(rename_all = "camelCase")]
+    UserRolesChanged {
+        user_id: i32,
+    },
+    #[serde(rename_all = "camelCase")]
+    UserPermissionsChanged {
+        user_id: i32,
+    },
+
+    RoleCreated(Role),
+    RoleUpdated(Role),
+    RoleDeleted(Role),
+    #[serde(rename_all = "camelCase")]
+    RoleUsersChanged {
+        role_id: i32,
+    },
+    #[serde(rename_all = "camelCase")]
+    RolePermissionsChanged {
+        role_id: i32,
+    },
+
+    GroupCreated(Group),
+    GroupUpdated(Group),
+    GroupDeleted(Group),
+    #[serde(rename_all = "camelCase")]
+    GroupMembersChanged {
+        group_id: i32,
+    },
+    #[serde(rename_all = "camelCase")]
+    GroupRolesChanged {
+        group_id: i32,
+    },
+}
+
+/// Stage in the lifecycle of an entity
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lifecycle {
+    Created,
+    Updated,
+    Deleted,
+}
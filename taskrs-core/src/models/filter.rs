@@ -0,0 +1,287 @@
+use crate::error::FilterError;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use taskrs_db::sea_orm::Order;
+
+/// Operators that can be used to filter a field
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOperator {
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    In,
+    Like,
+    IsNull,
+}
+
+impl FilterOperator {
+    /// Operators for fields that are compared by equality only, e.g. booleans
+    pub const EQUALITY: &'static [FilterOperator] = &[Self::Eq, Self::Ne];
+    /// Operators for ordered fields like numbers and timestamps
+    pub const COMPARISON: &'static [FilterOperator] =
+        &[Self::Eq, Self::Ne, Self::Lt, Self::Gt, Self::In];
+    /// Operators for text fields
+    pub const TEXT: &'static [FilterOperator] = &[Self::Eq, Self::Ne, Self::In, Self::Like];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Eq => "eq",
+            Self::Ne => "ne",
+            Self::Lt => "lt",
+            Self::Gt => "gt",
+            Self::In => "in",
+            Self::Like => "like",
+            Self::IsNull => "is_null",
+        }
+    }
+}
+
+impl FromStr for FilterOperator {
+    type Err = FilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "eq" => Ok(Self::Eq),
+            "ne" => Ok(Self::Ne),
+            "lt" => Ok(Self::Lt),
+            "gt" => Ok(Self::Gt),
+            "in" => Ok(Self::In),
+            "like" => Ok(Self::Like),
+            "is_null" => Ok(Self::IsNull),
+            _ => Err(FilterError::UnknownOperator(s.to_string())),
+        }
+    }
+}
+
+/// A field of an entity that can be filtered, together with the allowed operators
+#[derive(Clone, Debug)]
+pub struct FilterField<C> {
+    pub name: &'static str,
+    pub column: C,
+    pub operators: &'static [FilterOperator],
+    /// Allows [`FilterOperator::IsNull`] in addition to `operators`
+    pub nullable: bool,
+}
+
+impl<C> FilterField<C> {
+    pub fn new(name: &'static str, column: C, operators: &'static [FilterOperator]) -> Self {
+        Self {
+            name,
+            column,
+            operators,
+            nullable: false,
+        }
+    }
+
+    /// Allows [`FilterOperator::IsNull`] in addition to the given operators
+    pub fn nullable(mut self) -> Self {
+        self.nullable = true;
+        self
+    }
+
+    pub fn allows(&self, operator: FilterOperator) -> bool {
+        self.operators.contains(&operator) || (self.nullable && operator == FilterOperator::IsNull)
+    }
+}
+
+/// A field of an entity that can be sorted by
+#[derive(Clone, Debug)]
+pub struct SortField<C> {
+    pub name: &'static str,
+    pub column: C,
+}
+
+impl<C> SortField<C> {
+    pub fn new(name: &'static str, column: C) -> Self {
+        Self { name, column }
+    }
+}
+
+/// A single filter requested by a client, e.g. `filter[email][like]=%@corp.com`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Filter {
+    pub field: String,
+    pub operator: FilterOperator,
+    pub value: String,
+}
+
+/// A single sort key requested by a client, e.g. `-inserted_at`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sort {
+    pub field: String,
+    pub descending: bool,
+}
+
+impl Sort {
+    pub fn order(&self) -> Order {
+        if self.descending {
+            Order::Desc
+        } else {
+            Order::Asc
+        }
+    }
+}
+
+/// Filters and sort keys of a list request.
+/// Fields are validated against the allow-list of an entity when the query is applied.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ListQuery {
+    pub filters: Vec<Filter>,
+    pub sort: Vec<Sort>,
+}
+
+impl ListQuery {
+    /// Parses query string pairs.
+    ///
+    /// Filters use the form `filter[<field>][<operator>]=<value>`, where the operator
+    /// defaults to `eq` if omitted. Values of `in` are separated by commas,
+    /// `is_null` takes `true` or `false`.
+    /// Sort keys are separated by commas and prefixed with `-` for descending order,
+    /// e.g. `sort=-inserted_at,id`. Other parameters are ignored.
+    pub fn from_pairs<I>(pairs: I) -> Result<Self, FilterError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut query = Self::default();
+
+        for (key, value) in pairs {
+            if key == "sort" {
+                for key in value.split(',').map(str::trim).filter(|k| !k.is_empty()) {
+                    let (field, descending) = match key.strip_prefix('-') {
+                        Some(field) => (field, true),
+                        None => (key.strip_prefix('+').unwrap_or(key), false),
+                    };
+                    if field.is_empty() {
+                        return Err(FilterError::MalformedParameter(key.to_string()));
+                    }
+                    query.sort.push(Sort {
+                        field: field.to_string(),
+                        descending,
+                    });
+                }
+            } else if let Some(rest) = key.strip_prefix("filter") {
+                let (field, operator) = parse_filter_key(rest)?
+                    .ok_or_else(|| FilterError::MalformedParameter(key.clone()))?;
+                query.filters.push(Filter {
+                    field,
+                    operator,
+                    value,
+                });
+            }
+        }
+
+        Ok(query)
+    }
+}
+
+/// Parses the `[<field>]` or `[<field>][<operator>]` part of a filter key.
+/// Returns `None` if the key is malformed.
+fn parse_filter_key(key: &str) -> Result<Option<(String, FilterOperator)>, FilterError> {
+    let (field, rest) = match key.strip_prefix('[').and_then(|key| key.split_once(']')) {
+        Some((field, rest)) if !field.is_empty() => (field, rest),
+        _ => return Ok(None),
+    };
+
+    let operator = if rest.is_empty() {
+        FilterOperator::Eq
+    } else {
+        match rest
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+        {
+            Some(operator) => operator.parse()?,
+            None => return Ok(None),
+        }
+    };
+
+    Ok(Some((field.to_string(), operator)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Filter, FilterOperator, ListQuery, Sort};
+    use crate::error::FilterError;
+
+    fn parse(pairs: &[(&str, &str)]) -> Result<ListQuery, FilterError> {
+        ListQuery::from_pairs(
+            pairs
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string())),
+        )
+    }
+
+    fn filter(field: &str, operator: FilterOperator, value: &str) -> Filter {
+        Filter {
+            field: field.to_string(),
+            operator,
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn filters_take_the_operator_of_the_key() {
+        let query = parse(&[("filter[email][like]", "%@corp.com")]).unwrap();
+
+        assert_eq!(
+            query.filters,
+            vec![filter("email", FilterOperator::Like, "%@corp.com")]
+        );
+    }
+
+    #[test]
+    fn filters_without_operator_compare_equality() {
+        let query = parse(&[("filter[id]", "1"), ("page", "2")]).unwrap();
+
+        assert_eq!(query.filters, vec![filter("id", FilterOperator::Eq, "1")]);
+        assert!(query.sort.is_empty());
+    }
+
+    #[test]
+    fn sort_keys_keep_their_order_and_direction() {
+        let query = parse(&[("sort", "-inserted_at,id")]).unwrap();
+
+        assert_eq!(
+            query.sort,
+            vec![
+                Sort {
+                    field: "inserted_at".to_string(),
+                    descending: true,
+                },
+                Sort {
+                    field: "id".to_string(),
+                    descending: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_filter_keys() {
+        for key in ["filter[]", "filter[a]x", "filter", "filter[a][eq"] {
+            let result = parse(&[(key, "1")]);
+
+            assert!(
+                matches!(&result, Err(FilterError::MalformedParameter(p)) if p == key),
+                "{} parsed to {:?}",
+                key,
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_operators() {
+        let result = parse(&[("filter[a][bogus]", "1")]);
+
+        assert!(matches!(result, Err(FilterError::UnknownOperator(o)) if o == "bogus"));
+    }
+
+    #[test]
+    fn rejects_sort_keys_without_field() {
+        let result = parse(&[("sort", "-")]);
+
+        assert!(matches!(result, Err(FilterError::MalformedParameter(p)) if p == "-"));
+    }
+}
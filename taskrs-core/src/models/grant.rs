@@ -0,0 +1,38 @@
+use crate::models::user_permission::UserPermission;
+use crate::models::user_role::UserRole;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Period in which a grant of a role or permission to a user is valid.
+/// Both bounds are optional, a grant without bounds is valid indefinitely.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GrantValidity {
+    pub valid_from: Option<NaiveDateTime>,
+    pub valid_until: Option<NaiveDateTime>,
+}
+
+/// Grants removed because their validity ended.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpiredGrants {
+    pub user_roles: Vec<UserRole>,
+    pub user_permissions: Vec<UserPermission>,
+}
+
+/// Grants actually changed by granting, revoking or replacing grants, listed by the ids of
+/// the granted roles, permissions or users. Grants that already were in the requested state
+/// are not listed.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GrantChanges {
+    pub added: Vec<i32>,
+    pub updated: Vec<i32>,
+    pub removed: Vec<i32>,
+}
+
+impl GrantChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
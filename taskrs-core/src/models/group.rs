@@ -0,0 +1,25 @@
+use chrono::NaiveDateTime;
+use taskrs_db::models::group;
+use taskrs_macros::Dto;
+
+#[derive(Dto)]
+#[dto(
+    model = "group",
+    read = "Group",
+    create = "GroupCreate",
+    update = "GroupUpdate"
+)]
+#[allow(dead_code)]
+struct GroupFields {
+    #[dto(primary_key)]
+    id: i32,
+    name: String,
+    description: Option<String>,
+    #[dto(read_only)]
+    inserted_at: Option<NaiveDateTime>,
+    #[dto(read_only)]
+    updated_at: Option<NaiveDateTime>,
+    /// Incremented on every update, used for optimistic concurrency control
+    #[dto(version)]
+    version: i32,
+}
@@ -0,0 +1,13 @@
+use chrono::NaiveDateTime;
+use taskrs_db::models::group_member;
+use taskrs_macros::Dto;
+
+#[derive(Dto)]
+#[dto(model = "group_member", read = "GroupMember", create = "GroupMember")]
+#[allow(dead_code)]
+struct GroupMemberFields {
+    group_id: i32,
+    user_id: i32,
+    #[dto(read_only)]
+    inserted_at: Option<NaiveDateTime>,
+}
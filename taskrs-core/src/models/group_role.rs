@@ -0,0 +1,13 @@
+use chrono::NaiveDateTime;
+use taskrs_db::models::group_role;
+use taskrs_macros::Dto;
+
+#[derive(Dto)]
+#[dto(model = "group_role", read = "GroupRole", create = "GroupRole")]
+#[allow(dead_code)]
+struct GroupRoleFields {
+    group_id: i32,
+    role_id: i32,
+    #[dto(read_only)]
+    inserted_at: Option<NaiveDateTime>,
+}
@@ -0,0 +1,85 @@
+use crate::models::IntoActiveModel;
+use chrono::NaiveDateTime;
+use taskrs_db::models::idempotency_key;
+use taskrs_db::sea_orm::ActiveValue;
+
+/// Key sent with a request, so that retries of the request get its response instead of running it again
+#[derive(Clone, Debug, Default)]
+pub struct IdempotencyKey {
+    pub id: i32,
+    pub scope: String,
+    pub key: String,
+    pub fingerprint: String,
+    /// `None` while the request is still running
+    pub response: Option<StoredResponse>,
+    /// End of the lease of a running request, a retry takes over the key afterwards
+    pub locked_until: Option<NaiveDateTime>,
+    pub expires_at: NaiveDateTime,
+    pub inserted_at: Option<NaiveDateTime>,
+}
+
+impl From<idempotency_key::Model> for IdempotencyKey {
+    fn from(model: idempotency_key::Model) -> Self {
+        let response = model.response_status.map(|status| StoredResponse {
+            status: status as u16,
+            content_type: model.response_content_type,
+            headers: model
+                .response_headers
+                .and_then(|headers| serde_json::from_str(&headers).ok())
+                .unwrap_or_default(),
+            body: model.response_body.unwrap_or_default(),
+        });
+
+        Self {
+            id: model.id,
+            scope: model.scope,
+            key: model.key,
+            fingerprint: model.fingerprint,
+            response,
+            locked_until: model.locked_until,
+            expires_at: model.expires_at,
+            inserted_at: model.inserted_at,
+        }
+    }
+}
+
+/// Response replayed to retries of a request
+#[derive(Clone, Debug, Default)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    /// Further headers, like `Location` or `ETag`, in the order they were sent
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct IdempotencyKeyCreate {
+    pub scope: String,
+    pub key: String,
+    pub fingerprint: String,
+    pub locked_until: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+impl IntoActiveModel<idempotency_key::ActiveModel> for IdempotencyKeyCreate {
+    fn into_active_model(self) -> idempotency_key::ActiveModel {
+        idempotency_key::ActiveModel {
+            scope: ActiveValue::Set(self.scope),
+            key: ActiveValue::Set(self.key),
+            fingerprint: ActiveValue::Set(self.fingerprint),
+            locked_until: ActiveValue::Set(Some(self.locked_until)),
+            expires_at: ActiveValue::Set(self.expires_at),
+            ..Default::default()
+        }
+    }
+}
+
+/// Outcome of claiming an idempotency key for a request
+#[derive(Clone, Debug)]
+pub enum IdempotencyClaim {
+    /// The key was not used yet, the request runs and its response is stored
+    Claimed(IdempotencyKey),
+    /// The key was used by an earlier request, which might still be running
+    Existing(IdempotencyKey),
+}
@@ -1,12 +1,23 @@
+pub mod audit_event;
 pub mod auth;
+pub mod domain_event;
+pub mod filter;
+pub mod grant;
+pub mod group;
+pub mod group_member;
+pub mod group_role;
+pub mod idempotency_key;
+pub mod pagination;
 pub mod permission;
 pub mod refresh_token;
 pub mod role;
 pub mod role_permission;
+pub mod search;
 pub mod user;
 pub mod user_permission;
 pub mod user_role;
 
+use serde::{Deserialize, Deserializer};
 use taskrs_db::sea_orm::ActiveModelTrait;
 
 // Define trait in this to work around compiler error
@@ -17,3 +28,14 @@ where
     /// Method to call to perform the conversion
     fn into_active_model(self) -> A;
 }
+
+/// Deserializes a present field into `Some`, so that `null` becomes `Some(None)`.
+/// Used with `#[serde(default)]` on `Option<Option<T>>` fields of update models
+/// to distinguish a missing field from a field that should be set to `NULL`.
+pub fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
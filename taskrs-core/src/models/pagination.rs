@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+/// Direction in which a cursor continues a list
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CursorDirection {
+    #[serde(rename = "n")]
+    Next,
+    #[serde(rename = "p")]
+    Prev,
+}
+
+/// Position in a keyset paginated list.
+/// Holds the sort key and the primary key of the row next to the requested page.
+/// Clients only see it encoded as an opaque string.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Cursor {
+    /// `None` for a sort key that is `NULL`
+    #[serde(rename = "k")]
+    pub keys: Vec<Option<String>>,
+    #[serde(rename = "d")]
+    pub direction: CursorDirection,
+}
+
+/// A single page of a keyset paginated list
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    /// Cursor of the following page, `None` on the last page
+    pub next: Option<String>,
+    /// Cursor of the preceding page, `None` on the first page
+    pub prev: Option<String>,
+    /// Number of all matching entities, only counted on request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<usize>,
+}
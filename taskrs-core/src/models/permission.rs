@@ -1,98 +1,61 @@
-use crate::models::IntoActiveModel;
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use taskrs_db::models::permission;
-use taskrs_db::sea_orm::ActiveValue;
+use taskrs_db::models::permission::PermissionEffect;
+use taskrs_macros::Dto;
+
+#[derive(Dto)]
+#[dto(
+    model = "permission",
+    read = "Permission",
+    create = "PermissionCreate",
+    update = "PermissionUpdate"
+)]
+#[allow(dead_code)]
+struct PermissionFields {
+    #[dto(primary_key)]
+    id: i32,
+    name: String,
+    group: String,
+    description: Option<String>,
+    #[dto(read_only)]
+    inserted_at: Option<NaiveDateTime>,
+    #[dto(read_only)]
+    updated_at: Option<NaiveDateTime>,
+}
 
+/// Permissions sharing the same group
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
-pub struct Permission {
-    pub id: i32,
-    pub name: String,
+pub struct PermissionGroup {
     pub group: String,
-    pub description: Option<String>,
-    pub inserted_at: Option<NaiveDateTime>,
-    pub updated_at: Option<NaiveDateTime>,
-}
-
-impl From<permission::Model> for Permission {
-    fn from(model: permission::Model) -> Self {
-        Self {
-            id: model.id,
-            name: model.name,
-            group: model.group,
-            description: None,
-            inserted_at: model.inserted_at,
-            updated_at: model.updated_at,
-        }
-    }
+    pub permissions: Vec<Permission>,
 }
 
+/// Describes how the effective state of a permission for a user was resolved.
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
-pub struct PermissionCreate {
-    pub name: String,
-    pub group: String,
-    pub description: Option<String>,
-    pub inserted_at: Option<NaiveDateTime>,
-    pub updated_at: Option<NaiveDateTime>,
-}
-
-impl IntoActiveModel<permission::ActiveModel> for PermissionCreate {
-    fn into_active_model(self) -> permission::ActiveModel {
-        let mut active_model = permission::ActiveModel {
-            name: ActiveValue::Set(self.name),
-            group: ActiveValue::Set(self.group),
-            description: ActiveValue::Set(self.description),
-            ..Default::default()
-        };
-
-        if let Some(inserted_at) = self.inserted_at {
-            active_model.inserted_at = ActiveValue::Set(Some(inserted_at));
-        }
-        if let Some(updated_at) = self.updated_at {
-            active_model.updated_at = ActiveValue::Set(Some(updated_at));
-        }
-
-        active_model
-    }
+pub struct PermissionExplanation {
+    pub permission_id: i32,
+    /// `true` if at least one rule allows the permission and no rule denies it
+    pub granted: bool,
+    pub allowed_by: Vec<PermissionRule>,
+    /// Rules blocking the permission. Denies always override allows.
+    pub denied_by: Vec<PermissionRule>,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
-pub struct PermissionUpdate {
-    pub id: i32,
-    pub name: Option<String>,
-    pub group: Option<String>,
-    pub description: Option<Option<String>>,
-    pub inserted_at: Option<Option<NaiveDateTime>>,
-    pub updated_at: Option<Option<NaiveDateTime>>,
+pub struct PermissionRule {
+    pub effect: PermissionEffect,
+    pub source: PermissionRuleSource,
 }
 
-impl IntoActiveModel<permission::ActiveModel> for PermissionUpdate {
-    fn into_active_model(self) -> permission::ActiveModel {
-        let mut active_model = permission::ActiveModel {
-            id: ActiveValue::Set(self.id),
-            ..Default::default()
-        };
-
-        if let Some(name) = self.name {
-            active_model.name = ActiveValue::Set(name);
-        }
-        if let Some(group) = self.group {
-            active_model.group = ActiveValue::Set(group);
-        }
-        if let Some(description) = self.description {
-            active_model.description = ActiveValue::Set(description);
-        }
-
-        if let Some(inserted_at) = self.inserted_at {
-            active_model.inserted_at = ActiveValue::Set(inserted_at);
-        }
-        if let Some(updated_at) = self.updated_at {
-            active_model.updated_at = ActiveValue::Set(updated_at);
-        }
-
-        active_model
-    }
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PermissionRuleSource {
+    /// Rule assigned to the user directly
+    Direct,
+    /// Rule assigned to one of the user's roles
+    Role { id: i32, name: String },
 }
@@ -1,90 +1,47 @@
-use crate::models::IntoActiveModel;
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use taskrs_db::models::role;
-use taskrs_db::sea_orm::ActiveValue;
-
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct Role {
-    pub id: i32,
-    pub name: String,
-    pub description: Option<String>,
-    pub inserted_at: Option<NaiveDateTime>,
-    pub updated_at: Option<NaiveDateTime>,
-}
-
-impl From<role::Model> for Role {
-    fn from(model: role::Model) -> Self {
-        Self {
-            id: model.id,
-            name: model.name,
-            description: None,
-            inserted_at: model.inserted_at,
-            updated_at: model.updated_at,
-        }
-    }
-}
-
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct RoleCreate {
-    pub name: String,
-    pub description: Option<String>,
-    pub inserted_at: Option<NaiveDateTime>,
-    pub updated_at: Option<NaiveDateTime>,
-}
-
-impl IntoActiveModel<role::ActiveModel> for RoleCreate {
-    fn into_active_model(self) -> role::ActiveModel {
-        let mut active_model = role::ActiveModel {
-            name: ActiveValue::Set(self.name),
-            description: ActiveValue::Set(self.description),
-            ..Default::default()
-        };
-
-        if let Some(inserted_at) = self.inserted_at {
-            active_model.inserted_at = ActiveValue::Set(Some(inserted_at));
-        }
-        if let Some(updated_at) = self.updated_at {
-            active_model.updated_at = ActiveValue::Set(Some(updated_at));
-        }
-
-        active_model
-    }
+use taskrs_macros::Dto;
+
+#[derive(Dto)]
+#[dto(
+    model = "role",
+    read = "Role",
+    create = "RoleCreate",
+    update = "RoleUpdate"
+)]
+#[allow(dead_code)]
+struct RoleFields {
+    #[dto(primary_key)]
+    id: i32,
+    name: String,
+    description: Option<String>,
+    /// System roles are only created by seeding and can not be modified
+    #[dto(read_only)]
+    is_system: bool,
+    #[dto(read_only)]
+    inserted_at: Option<NaiveDateTime>,
+    #[dto(read_only)]
+    updated_at: Option<NaiveDateTime>,
+    /// Incremented on every update, used for optimistic concurrency control
+    #[dto(version)]
+    version: i32,
 }
 
+/// Role of a user together with every way it is granted to the user.
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
-pub struct RoleUpdate {
-    pub id: i32,
-    pub name: Option<String>,
-    pub description: Option<Option<String>>,
-    pub inserted_at: Option<Option<NaiveDateTime>>,
-    pub updated_at: Option<Option<NaiveDateTime>>,
+pub struct AssignedRole {
+    #[serde(flatten)]
+    pub role: Role,
+    pub sources: Vec<RoleSource>,
 }
 
-impl IntoActiveModel<role::ActiveModel> for RoleUpdate {
-    fn into_active_model(self) -> role::ActiveModel {
-        let mut active_model = role::ActiveModel {
-            id: ActiveValue::Set(self.id),
-            ..Default::default()
-        };
-
-        if let Some(name) = self.name {
-            active_model.name = ActiveValue::Set(name);
-        }
-        if let Some(description) = self.description {
-            active_model.description = ActiveValue::Set(description);
-        }
-
-        if let Some(inserted_at) = self.inserted_at {
-            active_model.inserted_at = ActiveValue::Set(inserted_at);
-        }
-        if let Some(updated_at) = self.updated_at {
-            active_model.updated_at = ActiveValue::Set(updated_at);
-        }
-
-        active_model
-    }
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum RoleSource {
+    /// Role granted to the user directly
+    Direct,
+    /// Role inherited through a group the user is a member of
+    Group { id: i32, name: String },
 }
@@ -0,0 +1,15 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Entity found by a full-text search
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit<T> {
+    /// Relevance reported by the database, higher is better.
+    /// Only comparable between hits of the same search.
+    pub rank: f64,
+    /// Matched fields with each match wrapped in `<mark>` tags.
+    /// The values are HTML escaped.
+    pub highlights: BTreeMap<String, String>,
+    pub item: T,
+}
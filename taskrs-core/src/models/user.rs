@@ -1,45 +1,36 @@
-use crate::models::IntoActiveModel;
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use taskrs_db::models::user;
-use taskrs_db::sea_orm::ActiveValue;
+use taskrs_macros::Dto;
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct User {
-    pub id: i32,
-    pub email: String,
-    pub first_name: Option<String>,
-    pub last_name: Option<String>,
-    pub enabled: bool,
-    pub inserted_at: Option<NaiveDateTime>,
-    pub updated_at: Option<NaiveDateTime>,
-}
-
-impl From<user::Model> for User {
-    fn from(model: user::Model) -> Self {
-        Self {
-            id: model.id,
-            email: model.email,
-            first_name: model.first_name,
-            last_name: model.last_name,
-            enabled: model.enabled,
-            inserted_at: model.inserted_at,
-            updated_at: model.updated_at,
-        }
-    }
-}
-
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct UserCreate {
-    pub email: String,
-    pub password: String,
-    pub first_name: Option<String>,
-    pub last_name: Option<String>,
-    pub enabled: bool,
-    pub inserted_at: Option<NaiveDateTime>,
-    pub updated_at: Option<NaiveDateTime>,
+#[derive(Dto)]
+#[dto(
+    model = "user",
+    read = "User",
+    create = "UserCreate",
+    update = "UserUpdate"
+)]
+#[allow(dead_code)]
+struct UserFields {
+    #[dto(primary_key)]
+    id: i32,
+    email: String,
+    /// Hashed by `hash_password` before it is stored
+    #[dto(write_only, rename = "password")]
+    password_hash: String,
+    first_name: Option<String>,
+    last_name: Option<String>,
+    #[dto(default)]
+    enabled: bool,
+    #[dto(read_only)]
+    inserted_at: Option<NaiveDateTime>,
+    #[dto(read_only)]
+    updated_at: Option<NaiveDateTime>,
+    #[dto(read_only)]
+    deleted_at: Option<NaiveDateTime>,
+    /// Incremented on every update, used for optimistic concurrency control
+    #[dto(version)]
+    version: i32,
 }
 
 impl UserCreate {
@@ -51,41 +42,6 @@ impl UserCreate {
     }
 }
 
-impl IntoActiveModel<user::ActiveModel> for UserCreate {
-    fn into_active_model(self) -> user::ActiveModel {
-        let mut active_model = user::ActiveModel {
-            email: ActiveValue::Set(self.email),
-            password_hash: ActiveValue::Set(self.password),
-            first_name: ActiveValue::Set(self.first_name),
-            last_name: ActiveValue::Set(self.last_name),
-            enabled: ActiveValue::Set(self.enabled),
-            ..Default::default()
-        };
-
-        if let Some(inserted_at) = self.inserted_at {
-            active_model.inserted_at = ActiveValue::Set(Some(inserted_at));
-        }
-        if let Some(updated_at) = self.updated_at {
-            active_model.updated_at = ActiveValue::Set(Some(updated_at));
-        }
-
-        active_model
-    }
-}
-
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct UserUpdate {
-    pub id: i32,
-    pub email: Option<String>,
-    pub password: Option<String>,
-    pub first_name: Option<Option<String>>,
-    pub last_name: Option<Option<String>>,
-    pub enabled: Option<bool>,
-    pub inserted_at: Option<Option<NaiveDateTime>>,
-    pub updated_at: Option<Option<NaiveDateTime>>,
-}
-
 impl UserUpdate {
     pub fn hash_password(mut self) -> Result<Self, argon2::Error> {
         if let Some(password) = &self.password {
@@ -98,36 +54,23 @@ impl UserUpdate {
     }
 }
 
-impl IntoActiveModel<user::ActiveModel> for UserUpdate {
-    fn into_active_model(self) -> user::ActiveModel {
-        let mut active_model = user::ActiveModel {
-            id: ActiveValue::Set(self.id),
-            ..Default::default()
-        };
-
-        if let Some(email) = self.email {
-            active_model.email = ActiveValue::Set(email);
-        }
-        if let Some(password) = self.password {
-            active_model.password_hash = ActiveValue::Set(password);
-        }
-        if let Some(first_name) = self.first_name {
-            active_model.first_name = ActiveValue::Set(first_name);
-        }
-        if let Some(last_name) = self.last_name {
-            active_model.last_name = ActiveValue::Set(last_name);
-        }
-        if let Some(enabled) = self.enabled {
-            active_model.enabled = ActiveValue::Set(enabled);
-        }
+/// Fields users may change on their own profile
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileUpdate {
+    #[serde(default, deserialize_with = "crate::models::deserialize_some")]
+    pub first_name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "crate::models::deserialize_some")]
+    pub last_name: Option<Option<String>>,
+}
 
-        if let Some(inserted_at) = self.inserted_at {
-            active_model.inserted_at = ActiveValue::Set(inserted_at);
-        }
-        if let Some(updated_at) = self.updated_at {
-            active_model.updated_at = ActiveValue::Set(updated_at);
+impl ProfileUpdate {
+    pub fn into_user_update(self, id: i32) -> UserUpdate {
+        UserUpdate {
+            id,
+            first_name: self.first_name,
+            last_name: self.last_name,
+            ..Default::default()
         }
-
-        active_model
     }
 }
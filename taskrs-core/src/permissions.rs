@@ -1,6 +1,4 @@
-use build_const::build_const;
+//! Permissions defined in the catalog files in `permissions/`.
+//! The `Permission` enum is generated by the build script.
 
-build_const!("permissions");
-
-pub const ALL_PERMISSIONS: &str =
-    include_str!(concat!(env!("OUT_DIR"), concat!("/permissions.json")));
+include!(concat!(env!("OUT_DIR"), "/permissions.rs"));
@@ -1,27 +1,38 @@
-use crate::logic::{CreateModelTrait, DeleteModelTrait, ReadModelTrait};
-use crate::models::permission::{Permission, PermissionCreate};
+use crate::logic::{
+    events, update_versioned, CreateModelTrait, DeleteModelTrait, ReadModelTrait, UpdateModelTrait,
+};
+use crate::models::permission::{Permission, PermissionCreate, PermissionUpdate};
 use crate::models::role::{Role, RoleCreate};
+use crate::models::role_permission::RolePermission;
 use crate::models::user::{User, UserCreate};
-use crate::permissions::ALL_PERMISSIONS;
-use futures::try_join;
-use taskrs_db::sea_orm::{ColumnTrait, Condition, ConnectionTrait, DbConn, DbErr};
+use std::collections::HashSet;
+use taskrs_db::models::permission::PermissionEffect;
+use taskrs_db::sea_orm::{ActiveValue, ColumnTrait, Condition, DbConn};
 
-const ROOT_ROLE_NAME: &str = "root";
+pub const ROOT_ROLE_NAME: &str = "root";
 const ROOT_ROLE_DESCRIPTION: &str = "Role which has every permission that is seeded at startup";
 
 #[instrument(name = "seed_permissions", level = "debug", skip_all)]
 pub async fn seed_permissions(db: &DbConn) -> anyhow::Result<()> {
-    debug!("Deserializing embedded permissions");
-    let json_permissions: Vec<PermissionCreate> = serde_json::from_str(ALL_PERMISSIONS)?;
+    debug!("Collecting permissions from catalog");
+    let catalog_permissions: Vec<PermissionCreate> = crate::permissions::Permission::ALL
+        .iter()
+        .map(|p| PermissionCreate {
+            name: p.name().to_string(),
+            group: p.group().to_string(),
+            description: Some(p.description().to_string()),
+            ..Default::default()
+        })
+        .collect();
 
-    db.transaction::<_, (), DbErr>(|txn| {
+    events::transaction(db, |txn| {
         Box::pin(async move {
             debug!("Geting current permissions");
             let db_permissions = Permission::all(txn).await?;
 
             // Compare to get new permissions
             debug!("Finding new permissions");
-            let new_permissions: Vec<PermissionCreate> = json_permissions
+            let new_permissions: Vec<PermissionCreate> = catalog_permissions
                 .iter()
                 .filter(|p| {
                     !db_permissions
@@ -31,14 +42,31 @@ pub async fn seed_permissions(db: &DbConn) -> anyhow::Result<()> {
                 .cloned()
                 .collect();
 
+            // Compare to get changed descriptions
+            debug!("Finding changed permissions");
+            let changed_permissions: Vec<PermissionUpdate> = db_permissions
+                .iter()
+                .filter_map(|dbp| {
+                    catalog_permissions
+                        .iter()
+                        .find(|cp| cp.group == dbp.group && cp.name == dbp.name)
+                        .filter(|cp| cp.description != dbp.description)
+                        .map(|cp| PermissionUpdate {
+                            id: dbp.id,
+                            description: Some(cp.description.clone()),
+                            ..Default::default()
+                        })
+                })
+                .collect();
+
             // Compare to get old permissions
             debug!("Finding old permissions");
             let old_permission_ids: Vec<i32> = db_permissions
                 .iter()
                 .filter(|p| {
-                    !json_permissions
+                    !catalog_permissions
                         .iter()
-                        .any(|jp| jp.group == p.group && jp.name == p.name)
+                        .any(|cp| cp.group == p.group && cp.name == p.name)
                 })
                 .map(|p| p.id)
                 .collect();
@@ -49,6 +77,12 @@ pub async fn seed_permissions(db: &DbConn) -> anyhow::Result<()> {
                 Permission::create_many(new_permissions, txn).await?;
             }
 
+            // Update descriptions
+            debug!("Updating changed permissions");
+            for permission in changed_permissions {
+                Permission::update(permission, txn).await?;
+            }
+
             // Remove old permissions
             debug!("Removing old permissions");
             Permission::delete_condition(
@@ -81,12 +115,27 @@ pub async fn seed_root_role(db: &DbConn) -> anyhow::Result<Role> {
                 RoleCreate {
                     name: ROOT_ROLE_NAME.to_string(),
                     description: Some(ROOT_ROLE_DESCRIPTION.to_string()),
+                    is_system: true,
                     ..Default::default()
                 },
                 db,
             )
             .await?
         }
+        Some(role) if !role.is_system => {
+            debug!("Marking root role as system role");
+            update_versioned(
+                taskrs_db::models::role::ActiveModel {
+                    id: ActiveValue::Set(role.id),
+                    is_system: ActiveValue::Set(true),
+                    ..Default::default()
+                },
+                taskrs_db::models::role::Column::Version,
+                db,
+            )
+            .await?
+            .into()
+        }
         Some(role) => role,
     };
 
@@ -95,30 +144,57 @@ pub async fn seed_root_role(db: &DbConn) -> anyhow::Result<Role> {
 
 #[instrument(name = "seed_root_role_permissions", level = "debug", skip_all)]
 pub async fn seed_root_role_permissions(role_id: i32, db: &DbConn) -> anyhow::Result<()> {
-    debug!("Getting all permissions");
-    let all_permissions_future = Permission::all(db);
-    let role_permissions_future = Role::permissions(role_id, db);
+    events::transaction(db, |txn| {
+        Box::pin(async move {
+            debug!("Getting all permissions");
+            let permission_ids: HashSet<i32> = Permission::all(txn)
+                .await?
+                .into_iter()
+                .map(|p| p.id)
+                .collect();
 
-    let (all_permissions, role_permissions) =
-        try_join!(all_permissions_future, role_permissions_future)?;
+            debug!("Getting permission rules of root role");
+            let role_permissions = RolePermission::find(
+                Condition::all()
+                    .add(taskrs_db::models::role_permission::Column::RoleId.eq(role_id)),
+                txn,
+            )
+            .await?;
 
-    let new_permission_ids: Vec<i32> = all_permissions
-        .iter()
-        .filter(|ap| role_permissions.iter().any(|rp| ap.id == rp.id))
-        .map(|p| p.id)
-        .collect();
+            // Permissions that are not allowed yet, including denied ones
+            let new_permission_ids: Vec<i32> = permission_ids
+                .iter()
+                .filter(|id| {
+                    !role_permissions
+                        .iter()
+                        .any(|rp| rp.permission_id == **id && rp.effect == PermissionEffect::Allow)
+                })
+                .copied()
+                .collect();
 
-    let old_permission_ids: Vec<i32> = role_permissions
-        .iter()
-        .filter(|rp| !all_permissions.iter().any(|ap| rp.id == ap.id))
-        .map(|p| p.id)
-        .collect();
+            // Rules of permissions that no longer exist
+            let old_permission_ids: Vec<i32> = role_permissions
+                .iter()
+                .filter(|rp| !permission_ids.contains(&rp.permission_id))
+                .map(|rp| rp.permission_id)
+                .collect();
+
+            // Update permissions
+            let granted =
+                Role::assign_permissions(role_id, new_permission_ids, PermissionEffect::Allow, txn)
+                    .await?;
+            let revoked = Role::remove_permissions(role_id, old_permission_ids, txn).await?;
+            debug!(
+                "Allowed {} and removed {} permissions of root role",
+                granted.added.len() + granted.updated.len(),
+                revoked.removed.len()
+            );
 
-    // Update permissions
-    let grant_future = Role::grant_permissions(role_id, new_permission_ids, db);
-    let revoke_future = Role::revoke_permissions(role_id, old_permission_ids, db);
+            Ok(())
+        })
+    })
+    .await?;
 
-    try_join!(grant_future, revoke_future)?;
     Ok(())
 }
 
@@ -131,7 +207,7 @@ pub async fn seed_root_user(
     db: &DbConn,
 ) -> anyhow::Result<User> {
     debug!("Check if user already exists");
-    let db_user = User::find_one(
+    let db_user = User::find_one_with_deleted(
         Condition::all().add(taskrs_db::models::user::Column::Email.eq(email.clone())),
         db,
     )
@@ -64,20 +64,20 @@ impl ConnectionBuilder {
         let mut options = ConnectOptions::from(self.url);
 
         if let Some(min_connections) = self.min_connections {
-            tracing::Span::current().record("min_connections", &min_connections);
+            tracing::Span::current().record("min_connections", min_connections);
             options.min_connections(min_connections);
         }
         if let Some(max_connections) = self.max_connections {
-            tracing::Span::current().record("max_connections", &max_connections);
+            tracing::Span::current().record("max_connections", max_connections);
             options.max_connections(max_connections);
         }
         if let Some(connect_timeout) = self.connect_timeout {
             tracing::Span::current()
-                .record("connect_timeout", &tracing::field::debug(&connect_timeout));
+                .record("connect_timeout", tracing::field::debug(&connect_timeout));
             options.connect_timeout(connect_timeout);
         }
         if let Some(idle_timeout) = self.idle_timeout {
-            tracing::Span::current().record("idle_timeout", &tracing::field::debug(&idle_timeout));
+            tracing::Span::current().record("idle_timeout", tracing::field::debug(&idle_timeout));
             options.idle_timeout(idle_timeout);
         }
         options.sqlx_logging(false);
@@ -4,6 +4,7 @@ extern crate tracing;
 pub mod connection;
 pub mod migrations;
 pub mod models;
+pub mod schema;
 pub mod utils;
 
 pub use sea_orm;
@@ -0,0 +1,70 @@
+use crate::migrations::{drop_column, Migration};
+use crate::models::{user_permission, user_role};
+use async_trait::async_trait;
+use sea_orm::sea_query::{ColumnDef, Table};
+use sea_orm::{DbBackend, Statement};
+
+/// Adds `valid_from` and `valid_until` to `user_roles` and `user_permissions`.
+/// Existing grants stay valid indefinitely.
+#[derive(Default)]
+pub(crate) struct AddGrantValidityMigration;
+
+#[async_trait]
+impl Migration for AddGrantValidityMigration {
+    fn order(&self) -> u32 {
+        40
+    }
+
+    fn name(&self) -> String {
+        String::from("add_grant_validity")
+    }
+
+    fn up_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        let mut statements = vec![];
+
+        // UserRoles
+        let user_roles_valid_from_stmt = Table::alter()
+            .table(user_role::Entity)
+            .add_column(ColumnDef::new(user_role::Column::ValidFrom).date_time())
+            .to_owned();
+        statements.push(backend.build(&user_roles_valid_from_stmt));
+
+        let user_roles_valid_until_stmt = Table::alter()
+            .table(user_role::Entity)
+            .add_column(ColumnDef::new(user_role::Column::ValidUntil).date_time())
+            .to_owned();
+        statements.push(backend.build(&user_roles_valid_until_stmt));
+
+        // UserPermissions
+        let user_permissions_valid_from_stmt = Table::alter()
+            .table(user_permission::Entity)
+            .add_column(ColumnDef::new(user_permission::Column::ValidFrom).date_time())
+            .to_owned();
+        statements.push(backend.build(&user_permissions_valid_from_stmt));
+
+        let user_permissions_valid_until_stmt = Table::alter()
+            .table(user_permission::Entity)
+            .add_column(ColumnDef::new(user_permission::Column::ValidUntil).date_time())
+            .to_owned();
+        statements.push(backend.build(&user_permissions_valid_until_stmt));
+
+        statements
+    }
+
+    fn down_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        vec![
+            drop_column(
+                backend,
+                user_permission::Entity,
+                user_permission::Column::ValidUntil,
+            ),
+            drop_column(
+                backend,
+                user_permission::Entity,
+                user_permission::Column::ValidFrom,
+            ),
+            drop_column(backend, user_role::Entity, user_role::Column::ValidUntil),
+            drop_column(backend, user_role::Entity, user_role::Column::ValidFrom),
+        ]
+    }
+}
@@ -0,0 +1,55 @@
+use crate::migrations::{drop_column, Migration};
+use crate::models::idempotency_key;
+use async_trait::async_trait;
+use sea_orm::sea_query::{ColumnDef, Table};
+use sea_orm::{DbBackend, Statement};
+
+/// Adds `response_headers` and `locked_until` to `idempotency_keys`.
+/// Replays keep the headers of the stored response, like `Location` and `ETag`.
+/// Keys of requests that are still running are leased until `locked_until`,
+/// afterwards the request is assumed to have crashed and a retry takes the key over.
+#[derive(Default)]
+pub(crate) struct AddIdempotencyLeasesMigration;
+
+#[async_trait]
+impl Migration for AddIdempotencyLeasesMigration {
+    fn order(&self) -> u32 {
+        130
+    }
+
+    fn name(&self) -> String {
+        String::from("add_idempotency_leases")
+    }
+
+    fn up_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        // SQLite adds a single column per statement
+        let response_headers_stmt = Table::alter()
+            .table(idempotency_key::Entity)
+            .add_column(ColumnDef::new(idempotency_key::Column::ResponseHeaders).text())
+            .to_owned();
+        let locked_until_stmt = Table::alter()
+            .table(idempotency_key::Entity)
+            .add_column(ColumnDef::new(idempotency_key::Column::LockedUntil).date_time())
+            .to_owned();
+
+        vec![
+            backend.build(&response_headers_stmt),
+            backend.build(&locked_until_stmt),
+        ]
+    }
+
+    fn down_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        vec![
+            drop_column(
+                backend,
+                idempotency_key::Entity,
+                idempotency_key::Column::LockedUntil,
+            ),
+            drop_column(
+                backend,
+                idempotency_key::Entity,
+                idempotency_key::Column::ResponseHeaders,
+            ),
+        ]
+    }
+}
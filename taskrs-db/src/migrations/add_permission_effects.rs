@@ -0,0 +1,67 @@
+use crate::migrations::{drop_column, Migration};
+use crate::models::permission::PermissionEffect;
+use crate::models::{role_permission, user_permission};
+use async_trait::async_trait;
+use sea_orm::sea_query::{ColumnDef, Table};
+use sea_orm::{ActiveEnum, DbBackend, Statement};
+
+/// Adds an `effect` column to `role_permissions` and `user_permissions`.
+/// Existing rows become `allow` rules.
+#[derive(Default)]
+pub(crate) struct AddPermissionEffectsMigration;
+
+#[async_trait]
+impl Migration for AddPermissionEffectsMigration {
+    fn order(&self) -> u32 {
+        30
+    }
+
+    fn name(&self) -> String {
+        String::from("add_permission_effects")
+    }
+
+    fn up_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        let mut statements = vec![];
+
+        // RolePermissions
+        let role_permissions_stmt = Table::alter()
+            .table(role_permission::Entity)
+            .add_column(
+                ColumnDef::new(role_permission::Column::Effect)
+                    .string_len(16)
+                    .not_null()
+                    .default(PermissionEffect::Allow.to_value()),
+            )
+            .to_owned();
+        statements.push(backend.build(&role_permissions_stmt));
+
+        // UserPermissions
+        let user_permissions_stmt = Table::alter()
+            .table(user_permission::Entity)
+            .add_column(
+                ColumnDef::new(user_permission::Column::Effect)
+                    .string_len(16)
+                    .not_null()
+                    .default(PermissionEffect::Allow.to_value()),
+            )
+            .to_owned();
+        statements.push(backend.build(&user_permissions_stmt));
+
+        statements
+    }
+
+    fn down_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        vec![
+            drop_column(
+                backend,
+                user_permission::Entity,
+                user_permission::Column::Effect,
+            ),
+            drop_column(
+                backend,
+                role_permission::Entity,
+                role_permission::Column::Effect,
+            ),
+        ]
+    }
+}
@@ -0,0 +1,125 @@
+use crate::migrations::{create_index, drop_index, Migration};
+use crate::models::{
+    group_member, group_role, permission, refresh_token, role_permission, user_permission,
+    user_role,
+};
+use async_trait::async_trait;
+use sea_orm::sea_query::{Index, IndexCreateStatement};
+use sea_orm::{DbBackend, Statement};
+
+/// Indexes the columns relations are looked up by and makes permission names unique within their group.
+///
+/// Foreign keys are not touched here, see [`super::enforce_relation_foreign_keys`].
+/// Primary keys of join tables only cover lookups by their first column though,
+/// e.g. the roles of a user but not the users of a role.
+/// MySQL indexes foreign key columns by itself, the other backends need explicit indexes.
+/// Indexes are created on existing tables, so SQLite needs no table rebuild.
+#[derive(Default)]
+pub(crate) struct AddRelationIndexesMigration;
+
+#[async_trait]
+impl Migration for AddRelationIndexesMigration {
+    fn order(&self) -> u32 {
+        120
+    }
+
+    fn name(&self) -> String {
+        String::from("add_relation_indexes")
+    }
+
+    fn up_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        let mut indexes = vec![
+            refresh_token_index(),
+            // Fails if permissions share a group and name, those have to be merged by hand
+            Index::create()
+                .name("idx-permissions-group-name")
+                .table(permission::Entity)
+                .col(permission::Column::Group)
+                .col(permission::Column::Name)
+                .unique()
+                .to_owned(),
+        ];
+        if backend != DbBackend::MySql {
+            indexes.extend(foreign_key_indexes());
+        }
+
+        indexes
+            .iter()
+            .map(|index| create_index(backend, index))
+            .collect()
+    }
+
+    fn down_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        let mut statements = vec![];
+
+        if backend != DbBackend::MySql {
+            statements.extend([
+                drop_index(backend, group_role::Entity, "idx-group_roles-role_id"),
+                drop_index(backend, group_member::Entity, "idx-group_members-user_id"),
+                drop_index(backend, user_role::Entity, "idx-user_roles-role_id"),
+                drop_index(
+                    backend,
+                    role_permission::Entity,
+                    "idx-role_permissions-permission_id",
+                ),
+                drop_index(
+                    backend,
+                    user_permission::Entity,
+                    "idx-user_permissions-permission_id",
+                ),
+                drop_index(backend, refresh_token::Entity, "idx-refresh_tokens-user_id"),
+            ]);
+        }
+        statements.extend([
+            drop_index(backend, permission::Entity, "idx-permissions-group-name"),
+            drop_index(backend, refresh_token::Entity, "idx-refresh_tokens-token"),
+        ]);
+
+        statements
+    }
+}
+
+/// Index of the column refresh tokens are looked up by
+pub(super) fn refresh_token_index() -> IndexCreateStatement {
+    Index::create()
+        .name("idx-refresh_tokens-token")
+        .table(refresh_token::Entity)
+        .col(refresh_token::Column::Token)
+        .to_owned()
+}
+
+/// Indexes of foreign key columns that are not the first column of a primary key
+pub(super) fn foreign_key_indexes() -> Vec<IndexCreateStatement> {
+    vec![
+        Index::create()
+            .name("idx-refresh_tokens-user_id")
+            .table(refresh_token::Entity)
+            .col(refresh_token::Column::UserId)
+            .to_owned(),
+        Index::create()
+            .name("idx-user_permissions-permission_id")
+            .table(user_permission::Entity)
+            .col(user_permission::Column::PermissionId)
+            .to_owned(),
+        Index::create()
+            .name("idx-role_permissions-permission_id")
+            .table(role_permission::Entity)
+            .col(role_permission::Column::PermissionId)
+            .to_owned(),
+        Index::create()
+            .name("idx-user_roles-role_id")
+            .table(user_role::Entity)
+            .col(user_role::Column::RoleId)
+            .to_owned(),
+        Index::create()
+            .name("idx-group_members-user_id")
+            .table(group_member::Entity)
+            .col(group_member::Column::UserId)
+            .to_owned(),
+        Index::create()
+            .name("idx-group_roles-role_id")
+            .table(group_role::Entity)
+            .col(group_role::Column::RoleId)
+            .to_owned(),
+    ]
+}
@@ -0,0 +1,39 @@
+use crate::migrations::{drop_column, Migration};
+use crate::models::role;
+use async_trait::async_trait;
+use sea_orm::sea_query::{ColumnDef, Table};
+use sea_orm::{DbBackend, Statement};
+
+/// Adds an `is_system` flag to `roles`.
+/// Existing roles are no system roles, seeding marks the root role.
+#[derive(Default)]
+pub(crate) struct AddSystemRolesMigration;
+
+#[async_trait]
+impl Migration for AddSystemRolesMigration {
+    fn order(&self) -> u32 {
+        50
+    }
+
+    fn name(&self) -> String {
+        String::from("add_system_roles")
+    }
+
+    fn up_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        let roles_stmt = Table::alter()
+            .table(role::Entity)
+            .add_column(
+                ColumnDef::new(role::Column::IsSystem)
+                    .boolean()
+                    .not_null()
+                    .default(false),
+            )
+            .to_owned();
+
+        vec![backend.build(&roles_stmt)]
+    }
+
+    fn down_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        vec![drop_column(backend, role::Entity, role::Column::IsSystem)]
+    }
+}
@@ -0,0 +1,87 @@
+use crate::migrations::Migration;
+use async_trait::async_trait;
+use sea_orm::{DbBackend, Statement};
+
+/// Adds a full-text index over email and names of `users`, using the native facility of each backend:
+/// - SQLite: external content FTS5 table `users_search`, kept up to date by triggers
+/// - Postgres: generated `tsvector` column `search` with a GIN index
+/// - MySQL: FULLTEXT index
+#[derive(Default)]
+pub(crate) struct AddUserSearchMigration;
+
+#[async_trait]
+impl Migration for AddUserSearchMigration {
+    fn order(&self) -> u32 {
+        80
+    }
+
+    fn name(&self) -> String {
+        String::from("add_user_search")
+    }
+
+    fn up_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        let statements: &[&str] = match backend {
+            DbBackend::Sqlite => &[
+                "CREATE VIRTUAL TABLE users_search USING fts5(\
+                    email, first_name, last_name, content='users', content_rowid='id'\
+                )",
+                "CREATE TRIGGER users_search_insert AFTER INSERT ON users BEGIN \
+                    INSERT INTO users_search(rowid, email, first_name, last_name) \
+                    VALUES (new.id, new.email, new.first_name, new.last_name); \
+                END",
+                "CREATE TRIGGER users_search_delete AFTER DELETE ON users BEGIN \
+                    INSERT INTO users_search(users_search, rowid, email, first_name, last_name) \
+                    VALUES ('delete', old.id, old.email, old.first_name, old.last_name); \
+                END",
+                "CREATE TRIGGER users_search_update AFTER UPDATE ON users BEGIN \
+                    INSERT INTO users_search(users_search, rowid, email, first_name, last_name) \
+                    VALUES ('delete', old.id, old.email, old.first_name, old.last_name); \
+                    INSERT INTO users_search(rowid, email, first_name, last_name) \
+                    VALUES (new.id, new.email, new.first_name, new.last_name); \
+                END",
+                // Index existing users
+                "INSERT INTO users_search(users_search) VALUES ('rebuild')",
+            ],
+            DbBackend::Postgres => &[
+                // Split emails, so that their parts can be searched
+                "ALTER TABLE users ADD COLUMN search tsvector GENERATED ALWAYS AS (\
+                    to_tsvector('simple', \
+                        translate(email, '@.', '  ') || ' ' || \
+                        coalesce(first_name, '') || ' ' || \
+                        coalesce(last_name, '')\
+                    )\
+                ) STORED",
+                "CREATE INDEX idx_users_search ON users USING GIN (search)",
+            ],
+            DbBackend::MySql => {
+                &["ALTER TABLE users ADD FULLTEXT INDEX idx_users_search (email, first_name, last_name)"]
+            }
+        };
+
+        statements
+            .iter()
+            .map(|sql| Statement::from_string(backend, sql.to_string()))
+            .collect()
+    }
+
+    fn down_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        let statements: &[&str] = match backend {
+            DbBackend::Sqlite => &[
+                "DROP TRIGGER users_search_insert",
+                "DROP TRIGGER users_search_delete",
+                "DROP TRIGGER users_search_update",
+                "DROP TABLE users_search",
+            ],
+            DbBackend::Postgres => &[
+                "DROP INDEX idx_users_search",
+                "ALTER TABLE users DROP COLUMN search",
+            ],
+            DbBackend::MySql => &["ALTER TABLE users DROP INDEX idx_users_search"],
+        };
+
+        statements
+            .iter()
+            .map(|sql| Statement::from_string(backend, sql.to_string()))
+            .collect()
+    }
+}
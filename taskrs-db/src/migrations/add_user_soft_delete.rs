@@ -0,0 +1,33 @@
+use crate::migrations::{drop_column, Migration};
+use crate::models::user;
+use async_trait::async_trait;
+use sea_orm::sea_query::{ColumnDef, Table};
+use sea_orm::{DbBackend, Statement};
+
+/// Adds `deleted_at` to `users`. Users with a timestamp are soft deleted.
+#[derive(Default)]
+pub(crate) struct AddUserSoftDeleteMigration;
+
+#[async_trait]
+impl Migration for AddUserSoftDeleteMigration {
+    fn order(&self) -> u32 {
+        70
+    }
+
+    fn name(&self) -> String {
+        String::from("add_user_soft_delete")
+    }
+
+    fn up_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        let users_stmt = Table::alter()
+            .table(user::Entity)
+            .add_column(ColumnDef::new(user::Column::DeletedAt).date_time())
+            .to_owned();
+
+        vec![backend.build(&users_stmt)]
+    }
+
+    fn down_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        vec![drop_column(backend, user::Entity, user::Column::DeletedAt)]
+    }
+}
@@ -0,0 +1,65 @@
+use crate::migrations::{drop_column, Migration};
+use crate::models::{group, role, user};
+use async_trait::async_trait;
+use sea_orm::sea_query::{ColumnDef, Table};
+use sea_orm::{DbBackend, Statement};
+
+/// Adds `version` to mutable entities. It is incremented on every update,
+/// so that concurrent updates of the same row can be detected.
+#[derive(Default)]
+pub(crate) struct AddVersionsMigration;
+
+#[async_trait]
+impl Migration for AddVersionsMigration {
+    fn order(&self) -> u32 {
+        90
+    }
+
+    fn name(&self) -> String {
+        String::from("add_versions")
+    }
+
+    fn up_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        let users_stmt = Table::alter()
+            .table(user::Entity)
+            .add_column(
+                ColumnDef::new(user::Column::Version)
+                    .integer()
+                    .not_null()
+                    .default(1),
+            )
+            .to_owned();
+        let roles_stmt = Table::alter()
+            .table(role::Entity)
+            .add_column(
+                ColumnDef::new(role::Column::Version)
+                    .integer()
+                    .not_null()
+                    .default(1),
+            )
+            .to_owned();
+        let groups_stmt = Table::alter()
+            .table(group::Entity)
+            .add_column(
+                ColumnDef::new(group::Column::Version)
+                    .integer()
+                    .not_null()
+                    .default(1),
+            )
+            .to_owned();
+
+        vec![
+            backend.build(&users_stmt),
+            backend.build(&roles_stmt),
+            backend.build(&groups_stmt),
+        ]
+    }
+
+    fn down_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        vec![
+            drop_column(backend, user::Entity, user::Column::Version),
+            drop_column(backend, role::Entity, role::Column::Version),
+            drop_column(backend, group::Entity, group::Column::Version),
+        ]
+    }
+}
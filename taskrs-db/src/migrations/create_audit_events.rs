@@ -0,0 +1,84 @@
+use crate::migrations::{create_index, Migration};
+use crate::models::audit_event;
+use async_trait::async_trait;
+use sea_orm::sea_query::{ColumnDef, Index, Table};
+use sea_orm::{DbBackend, Statement};
+
+/// Creates `audit_events`, which records every change of audited data.
+/// Actors are not referenced by a foreign key, so that events outlive purged users.
+#[derive(Default)]
+pub(crate) struct CreateAuditEventsMigration;
+
+#[async_trait]
+impl Migration for CreateAuditEventsMigration {
+    fn order(&self) -> u32 {
+        100
+    }
+
+    fn name(&self) -> String {
+        String::from("create_audit_events")
+    }
+
+    fn up_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        let audit_events_stmt = Table::create()
+            .table(audit_event::Entity)
+            .col(
+                ColumnDef::new(audit_event::Column::Id)
+                    .integer()
+                    .not_null()
+                    .auto_increment()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(audit_event::Column::ActorId).integer())
+            .col(ColumnDef::new(audit_event::Column::ImpersonatorId).integer())
+            .col(
+                ColumnDef::new(audit_event::Column::Action)
+                    .string_len(64)
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(audit_event::Column::EntityType)
+                    .string_len(64)
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(audit_event::Column::EntityId)
+                    .integer()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(audit_event::Column::Changes)
+                    .text()
+                    .not_null(),
+            )
+            .col(ColumnDef::new(audit_event::Column::RequestId).string_len(128))
+            .col(ColumnDef::new(audit_event::Column::IpAddress).string_len(64))
+            .col(ColumnDef::new(audit_event::Column::InsertedAt).date_time())
+            .to_owned();
+
+        let entity_index_stmt = Index::create()
+            .name("idx-audit_events-entity")
+            .table(audit_event::Entity)
+            .col(audit_event::Column::EntityType)
+            .col(audit_event::Column::EntityId)
+            .to_owned();
+
+        let actor_index_stmt = Index::create()
+            .name("idx-audit_events-actor_id")
+            .table(audit_event::Entity)
+            .col(audit_event::Column::ActorId)
+            .to_owned();
+
+        vec![
+            backend.build(&audit_events_stmt),
+            create_index(backend, &entity_index_stmt),
+            create_index(backend, &actor_index_stmt),
+        ]
+    }
+
+    fn down_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        let stmt = Table::drop().table(audit_event::Entity).to_owned();
+
+        vec![backend.build(&stmt)]
+    }
+}
@@ -0,0 +1,152 @@
+use crate::migrations::Migration;
+use crate::models::{group, group_member, group_role, role, user};
+use async_trait::async_trait;
+use sea_orm::sea_query::{ColumnDef, ForeignKey, ForeignKeyAction, Index, Table};
+use sea_orm::{DbBackend, Statement};
+
+#[derive(Default)]
+pub(crate) struct CreateGroupsMigration;
+
+#[async_trait]
+impl Migration for CreateGroupsMigration {
+    fn order(&self) -> u32 {
+        60
+    }
+
+    fn name(&self) -> String {
+        String::from("create_groups")
+    }
+
+    fn up_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        let mut statements = vec![];
+
+        // Groups
+        let groups_stmt = Table::create()
+            .table(group::Entity)
+            .col(
+                ColumnDef::new(group::Column::Id)
+                    .integer()
+                    .not_null()
+                    .auto_increment()
+                    .primary_key(),
+            )
+            .col(
+                ColumnDef::new(group::Column::Name)
+                    .string_len(256)
+                    .not_null(),
+            )
+            .col(ColumnDef::new(group::Column::Description).string())
+            .col(ColumnDef::new(group::Column::InsertedAt).date_time())
+            .col(ColumnDef::new(group::Column::UpdatedAt).date_time())
+            .to_owned();
+        statements.push(backend.build(&groups_stmt));
+
+        // GroupMembers
+        let group_members_stmt = Table::create()
+            .table(group_member::Entity)
+            .col(
+                ColumnDef::new(group_member::Column::GroupId)
+                    .integer()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(group_member::Column::UserId)
+                    .integer()
+                    .not_null(),
+            )
+            .col(ColumnDef::new(group_member::Column::InsertedAt).date_time())
+            .primary_key(
+                Index::create()
+                    .name("pk-group_members")
+                    .col(group_member::Column::GroupId)
+                    .col(group_member::Column::UserId)
+                    .primary(),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-group_members-groups")
+                    .from_tbl(group_member::Entity)
+                    .from_col(group_member::Column::GroupId)
+                    .to_tbl(group::Entity)
+                    .to_col(group::Column::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-group_members-users")
+                    .from_tbl(group_member::Entity)
+                    .from_col(group_member::Column::UserId)
+                    .to_tbl(user::Entity)
+                    .to_col(user::Column::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade),
+            )
+            .to_owned();
+        statements.push(backend.build(&group_members_stmt));
+
+        // GroupRoles
+        let group_roles_stmt = Table::create()
+            .table(group_role::Entity)
+            .col(
+                ColumnDef::new(group_role::Column::GroupId)
+                    .integer()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(group_role::Column::RoleId)
+                    .integer()
+                    .not_null(),
+            )
+            .col(ColumnDef::new(group_role::Column::InsertedAt).date_time())
+            .primary_key(
+                Index::create()
+                    .name("pk-group_roles")
+                    .col(group_role::Column::GroupId)
+                    .col(group_role::Column::RoleId)
+                    .primary(),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-group_roles-groups")
+                    .from_tbl(group_role::Entity)
+                    .from_col(group_role::Column::GroupId)
+                    .to_tbl(group::Entity)
+                    .to_col(group::Column::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-group_roles-roles")
+                    .from_tbl(group_role::Entity)
+                    .from_col(group_role::Column::RoleId)
+                    .to_tbl(role::Entity)
+                    .to_col(role::Column::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade),
+            )
+            .to_owned();
+        statements.push(backend.build(&group_roles_stmt));
+
+        statements
+    }
+
+    fn down_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        let mut statements = vec![];
+
+        // GroupRoles
+        let group_roles_stmt = Table::drop().table(group_role::Entity).to_owned();
+        statements.push(backend.build(&group_roles_stmt));
+
+        // GroupMembers
+        let group_members_stmt = Table::drop().table(group_member::Entity).to_owned();
+        statements.push(backend.build(&group_members_stmt));
+
+        // Groups
+        let groups_stmt = Table::drop().table(group::Entity).to_owned();
+        statements.push(backend.build(&groups_stmt));
+
+        statements
+    }
+}
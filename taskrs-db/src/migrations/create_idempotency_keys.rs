@@ -0,0 +1,84 @@
+use crate::migrations::{create_index, Migration};
+use crate::models::idempotency_key;
+use async_trait::async_trait;
+use sea_orm::sea_query::{ColumnDef, Index, Table};
+use sea_orm::{DbBackend, Statement};
+
+/// Creates `idempotency_keys`, which stores the responses of requests sent with an `Idempotency-Key`
+/// so that retries are answered without running the request again.
+#[derive(Default)]
+pub(crate) struct CreateIdempotencyKeysMigration;
+
+#[async_trait]
+impl Migration for CreateIdempotencyKeysMigration {
+    fn order(&self) -> u32 {
+        110
+    }
+
+    fn name(&self) -> String {
+        String::from("create_idempotency_keys")
+    }
+
+    fn up_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        let idempotency_keys_stmt = Table::create()
+            .table(idempotency_key::Entity)
+            .col(
+                ColumnDef::new(idempotency_key::Column::Id)
+                    .integer()
+                    .not_null()
+                    .auto_increment()
+                    .primary_key(),
+            )
+            .col(
+                ColumnDef::new(idempotency_key::Column::Scope)
+                    .string_len(64)
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(idempotency_key::Column::Key)
+                    .string_len(255)
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(idempotency_key::Column::Fingerprint)
+                    .string_len(64)
+                    .not_null(),
+            )
+            .col(ColumnDef::new(idempotency_key::Column::ResponseStatus).integer())
+            .col(ColumnDef::new(idempotency_key::Column::ResponseContentType).string_len(255))
+            .col(ColumnDef::new(idempotency_key::Column::ResponseBody).text())
+            .col(
+                ColumnDef::new(idempotency_key::Column::ExpiresAt)
+                    .date_time()
+                    .not_null(),
+            )
+            .col(ColumnDef::new(idempotency_key::Column::InsertedAt).date_time())
+            .to_owned();
+
+        let key_index_stmt = Index::create()
+            .name("idx-idempotency_keys-scope-key")
+            .table(idempotency_key::Entity)
+            .col(idempotency_key::Column::Scope)
+            .col(idempotency_key::Column::Key)
+            .unique()
+            .to_owned();
+
+        let expires_at_index_stmt = Index::create()
+            .name("idx-idempotency_keys-expires_at")
+            .table(idempotency_key::Entity)
+            .col(idempotency_key::Column::ExpiresAt)
+            .to_owned();
+
+        vec![
+            backend.build(&idempotency_keys_stmt),
+            create_index(backend, &key_index_stmt),
+            create_index(backend, &expires_at_index_stmt),
+        ]
+    }
+
+    fn down_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        let stmt = Table::drop().table(idempotency_key::Entity).to_owned();
+
+        vec![backend.build(&stmt)]
+    }
+}
@@ -1,9 +1,13 @@
 use crate::migrations::Migration;
-use crate::models::{permission, role, role_permission, user_permission, user_role};
+use crate::models::{permission, role, role_permission, user, user_permission, user_role};
 use async_trait::async_trait;
-use sea_orm::sea_query::Table;
-use sea_orm::{DbBackend, Schema, Statement};
+use sea_orm::sea_query::{ColumnDef, ForeignKey, ForeignKeyAction, Index, Table};
+use sea_orm::{DbBackend, Statement};
 
+/// Creates the tables exactly as the baseline release did, when they were derived from the entities.
+/// The foreign keys with their cascades come from the `belongs_to` relations of that time.
+/// The entities gained columns since, so the tables are spelled out to keep generating the same SQL.
+/// Databases that applied this migration never run it again, changes belong into a new migration.
 #[derive(Default)]
 pub(crate) struct CreateRoleBasedAccessControlMigration;
 
@@ -18,27 +22,185 @@ impl Migration for CreateRoleBasedAccessControlMigration {
     }
 
     fn up_statements(&self, backend: DbBackend) -> Vec<Statement> {
-        let schema = Schema::new(backend);
         let mut statements = vec![];
 
         // Permissions
-        let permissions_stmt = schema.create_table_from_entity(permission::Entity);
+        let permissions_stmt = Table::create()
+            .table(permission::Entity)
+            .col(
+                ColumnDef::new(permission::Column::Id)
+                    .integer()
+                    .not_null()
+                    .auto_increment()
+                    .primary_key(),
+            )
+            .col(
+                ColumnDef::new(permission::Column::Name)
+                    .string_len(256)
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(permission::Column::Group)
+                    .string_len(256)
+                    .not_null(),
+            )
+            .col(ColumnDef::new(permission::Column::Description).string())
+            .col(ColumnDef::new(permission::Column::InsertedAt).date_time())
+            .col(ColumnDef::new(permission::Column::UpdatedAt).date_time())
+            .to_owned();
         statements.push(backend.build(&permissions_stmt));
 
         // Roles
-        let roles_stmt = schema.create_table_from_entity(role::Entity);
+        let roles_stmt = Table::create()
+            .table(role::Entity)
+            .col(
+                ColumnDef::new(role::Column::Id)
+                    .integer()
+                    .not_null()
+                    .auto_increment()
+                    .primary_key(),
+            )
+            .col(
+                ColumnDef::new(role::Column::Name)
+                    .string_len(256)
+                    .not_null(),
+            )
+            .col(ColumnDef::new(role::Column::Description).string())
+            .col(ColumnDef::new(role::Column::InsertedAt).date_time())
+            .col(ColumnDef::new(role::Column::UpdatedAt).date_time())
+            .to_owned();
         statements.push(backend.build(&roles_stmt));
 
         // UserPermissions
-        let user_permissions_stmt = schema.create_table_from_entity(user_permission::Entity);
+        let user_permissions_stmt = Table::create()
+            .table(user_permission::Entity)
+            .col(
+                ColumnDef::new(user_permission::Column::UserId)
+                    .integer()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(user_permission::Column::PermissionId)
+                    .integer()
+                    .not_null(),
+            )
+            .col(ColumnDef::new(user_permission::Column::InsertedAt).date_time())
+            .primary_key(
+                Index::create()
+                    .name("pk-user_permissions")
+                    .col(user_permission::Column::UserId)
+                    .col(user_permission::Column::PermissionId)
+                    .primary(),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-user_permissions-users")
+                    .from_tbl(user_permission::Entity)
+                    .from_col(user_permission::Column::UserId)
+                    .to_tbl(user::Entity)
+                    .to_col(user::Column::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-user_permissions-permissions")
+                    .from_tbl(user_permission::Entity)
+                    .from_col(user_permission::Column::PermissionId)
+                    .to_tbl(permission::Entity)
+                    .to_col(permission::Column::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade),
+            )
+            .to_owned();
         statements.push(backend.build(&user_permissions_stmt));
 
         // RolePermissions
-        let role_permissions_stmt = schema.create_table_from_entity(role_permission::Entity);
+        let role_permissions_stmt = Table::create()
+            .table(role_permission::Entity)
+            .col(
+                ColumnDef::new(role_permission::Column::RoleId)
+                    .integer()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(role_permission::Column::PermissionId)
+                    .integer()
+                    .not_null(),
+            )
+            .col(ColumnDef::new(role_permission::Column::InsertedAt).date_time())
+            .primary_key(
+                Index::create()
+                    .name("pk-role_permissions")
+                    .col(role_permission::Column::RoleId)
+                    .col(role_permission::Column::PermissionId)
+                    .primary(),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-role_permissions-permissions")
+                    .from_tbl(role_permission::Entity)
+                    .from_col(role_permission::Column::PermissionId)
+                    .to_tbl(permission::Entity)
+                    .to_col(permission::Column::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-role_permissions-roles")
+                    .from_tbl(role_permission::Entity)
+                    .from_col(role_permission::Column::RoleId)
+                    .to_tbl(role::Entity)
+                    .to_col(role::Column::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade),
+            )
+            .to_owned();
         statements.push(backend.build(&role_permissions_stmt));
 
         // UserRoles
-        let user_roles_stmt = schema.create_table_from_entity(user_role::Entity);
+        let user_roles_stmt = Table::create()
+            .table(user_role::Entity)
+            .col(
+                ColumnDef::new(user_role::Column::UserId)
+                    .integer()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(user_role::Column::RoleId)
+                    .integer()
+                    .not_null(),
+            )
+            .col(ColumnDef::new(user_role::Column::InsertedAt).date_time())
+            .primary_key(
+                Index::create()
+                    .name("pk-user_roles")
+                    .col(user_role::Column::UserId)
+                    .col(user_role::Column::RoleId)
+                    .primary(),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-user_roles-roles")
+                    .from_tbl(user_role::Entity)
+                    .from_col(user_role::Column::RoleId)
+                    .to_tbl(role::Entity)
+                    .to_col(role::Column::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk-user_roles-users")
+                    .from_tbl(user_role::Entity)
+                    .from_col(user_role::Column::UserId)
+                    .to_tbl(user::Entity)
+                    .to_col(user::Column::Id)
+                    .on_delete(ForeignKeyAction::Cascade)
+                    .on_update(ForeignKeyAction::Cascade),
+            )
+            .to_owned();
         statements.push(backend.build(&user_roles_stmt));
 
         statements
@@ -70,3 +232,76 @@ impl Migration for CreateRoleBasedAccessControlMigration {
         statements
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::CreateRoleBasedAccessControlMigration;
+    use crate::migrations::tests::assert_statements;
+    use sea_orm::DbBackend;
+    // SQL generated from the entities by the baseline release, relations included
+    #[test]
+    fn sqlite_statements_are_the_released_ones() {
+        assert_statements(
+            &CreateRoleBasedAccessControlMigration,
+            DbBackend::Sqlite,
+            &[
+                r#"CREATE TABLE `permissions` ( `id` integer NOT NULL PRIMARY KEY AUTOINCREMENT, `name` text(256) NOT NULL, `group` text(256) NOT NULL, `description` text, `inserted_at` text, `updated_at` text )"#,
+                r#"CREATE TABLE `roles` ( `id` integer NOT NULL PRIMARY KEY AUTOINCREMENT, `name` text(256) NOT NULL, `description` text, `inserted_at` text, `updated_at` text )"#,
+                r#"CREATE TABLE `user_permissions` ( `user_id` integer NOT NULL, `permission_id` integer NOT NULL, `inserted_at` text, CONSTRAINT `pk-user_permissions`PRIMARY KEY (`user_id`, `permission_id`), FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE ON UPDATE CASCADE, FOREIGN KEY (`permission_id`) REFERENCES `permissions` (`id`) ON DELETE CASCADE ON UPDATE CASCADE )"#,
+                r#"CREATE TABLE `role_permissions` ( `role_id` integer NOT NULL, `permission_id` integer NOT NULL, `inserted_at` text, CONSTRAINT `pk-role_permissions`PRIMARY KEY (`role_id`, `permission_id`), FOREIGN KEY (`permission_id`) REFERENCES `permissions` (`id`) ON DELETE CASCADE ON UPDATE CASCADE, FOREIGN KEY (`role_id`) REFERENCES `roles` (`id`) ON DELETE CASCADE ON UPDATE CASCADE )"#,
+                r#"CREATE TABLE `user_roles` ( `user_id` integer NOT NULL, `role_id` integer NOT NULL, `inserted_at` text, CONSTRAINT `pk-user_roles`PRIMARY KEY (`user_id`, `role_id`), FOREIGN KEY (`role_id`) REFERENCES `roles` (`id`) ON DELETE CASCADE ON UPDATE CASCADE, FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE ON UPDATE CASCADE )"#,
+            ],
+            &[
+                r#"DROP TABLE `user_roles`"#,
+                r#"DROP TABLE `role_permissions`"#,
+                r#"DROP TABLE `user_permissions`"#,
+                r#"DROP TABLE `roles`"#,
+                r#"DROP TABLE `permissions`"#,
+            ],
+        );
+    }
+
+    #[test]
+    fn postgres_statements_are_the_released_ones() {
+        assert_statements(
+            &CreateRoleBasedAccessControlMigration,
+            DbBackend::Postgres,
+            &[
+                r#"CREATE TABLE "permissions" ( "id" serial NOT NULL PRIMARY KEY, "name" varchar(256) NOT NULL, "group" varchar(256) NOT NULL, "description" varchar, "inserted_at" timestamp without time zone, "updated_at" timestamp without time zone )"#,
+                r#"CREATE TABLE "roles" ( "id" serial NOT NULL PRIMARY KEY, "name" varchar(256) NOT NULL, "description" varchar, "inserted_at" timestamp without time zone, "updated_at" timestamp without time zone )"#,
+                r#"CREATE TABLE "user_permissions" ( "user_id" integer NOT NULL, "permission_id" integer NOT NULL, "inserted_at" timestamp without time zone, CONSTRAINT "pk-user_permissions" PRIMARY KEY ("user_id", "permission_id"), CONSTRAINT "fk-user_permissions-users" FOREIGN KEY ("user_id") REFERENCES "users" ("id") ON DELETE CASCADE ON UPDATE CASCADE, CONSTRAINT "fk-user_permissions-permissions" FOREIGN KEY ("permission_id") REFERENCES "permissions" ("id") ON DELETE CASCADE ON UPDATE CASCADE )"#,
+                r#"CREATE TABLE "role_permissions" ( "role_id" integer NOT NULL, "permission_id" integer NOT NULL, "inserted_at" timestamp without time zone, CONSTRAINT "pk-role_permissions" PRIMARY KEY ("role_id", "permission_id"), CONSTRAINT "fk-role_permissions-permissions" FOREIGN KEY ("permission_id") REFERENCES "permissions" ("id") ON DELETE CASCADE ON UPDATE CASCADE, CONSTRAINT "fk-role_permissions-roles" FOREIGN KEY ("role_id") REFERENCES "roles" ("id") ON DELETE CASCADE ON UPDATE CASCADE )"#,
+                r#"CREATE TABLE "user_roles" ( "user_id" integer NOT NULL, "role_id" integer NOT NULL, "inserted_at" timestamp without time zone, CONSTRAINT "pk-user_roles" PRIMARY KEY ("user_id", "role_id"), CONSTRAINT "fk-user_roles-roles" FOREIGN KEY ("role_id") REFERENCES "roles" ("id") ON DELETE CASCADE ON UPDATE CASCADE, CONSTRAINT "fk-user_roles-users" FOREIGN KEY ("user_id") REFERENCES "users" ("id") ON DELETE CASCADE ON UPDATE CASCADE )"#,
+            ],
+            &[
+                r#"DROP TABLE "user_roles""#,
+                r#"DROP TABLE "role_permissions""#,
+                r#"DROP TABLE "user_permissions""#,
+                r#"DROP TABLE "roles""#,
+                r#"DROP TABLE "permissions""#,
+            ],
+        );
+    }
+
+    #[test]
+    fn mysql_statements_are_the_released_ones() {
+        assert_statements(
+            &CreateRoleBasedAccessControlMigration,
+            DbBackend::MySql,
+            &[
+                r#"CREATE TABLE `permissions` ( `id` int NOT NULL AUTO_INCREMENT PRIMARY KEY, `name` varchar(256) NOT NULL, `group` varchar(256) NOT NULL, `description` varchar(255), `inserted_at` datetime, `updated_at` datetime )"#,
+                r#"CREATE TABLE `roles` ( `id` int NOT NULL AUTO_INCREMENT PRIMARY KEY, `name` varchar(256) NOT NULL, `description` varchar(255), `inserted_at` datetime, `updated_at` datetime )"#,
+                r#"CREATE TABLE `user_permissions` ( `user_id` int NOT NULL, `permission_id` int NOT NULL, `inserted_at` datetime, PRIMARY KEY `pk-user_permissions` (`user_id`, `permission_id`), CONSTRAINT `fk-user_permissions-users` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE ON UPDATE CASCADE, CONSTRAINT `fk-user_permissions-permissions` FOREIGN KEY (`permission_id`) REFERENCES `permissions` (`id`) ON DELETE CASCADE ON UPDATE CASCADE )"#,
+                r#"CREATE TABLE `role_permissions` ( `role_id` int NOT NULL, `permission_id` int NOT NULL, `inserted_at` datetime, PRIMARY KEY `pk-role_permissions` (`role_id`, `permission_id`), CONSTRAINT `fk-role_permissions-permissions` FOREIGN KEY (`permission_id`) REFERENCES `permissions` (`id`) ON DELETE CASCADE ON UPDATE CASCADE, CONSTRAINT `fk-role_permissions-roles` FOREIGN KEY (`role_id`) REFERENCES `roles` (`id`) ON DELETE CASCADE ON UPDATE CASCADE )"#,
+                r#"CREATE TABLE `user_roles` ( `user_id` int NOT NULL, `role_id` int NOT NULL, `inserted_at` datetime, PRIMARY KEY `pk-user_roles` (`user_id`, `role_id`), CONSTRAINT `fk-user_roles-roles` FOREIGN KEY (`role_id`) REFERENCES `roles` (`id`) ON DELETE CASCADE ON UPDATE CASCADE, CONSTRAINT `fk-user_roles-users` FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE ON UPDATE CASCADE )"#,
+            ],
+            &[
+                r#"DROP TABLE `user_roles`"#,
+                r#"DROP TABLE `role_permissions`"#,
+                r#"DROP TABLE `user_permissions`"#,
+                r#"DROP TABLE `roles`"#,
+                r#"DROP TABLE `permissions`"#,
+            ],
+        );
+    }
+}
@@ -1,9 +1,12 @@
 use crate::migrations::Migration;
 use crate::models::user;
 use async_trait::async_trait;
-use sea_orm::sea_query::Table;
-use sea_orm::{DbBackend, Schema, Statement};
+use sea_orm::sea_query::{ColumnDef, Table};
+use sea_orm::{DbBackend, Statement};
 
+/// Creates the table exactly as the baseline release did, when it was derived from the entity.
+/// The entity gained columns since, so the table is spelled out to keep generating the same SQL.
+/// Databases that applied this migration never run it again, changes belong into a new migration.
 #[derive(Default)]
 pub(crate) struct CreateUsersMigration;
 
@@ -18,8 +21,32 @@ impl Migration for CreateUsersMigration {
     }
 
     fn up_statements(&self, backend: DbBackend) -> Vec<Statement> {
-        let schema = Schema::new(backend);
-        let stmt = schema.create_table_from_entity(user::Entity);
+        let stmt = Table::create()
+            .table(user::Entity)
+            .col(
+                ColumnDef::new(user::Column::Id)
+                    .integer()
+                    .not_null()
+                    .auto_increment()
+                    .primary_key(),
+            )
+            .col(
+                ColumnDef::new(user::Column::Email)
+                    .string_len(256)
+                    .not_null()
+                    .unique_key(),
+            )
+            .col(
+                ColumnDef::new(user::Column::PasswordHash)
+                    .string_len(1024)
+                    .not_null(),
+            )
+            .col(ColumnDef::new(user::Column::FirstName).string_len(256))
+            .col(ColumnDef::new(user::Column::LastName).string_len(256))
+            .col(ColumnDef::new(user::Column::Enabled).boolean().not_null())
+            .col(ColumnDef::new(user::Column::InsertedAt).date_time())
+            .col(ColumnDef::new(user::Column::UpdatedAt).date_time())
+            .to_owned();
 
         vec![backend.build(&stmt)]
     }
@@ -30,3 +57,46 @@ impl Migration for CreateUsersMigration {
         vec![backend.build(&stmt)]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::CreateUsersMigration;
+    use crate::migrations::tests::assert_statements;
+    use sea_orm::DbBackend;
+    // SQL generated from the entity by the baseline release
+    #[test]
+    fn sqlite_statements_are_the_released_ones() {
+        assert_statements(
+            &CreateUsersMigration,
+            DbBackend::Sqlite,
+            &[
+                r#"CREATE TABLE `users` ( `id` integer NOT NULL PRIMARY KEY AUTOINCREMENT, `email` text(256) NOT NULL UNIQUE, `password_hash` text(1024) NOT NULL, `first_name` text(256), `last_name` text(256), `enabled` integer NOT NULL, `inserted_at` text, `updated_at` text )"#,
+            ],
+            &[r#"DROP TABLE `users`"#],
+        );
+    }
+
+    #[test]
+    fn postgres_statements_are_the_released_ones() {
+        assert_statements(
+            &CreateUsersMigration,
+            DbBackend::Postgres,
+            &[
+                r#"CREATE TABLE "users" ( "id" serial NOT NULL PRIMARY KEY, "email" varchar(256) NOT NULL UNIQUE, "password_hash" varchar(1024) NOT NULL, "first_name" varchar(256), "last_name" varchar(256), "enabled" bool NOT NULL, "inserted_at" timestamp without time zone, "updated_at" timestamp without time zone )"#,
+            ],
+            &[r#"DROP TABLE "users""#],
+        );
+    }
+
+    #[test]
+    fn mysql_statements_are_the_released_ones() {
+        assert_statements(
+            &CreateUsersMigration,
+            DbBackend::MySql,
+            &[
+                r#"CREATE TABLE `users` ( `id` int NOT NULL AUTO_INCREMENT PRIMARY KEY, `email` varchar(256) NOT NULL UNIQUE, `password_hash` varchar(1024) NOT NULL, `first_name` varchar(256), `last_name` varchar(256), `enabled` bool NOT NULL, `inserted_at` datetime, `updated_at` datetime )"#,
+            ],
+            &[r#"DROP TABLE `users`"#],
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use crate::migrations::add_relation_indexes::{foreign_key_indexes, refresh_token_index};
+use crate::migrations::{create_index, Migration};
+use crate::models::{
+    group_member, group_role, refresh_token, role_permission, user_permission, user_role,
+};
+use async_trait::async_trait;
+use sea_orm::sea_query::{Alias, Expr, Query, Table, TableCreateStatement};
+use sea_orm::{DbBackend, EntityTrait, Schema, Statement};
+
+/// Removes orphaned relations and makes sure every relation table has its cascading foreign keys.
+///
+/// Rows of `refresh_tokens` and the join tables whose user, role, permission or group
+/// no longer exists are deleted first, a foreign key can not be declared over them.
+/// SQLite only enforces foreign keys on connections that enable them,
+/// so databases written by other tools can hold such rows.
+/// SQLite can not add a foreign key to an existing table either, the relation tables are rebuilt
+/// from their entities there: renamed, created again, filled with the old rows and their indexes recreated.
+/// That declares the keys even on tables that were created or copied without them.
+/// The migrations creating relation tables declare the keys on every backend,
+/// Postgres and MySQL enforce them on every connection, so only the orphan cleanup runs there.
+///
+/// Rolling back keeps the tables as they are, deleted orphans can not be restored.
+#[derive(Default)]
+pub(crate) struct EnforceRelationForeignKeysMigration;
+
+#[async_trait]
+impl Migration for EnforceRelationForeignKeysMigration {
+    fn order(&self) -> u32 {
+        140
+    }
+
+    fn name(&self) -> String {
+        String::from("enforce_relation_foreign_keys")
+    }
+
+    fn up_statements(&self, backend: DbBackend) -> Vec<Statement> {
+        let tables = relation_tables(backend);
+        let mut statements: Vec<Statement> = tables
+            .iter()
+            .flat_map(|(table, stmt)| delete_orphans(backend, table, stmt))
+            .collect();
+
+        if backend == DbBackend::Sqlite {
+            for (table, stmt) in &tables {
+                statements.extend(rebuild(backend, table, stmt));
+            }
+            // Indexes were dropped with the old tables
+            statements.push(create_index(backend, &refresh_token_index()));
+            statements.extend(
+                foreign_key_indexes()
+                    .iter()
+                    .map(|index| create_index(backend, index)),
+            );
+        }
+
+        statements
+    }
+}
+
+/// Tables with foreign keys by their name, as their entities create them
+fn relation_tables(backend: DbBackend) -> Vec<(String, TableCreateStatement)> {
+    let schema = Schema::new(backend);
+    vec![
+        relation_table(&schema, refresh_token::Entity),
+        relation_table(&schema, user_role::Entity),
+        relation_table(&schema, user_permission::Entity),
+        relation_table(&schema, role_permission::Entity),
+        relation_table(&schema, group_member::Entity),
+        relation_table(&schema, group_role::Entity),
+    ]
+}
+
+fn relation_table<E: EntityTrait>(schema: &Schema, entity: E) -> (String, TableCreateStatement) {
+    (
+        entity.table_name().to_string(),
+        schema.create_table_from_entity(entity),
+    )
+}
+
+/// Deletes the rows of the table referencing no row, every foreign key has a single column
+fn delete_orphans(backend: DbBackend, table: &str, stmt: &TableCreateStatement) -> Vec<Statement> {
+    stmt.get_foreign_key_create_stmts()
+        .iter()
+        .map(|foreign_key| foreign_key.get_foreign_key())
+        .flat_map(|foreign_key| {
+            let ref_table = foreign_key.get_ref_table().unwrap_or_default();
+            foreign_key
+                .get_columns()
+                .into_iter()
+                .zip(foreign_key.get_ref_columns())
+                .map(move |(column, ref_column)| {
+                    Query::delete()
+                        .from_table(Alias::new(table))
+                        .and_where(
+                            Expr::col(Alias::new(&column)).not_in_subquery(
+                                Query::select()
+                                    .column(Alias::new(&ref_column))
+                                    .from(Alias::new(&ref_table))
+                                    .to_owned(),
+                            ),
+                        )
+                        .to_owned()
+                })
+        })
+        .map(|delete| backend.build(&delete))
+        .collect()
+}
+
+/// Recreates the table with the foreign keys of its entity, keeping its rows
+fn rebuild(backend: DbBackend, table: &str, stmt: &TableCreateStatement) -> Vec<Statement> {
+    let old_table = format!("{}_old", table);
+    let columns = stmt
+        .get_columns()
+        .iter()
+        .map(|column| format!("`{}`", column.get_column_name()))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    vec![
+        backend.build(
+            &Table::rename()
+                .table(Alias::new(table), Alias::new(&old_table))
+                .to_owned(),
+        ),
+        backend.build(stmt),
+        Statement::from_string(
+            backend,
+            format!(
+                "INSERT INTO `{}` ({}) SELECT {} FROM `{}`",
+                table, columns, columns, old_table
+            ),
+        ),
+        backend.build(&Table::drop().table(Alias::new(&old_table)).to_owned()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EnforceRelationForeignKeysMigration;
+    use crate::migrations::tests::assert_statements;
+    use crate::migrations::Migration;
+    use sea_orm::DbBackend;
+
+    #[test]
+    fn postgres_only_deletes_orphans() {
+        assert_statements(
+            &EnforceRelationForeignKeysMigration,
+            DbBackend::Postgres,
+            &[
+                r#"DELETE FROM "refresh_tokens" WHERE "user_id" NOT IN (SELECT "id" FROM "users")"#,
+                r#"DELETE FROM "user_roles" WHERE "role_id" NOT IN (SELECT "id" FROM "roles")"#,
+                r#"DELETE FROM "user_roles" WHERE "user_id" NOT IN (SELECT "id" FROM "users")"#,
+                r#"DELETE FROM "user_permissions" WHERE "user_id" NOT IN (SELECT "id" FROM "users")"#,
+                r#"DELETE FROM "user_permissions" WHERE "permission_id" NOT IN (SELECT "id" FROM "permissions")"#,
+                r#"DELETE FROM "role_permissions" WHERE "permission_id" NOT IN (SELECT "id" FROM "permissions")"#,
+                r#"DELETE FROM "role_permissions" WHERE "role_id" NOT IN (SELECT "id" FROM "roles")"#,
+                r#"DELETE FROM "group_members" WHERE "group_id" NOT IN (SELECT "id" FROM "groups")"#,
+                r#"DELETE FROM "group_members" WHERE "user_id" NOT IN (SELECT "id" FROM "users")"#,
+                r#"DELETE FROM "group_roles" WHERE "group_id" NOT IN (SELECT "id" FROM "groups")"#,
+                r#"DELETE FROM "group_roles" WHERE "role_id" NOT IN (SELECT "id" FROM "roles")"#,
+            ],
+            &[],
+        );
+    }
+
+    #[test]
+    fn sqlite_rebuilds_tables_keeping_their_rows() {
+        let statements: Vec<String> = EnforceRelationForeignKeysMigration
+            .up_statements(DbBackend::Sqlite)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        let rebuild = statements
+            .iter()
+            .skip_while(|sql| !sql.contains("RENAME TO `user_roles_old`"))
+            .take(4)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            rebuild[0],
+            "ALTER TABLE `user_roles` RENAME TO `user_roles_old`"
+        );
+        assert!(rebuild[1].starts_with("CREATE TABLE `user_roles` ("));
+        assert!(rebuild[1].contains(
+            "FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE ON UPDATE CASCADE"
+        ));
+        assert_eq!(
+            rebuild[2],
+            "INSERT INTO `user_roles` (`user_id`, `role_id`, `valid_from`, `valid_until`, `inserted_at`) \
+            SELECT `user_id`, `role_id`, `valid_from`, `valid_until`, `inserted_at` FROM `user_roles_old`"
+        );
+        assert_eq!(rebuild[3], "DROP TABLE `user_roles_old`");
+        assert_eq!(
+            statements.last().unwrap(),
+            "CREATE INDEX `idx-group_roles-role_id` ON `group_roles` (`role_id`)"
+        );
+    }
+}
@@ -0,0 +1,329 @@
+use crate::migrations::MigrationError;
+use sea_orm::{
+    ConnectionTrait, DatabaseTransaction, DbBackend, DbConn, DbErr, Statement, TransactionError,
+};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// Key of the Postgres advisory lock, "taskrs" in ASCII
+const ADVISORY_LOCK_KEY: i64 = 0x7461_736b_7273;
+/// Name of the MySQL lock
+const NAMED_LOCK: &str = "taskrs_migrations";
+/// Table holding the lock row on SQLite
+pub(crate) const LOCK_TABLE: &str = "_migration_lock";
+/// Age after which a SQLite lock row is taken over, its holder stopped refreshing it and is assumed to have crashed.
+/// Shorter than the default lock timeout, so instances restarting after a crash do not time out.
+const STALE_LOCK_ROW: Duration = Duration::from_secs(30);
+/// Interval in which the holder refreshes its SQLite lock row
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+/// Delay between attempts of locks that can not be waited for by the database
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Lock serializing migrations and seeding across instances sharing a database.
+/// Postgres and MySQL hold the lock with the session of a dedicated connection,
+/// so it is released even if the instance dies. SQLite has no such locks,
+/// a single row in `_migration_lock` marks the holder instead. The holder refreshes the row
+/// while it runs, rows it stopped refreshing are taken over.
+///
+/// The lock has to be released with [`MigrationLock::release`], it is not reentrant.
+pub struct MigrationLock {
+    held: Held,
+}
+
+enum Held {
+    /// Connection whose session holds the lock, on Postgres until the transaction ends
+    Session(DatabaseTransaction),
+    /// Owner written into the lock row and the task refreshing it
+    Row {
+        owner: String,
+        heartbeat: Option<JoinHandle<()>>,
+    },
+}
+
+impl MigrationLock {
+    /// Waits up to `timeout` until no other instance holds the lock and takes it
+    #[instrument(name = "migration_lock_acquire", level = "debug", skip_all)]
+    pub async fn acquire(db: &DbConn, timeout: Duration) -> Result<Self, MigrationError> {
+        let held = match db.get_database_backend() {
+            DbBackend::Postgres => lock_advisory(db, timeout).await?,
+            DbBackend::MySql => lock_named(db, timeout).await?,
+            DbBackend::Sqlite => lock_row(db, timeout).await?,
+        };
+
+        debug!("Acquired migration lock");
+        Ok(Self { held })
+    }
+
+    /// Releases the lock for the next instance
+    #[instrument(name = "migration_lock_release", level = "debug", skip_all)]
+    pub async fn release(self, db: &DbConn) -> Result<(), MigrationError> {
+        match self.held {
+            Held::Session(txn) => {
+                if txn.get_database_backend() == DbBackend::MySql {
+                    // Named locks outlive transactions, the connection returns to the pool
+                    txn.execute(Statement::from_sql_and_values(
+                        DbBackend::MySql,
+                        "SELECT RELEASE_LOCK(?)",
+                        vec![NAMED_LOCK.into()],
+                    ))
+                    .await
+                    .map_err(connection_error)?;
+                }
+                txn.commit().await.map_err(connection_error)?;
+            }
+            Held::Row { owner, heartbeat } => {
+                if let Some(heartbeat) = heartbeat {
+                    heartbeat.abort();
+                }
+                db.execute(Statement::from_sql_and_values(
+                    DbBackend::Sqlite,
+                    &format!("DELETE FROM {} WHERE owner = ?", LOCK_TABLE),
+                    vec![owner.into()],
+                ))
+                .await
+                .map_err(connection_error)?;
+            }
+        }
+
+        debug!("Released migration lock");
+        Ok(())
+    }
+}
+
+async fn lock_advisory(db: &DbConn, timeout: Duration) -> Result<Held, MigrationError> {
+    let txn = db.begin().await.map_err(connection_error)?;
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        let row = txn
+            .query_one(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                "SELECT pg_try_advisory_xact_lock($1) AS locked",
+                vec![ADVISORY_LOCK_KEY.into()],
+            ))
+            .await
+            .map_err(connection_error)?;
+        let locked = match row {
+            Some(row) => row
+                .try_get::<bool>("", "locked")
+                .map_err(connection_error)?,
+            None => false,
+        };
+        if locked {
+            return Ok(Held::Session(txn));
+        }
+
+        wait(deadline).await?;
+    }
+}
+
+async fn lock_named(db: &DbConn, timeout: Duration) -> Result<Held, MigrationError> {
+    let txn = db.begin().await.map_err(connection_error)?;
+
+    // Waits by itself, but only for whole seconds
+    let seconds = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+    let row = txn
+        .query_one(Statement::from_sql_and_values(
+            DbBackend::MySql,
+            "SELECT CAST(GET_LOCK(?, ?) AS SIGNED) AS locked",
+            vec![NAMED_LOCK.into(), seconds.into()],
+        ))
+        .await
+        .map_err(connection_error)?;
+    let locked = match row {
+        Some(row) => row
+            .try_get::<Option<i64>>("", "locked")
+            .map_err(connection_error)?,
+        None => None,
+    };
+
+    match locked {
+        Some(1) => Ok(Held::Session(txn)),
+        _ => Err(MigrationError::LockTimeout),
+    }
+}
+
+async fn lock_row(db: &DbConn, timeout: Duration) -> Result<Held, MigrationError> {
+    db.execute(Statement::from_string(
+        DbBackend::Sqlite,
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} (\
+                id INTEGER NOT NULL PRIMARY KEY CHECK (id = 1), \
+                owner TEXT NOT NULL, \
+                locked_at TEXT NOT NULL\
+            )",
+            LOCK_TABLE
+        ),
+    ))
+    .await
+    .map_err(connection_error)?;
+
+    let owner = format!("{}-{:016x}", std::process::id(), rand::random::<u64>());
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        let now = chrono::Utc::now().naive_utc();
+        let stale = now - chrono::Duration::seconds(STALE_LOCK_ROW.as_secs() as i64);
+        let removed = db
+            .execute(Statement::from_sql_and_values(
+                DbBackend::Sqlite,
+                &format!("DELETE FROM {} WHERE locked_at < ?", LOCK_TABLE),
+                vec![stale.into()],
+            ))
+            .await
+            .map_err(connection_error)?;
+        if removed.rows_affected() > 0 {
+            warn!("Took over stale migration lock");
+        }
+
+        let inserted = db
+            .execute(Statement::from_sql_and_values(
+                DbBackend::Sqlite,
+                &format!(
+                    "INSERT OR IGNORE INTO {} (id, owner, locked_at) VALUES (1, ?, ?)",
+                    LOCK_TABLE
+                ),
+                vec![owner.clone().into(), now.into()],
+            ))
+            .await
+            .map_err(connection_error)?;
+        if inserted.rows_affected() == 1 {
+            let heartbeat = spawn_heartbeat(db, owner.clone());
+            return Ok(Held::Row { owner, heartbeat });
+        }
+
+        wait(deadline).await?;
+    }
+}
+
+/// Refreshes the lock row until aborted, so migrations running longer than [`STALE_LOCK_ROW`] keep the lock
+fn spawn_heartbeat(db: &DbConn, owner: String) -> Option<JoinHandle<()>> {
+    // The task needs its own handle of the pool
+    let db = match db {
+        #[cfg(feature = "sqlite")]
+        DbConn::SqlxSqlitePoolConnection(conn) => DbConn::SqlxSqlitePoolConnection(conn.clone()),
+        _ => return None,
+    };
+
+    Some(tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+            let refreshed = db
+                .execute(Statement::from_sql_and_values(
+                    DbBackend::Sqlite,
+                    &format!("UPDATE {} SET locked_at = ? WHERE owner = ?", LOCK_TABLE),
+                    vec![chrono::Utc::now().naive_utc().into(), owner.clone().into()],
+                ))
+                .await;
+            match refreshed {
+                Ok(result) if result.rows_affected() == 0 => {
+                    warn!("Migration lock was taken over by another instance");
+                    return;
+                }
+                Ok(_) => {}
+                Err(err) => warn!("Error while refreshing the migration lock: {}", err),
+            }
+        }
+    }))
+}
+
+/// Sleeps before the next attempt, fails once the deadline passed
+async fn wait(deadline: Instant) -> Result<(), MigrationError> {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    if remaining.is_zero() {
+        return Err(MigrationError::LockTimeout);
+    }
+
+    debug!("Migration lock is held by another instance, waiting");
+    tokio::time::sleep(remaining.min(POLL_INTERVAL)).await;
+    Ok(())
+}
+
+fn connection_error(err: DbErr) -> MigrationError {
+    MigrationError::Db(TransactionError::Connection(err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MigrationLock, LOCK_TABLE};
+    use crate::migrations::MigrationError;
+    use sea_orm::{ConnectionTrait, Database, DbBackend, DbConn, Statement};
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+
+    const WAIT: Duration = Duration::from_millis(500);
+
+    async fn sqlite() -> (DbConn, PathBuf) {
+        let path =
+            std::env::temp_dir().join(format!("taskrs-lock-{:016x}.db", rand::random::<u64>()));
+        let db = Database::connect(&format!("sqlite:{}?mode=rwc", path.display()))
+            .await
+            .expect("test database can be opened");
+        (db, path)
+    }
+
+    /// Asserts that a second acquire waits for the held lock and gives up after its timeout
+    async fn assert_waits_while_held(db: &DbConn) {
+        let lock = MigrationLock::acquire(db, Duration::ZERO)
+            .await
+            .expect("free lock is acquired");
+
+        let started = Instant::now();
+        let second = MigrationLock::acquire(db, WAIT).await;
+        assert!(matches!(second, Err(MigrationError::LockTimeout)));
+        assert!(started.elapsed() >= WAIT);
+
+        lock.release(db).await.expect("lock is released");
+        let third = MigrationLock::acquire(db, Duration::ZERO)
+            .await
+            .expect("released lock is acquired again");
+        third.release(db).await.expect("lock is released");
+    }
+
+    #[tokio::test]
+    async fn sqlite_acquire_times_out_while_held() {
+        let (db, path) = sqlite().await;
+
+        assert_waits_while_held(&db).await;
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn sqlite_takes_over_stale_lock_rows() {
+        let (db, path) = sqlite().await;
+        let crashed = MigrationLock::acquire(&db, Duration::ZERO)
+            .await
+            .expect("free lock is acquired");
+        // The holder stopped refreshing its row an hour ago
+        let locked_at = chrono::Utc::now().naive_utc() - chrono::Duration::hours(1);
+        db.execute(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            &format!("UPDATE {} SET locked_at = ?", LOCK_TABLE),
+            vec![locked_at.into()],
+        ))
+        .await
+        .expect("lock row is updated");
+
+        let lock = MigrationLock::acquire(&db, Duration::ZERO)
+            .await
+            .expect("stale lock is taken over");
+        lock.release(&db).await.expect("lock is released");
+        drop(crashed);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn postgres_acquire_times_out_while_held() {
+        let url = match std::env::var("TASKRS_TEST_POSTGRES_URL") {
+            Ok(url) => url,
+            Err(_) => return eprintln!("TASKRS_TEST_POSTGRES_URL is not set, skipping"),
+        };
+        let db = Database::connect(&url)
+            .await
+            .expect("test database can be opened");
+
+        assert_waits_while_held(&db).await;
+    }
+}
@@ -1,17 +1,49 @@
+mod add_grant_validity;
+mod add_idempotency_leases;
+mod add_permission_effects;
+mod add_relation_indexes;
+mod add_system_roles;
+mod add_user_search;
+mod add_user_soft_delete;
+mod add_versions;
+mod create_audit_events;
+mod create_groups;
+mod create_idempotency_keys;
 mod create_refresh_tokens;
 mod create_role_based_access_control;
 mod create_users;
-
+mod enforce_relation_foreign_keys;
+pub(crate) mod lock;
+
+use crate::schema::{column_exists, table_exists};
+use add_grant_validity::AddGrantValidityMigration;
+use add_idempotency_leases::AddIdempotencyLeasesMigration;
+use add_permission_effects::AddPermissionEffectsMigration;
+use add_relation_indexes::AddRelationIndexesMigration;
+use add_system_roles::AddSystemRolesMigration;
+use add_user_search::AddUserSearchMigration;
+use add_user_soft_delete::AddUserSoftDeleteMigration;
+use add_versions::AddVersionsMigration;
 use async_trait::async_trait;
+use create_audit_events::CreateAuditEventsMigration;
+use create_groups::CreateGroupsMigration;
+use create_idempotency_keys::CreateIdempotencyKeysMigration;
 use create_refresh_tokens::CreateRefreshTokensMigration;
 use create_role_based_access_control::CreateRoleBasedAccessControlMigration;
 use create_users::CreateUsersMigration;
+use enforce_relation_foreign_keys::EnforceRelationForeignKeysMigration;
 use itertools::Itertools;
+pub use lock::MigrationLock;
 use sea_orm::prelude::*;
+use sea_orm::sea_query::{
+    ColumnDef as TableColumnDef, Expr, Index, IndexCreateStatement, MysqlQueryBuilder,
+    PostgresQueryBuilder, Query, SqliteQueryBuilder, Table,
+};
 use sea_orm::{
-    ActiveValue, ConnectionTrait, DbBackend, ExecResult, QueryOrder, Schema, Statement,
-    TransactionError,
+    ConnectionTrait, DbBackend, FromQueryResult, QuerySelect, Schema, Statement, TransactionError,
 };
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 
 /// Used to perform migrations.
 /// Use `target` to update database to a specific migration.
@@ -27,12 +59,25 @@ impl Migrations {
                 Box::new(CreateUsersMigration),
                 Box::new(CreateRefreshTokensMigration),
                 Box::new(CreateRoleBasedAccessControlMigration),
+                Box::new(AddPermissionEffectsMigration),
+                Box::new(AddGrantValidityMigration),
+                Box::new(AddSystemRolesMigration),
+                Box::new(CreateGroupsMigration),
+                Box::new(AddUserSoftDeleteMigration),
+                Box::new(AddUserSearchMigration),
+                Box::new(AddVersionsMigration),
+                Box::new(CreateAuditEventsMigration),
+                Box::new(CreateIdempotencyKeysMigration),
+                Box::new(AddRelationIndexesMigration),
+                Box::new(AddIdempotencyLeasesMigration),
+                Box::new(EnforceRelationForeignKeysMigration),
             ],
             target,
         }
     }
 
     /// Runs the migrations. If a target is present, tries to update database to that specific migration.
+    /// Instances sharing the database have to hold the [`MigrationLock`] while running them.
     #[instrument(name = "migrations_run", level = "debug", skip_all, fields(target))]
     pub async fn run(self, db: &DbConn) -> Result<(), MigrationError> {
         debug!("Creating migrations table if it does not exist");
@@ -40,111 +85,285 @@ impl Migrations {
             .await
             .map_err(|e| MigrationError::Db(TransactionError::Connection(e)))?;
 
-        trace!("Sort migrations by their order property");
-        let ordered_migrations = self
-            .migrations
-            .into_iter()
-            .sorted_by(|a, b| Ord::cmp(&a.order(), &b.order()));
-
-        if let Some(target) = self.target {
-            // Update/Downgrade to specific migration
-            tracing::Span::current().record("target", &tracing::field::display(&target));
-            debug!("Updating database to specific migrations");
-            Migrations::run_to_target(ordered_migrations.collect(), target, db).await?;
-        } else {
-            // Update to latest migration
-            debug!("Updating database to latest migration");
-            Migrations::check_and_up(ordered_migrations.collect(), db).await?;
+        if let Some(target) = &self.target {
+            tracing::Span::current().record("target", tracing::field::display(target));
+        }
+
+        for planned in self.plan(db).await? {
+            match planned.direction {
+                MigrationDirection::Up => planned.migration.up(db).await,
+                MigrationDirection::Down => planned.migration.down(db).await,
+            }
+            .map_err(MigrationError::Db)?;
         }
 
         Ok(())
     }
 
-    async fn check_and_up(
-        migrations: Vec<Box<dyn Migration>>,
-        db: &DbConn,
-    ) -> Result<(), MigrationError> {
-        // Get already run migrations
-        debug!("Get already run migrations to skip them");
-        let db_migrations = migration::Entity::find()
-            .order_by_asc(migration::Column::Order)
-            .all(db)
+    /// Migrations that [`Migrations::run`] would apply or roll back, in the order it would run them.
+    /// Nothing is changed, so the plan can be inspected first, e.g. for a dry run.
+    pub async fn plan(&self, db: &DbConn) -> Result<Vec<PlannedMigration<'_>>, MigrationError> {
+        let applied = applied_migrations(db)
             .await
             .map_err(|e| MigrationError::Db(TransactionError::Connection(e)))?;
-
-        debug!("Running remaining migrations");
-        for migration in migrations {
-            if !db_migrations.iter().any(|m| m.name == migration.name()) {
-                migration.up(db).await.map_err(MigrationError::Db)?;
+        let is_applied = |migration: &&dyn Migration| applied.contains_key(&migration.name());
+        let ordered = self.ordered();
+
+        let (direction, migrations): (_, Vec<&dyn Migration>) = match &self.target {
+            None => {
+                debug!("Planning update to latest migration");
+                let pending = ordered.into_iter().filter(|m| !is_applied(m));
+                (MigrationDirection::Up, pending.collect())
             }
-        }
+            Some(target) => {
+                debug!("Check if provided target is a known migration");
+                let target_order = ordered
+                    .iter()
+                    .find(|m| &m.name() == target)
+                    .map(|m| m.order())
+                    .ok_or(MigrationError::TargetInvalid)?;
+
+                if applied.contains_key(target) {
+                    debug!("Planning downgrade to target migration");
+                    let later = ordered
+                        .into_iter()
+                        .rev()
+                        .filter(|m| m.order() > target_order && is_applied(m));
+                    (MigrationDirection::Down, later.collect())
+                } else {
+                    debug!("Planning upgrade to target migration");
+                    let pending = ordered
+                        .into_iter()
+                        .filter(|m| m.order() <= target_order && !is_applied(m));
+                    (MigrationDirection::Up, pending.collect())
+                }
+            }
+        };
 
-        Ok(())
+        Ok(migrations
+            .into_iter()
+            .map(|migration| PlannedMigration {
+                migration,
+                direction,
+            })
+            .collect())
     }
 
-    async fn run_to_target(
-        migrations: Vec<Box<dyn Migration>>,
-        target: String,
-        db: &DbConn,
-    ) -> Result<(), MigrationError> {
-        // Check if target is a valid
-        debug!("Check if provided target is a known migration");
-        let target_order =
-            if let Some(target_migration) = migrations.iter().find(|m| m.name() == target) {
-                target_migration.order()
-            } else {
-                // Target is no valid migration
-                return Err(MigrationError::TargetInvalid);
-            };
-
-        // Get already run migrations
-        debug!("Retrieve database migration state to determine if need to upgrade or downgrade");
-        let db_migrations = migration::Entity::find()
-            .order_by_asc(migration::Column::Order)
-            .all(db)
+    /// Every known migration with the time it was applied at, ordered like they are applied
+    pub async fn status(&self, db: &DbConn) -> Result<Vec<MigrationStatus>, MigrationError> {
+        let applied = applied_migrations(db)
             .await
             .map_err(|e| MigrationError::Db(TransactionError::Connection(e)))?;
 
-        // Check if need to down or up
-        if db_migrations.iter().any(|m| m.name == target) {
-            // Downgrade
-            debug!("Downgrade to target migration");
-            let down_migration_names: Vec<String> = db_migrations
-                .iter()
-                .filter(|m| m.order > target_order)
-                .map(|m| m.name.clone())
-                .collect();
-
-            let down_migrations = migrations
-                .iter()
-                .filter(|m| down_migration_names.iter().any(|dm| dm == &m.name()));
-
-            for down_migration in down_migrations.rev() {
-                down_migration.down(db).await.map_err(MigrationError::Db)?;
-            }
-        } else {
-            // Upgrade
-            debug!("Upgrade to target migration");
-            let up_migrations = migrations.into_iter().filter(|m| m.order() <= target_order);
-            Migrations::check_and_up(up_migrations.collect(), db).await?;
+        Ok(self
+            .ordered()
+            .into_iter()
+            .map(|migration| MigrationStatus {
+                name: migration.name(),
+                order: migration.order(),
+                run_at: applied.get(&migration.name()).map(|m| m.run_at),
+                changed: applied
+                    .get(&migration.name())
+                    .and_then(|m| m.checksum.as_ref())
+                    .is_some_and(|checksum| {
+                        checksum != &migration.checksum(db.get_database_backend())
+                    }),
+                unverified: applied
+                    .get(&migration.name())
+                    .is_some_and(|m| m.checksum.is_none()),
+            })
+            .collect())
+    }
+
+    /// Applied migrations whose statements changed since they were applied.
+    /// Migrations applied before checksums were recorded have none to compare with,
+    /// they are left out and listed as unverified by [`Migrations::status`].
+    pub async fn changed(&self, db: &DbConn) -> Result<Vec<ChecksumMismatch>, MigrationError> {
+        let backend = db.get_database_backend();
+        let applied = applied_migrations(db)
+            .await
+            .map_err(|e| MigrationError::Db(TransactionError::Connection(e)))?;
+
+        Ok(self
+            .ordered()
+            .into_iter()
+            .filter_map(|migration| {
+                let recorded = applied.get(&migration.name())?.checksum.clone()?;
+                let current = migration.checksum(backend);
+                (recorded != current).then(|| ChecksumMismatch {
+                    name: migration.name(),
+                    recorded,
+                    current,
+                })
+            })
+            .collect())
+    }
+
+    fn ordered(&self) -> Vec<&dyn Migration> {
+        trace!("Sort migrations by their order property");
+        self.migrations
+            .iter()
+            .map(|migration| migration.as_ref())
+            .sorted_by_key(|migration| migration.order())
+            .collect()
+    }
+}
+
+/// Whether a migration is applied or rolled back
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MigrationDirection {
+    Up,
+    Down,
+}
+
+/// Migration that is applied or rolled back to reach the target of [`Migrations`]
+pub struct PlannedMigration<'m> {
+    pub migration: &'m dyn Migration,
+    pub direction: MigrationDirection,
+}
+
+impl PlannedMigration<'_> {
+    /// Statements that are executed for the backend
+    pub fn statements(&self, backend: DbBackend) -> Vec<Statement> {
+        match self.direction {
+            MigrationDirection::Up => self.migration.up_statements(backend),
+            MigrationDirection::Down => self.migration.down_statements(backend),
         }
+    }
+}
 
-        Ok(())
+/// Known migration, `run_at` is `None` while it is pending.
+/// `changed` tells if its statements differ from the ones it was applied with.
+/// `unverified` migrations were applied before checksums were recorded, so changes can not be told.
+/// Their checksums are not backfilled from the current statements, which might already differ.
+#[derive(Clone, Debug)]
+pub struct MigrationStatus {
+    pub name: String,
+    pub order: u32,
+    pub run_at: Option<DateTime>,
+    pub changed: bool,
+    pub unverified: bool,
+}
+
+/// Applied migration whose statements changed, checksums are the hex encoded SHA-256
+#[derive(Clone, Debug)]
+pub struct ChecksumMismatch {
+    pub name: String,
+    pub recorded: String,
+    pub current: String,
+}
+
+/// Row of an applied migration.
+/// The order is left out, `u32` can not be decoded from the integer column on Postgres.
+#[derive(Clone, Debug, FromQueryResult)]
+struct AppliedMigration {
+    name: String,
+    run_at: DateTime,
+    checksum: Option<String>,
+}
+
+/// Applied migrations by their name, none if the migrations table does not exist yet
+async fn applied_migrations(db: &DbConn) -> Result<HashMap<String, AppliedMigration>, DbErr> {
+    let table = migration::Entity.table_name().to_string();
+    if !table_exists(db, &table).await? {
+        return Ok(HashMap::new());
     }
+
+    debug!("Get already run migrations");
+    let query = migration::Entity::find()
+        .select_only()
+        .column(migration::Column::Name)
+        .column(migration::Column::RunAt);
+    let query = match column_exists(db, &table, migration::Column::Checksum.as_str()).await? {
+        true => query.column(migration::Column::Checksum),
+        // Created before checksums were recorded, the next run adds the column
+        false => query.column_as(Expr::cust("NULL"), "checksum"),
+    };
+    let migrations = query.into_model::<AppliedMigration>().all(db).await?;
+
+    Ok(migrations
+        .into_iter()
+        .map(|m| (m.name.clone(), m))
+        .collect())
 }
 
-async fn create_migrations_table(db: &DbConn) -> Result<ExecResult, DbErr> {
-    let schema = Schema::new(db.get_database_backend());
+async fn create_migrations_table(db: &DbConn) -> Result<(), DbErr> {
+    let backend = db.get_database_backend();
+    let schema = Schema::new(backend);
     let mut stmt = schema.create_table_from_entity(migration::Entity);
     stmt.if_not_exists();
+    db.execute(backend.build(&stmt)).await?;
+
+    let table = migration::Entity.table_name();
+    if !column_exists(db, table, migration::Column::Checksum.as_str()).await? {
+        debug!("Adding checksum column to migrations table");
+        let stmt = Table::alter()
+            .table(migration::Entity)
+            .add_column(TableColumnDef::new(migration::Column::Checksum).string_len(64))
+            .to_owned();
+        db.execute(backend.build(&stmt)).await?;
+    }
+
+    Ok(())
+}
 
-    db.execute(db.get_database_backend().build(&stmt)).await
+/// Builds a statement dropping a single column.
+/// `sea-query` refuses to build `DROP COLUMN` for SQLite, although SQLite supports it since 3.35.
+pub(crate) fn drop_column<T, C>(backend: DbBackend, table: T, column: C) -> Statement
+where
+    T: Iden + 'static,
+    C: Iden + 'static,
+{
+    match backend {
+        DbBackend::Sqlite => Statement::from_string(
+            backend,
+            format!(
+                "ALTER TABLE `{}` DROP COLUMN `{}`",
+                table.to_string(),
+                column.to_string()
+            ),
+        ),
+        _ => backend.build(&Table::alter().table(table).drop_column(column).to_owned()),
+    }
+}
+
+/// Builds a statement dropping an index.
+/// Only MySQL scopes index names to their table,
+/// `sea-query` adds the table for SQLite as well, which SQLite rejects.
+pub(crate) fn drop_index<T>(backend: DbBackend, table: T, name: &str) -> Statement
+where
+    T: Iden + 'static,
+{
+    let sql = match backend {
+        DbBackend::MySql => Index::drop()
+            .name(name)
+            .table(table)
+            .to_string(MysqlQueryBuilder),
+        DbBackend::Postgres => Index::drop().name(name).to_string(PostgresQueryBuilder),
+        DbBackend::Sqlite => format!("DROP INDEX `{}`", name),
+    };
+
+    Statement::from_string(backend, sql)
+}
+
+/// Builds a statement creating an index.
+/// `DbBackend::build` only supports table and query statements.
+pub(crate) fn create_index(backend: DbBackend, stmt: &IndexCreateStatement) -> Statement {
+    let sql = match backend {
+        DbBackend::MySql => stmt.to_string(MysqlQueryBuilder),
+        DbBackend::Postgres => stmt.to_string(PostgresQueryBuilder),
+        DbBackend::Sqlite => stmt.to_string(SqliteQueryBuilder),
+    };
+
+    Statement::from_string(backend, sql)
 }
 
 #[derive(Debug)]
 pub enum MigrationError {
     Db(TransactionError<DbErr>),
     TargetInvalid,
+    /// Another instance held the migration lock for longer than the timeout
+    LockTimeout,
 }
 
 impl std::fmt::Display for MigrationError {
@@ -152,6 +371,10 @@ impl std::fmt::Display for MigrationError {
         match self {
             MigrationError::Db(e) => std::fmt::Display::fmt(e, f),
             MigrationError::TargetInvalid => write!(f, "Provided target migration is not valid"),
+            MigrationError::LockTimeout => write!(
+                f,
+                "Timed out waiting for the migration lock held by another instance"
+            ),
         }
     }
 }
@@ -168,6 +391,16 @@ pub trait Migration: Sync {
     fn name(&self) -> String;
     /// Creates all statements necessary to execute migration
     fn up_statements(&self, backend: DbBackend) -> Vec<Statement>;
+    /// Hex encoded SHA-256 of the up statements, recorded when the migration is applied
+    fn checksum(&self, backend: DbBackend) -> String {
+        let mut hasher = Sha256::new();
+        for statement in self.up_statements(backend) {
+            hasher.update(statement.to_string().as_bytes());
+            hasher.update(b";\n");
+        }
+
+        format!("{:x}", hasher.finalize())
+    }
     /// Creates all statements necessary to rollback migration
     fn down_statements(&self, _backend: DbBackend) -> Vec<Statement> {
         vec![]
@@ -187,6 +420,7 @@ pub trait Migration: Sync {
         let name = self.name();
         let order = self.order();
         let statements = self.up_statements(db.get_database_backend());
+        let checksum = self.checksum(db.get_database_backend());
 
         db.transaction::<_, (), DbErr>(|txn| {
             Box::pin(async move {
@@ -194,9 +428,9 @@ pub trait Migration: Sync {
 
                 debug!("Check if migration already exists");
                 if migration::Entity::find_by_id(name.clone())
-                    .one(txn)
+                    .count(txn)
                     .await?
-                    .is_some()
+                    > 0
                 {
                     // Migration is already present
                     debug!("Migration already exists. Skipping");
@@ -211,13 +445,23 @@ pub trait Migration: Sync {
 
                 // Insert migration
                 debug!("Insert migration into migrations table");
-                migration::ActiveModel {
-                    name: ActiveValue::Set(name),
-                    order: ActiveValue::Set(order),
-                    ..Default::default()
-                }
-                .insert(txn)
-                .await?;
+                // Executed as statement, inserting the model would decode the order on Postgres
+                let stmt = Query::insert()
+                    .into_table(migration::Entity)
+                    .columns([
+                        migration::Column::Name,
+                        migration::Column::Order,
+                        migration::Column::RunAt,
+                        migration::Column::Checksum,
+                    ])
+                    .values_panic([
+                        name.into(),
+                        order.into(),
+                        chrono::Utc::now().naive_utc().into(),
+                        checksum.into(),
+                    ])
+                    .to_owned();
+                txn.execute(txn.get_database_backend().build(&stmt)).await?;
 
                 debug!("Migration successful");
                 Ok(())
@@ -244,7 +488,11 @@ pub trait Migration: Sync {
                 debug!("Started transaction");
 
                 debug!("Check if migration exists");
-                if let Some(db_mig) = migration::Entity::find_by_id(name.clone()).one(txn).await? {
+                if migration::Entity::find_by_id(name.clone())
+                    .count(txn)
+                    .await?
+                    > 0
+                {
                     // Run statements
                     debug!("Execute down statements");
                     for statement in statements {
@@ -253,7 +501,10 @@ pub trait Migration: Sync {
 
                     // Remove migration
                     debug!("Remove migration from migrations table");
-                    db_mig.delete(txn).await?;
+                    migration::Entity::delete_many()
+                        .filter(migration::Column::Name.eq(name))
+                        .exec(txn)
+                        .await?;
                 }
 
                 debug!("Migration successful");
@@ -277,6 +528,7 @@ pub mod migration {
         pub name: String,
         pub order: u32,
         pub run_at: DateTime,
+        pub checksum: Option<String>,
     }
 
     #[derive(Debug, Copy, Clone, EnumIter, DeriveRelation)]
@@ -289,3 +541,34 @@ pub mod migration {
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::Migration;
+    use sea_orm::DbBackend;
+
+    /// Asserts the SQL a migration generates for a backend, statement by statement
+    pub(crate) fn assert_statements(
+        migration: &dyn Migration,
+        backend: DbBackend,
+        up: &[&str],
+        down: &[&str],
+    ) {
+        let sql = |statements: Vec<sea_orm::Statement>| -> Vec<String> {
+            statements.iter().map(ToString::to_string).collect()
+        };
+
+        assert_eq!(
+            sql(migration.up_statements(backend)),
+            up,
+            "{:?} up",
+            backend
+        );
+        assert_eq!(
+            sql(migration.down_statements(backend)),
+            down,
+            "{:?} down",
+            backend
+        );
+    }
+}
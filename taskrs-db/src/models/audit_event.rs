@@ -0,0 +1,87 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, Default, DeriveModel, DeriveActiveModel)]
+pub struct Model {
+    pub id: i32,
+    pub actor_id: Option<i32>,
+    pub impersonator_id: Option<i32>,
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: i32,
+    /// JSON object with the changed fields before and after the change
+    pub changes: String,
+    pub request_id: Option<String>,
+    pub ip_address: Option<String>,
+    pub inserted_at: Option<DateTime>,
+}
+
+#[derive(Copy, Clone, Default, Debug, DeriveEntity)]
+pub struct Entity;
+
+impl EntityName for Entity {
+    fn table_name(&self) -> &str {
+        "audit_events"
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
+pub enum Column {
+    Id,
+    ActorId,
+    ImpersonatorId,
+    Action,
+    EntityType,
+    EntityId,
+    Changes,
+    RequestId,
+    IpAddress,
+    InsertedAt,
+}
+
+impl ColumnTrait for Column {
+    type EntityName = Entity;
+
+    fn def(&self) -> ColumnDef {
+        match self {
+            Self::Id => ColumnType::Integer.def(),
+            Self::ActorId => ColumnType::Integer.def().nullable(),
+            Self::ImpersonatorId => ColumnType::Integer.def().nullable(),
+            Self::Action => ColumnType::String(Some(64)).def(),
+            Self::EntityType => ColumnType::String(Some(64)).def(),
+            Self::EntityId => ColumnType::Integer.def(),
+            Self::Changes => ColumnType::Text.def(),
+            Self::RequestId => ColumnType::String(Some(128)).def().nullable(),
+            Self::IpAddress => ColumnType::String(Some(64)).def().nullable(),
+            Self::InsertedAt => ColumnType::DateTime.def().nullable(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
+pub enum PrimaryKey {
+    Id,
+}
+
+impl PrimaryKeyTrait for PrimaryKey {
+    type ValueType = i32;
+
+    fn auto_increment() -> bool {
+        true
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {
+    #[cfg(feature = "db-timestamps")]
+    fn before_save(mut self, insert: bool) -> Result<Self, DbErr> {
+        // Inserted timestamp
+        if let (&sea_orm::ActiveValue::NotSet, true) = (&self.inserted_at, insert) {
+            trace!("Setting inserted_at timestamp for audit_event");
+            self.inserted_at = sea_orm::ActiveValue::Set(Some(chrono::Utc::now().naive_utc()));
+        }
+
+        Ok(self)
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
@@ -0,0 +1,102 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, Default, DeriveModel, DeriveActiveModel)]
+pub struct Model {
+    pub group_id: i32,
+    pub role_id: i32,
+    pub inserted_at: Option<DateTime>,
+}
+
+#[derive(Copy, Clone, Default, Debug, DeriveEntity)]
+pub struct Entity;
+
+impl EntityName for Entity {
+    fn table_name(&self) -> &str {
+        "group_roles"
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
+pub enum Column {
+    GroupId,
+    RoleId,
+    InsertedAt,
+}
+
+impl ColumnTrait for Column {
+    type EntityName = Entity;
+
+    fn def(&self) -> ColumnDef {
+        match self {
+            Self::GroupId => ColumnType::Integer.def(),
+            Self::RoleId => ColumnType::Integer.def(),
+            Self::InsertedAt => ColumnType::DateTime.def().nullable(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
+pub enum PrimaryKey {
+    GroupId,
+    RoleId,
+}
+
+impl PrimaryKeyTrait for PrimaryKey {
+    type ValueType = (i32, i32);
+
+    fn auto_increment() -> bool {
+        false
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {
+    #[cfg(feature = "db-timestamps")]
+    fn before_save(mut self, insert: bool) -> Result<Self, DbErr> {
+        let timestamp = chrono::Utc::now().naive_utc();
+
+        // Inserted timestamp
+        if let (&sea_orm::ActiveValue::NotSet, true) = (&self.inserted_at, insert) {
+            trace!("Setting inserted_at timestamp for group_role");
+            self.inserted_at = sea_orm::ActiveValue::Set(Some(timestamp));
+        }
+
+        Ok(self)
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {
+    Group,
+    Role,
+}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        match self {
+            Relation::Group => Entity::belongs_to(crate::models::group::Entity)
+                .from(Column::GroupId)
+                .to(crate::models::group::Column::Id)
+                .on_update(ForeignKeyAction::Cascade)
+                .on_delete(ForeignKeyAction::Cascade)
+                .into(),
+            Relation::Role => Entity::belongs_to(crate::models::role::Entity)
+                .from(Column::RoleId)
+                .to(crate::models::role::Column::Id)
+                .on_update(ForeignKeyAction::Cascade)
+                .on_delete(ForeignKeyAction::Cascade)
+                .into(),
+        }
+    }
+}
+
+impl Related<crate::models::group::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Group.def()
+    }
+}
+
+impl Related<crate::models::role::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Role.def()
+    }
+}
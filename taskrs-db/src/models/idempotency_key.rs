@@ -0,0 +1,94 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, Default, DeriveModel, DeriveActiveModel)]
+pub struct Model {
+    pub id: i32,
+    /// Hash of the session the key was sent with, keys of different sessions never collide
+    pub scope: String,
+    pub key: String,
+    /// Hash of method, uri and body of the request
+    pub fingerprint: String,
+    /// Status of the stored response, `None` while the request is still running
+    pub response_status: Option<i32>,
+    pub response_content_type: Option<String>,
+    pub response_body: Option<String>,
+    /// Further headers of the stored response as JSON array of name and value pairs
+    pub response_headers: Option<String>,
+    /// End of the lease of a request that is still running
+    pub locked_until: Option<DateTime>,
+    pub expires_at: DateTime,
+    pub inserted_at: Option<DateTime>,
+}
+
+#[derive(Copy, Clone, Default, Debug, DeriveEntity)]
+pub struct Entity;
+
+impl EntityName for Entity {
+    fn table_name(&self) -> &str {
+        "idempotency_keys"
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
+pub enum Column {
+    Id,
+    Scope,
+    Key,
+    Fingerprint,
+    ResponseStatus,
+    ResponseContentType,
+    ResponseBody,
+    ResponseHeaders,
+    LockedUntil,
+    ExpiresAt,
+    InsertedAt,
+}
+
+impl ColumnTrait for Column {
+    type EntityName = Entity;
+
+    fn def(&self) -> ColumnDef {
+        match self {
+            Self::Id => ColumnType::Integer.def(),
+            Self::Scope => ColumnType::String(Some(64)).def(),
+            Self::Key => ColumnType::String(Some(255)).def(),
+            Self::Fingerprint => ColumnType::String(Some(64)).def(),
+            Self::ResponseStatus => ColumnType::Integer.def().nullable(),
+            Self::ResponseContentType => ColumnType::String(Some(255)).def().nullable(),
+            Self::ResponseBody => ColumnType::Text.def().nullable(),
+            Self::ResponseHeaders => ColumnType::Text.def().nullable(),
+            Self::LockedUntil => ColumnType::DateTime.def().nullable(),
+            Self::ExpiresAt => ColumnType::DateTime.def(),
+            Self::InsertedAt => ColumnType::DateTime.def().nullable(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
+pub enum PrimaryKey {
+    Id,
+}
+
+impl PrimaryKeyTrait for PrimaryKey {
+    type ValueType = i32;
+
+    fn auto_increment() -> bool {
+        true
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {
+    #[cfg(feature = "db-timestamps")]
+    fn before_save(mut self, insert: bool) -> Result<Self, DbErr> {
+        // Inserted timestamp
+        if let (&sea_orm::ActiveValue::NotSet, true) = (&self.inserted_at, insert) {
+            trace!("Setting inserted_at timestamp for idempotency_key");
+            self.inserted_at = sea_orm::ActiveValue::Set(Some(chrono::Utc::now().naive_utc()));
+        }
+
+        Ok(self)
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
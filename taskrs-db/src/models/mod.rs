@@ -1,3 +1,8 @@
+pub mod audit_event;
+pub mod group;
+pub mod group_member;
+pub mod group_role;
+pub mod idempotency_key;
 pub mod permission;
 pub mod refresh_token;
 pub mod role;
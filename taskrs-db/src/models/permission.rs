@@ -1,4 +1,5 @@
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Default, DeriveModel, DeriveActiveModel)]
 pub struct Model {
@@ -120,3 +121,28 @@ impl Related<crate::models::user::Entity> for Entity {
         )
     }
 }
+
+/// Effect of a permission rule assigned to a user or role.
+/// A `Deny` rule always overrides any `Allow` rule for the same permission.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    EnumIter,
+    DeriveActiveEnum,
+    Deserialize,
+    Serialize,
+)]
+#[serde(rename_all = "camelCase")]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+pub enum PermissionEffect {
+    #[default]
+    #[sea_orm(string_value = "allow")]
+    Allow,
+    #[sea_orm(string_value = "deny")]
+    Deny,
+}
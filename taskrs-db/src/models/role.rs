@@ -5,8 +5,10 @@ pub struct Model {
     pub id: i32,
     pub name: String,
     pub description: Option<String>,
+    pub is_system: bool,
     pub inserted_at: Option<DateTime>,
     pub updated_at: Option<DateTime>,
+    pub version: i32,
 }
 
 #[derive(Copy, Clone, Default, Debug, DeriveEntity)]
@@ -23,8 +25,10 @@ pub enum Column {
     Id,
     Name,
     Description,
+    IsSystem,
     InsertedAt,
     UpdatedAt,
+    Version,
 }
 
 impl ColumnTrait for Column {
@@ -35,8 +39,10 @@ impl ColumnTrait for Column {
             Self::Id => ColumnType::Integer.def(),
             Self::Name => ColumnType::String(Some(256)).def(),
             Self::Description => ColumnType::String(None).def().nullable(),
+            Self::IsSystem => ColumnType::Boolean.def(),
             Self::InsertedAt => ColumnType::DateTime.def().nullable(),
             Self::UpdatedAt => ColumnType::DateTime.def().nullable(),
+            Self::Version => ColumnType::Integer.def(),
         }
     }
 }
@@ -77,6 +83,7 @@ impl ActiveModelBehavior for ActiveModel {
 
 #[derive(Copy, Clone, Debug, EnumIter)]
 pub enum Relation {
+    Group,
     Permission,
     User,
 }
@@ -84,12 +91,23 @@ pub enum Relation {
 impl RelationTrait for Relation {
     fn def(&self) -> RelationDef {
         match self {
+            Relation::Group => Entity::has_many(crate::models::group::Entity).into(),
             Relation::Permission => Entity::has_many(crate::models::permission::Entity).into(),
             Relation::User => Entity::has_many(crate::models::user::Entity).into(),
         }
     }
 }
 
+impl Related<crate::models::group::Entity> for Entity {
+    fn to() -> RelationDef {
+        crate::models::group_role::Relation::Group.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(crate::models::group_role::Relation::Role.def().rev())
+    }
+}
+
 impl Related<crate::models::permission::Entity> for Entity {
     fn to() -> RelationDef {
         crate::models::role_permission::Relation::Permission.def()
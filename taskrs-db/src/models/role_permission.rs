@@ -1,9 +1,11 @@
+use crate::models::permission::PermissionEffect;
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, Default, DeriveModel, DeriveActiveModel)]
 pub struct Model {
     pub role_id: i32,
     pub permission_id: i32,
+    pub effect: PermissionEffect,
     pub inserted_at: Option<DateTime>,
 }
 
@@ -20,6 +22,7 @@ impl EntityName for Entity {
 pub enum Column {
     RoleId,
     PermissionId,
+    Effect,
     InsertedAt,
 }
 
@@ -30,6 +33,7 @@ impl ColumnTrait for Column {
         match self {
             Self::RoleId => ColumnType::Integer.def(),
             Self::PermissionId => ColumnType::Integer.def(),
+            Self::Effect => PermissionEffect::db_type(),
             Self::InsertedAt => ColumnType::DateTime.def().nullable(),
         }
     }
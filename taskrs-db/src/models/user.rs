@@ -10,6 +10,8 @@ pub struct Model {
     pub enabled: bool,
     pub inserted_at: Option<DateTime>,
     pub updated_at: Option<DateTime>,
+    pub deleted_at: Option<DateTime>,
+    pub version: i32,
 }
 
 #[derive(Copy, Clone, Default, Debug, DeriveEntity)]
@@ -31,6 +33,8 @@ pub enum Column {
     Enabled,
     InsertedAt,
     UpdatedAt,
+    DeletedAt,
+    Version,
 }
 
 impl ColumnTrait for Column {
@@ -46,6 +50,8 @@ impl ColumnTrait for Column {
             Self::Enabled => ColumnType::Boolean.def(),
             Self::InsertedAt => ColumnType::DateTime.def().nullable(),
             Self::UpdatedAt => ColumnType::DateTime.def().nullable(),
+            Self::DeletedAt => ColumnType::DateTime.def().nullable(),
+            Self::Version => ColumnType::Integer.def(),
         }
     }
 }
@@ -86,6 +92,7 @@ impl ActiveModelBehavior for ActiveModel {
 
 #[derive(Copy, Clone, Debug, EnumIter)]
 pub enum Relation {
+    Group,
     Permission,
     RefreshToken,
     Role,
@@ -94,6 +101,7 @@ pub enum Relation {
 impl RelationTrait for Relation {
     fn def(&self) -> RelationDef {
         match self {
+            Self::Group => Entity::has_many(crate::models::group::Entity).into(),
             Self::Permission => Entity::has_many(crate::models::permission::Entity).into(),
             Self::RefreshToken => Entity::has_many(crate::models::refresh_token::Entity).into(),
             Self::Role => Entity::has_many(crate::models::role::Entity).into(),
@@ -101,6 +109,16 @@ impl RelationTrait for Relation {
     }
 }
 
+impl Related<crate::models::group::Entity> for Entity {
+    fn to() -> RelationDef {
+        crate::models::group_member::Relation::Group.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(crate::models::group_member::Relation::User.def().rev())
+    }
+}
+
 impl Related<crate::models::permission::Entity> for Entity {
     fn to() -> RelationDef {
         crate::models::user_permission::Relation::Permission.def()
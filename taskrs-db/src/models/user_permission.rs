@@ -1,9 +1,13 @@
+use crate::models::permission::PermissionEffect;
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, Default, DeriveModel, DeriveActiveModel)]
 pub struct Model {
     pub user_id: i32,
     pub permission_id: i32,
+    pub effect: PermissionEffect,
+    pub valid_from: Option<DateTime>,
+    pub valid_until: Option<DateTime>,
     pub inserted_at: Option<DateTime>,
 }
 
@@ -20,6 +24,9 @@ impl EntityName for Entity {
 pub enum Column {
     UserId,
     PermissionId,
+    Effect,
+    ValidFrom,
+    ValidUntil,
     InsertedAt,
 }
 
@@ -30,6 +37,9 @@ impl ColumnTrait for Column {
         match self {
             Self::UserId => ColumnType::Integer.def(),
             Self::PermissionId => ColumnType::Integer.def(),
+            Self::Effect => PermissionEffect::db_type(),
+            Self::ValidFrom => ColumnType::DateTime.def().nullable(),
+            Self::ValidUntil => ColumnType::DateTime.def().nullable(),
             Self::InsertedAt => ColumnType::DateTime.def().nullable(),
         }
     }
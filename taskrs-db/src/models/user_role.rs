@@ -4,6 +4,8 @@ use sea_orm::entity::prelude::*;
 pub struct Model {
     pub user_id: i32,
     pub role_id: i32,
+    pub valid_from: Option<DateTime>,
+    pub valid_until: Option<DateTime>,
     pub inserted_at: Option<DateTime>,
 }
 
@@ -20,6 +22,8 @@ impl EntityName for Entity {
 pub enum Column {
     UserId,
     RoleId,
+    ValidFrom,
+    ValidUntil,
     InsertedAt,
 }
 
@@ -30,6 +34,8 @@ impl ColumnTrait for Column {
         match self {
             Self::UserId => ColumnType::Integer.def(),
             Self::RoleId => ColumnType::Integer.def(),
+            Self::ValidFrom => ColumnType::DateTime.def().nullable(),
+            Self::ValidUntil => ColumnType::DateTime.def().nullable(),
             Self::InsertedAt => ColumnType::DateTime.def().nullable(),
         }
     }
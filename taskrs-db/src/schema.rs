@@ -0,0 +1,491 @@
+use crate::migrations::{lock, migration};
+use crate::models::{
+    audit_event, group, group_member, group_role, idempotency_key, permission, refresh_token, role,
+    role_permission, user, user_permission, user_role,
+};
+use itertools::Itertools;
+use sea_orm::sea_query::{ColumnSpec, ForeignKeyAction, TableCreateStatement};
+use sea_orm::{
+    ConnectionTrait, DbBackend, DbConn, DbErr, EntityName, EntityTrait, Schema, Statement,
+};
+use std::collections::HashMap;
+
+/// Compares the live schema against the tables the entities in [`crate::models`] would create.
+/// Tables, columns, their nullability and the indexes and foreign keys the entities declare are compared.
+/// Column types are not, their names differ between backends,
+/// and indexes added by migrations only for lookups are no difference either.
+/// Neither are search structures, which are virtual tables on SQLite and generated columns on Postgres.
+pub async fn check(db: &DbConn) -> Result<Vec<SchemaDifference>, DbErr> {
+    let expected = entity_tables(db.get_database_backend());
+    let live_tables = table_names(db).await?;
+    let mut differences = Vec::new();
+
+    for (table, stmt) in &expected {
+        if !live_tables.contains(table) {
+            differences.push(SchemaDifference::MissingTable(table.clone()));
+            continue;
+        }
+
+        let live_columns = columns(db, table).await?;
+        for column in stmt.get_columns() {
+            let name = column.get_column_name();
+            let specs = column.get_column_spec();
+            let nullable = !specs
+                .iter()
+                .any(|spec| matches!(spec, ColumnSpec::NotNull | ColumnSpec::PrimaryKey));
+            match live_columns.get(&name) {
+                None => differences.push(SchemaDifference::MissingColumn {
+                    table: table.clone(),
+                    column: name,
+                }),
+                Some(live_nullable) if *live_nullable != nullable => {
+                    differences.push(SchemaDifference::Nullability {
+                        table: table.clone(),
+                        column: name,
+                        nullable,
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        for column in live_columns.keys().sorted() {
+            if !stmt
+                .get_columns()
+                .iter()
+                .any(|c| &c.get_column_name() == column)
+            {
+                differences.push(SchemaDifference::UnexpectedColumn {
+                    table: table.clone(),
+                    column: column.clone(),
+                });
+            }
+        }
+
+        let live_indexes = indexes(db, table).await?;
+        let unique_columns = stmt.get_columns().iter().filter(|column| {
+            column
+                .get_column_spec()
+                .iter()
+                .any(|spec| matches!(spec, ColumnSpec::UniqueKey))
+        });
+        for column in unique_columns {
+            let columns = vec![column.get_column_name()];
+            if !live_indexes
+                .iter()
+                .any(|index| index.unique && index.columns == columns)
+            {
+                differences.push(SchemaDifference::MissingIndex {
+                    table: table.clone(),
+                    columns,
+                    unique: true,
+                });
+            }
+        }
+        for index in stmt.get_indexes() {
+            if index.is_primary_key() {
+                continue;
+            }
+            let columns = index.get_index_spec().get_column_names();
+            let unique = index.is_unique_key();
+            // An index also serves lookups by a prefix of its columns
+            if !live_indexes.iter().any(|live| {
+                live.columns.starts_with(&columns) && (!unique || live.columns == columns)
+            }) {
+                differences.push(SchemaDifference::MissingIndex {
+                    table: table.clone(),
+                    columns,
+                    unique,
+                });
+            }
+        }
+
+        let live_foreign_keys = foreign_keys(db, table).await?;
+        for foreign_key in stmt.get_foreign_key_create_stmts() {
+            let foreign_key = foreign_key.get_foreign_key();
+            let columns = foreign_key.get_columns();
+            let ref_table = foreign_key.get_ref_table().unwrap_or_default();
+            let ref_columns = foreign_key.get_ref_columns();
+            let on_delete = action_name(foreign_key.get_on_delete());
+            match live_foreign_keys.iter().find(|live| {
+                live.columns == columns
+                    && live.ref_table == ref_table
+                    && live.ref_columns == ref_columns
+            }) {
+                None => differences.push(SchemaDifference::MissingForeignKey {
+                    table: table.clone(),
+                    columns,
+                    ref_table,
+                    ref_columns,
+                }),
+                // MySQL reports the default as `RESTRICT`, which behaves like `NO ACTION` there
+                Some(live)
+                    if live.on_delete != on_delete
+                        && !(live.on_delete == "RESTRICT" && on_delete == "NO ACTION") =>
+                {
+                    differences.push(SchemaDifference::ForeignKeyOnDelete {
+                        table: table.clone(),
+                        columns,
+                        on_delete: on_delete.to_string(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    let internal_table = migration::Entity.table_name();
+    for table in live_tables {
+        if table != internal_table
+            && table != lock::LOCK_TABLE
+            && !expected.iter().any(|(name, _)| name == &table)
+        {
+            differences.push(SchemaDifference::UnexpectedTable(table));
+        }
+    }
+
+    Ok(differences)
+}
+
+/// Difference between the live schema and the entities
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SchemaDifference {
+    MissingTable(String),
+    UnexpectedTable(String),
+    MissingColumn {
+        table: String,
+        column: String,
+    },
+    UnexpectedColumn {
+        table: String,
+        column: String,
+    },
+    /// The entity expects the column to be `nullable`, the live column is not or vice versa
+    Nullability {
+        table: String,
+        column: String,
+        nullable: bool,
+    },
+    MissingIndex {
+        table: String,
+        columns: Vec<String>,
+        unique: bool,
+    },
+    MissingForeignKey {
+        table: String,
+        columns: Vec<String>,
+        ref_table: String,
+        ref_columns: Vec<String>,
+    },
+    /// The live foreign key does something else than `on_delete` when the referenced row is deleted
+    ForeignKeyOnDelete {
+        table: String,
+        columns: Vec<String>,
+        on_delete: String,
+    },
+}
+
+impl std::fmt::Display for SchemaDifference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaDifference::MissingTable(table) => write!(f, "Table `{}` is missing", table),
+            SchemaDifference::UnexpectedTable(table) => {
+                write!(f, "Table `{}` belongs to no entity", table)
+            }
+            SchemaDifference::MissingColumn { table, column } => {
+                write!(f, "Column `{}`.`{}` is missing", table, column)
+            }
+            SchemaDifference::UnexpectedColumn { table, column } => write!(
+                f,
+                "Column `{}`.`{}` belongs to no entity field",
+                table, column
+            ),
+            SchemaDifference::Nullability {
+                table,
+                column,
+                nullable,
+            } => write!(
+                f,
+                "Column `{}`.`{}` should be {}",
+                table,
+                column,
+                if *nullable { "nullable" } else { "NOT NULL" }
+            ),
+            SchemaDifference::MissingIndex {
+                table,
+                columns,
+                unique,
+            } => write!(
+                f,
+                "{} on `{}` ({}) is missing",
+                if *unique { "Unique index" } else { "Index" },
+                table,
+                columns.join(", ")
+            ),
+            SchemaDifference::MissingForeignKey {
+                table,
+                columns,
+                ref_table,
+                ref_columns,
+            } => write!(
+                f,
+                "Foreign key on `{}` ({}) referencing `{}` ({}) is missing",
+                table,
+                columns.join(", "),
+                ref_table,
+                ref_columns.join(", ")
+            ),
+            SchemaDifference::ForeignKeyOnDelete {
+                table,
+                columns,
+                on_delete,
+            } => write!(
+                f,
+                "Foreign key on `{}` ({}) should be ON DELETE {}",
+                table,
+                columns.join(", "),
+                on_delete
+            ),
+        }
+    }
+}
+
+/// Tables of all entities by their name
+fn entity_tables(backend: DbBackend) -> Vec<(String, TableCreateStatement)> {
+    let schema = Schema::new(backend);
+    vec![
+        entity_table(&schema, audit_event::Entity),
+        entity_table(&schema, group::Entity),
+        entity_table(&schema, group_member::Entity),
+        entity_table(&schema, group_role::Entity),
+        entity_table(&schema, idempotency_key::Entity),
+        entity_table(&schema, permission::Entity),
+        entity_table(&schema, refresh_token::Entity),
+        entity_table(&schema, role::Entity),
+        entity_table(&schema, role_permission::Entity),
+        entity_table(&schema, user::Entity),
+        entity_table(&schema, user_permission::Entity),
+        entity_table(&schema, user_role::Entity),
+    ]
+}
+
+fn entity_table<E: EntityTrait>(schema: &Schema, entity: E) -> (String, TableCreateStatement) {
+    (
+        entity.table_name().to_string(),
+        schema.create_table_from_entity(entity),
+    )
+}
+
+/// Index of a live table, `columns` are in the order of the index
+struct LiveIndex {
+    unique: bool,
+    columns: Vec<String>,
+}
+
+/// Foreign key of a live table, the columns are in the order of the key
+struct LiveForeignKey {
+    columns: Vec<String>,
+    ref_table: String,
+    ref_columns: Vec<String>,
+    /// Referential action as reported by the database, e.g. `CASCADE`
+    on_delete: String,
+}
+
+/// Referential action like the databases report it, `NO ACTION` is their default
+fn action_name(action: Option<ForeignKeyAction>) -> &'static str {
+    match action {
+        Some(ForeignKeyAction::Restrict) => "RESTRICT",
+        Some(ForeignKeyAction::Cascade) => "CASCADE",
+        Some(ForeignKeyAction::SetNull) => "SET NULL",
+        Some(ForeignKeyAction::SetDefault) => "SET DEFAULT",
+        Some(ForeignKeyAction::NoAction) | None => "NO ACTION",
+    }
+}
+
+/// Whether the table exists in the current database or schema
+pub(crate) async fn table_exists(db: &DbConn, table: &str) -> Result<bool, DbErr> {
+    Ok(table_names(db).await?.iter().any(|name| name == table))
+}
+
+/// Tables of the current database or schema
+async fn table_names(db: &DbConn) -> Result<Vec<String>, DbErr> {
+    let sql = match db.get_database_backend() {
+        DbBackend::MySql => {
+            "SELECT table_name AS table_name FROM information_schema.tables \
+            WHERE table_schema = DATABASE() AND table_type = 'BASE TABLE' ORDER BY table_name"
+        }
+        DbBackend::Postgres => {
+            "SELECT table_name::text AS table_name FROM information_schema.tables \
+            WHERE table_schema = current_schema() AND table_type = 'BASE TABLE' ORDER BY table_name"
+        }
+        DbBackend::Sqlite => {
+            "SELECT t.name AS table_name FROM sqlite_master AS t \
+            WHERE t.type = 'table' AND t.name NOT LIKE 'sqlite_%' \
+            AND NOT EXISTS (SELECT 1 FROM sqlite_master AS v \
+                WHERE v.type = 'table' AND v.sql LIKE 'CREATE VIRTUAL TABLE%' \
+                AND (t.name = v.name OR t.name LIKE v.name || '\\_%' ESCAPE '\\')) \
+            ORDER BY t.name"
+        }
+    };
+
+    query(db, sql, None)
+        .await?
+        .iter()
+        .map(|row| row.try_get("", "table_name"))
+        .collect()
+}
+
+/// Whether the column exists in the table
+pub(crate) async fn column_exists(db: &DbConn, table: &str, column: &str) -> Result<bool, DbErr> {
+    Ok(columns(db, table).await?.contains_key(column))
+}
+
+/// Columns of the table by their name, with whether they are nullable
+async fn columns(db: &DbConn, table: &str) -> Result<HashMap<String, bool>, DbErr> {
+    let sql = match db.get_database_backend() {
+        DbBackend::MySql => {
+            "SELECT column_name AS column_name, \
+            CAST(CASE WHEN is_nullable = 'YES' THEN 1 ELSE 0 END AS SIGNED) AS nullable \
+            FROM information_schema.columns WHERE table_schema = DATABASE() AND table_name = ?"
+        }
+        DbBackend::Postgres => {
+            "SELECT column_name::text AS column_name, \
+            (CASE WHEN is_nullable = 'YES' THEN 1 ELSE 0 END)::bigint AS nullable \
+            FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = $1 \
+            AND is_generated = 'NEVER'"
+        }
+        DbBackend::Sqlite => {
+            "SELECT name AS column_name, 1 - \"notnull\" AS nullable FROM pragma_table_info(?)"
+        }
+    };
+
+    query(db, sql, Some(table))
+        .await?
+        .iter()
+        .map(|row| {
+            let name: String = row.try_get("", "column_name")?;
+            let nullable: i64 = row.try_get("", "nullable")?;
+            Ok((name, nullable != 0))
+        })
+        .collect()
+}
+
+/// Indexes of the table, including the ones backing primary keys and unique constraints
+async fn indexes(db: &DbConn, table: &str) -> Result<Vec<LiveIndex>, DbErr> {
+    let sql = match db.get_database_backend() {
+        DbBackend::MySql => {
+            "SELECT index_name AS index_name, \
+            CAST(CASE WHEN non_unique = 0 THEN 1 ELSE 0 END AS SIGNED) AS is_unique, \
+            column_name AS column_name FROM information_schema.statistics \
+            WHERE table_schema = DATABASE() AND table_name = ? ORDER BY index_name, seq_in_index"
+        }
+        DbBackend::Postgres => {
+            "SELECT i.relname::text AS index_name, \
+            (CASE WHEN ix.indisunique THEN 1 ELSE 0 END)::bigint AS is_unique, \
+            a.attname::text AS column_name FROM pg_index ix \
+            JOIN pg_class t ON t.oid = ix.indrelid \
+            JOIN pg_class i ON i.oid = ix.indexrelid \
+            JOIN pg_namespace n ON n.oid = t.relnamespace \
+            JOIN pg_attribute a ON a.attrelid = t.oid AND a.attnum = ANY(ix.indkey) \
+            WHERE n.nspname = current_schema() AND t.relname = $1 \
+            ORDER BY i.relname, array_position(ix.indkey::int2[], a.attnum)"
+        }
+        DbBackend::Sqlite => {
+            "SELECT il.name AS index_name, il.\"unique\" AS is_unique, ii.name AS column_name \
+            FROM pragma_index_list(?) AS il JOIN pragma_index_info(il.name) AS ii \
+            ORDER BY il.name, ii.seqno"
+        }
+    };
+
+    let mut indexes: Vec<(String, LiveIndex)> = Vec::new();
+    for row in query(db, sql, Some(table)).await? {
+        let name: String = row.try_get("", "index_name")?;
+        let unique: i64 = row.try_get("", "is_unique")?;
+        let column: String = row.try_get("", "column_name")?;
+        match indexes.last_mut() {
+            Some((last, index)) if last == &name => index.columns.push(column),
+            _ => indexes.push((
+                name,
+                LiveIndex {
+                    unique: unique != 0,
+                    columns: vec![column],
+                },
+            )),
+        }
+    }
+
+    Ok(indexes.into_iter().map(|(_, index)| index).collect())
+}
+
+/// Foreign keys of the table
+async fn foreign_keys(db: &DbConn, table: &str) -> Result<Vec<LiveForeignKey>, DbErr> {
+    let sql = match db.get_database_backend() {
+        DbBackend::MySql => {
+            "SELECT rc.constraint_name AS key_name, kcu.column_name AS column_name, \
+            kcu.referenced_table_name AS ref_table, kcu.referenced_column_name AS ref_column, \
+            rc.delete_rule AS on_delete FROM information_schema.referential_constraints AS rc \
+            JOIN information_schema.key_column_usage AS kcu \
+                ON kcu.constraint_schema = rc.constraint_schema \
+                AND kcu.constraint_name = rc.constraint_name AND kcu.table_name = rc.table_name \
+            WHERE rc.constraint_schema = DATABASE() AND rc.table_name = ? \
+            ORDER BY rc.constraint_name, kcu.ordinal_position"
+        }
+        DbBackend::Postgres => {
+            "SELECT rc.constraint_name::text AS key_name, kcu.column_name::text AS column_name, \
+            rkcu.table_name::text AS ref_table, rkcu.column_name::text AS ref_column, \
+            rc.delete_rule::text AS on_delete FROM information_schema.referential_constraints AS rc \
+            JOIN information_schema.key_column_usage AS kcu \
+                ON kcu.constraint_schema = rc.constraint_schema \
+                AND kcu.constraint_name = rc.constraint_name \
+            JOIN information_schema.key_column_usage AS rkcu \
+                ON rkcu.constraint_schema = rc.unique_constraint_schema \
+                AND rkcu.constraint_name = rc.unique_constraint_name \
+                AND rkcu.ordinal_position = kcu.position_in_unique_constraint \
+            WHERE kcu.table_schema = current_schema() AND kcu.table_name = $1 \
+            ORDER BY rc.constraint_name, kcu.ordinal_position"
+        }
+        DbBackend::Sqlite => {
+            "SELECT CAST(id AS TEXT) AS key_name, \"from\" AS column_name, \
+            \"table\" AS ref_table, \"to\" AS ref_column, on_delete AS on_delete \
+            FROM pragma_foreign_key_list(?) ORDER BY id, seq"
+        }
+    };
+
+    let mut foreign_keys: Vec<(String, LiveForeignKey)> = Vec::new();
+    for row in query(db, sql, Some(table)).await? {
+        let name: String = row.try_get("", "key_name")?;
+        let column: String = row.try_get("", "column_name")?;
+        let ref_column: String = row.try_get("", "ref_column")?;
+        match foreign_keys.last_mut() {
+            Some((last, foreign_key)) if last == &name => {
+                foreign_key.columns.push(column);
+                foreign_key.ref_columns.push(ref_column);
+            }
+            _ => foreign_keys.push((
+                name,
+                LiveForeignKey {
+                    columns: vec![column],
+                    ref_table: row.try_get("", "ref_table")?,
+                    ref_columns: vec![ref_column],
+                    on_delete: row.try_get("", "on_delete")?,
+                },
+            )),
+        }
+    }
+
+    Ok(foreign_keys
+        .into_iter()
+        .map(|(_, foreign_key)| foreign_key)
+        .collect())
+}
+
+async fn query(
+    db: &DbConn,
+    sql: &str,
+    table: Option<&str>,
+) -> Result<Vec<sea_orm::QueryResult>, DbErr> {
+    let backend = db.get_database_backend();
+    let values = table.map(|table| vec![table.into()]).unwrap_or_default();
+
+    db.query_all(Statement::from_sql_and_values(backend, sql, values))
+        .await
+}
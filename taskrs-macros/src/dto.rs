@@ -0,0 +1,919 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{
+    Attribute, Data, DeriveInput, Error, Fields, GenericArgument, Ident, Lit, Meta, NestedMeta,
+    Path, PathArguments, Result, Type,
+};
+
+/// Models to derive, given by the attributes of the definition
+struct Dto {
+    model: Path,
+    read: Ident,
+    create: Option<Ident>,
+    update: Option<Ident>,
+    fields: Vec<Field>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FieldKind {
+    Normal,
+    PrimaryKey,
+    Version,
+    ReadOnly,
+    WriteOnly,
+    Skip,
+}
+
+struct Field {
+    /// Field of the database model
+    column: Ident,
+    /// Field of the derived models
+    name: Ident,
+    ty: Type,
+    kind: FieldKind,
+    default: bool,
+    /// Columns gathered into the struct of the field, which has a field for each of them
+    flatten: Vec<Ident>,
+    docs: Vec<Attribute>,
+}
+
+impl Field {
+    /// Serde attribute of a field that gathers columns
+    fn serde_flatten(&self) -> TokenStream {
+        match self.flatten.is_empty() {
+            true => TokenStream::new(),
+            false => quote! { #[serde(flatten)] },
+        }
+    }
+
+    /// Documentation of the field in the create and update models.
+    /// Only write only fields keep their documentation, as they are not part of the read model.
+    fn write_docs(&self) -> &[Attribute] {
+        match self.kind {
+            FieldKind::WriteOnly => &self.docs,
+            _ => &[],
+        }
+    }
+
+    /// Inner type if the field is nullable
+    fn option_inner(&self) -> Option<&Type> {
+        let path = match &self.ty {
+            Type::Path(path) if path.qself.is_none() => &path.path,
+            _ => return None,
+        };
+        let segment = path.segments.last()?;
+        if segment.ident != "Option" {
+            return None;
+        }
+
+        match &segment.arguments {
+            PathArguments::AngleBracketed(arguments) => match arguments.args.first() {
+                Some(GenericArgument::Type(ty)) if arguments.args.len() == 1 => Some(ty),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+pub fn expand(input: DeriveInput) -> Result<TokenStream> {
+    let dto = parse(input)?;
+
+    let read = expand_read(&dto);
+    let create = match &dto.create {
+        Some(create) if *create == dto.read => expand_create_for_read(&dto, create)?,
+        Some(create) => expand_create(&dto, create),
+        None => TokenStream::new(),
+    };
+    let update = match &dto.update {
+        Some(update) => expand_update(&dto, update),
+        None => TokenStream::new(),
+    };
+
+    Ok(quote! {
+        #read
+        #create
+        #update
+    })
+}
+
+fn parse(input: DeriveInput) -> Result<Dto> {
+    let mut model = None;
+    let mut read = None;
+    let mut create = None;
+    let mut update = None;
+
+    for nested in dto_attributes(&input.attrs)? {
+        let (key, value) = match &nested {
+            NestedMeta::Meta(Meta::NameValue(name_value)) => match &name_value.lit {
+                Lit::Str(value) => (&name_value.path, value),
+                lit => return Err(Error::new(lit.span(), "Expected a string")),
+            },
+            _ => return Err(Error::new(nested.span(), "Expected `key = \"value\"`")),
+        };
+
+        if key.is_ident("model") {
+            model = Some(value.parse::<Path>()?);
+        } else if key.is_ident("read") {
+            read = Some(value.parse::<Ident>()?);
+        } else if key.is_ident("create") {
+            create = Some(value.parse::<Ident>()?);
+        } else if key.is_ident("update") {
+            update = Some(value.parse::<Ident>()?);
+        } else {
+            return Err(Error::new(key.span(), "Unknown attribute"));
+        }
+    }
+
+    let missing = |name: &str| Error::new(input.ident.span(), format!("Missing `{}`", name));
+    let model = model.ok_or_else(|| missing("model"))?;
+    let read = read.ok_or_else(|| missing("read"))?;
+
+    let named = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new(input.ident.span(), "Expected named fields")),
+        },
+        _ => return Err(Error::new(input.ident.span(), "Expected a struct")),
+    };
+
+    let fields = named
+        .iter()
+        .map(parse_field)
+        .collect::<Result<Vec<Field>>>()?;
+
+    if update.is_some() {
+        if let Some(field) = fields.iter().find(|field| !field.flatten.is_empty()) {
+            return Err(Error::new(
+                field.column.span(),
+                "`flatten` is not supported by update models",
+            ));
+        }
+    }
+
+    Ok(Dto {
+        model,
+        read,
+        create,
+        update,
+        fields,
+    })
+}
+
+fn parse_field(field: &syn::Field) -> Result<Field> {
+    let column = field.ident.clone().expect("Named fields have an ident");
+    let mut name = column.clone();
+    let mut kind = FieldKind::Normal;
+    let mut default = false;
+    let mut flatten = Vec::new();
+
+    for nested in dto_attributes(&field.attrs)? {
+        match &nested {
+            NestedMeta::Meta(Meta::Path(path)) => {
+                let field_kind = if path.is_ident("primary_key") {
+                    FieldKind::PrimaryKey
+                } else if path.is_ident("version") {
+                    FieldKind::Version
+                } else if path.is_ident("read_only") {
+                    FieldKind::ReadOnly
+                } else if path.is_ident("write_only") {
+                    FieldKind::WriteOnly
+                } else if path.is_ident("skip") {
+                    FieldKind::Skip
+                } else if path.is_ident("default") {
+                    default = true;
+                    continue;
+                } else {
+                    return Err(Error::new(path.span(), "Unknown attribute"));
+                };
+
+                if kind != FieldKind::Normal {
+                    return Err(Error::new(path.span(), "Conflicting attribute"));
+                }
+                kind = field_kind;
+            }
+            NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("flatten") => {
+                for nested in &list.nested {
+                    match nested {
+                        NestedMeta::Meta(Meta::Path(path)) if path.get_ident().is_some() => {
+                            flatten.push(path.get_ident().cloned().expect("Checked by the guard"))
+                        }
+                        _ => return Err(Error::new(nested.span(), "Expected a column")),
+                    }
+                }
+                if flatten.is_empty() {
+                    return Err(Error::new(list.span(), "Expected columns"));
+                }
+            }
+            NestedMeta::Meta(Meta::NameValue(name_value)) if name_value.path.is_ident("rename") => {
+                match &name_value.lit {
+                    Lit::Str(value) => name = value.parse::<Ident>()?,
+                    lit => return Err(Error::new(lit.span(), "Expected a string")),
+                }
+            }
+            _ => return Err(Error::new(nested.span(), "Unknown attribute")),
+        }
+    }
+
+    if !flatten.is_empty() && (kind != FieldKind::Normal || default) {
+        return Err(Error::new(
+            column.span(),
+            "`flatten` can not be combined with other attributes",
+        ));
+    }
+
+    if default && !matches!(kind, FieldKind::Normal | FieldKind::WriteOnly) {
+        return Err(Error::new(
+            column.span(),
+            "`default` only applies to fields set on create",
+        ));
+    }
+
+    let docs = field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("doc"))
+        .cloned()
+        .collect();
+
+    Ok(Field {
+        column,
+        name,
+        ty: field.ty.clone(),
+        kind,
+        default,
+        flatten,
+        docs,
+    })
+}
+
+/// Nested metas of all `#[dto(...)]` attributes
+fn dto_attributes(attrs: &[Attribute]) -> Result<Vec<NestedMeta>> {
+    let mut nested = vec![];
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("dto")) {
+        match attr.parse_meta()? {
+            Meta::List(list) => nested.extend(list.nested),
+            meta => return Err(Error::new(meta.span(), "Expected `dto(...)`")),
+        }
+    }
+
+    Ok(nested)
+}
+
+/// Name of the entity in documentation, e.g. `refresh token` for `RefreshToken`
+fn entity_noun(read: &Ident) -> String {
+    let mut noun = String::new();
+    for (i, c) in read.to_string().chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            noun.push(' ');
+        }
+        noun.extend(c.to_lowercase());
+    }
+
+    noun
+}
+
+fn expand_read(dto: &Dto) -> TokenStream {
+    let Dto { model, read, .. } = dto;
+    let fields: Vec<&Field> = dto
+        .fields
+        .iter()
+        .filter(|field| !matches!(field.kind, FieldKind::WriteOnly | FieldKind::Skip))
+        .collect();
+
+    let definitions = fields.iter().map(|field| {
+        let Field { name, ty, docs, .. } = field;
+        let flatten = field.serde_flatten();
+        quote! {
+            #(#docs)*
+            #flatten
+            pub #name: #ty,
+        }
+    });
+    // Binds every column without `..`, a column missing on the DTO fails to compile
+    let bindings = dto.fields.iter().map(|field| {
+        let column = &field.column;
+        match (field.flatten.as_slice(), &field.kind) {
+            ([], FieldKind::WriteOnly | FieldKind::Skip) => quote! { #column: _, },
+            ([], _) => quote! { #column, },
+            (columns, _) => quote! { #(#columns,)* },
+        }
+    });
+    let conversions = fields.iter().map(|field| {
+        let Field {
+            name, column, ty, ..
+        } = field;
+        match field.flatten.as_slice() {
+            [] if name == column => quote! { #name, },
+            [] => quote! { #name: #column, },
+            columns => quote! { #name: #ty { #(#columns,)* }, },
+        }
+    });
+
+    quote! {
+        #[derive(Clone, Debug, Default, ::serde::Deserialize, ::serde::Serialize)]
+        #[serde(rename_all = "camelCase")]
+        pub struct #read {
+            #(#definitions)*
+        }
+
+        impl ::std::convert::From<#model::Model> for #read {
+            fn from(model: #model::Model) -> Self {
+                let #model::Model {
+                    #(#bindings)*
+                } = model;
+
+                Self {
+                    #(#conversions)*
+                }
+            }
+        }
+    }
+}
+
+/// Fields set on create
+fn create_fields(dto: &Dto) -> impl Iterator<Item = &Field> {
+    dto.fields.iter().filter(|field| {
+        matches!(
+            field.kind,
+            FieldKind::Normal | FieldKind::ReadOnly | FieldKind::WriteOnly
+        )
+    })
+}
+
+/// Body of `into_active_model` for create.
+/// Nullable read only fields are only set if `Some`, others are always set.
+fn create_active_model(dto: &Dto) -> TokenStream {
+    let model = &dto.model;
+    let (optional, set): (Vec<&Field>, Vec<&Field>) = create_fields(dto)
+        .partition(|field| field.kind == FieldKind::ReadOnly && field.option_inner().is_some());
+
+    let set = set.iter().map(|field| {
+        let Field { name, column, .. } = field;
+        match field.flatten.as_slice() {
+            [] => quote! { #column: ::taskrs_db::sea_orm::ActiveValue::Set(self.#name), },
+            columns => quote! {
+                #(#columns: ::taskrs_db::sea_orm::ActiveValue::Set(self.#name.#columns),)*
+            },
+        }
+    });
+    let optional = optional.iter().map(|field| {
+        let Field { name, column, .. } = field;
+        quote! {
+            if let Some(#name) = self.#name {
+                active_model.#column = ::taskrs_db::sea_orm::ActiveValue::Set(Some(#name));
+            }
+        }
+    });
+
+    quote! {
+        #[allow(unused_mut)]
+        let mut active_model = #model::ActiveModel {
+            #(#set)*
+            ..Default::default()
+        };
+
+        #(#optional)*
+
+        active_model
+    }
+}
+
+fn expand_create(dto: &Dto, create: &Ident) -> TokenStream {
+    let model = &dto.model;
+
+    let definitions = create_fields(dto).map(|field| {
+        let Field { name, ty, .. } = field;
+        let docs = field.write_docs();
+        let serde = match field.kind {
+            FieldKind::ReadOnly => quote! { #[serde(skip_deserializing)] },
+            FieldKind::WriteOnly => quote! { #[serde(skip_serializing)] },
+            _ => TokenStream::new(),
+        };
+        let default = match field.default {
+            true => quote! { #[serde(default)] },
+            false => TokenStream::new(),
+        };
+        let flatten = field.serde_flatten();
+
+        quote! {
+            #(#docs)*
+            #serde
+            #default
+            #flatten
+            pub #name: #ty,
+        }
+    });
+    let body = create_active_model(dto);
+
+    quote! {
+        #[derive(Clone, Debug, Default, ::serde::Deserialize, ::serde::Serialize)]
+        #[serde(rename_all = "camelCase")]
+        pub struct #create {
+            #(#definitions)*
+        }
+
+        impl crate::models::IntoActiveModel<#model::ActiveModel> for #create {
+            fn into_active_model(self) -> #model::ActiveModel {
+                #body
+            }
+        }
+    }
+}
+
+/// The read model doubles as create model, e.g. for relations
+fn expand_create_for_read(dto: &Dto, create: &Ident) -> Result<TokenStream> {
+    if let Some(field) = dto
+        .fields
+        .iter()
+        .find(|field| field.kind == FieldKind::WriteOnly)
+    {
+        return Err(Error::new(
+            field.column.span(),
+            "The read model can not be the create model if it has write only fields",
+        ));
+    }
+
+    let model = &dto.model;
+    let body = create_active_model(dto);
+
+    Ok(quote! {
+        impl crate::models::IntoActiveModel<#model::ActiveModel> for #create {
+            fn into_active_model(self) -> #model::ActiveModel {
+                #body
+            }
+        }
+    })
+}
+
+fn expand_update(dto: &Dto, update: &Ident) -> TokenStream {
+    let model = &dto.model;
+    let noun = entity_noun(&dto.read);
+    let fields: Vec<&Field> = dto
+        .fields
+        .iter()
+        .filter(|field| field.kind != FieldKind::Skip)
+        .collect();
+
+    let definitions = fields.iter().map(|field| {
+        let Field { name, ty, .. } = field;
+        match field.kind {
+            FieldKind::PrimaryKey => quote! {
+                #[serde(skip_deserializing)]
+                pub #name: #ty,
+            },
+            FieldKind::Version => {
+                let doc = format!(
+                    "Version the update is based on. The update fails if the {} changed in the meantime.",
+                    noun
+                );
+                quote! {
+                    #[doc = #doc]
+                    pub #name: Option<#ty>,
+                }
+            }
+            FieldKind::ReadOnly => quote! {
+                #[serde(skip_deserializing)]
+                pub #name: Option<#ty>,
+            },
+            FieldKind::Normal | FieldKind::WriteOnly => {
+                let nullable = match field.option_inner() {
+                    Some(_) => quote! {
+                        #[serde(default, deserialize_with = "crate::models::deserialize_some")]
+                    },
+                    None => TokenStream::new(),
+                };
+                let write_only = match field.kind {
+                    FieldKind::WriteOnly => quote! { #[serde(skip_serializing)] },
+                    _ => TokenStream::new(),
+                };
+                let docs = field.write_docs();
+
+                quote! {
+                    #(#docs)*
+                    #nullable
+                    #write_only
+                    pub #name: Option<#ty>,
+                }
+            }
+            FieldKind::Skip => unreachable!("Skipped fields are filtered"),
+        }
+    });
+
+    let keys = fields
+        .iter()
+        .filter(|field| field.kind == FieldKind::PrimaryKey)
+        .map(|field| {
+            let Field { name, column, .. } = field;
+            quote! { #column: ::taskrs_db::sea_orm::ActiveValue::Set(self.#name), }
+        });
+    let changes = fields
+        .iter()
+        .filter(|field| field.kind != FieldKind::PrimaryKey)
+        .map(|field| {
+            let Field { name, column, .. } = field;
+            quote! {
+                if let Some(#name) = self.#name {
+                    active_model.#column = ::taskrs_db::sea_orm::ActiveValue::Set(#name);
+                }
+            }
+        });
+
+    quote! {
+        #[derive(Clone, Debug, Default, ::serde::Deserialize, ::serde::Serialize)]
+        #[serde(rename_all = "camelCase")]
+        pub struct #update {
+            #(#definitions)*
+        }
+
+        impl crate::models::IntoActiveModel<#model::ActiveModel> for #update {
+            fn into_active_model(self) -> #model::ActiveModel {
+                #[allow(unused_mut)]
+                let mut active_model = #model::ActiveModel {
+                    #(#keys)*
+                    ..Default::default()
+                };
+
+                #(#changes)*
+
+                active_model
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand;
+    use proc_macro2::TokenStream;
+    use quote::quote;
+    use syn::{parse_quote, DeriveInput};
+
+    fn expand_ok(input: DeriveInput) -> String {
+        match expand(input) {
+            Ok(tokens) => tokens.to_string(),
+            Err(err) => panic!("Expansion failed: {}", err),
+        }
+    }
+
+    fn expand_err(input: DeriveInput) -> String {
+        match expand(input) {
+            Ok(tokens) => panic!("Expansion succeeded: {}", tokens),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    /// Asserts that the expansion contains the tokens
+    fn assert_expands_to(expansion: &str, tokens: TokenStream) {
+        let tokens = tokens.to_string();
+        assert!(
+            expansion.contains(&tokens),
+            "`{}` not found in `{}`",
+            tokens,
+            expansion
+        );
+    }
+
+    fn user() -> DeriveInput {
+        parse_quote! {
+            #[dto(model = "user", read = "User", create = "UserCreate", update = "UserUpdate")]
+            struct UserFields {
+                #[dto(primary_key)]
+                id: i32,
+                email: String,
+                #[dto(write_only, rename = "password")]
+                password_hash: String,
+                first_name: Option<String>,
+                #[dto(default)]
+                enabled: bool,
+                #[dto(read_only)]
+                inserted_at: Option<NaiveDateTime>,
+                #[dto(version)]
+                version: i32,
+                #[dto(skip)]
+                search: String,
+            }
+        }
+    }
+
+    #[test]
+    fn read_model_leaves_out_write_only_and_skipped_fields() {
+        let expansion = expand_ok(user());
+
+        assert_expands_to(
+            &expansion,
+            quote! {
+                pub struct User {
+                    pub id: i32,
+                    pub email: String,
+                    pub first_name: Option<String>,
+                    pub enabled: bool,
+                    pub inserted_at: Option<NaiveDateTime>,
+                    pub version: i32,
+                }
+            },
+        );
+        assert_expands_to(
+            &expansion,
+            quote! { impl ::std::convert::From<user::Model> for User },
+        );
+    }
+
+    #[test]
+    fn read_model_binds_every_column_of_the_model() {
+        let expansion = expand_ok(user());
+
+        // No `..`, a column added to the model but not to the DTO fails to compile
+        assert_expands_to(
+            &expansion,
+            quote! {
+                let user::Model {
+                    id,
+                    email,
+                    password_hash: _,
+                    first_name,
+                    enabled,
+                    inserted_at,
+                    version,
+                    search: _,
+                } = model;
+            },
+        );
+        assert_expands_to(
+            &expansion,
+            quote! {
+                Self {
+                    id,
+                    email,
+                    first_name,
+                    enabled,
+                    inserted_at,
+                    version,
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn create_model_sets_fields_without_key_and_version() {
+        let expansion = expand_ok(user());
+
+        assert_expands_to(
+            &expansion,
+            quote! {
+                pub struct UserCreate {
+                    pub email: String,
+                    #[serde(skip_serializing)]
+                    pub password: String,
+                    pub first_name: Option<String>,
+                    #[serde(default)]
+                    pub enabled: bool,
+                    #[serde(skip_deserializing)]
+                    pub inserted_at: Option<NaiveDateTime>,
+                }
+            },
+        );
+        assert_expands_to(
+            &expansion,
+            quote! {
+                password_hash: ::taskrs_db::sea_orm::ActiveValue::Set(self.password),
+            },
+        );
+        // Filled by the database model unless set
+        assert_expands_to(
+            &expansion,
+            quote! {
+                if let Some(inserted_at) = self.inserted_at {
+                    active_model.inserted_at = ::taskrs_db::sea_orm::ActiveValue::Set(Some(inserted_at));
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn update_model_only_sets_changed_fields() {
+        let expansion = expand_ok(user());
+
+        assert_expands_to(
+            &expansion,
+            quote! {
+                #[serde(skip_deserializing)]
+                pub id: i32,
+            },
+        );
+        assert_expands_to(
+            &expansion,
+            quote! {
+                #[serde(default, deserialize_with = "crate::models::deserialize_some")]
+                pub first_name: Option<Option<String> >,
+            },
+        );
+        assert_expands_to(
+            &expansion,
+            quote! {
+                let mut active_model = user::ActiveModel {
+                    id: ::taskrs_db::sea_orm::ActiveValue::Set(self.id),
+                    ..Default::default()
+                };
+            },
+        );
+        assert_expands_to(
+            &expansion,
+            quote! {
+                if let Some(email) = self.email {
+                    active_model.email = ::taskrs_db::sea_orm::ActiveValue::Set(email);
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn update_model_documents_the_version() {
+        let expansion = expand_ok(user());
+
+        assert_expands_to(
+            &expansion,
+            quote! {
+                #[doc = "Version the update is based on. The update fails if the user changed in the meantime."]
+                pub version: Option<i32>,
+            },
+        );
+        assert_expands_to(
+            &expansion,
+            quote! {
+                if let Some(version) = self.version {
+                    active_model.version = ::taskrs_db::sea_orm::ActiveValue::Set(version);
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn read_model_doubles_as_create_model() {
+        let expansion = expand_ok(parse_quote! {
+            #[dto(model = "group_role", read = "GroupRole", create = "GroupRole")]
+            struct GroupRoleFields {
+                group_id: i32,
+                role_id: i32,
+            }
+        });
+
+        assert_expands_to(
+            &expansion,
+            quote! { impl crate::models::IntoActiveModel<group_role::ActiveModel> for GroupRole },
+        );
+        assert!(!expansion.contains("struct GroupRoleCreate"));
+    }
+
+    #[test]
+    fn flattened_columns_are_gathered_into_the_field() {
+        let expansion = expand_ok(parse_quote! {
+            #[dto(model = "user_role", read = "UserRole", create = "UserRole")]
+            struct UserRoleFields {
+                user_id: i32,
+                #[dto(flatten(valid_from, valid_until))]
+                validity: GrantValidity,
+            }
+        });
+
+        assert_expands_to(
+            &expansion,
+            quote! {
+                #[serde(flatten)]
+                pub validity: GrantValidity,
+            },
+        );
+        assert_expands_to(
+            &expansion,
+            quote! {
+                validity: GrantValidity {
+                    valid_from,
+                    valid_until,
+                },
+            },
+        );
+        assert_expands_to(
+            &expansion,
+            quote! {
+                valid_from: ::taskrs_db::sea_orm::ActiveValue::Set(self.validity.valid_from),
+                valid_until: ::taskrs_db::sea_orm::ActiveValue::Set(self.validity.valid_until),
+            },
+        );
+    }
+
+    #[test]
+    fn rejects_enums() {
+        let error = expand_err(parse_quote! {
+            #[dto(model = "user", read = "User")]
+            enum UserFields {
+                Id,
+            }
+        });
+
+        assert_eq!(error, "Expected a struct");
+    }
+
+    #[test]
+    fn rejects_tuple_structs() {
+        let error = expand_err(parse_quote! {
+            #[dto(model = "user", read = "User")]
+            struct UserFields(i32);
+        });
+
+        assert_eq!(error, "Expected named fields");
+    }
+
+    #[test]
+    fn rejects_missing_struct_attributes() {
+        let error = expand_err(parse_quote! {
+            #[dto(read = "User")]
+            struct UserFields {
+                id: i32,
+            }
+        });
+
+        assert_eq!(error, "Missing `model`");
+    }
+
+    #[test]
+    fn rejects_unknown_struct_attributes() {
+        let error = expand_err(parse_quote! {
+            #[dto(model = "user", read = "User", delete = "UserDelete")]
+            struct UserFields {
+                id: i32,
+            }
+        });
+
+        assert_eq!(error, "Unknown attribute");
+    }
+
+    #[test]
+    fn rejects_unknown_field_attributes() {
+        let error = expand_err(parse_quote! {
+            #[dto(model = "user", read = "User")]
+            struct UserFields {
+                #[dto(hidden)]
+                id: i32,
+            }
+        });
+
+        assert_eq!(error, "Unknown attribute");
+    }
+
+    #[test]
+    fn rejects_conflicting_field_attributes() {
+        let error = expand_err(parse_quote! {
+            #[dto(model = "user", read = "User")]
+            struct UserFields {
+                #[dto(read_only, write_only)]
+                id: i32,
+            }
+        });
+
+        assert_eq!(error, "Conflicting attribute");
+    }
+
+    #[test]
+    fn rejects_default_of_fields_not_set_on_create() {
+        let error = expand_err(parse_quote! {
+            #[dto(model = "user", read = "User", create = "UserCreate")]
+            struct UserFields {
+                #[dto(read_only, default)]
+                inserted_at: Option<NaiveDateTime>,
+            }
+        });
+
+        assert_eq!(error, "`default` only applies to fields set on create");
+    }
+
+    #[test]
+    fn rejects_write_only_fields_in_read_model_used_for_create() {
+        let error = expand_err(parse_quote! {
+            #[dto(model = "user", read = "User", create = "User")]
+            struct UserFields {
+                #[dto(write_only)]
+                password_hash: String,
+            }
+        });
+
+        assert_eq!(
+            error,
+            "The read model can not be the create model if it has write only fields"
+        );
+    }
+
+    #[test]
+    fn rejects_flatten_in_update_models() {
+        let error = expand_err(parse_quote! {
+            #[dto(model = "user_role", read = "UserRole", update = "UserRoleUpdate")]
+            struct UserRoleFields {
+                #[dto(flatten(valid_from, valid_until))]
+                validity: GrantValidity,
+            }
+        });
+
+        assert_eq!(error, "`flatten` is not supported by update models");
+    }
+}
@@ -0,0 +1,52 @@
+mod dto;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+/// Derives the read, create and update models of an entity from the definition of its fields.
+///
+/// The definition mirrors the fields of the `taskrs-db` model, the struct itself is not used.
+/// Generated code refers to `crate::models`, so the macro is meant to be used in `taskrs-core`.
+///
+/// ```ignore
+/// #[derive(Dto)]
+/// #[dto(model = "user", read = "User", create = "UserCreate", update = "UserUpdate")]
+/// struct UserFields {
+///     #[dto(primary_key)]
+///     id: i32,
+///     #[dto(write_only, rename = "password")]
+///     password_hash: String,
+///     #[dto(read_only)]
+///     inserted_at: Option<NaiveDateTime>,
+/// }
+/// ```
+///
+/// Struct attributes:
+/// - `model`: Module of the `taskrs-db` model
+/// - `read`: Name of the read model, implements `From<Model>`.
+///   Every column of the model needs a field, skipped or not, the conversion fails to compile otherwise.
+/// - `create`: Name of the create model, implements `IntoActiveModel`.
+///   If it is the read model, only `IntoActiveModel` is implemented for it.
+/// - `update`: Name of the update model, implements `IntoActiveModel`.
+///   Changed fields are `Some`, nullable fields are `Some(None)` if set to `null`.
+///
+/// Field attributes:
+/// - `primary_key`: Generated by the database, only identifies the entity on update
+/// - `version`: Version for optimistic concurrency control, only set on update
+/// - `read_only`: Not deserialized on create and update, e.g. timestamps.
+///   Nullable fields are only set if `Some`, so the database model can fill them.
+/// - `write_only`: Not part of the read model and never serialized, e.g. password hashes
+/// - `skip`: Not part of any model
+/// - `default`: Optional on create
+/// - `rename = "..."`: Name of the field in the models
+/// - `flatten(column, ...)`: Gathers the columns into the struct of the field,
+///   which has a field for each of them and is flattened by serde.
+///   Not supported by update models.
+#[proc_macro_derive(Dto, attributes(dto))]
+pub fn derive_dto(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    dto::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}